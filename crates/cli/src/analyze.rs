@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 
 use std::collections::BTreeMap;
 use std::sync::atomic::AtomicI32;
@@ -9,7 +9,7 @@ use tracing::{event, instrument, Level};
 #[cfg(feature = "grit_tracing")]
 use tracing_opentelemetry::OpenTelemetrySpanExt as _;
 
-use grit_cache::paths::cache_for_cwd;
+use grit_cache::paths::{cache_for_cwd, trigram_index_for_cwd};
 use grit_util::{FileRange, Position};
 use marzano_language::target_language::expand_paths;
 
@@ -19,13 +19,16 @@ use marzano_core::built_in_functions::BuiltIns;
 use marzano_core::pattern_compiler::{src_to_problem_libs, CompilationResult};
 use marzano_core::{
     api::{AnalysisLog, MatchResult},
+    fixes::{attach_alternative_fixes, AlternativeFixPattern},
     problem::Problem,
 };
+use marzano_gritmodule::config::GritPatternFix;
 use marzano_language::target_language::PatternLanguage;
 use marzano_util::cache::GritCache;
 use marzano_util::runtime::ExecutionContext;
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc;
@@ -66,6 +69,32 @@ impl<'b> RichPattern<'b> {
     }
 }
 
+/// Compile the alternative fixes of a pattern, with the same libraries and targets as the pattern itself
+pub fn compile_alternative_fixes(
+    fixes: &[GritPatternFix],
+    pattern_libs: &BTreeMap<String, String>,
+    language: Option<PatternLanguage>,
+    targets: Option<Vec<FileRange>>,
+) -> Result<Vec<AlternativeFixPattern>> {
+    fixes
+        .iter()
+        .map(|fix| {
+            let pattern = RichPattern {
+                body: &fix.body,
+                name: None,
+            };
+            let compiled = pattern
+                .compile(pattern_libs, language, targets.clone(), None)
+                .with_context(|| format!("Unable to compile fix '{}'", fix.label))?;
+            Ok(AlternativeFixPattern {
+                label: fix.label.clone(),
+                preferred: fix.preferred,
+                problem: compiled.problem,
+            })
+        })
+        .collect()
+}
+
 pub fn extract_rewritten_content(result: &MatchResult) -> Option<&String> {
     match result {
         MatchResult::AnalysisLog(_) => None,
@@ -140,6 +169,26 @@ macro_rules! emit_error {
     };
 }
 
+/// Drops the paths which the trigram index rules out, because they cannot
+/// contain the text the pattern requires.
+async fn filter_paths_with_index(
+    paths: Vec<PathBuf>,
+    compiled: &Problem,
+    refresh: bool,
+) -> Result<Vec<PathBuf>> {
+    let required = compiled.required_text();
+    if required.is_anything() {
+        return Ok(paths);
+    }
+    let mut index = trigram_index_for_cwd(refresh).await?;
+    index.update(&paths)?;
+    index.save()?;
+    Ok(paths
+        .into_iter()
+        .filter(|path| index.may_match(path, &required))
+        .collect())
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn par_apply_pattern<M>(
     multi: MultiProgress,
@@ -151,6 +200,7 @@ pub async fn par_apply_pattern<M>(
     arg: &ApplyPatternArgs,
     context: &ExecutionContext,
     format: &OutputFormat,
+    alternative_fixes: &[AlternativeFixPattern],
 ) -> M
 where
     M: Messager,
@@ -224,7 +274,14 @@ where
 
             drop(file_paths_tx);
 
-            let found_paths = file_paths_rx.iter().collect::<Vec<_>>();
+            let mut found_paths = file_paths_rx.iter().collect::<Vec<_>>();
+            if arg.index {
+                found_paths = emit_error!(
+                    owned_emitter,
+                    &arg.visibility,
+                    filter_paths_with_index(found_paths, &compiled, arg.refresh_cache).await
+                );
+            }
             (found_paths.len(), Some(found_paths))
         }
         ApplyInput::Virtual(ref virtual_info) => (virtual_info.files.len(), None),
@@ -263,7 +320,8 @@ where
 
         s.spawn(move |_| {
             let mut parse_errors: HashMap<String, usize> = HashMap::new();
-            for message in rx {
+            for mut message in rx {
+                attach_alternative_fixes(&mut message, alternative_fixes, context);
                 if cache_ref.is_useful() {
                     for res in message.iter() {
                         if let MatchResult::DoneFile(done_file) = res {
@@ -316,9 +374,11 @@ where
                 }
             }
 
+            // Cancelled executions skip the remaining files, so they are never processed
             loop {
                 if processed.load(Ordering::SeqCst) >= found_count.try_into().unwrap()
                     || !should_continue.load(Ordering::SeqCst)
+                    || context.is_cancelled()
                 {
                     break;
                 }
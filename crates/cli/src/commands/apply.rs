@@ -1,8 +1,10 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
 use indicatif::MultiProgress;
 
 use crate::{flags::GlobalFormatFlags, messenger_variant::create_emitter, updater::Updater};
+use marzano_core::transaction::undo_last_transaction;
+use marzano_gritmodule::searcher::find_journal_dir;
 use marzano_messenger::emit::ApplyDetails;
 use serde::Serialize;
 use std::env::current_dir;
@@ -20,6 +22,7 @@ use super::{
 pub struct ApplyArgs {
     #[clap(
         index = 1,
+        required_unless_present = "undo",
         help = "Pattern or workflow to apply",
         long_help = "The pattern to apply, in a few forms:
   - A pattern name (ex. `raw_no_console_log`)
@@ -28,10 +31,14 @@ pub struct ApplyArgs {
   - A path to a pattern file (ex. `./patterns/raw_no_console_log.grit`)
   - A workflow name (ex. `lint`)"
     )]
-    pattern_or_workflow: String,
+    pattern_or_workflow: Option<String>,
     #[clap(index = 2, value_parser, default_value = ".")]
     paths: Vec<PathBuf>,
 
+    /// Restore the files changed by the most recent `grit apply`, including created and deleted files. Refuses if any of them were edited since, unless `--force` is set
+    #[clap(long = "undo", conflicts_with = "pattern_or_workflow")]
+    undo: bool,
+
     #[cfg(feature = "workflows_v2")]
     #[command(flatten)]
     apply_migration_args: ApplyMigrationArgs,
@@ -49,6 +56,13 @@ pub(crate) async fn run_apply(
     details: &mut ApplyDetails,
     flags: &GlobalFormatFlags,
 ) -> Result<()> {
+    if args.undo {
+        return run_undo(args.apply_pattern_args.force).await;
+    }
+    let pattern_or_workflow = args
+        .pattern_or_workflow
+        .context("A pattern or workflow to apply is required")?;
+
     #[cfg(feature = "workflows_v2")]
     {
         let execution_id =
@@ -68,7 +82,7 @@ pub(crate) async fn run_apply(
         #[cfg(feature = "remote_workflows")]
         if args.apply_migration_args.remote {
             return crate::workflows::run_remote_workflow(
-                args.pattern_or_workflow,
+                pattern_or_workflow,
                 args.apply_migration_args,
                 ranges,
                 flags,
@@ -91,7 +105,7 @@ pub(crate) async fn run_apply(
             .ok();
 
         let custom_workflow =
-            crate::workflows::find_workflow_file_from(current_dir, &args.pattern_or_workflow, auth)
+            crate::workflows::find_workflow_file_from(current_dir, &pattern_or_workflow, auth)
                 .instrument(tracing::span!(
                     tracing::Level::INFO,
                     "grit_marzano.find_workflow",
@@ -132,7 +146,7 @@ pub(crate) async fn run_apply(
     }
 
     run_apply_pattern(
-        args.pattern_or_workflow,
+        pattern_or_workflow,
         args.shared_apply_args,
         args.paths,
         args.apply_pattern_args,
@@ -146,6 +160,15 @@ pub(crate) async fn run_apply(
     .await
 }
 
+async fn run_undo(force: bool) -> Result<()> {
+    let journal_dir = find_journal_dir(current_dir()?).await;
+    match undo_last_transaction(&journal_dir, force)? {
+        Some(restored) => log::info!("Restored {} files", restored),
+        None => log::info!("Nothing to undo"),
+    }
+    Ok(())
+}
+
 // TODO files that call stdlib functions don't work here
 #[cfg(test)]
 mod tests {
@@ -173,12 +196,14 @@ mod tests {
             matched: 0,
             rewritten: 0,
             named_pattern: None,
+            fix_label: None,
         };
         let apply_migration_args = ApplyMigrationArgs::default();
         let apply_pattern_args = ApplyPatternArgs::default();
         let args = ApplyArgs {
-            pattern_or_workflow: pattern,
+            pattern_or_workflow: Some(pattern),
             paths,
+            undo: false,
             apply_migration_args,
             apply_pattern_args,
             shared_apply_args: Default::default(),
@@ -18,9 +18,10 @@ use grit_util::Position;
 use indicatif::MultiProgress;
 use marzano_core::api::{AllDone, AllDoneReason, AnalysisLog, MatchResult};
 use marzano_core::pattern_compiler::CompilationResult;
+use marzano_core::type_provider::TsServerTypeProvider;
 use marzano_gritmodule::fetcher::KeepFetcherKind;
 use marzano_gritmodule::markdown::get_body_from_md_content;
-use marzano_gritmodule::searcher::{find_global_grit_dir, find_grit_modules_dir};
+use marzano_gritmodule::searcher::{find_global_grit_dir, find_grit_modules_dir, find_journal_dir};
 use marzano_gritmodule::utils::{infer_pattern, is_pattern_name, parse_remote_name};
 use marzano_language::target_language::PatternLanguage;
 use marzano_messenger::emit::FlushableMessenger as _;
@@ -30,25 +31,36 @@ use std::env;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicI32;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use tokio::fs;
 
 use crate::commands::filters::extract_filter_ranges;
 
 use crate::flags::GlobalFormatFlags;
 use crate::{
-    analyze::par_apply_pattern, error::GoodError, flags::OutputFormat,
-    messenger_variant::create_emitter, result_formatting::get_human_error, updater::Updater,
+    analyze::{compile_alternative_fixes, par_apply_pattern},
+    error::GoodError,
+    flags::OutputFormat,
+    messenger_variant::create_emitter,
+    result_formatting::get_human_error,
+    updater::Updater,
 };
 
 use marzano_messenger::{
     emit::{ApplyDetails, Messager, VisibilityLevels},
     output_mode::OutputMode,
+    transaction::TransactionalMessenger,
 };
 
-use crate::resolver::{get_grit_files_from_flags_or_cwd, GritModuleResolver};
+use crate::resolver::{
+    get_grit_files_from_flags_or_cwd, resolve_from_cwd, GritModuleResolver, Source,
+};
 use crate::utils::has_uncommitted_changes;
 
+use super::explain::{explain_pattern, ExplainTarget};
 use super::filters::SharedFilterArgs;
+use super::interrupt::cancel_on_ctrl_c;
+use super::limits::SharedLimitArgs;
 
 /// Apply a pattern to a set of paths on disk which will be rewritten in place
 #[derive(Deserialize)]
@@ -117,9 +129,9 @@ pub struct ApplyPatternArgs {
     #[clap(
         long = "force",
         default_value = "false",
-        help = "Force apply, even if there are uncommitted changes"
+        help = "Force apply, even if there are uncommitted changes, or undo, even if files changed since the apply"
     )]
-    force: bool,
+    pub force: bool,
     #[clap(long = "format", default_value = "false", conflicts_with_all = &["dry_run"], hide = true)]
     pub format: bool,
     #[clap(
@@ -155,12 +167,26 @@ pub struct ApplyPatternArgs {
     /// Clear cache before running apply
     #[clap(long = "refresh-cache", conflicts_with = "cache")]
     pub refresh_cache: bool,
+    /// Skip files which cannot contain the text the pattern requires, using a trigram index stored in the cache directory
+    #[clap(long = "index")]
+    pub index: bool,
     /// Interpret the request as a natural language request
     #[clap(long)]
     ai: bool,
     /// Change the default language to use for the pattern (if unset, JavaScript is used by default)
     #[clap(long = "language", alias = "lang")]
     pub language: Option<PatternLanguage>,
+    /// Path to a `tsserver` executable, used to look up TypeScript types for the `type_of` function
+    #[clap(long = "tsserver")]
+    pub tsserver: Option<PathBuf>,
+    /// Print every pattern operator tried against the nodes on a line of a file, given as `<file>:<line>`, without applying any changes
+    #[clap(long = "explain", conflicts_with_all = &["stdin", "interactive"])]
+    pub explain: Option<ExplainTarget>,
+    /// Apply the alternative fix with this label to every match which offers it, instead of the pattern's own rewrite
+    #[clap(long = "fix-label", conflicts_with = "dry_run")]
+    pub fix_label: Option<String>,
+    #[clap(flatten)]
+    pub shared_limits: SharedLimitArgs,
 }
 
 impl Default for ApplyPatternArgs {
@@ -176,9 +202,14 @@ impl Default for ApplyPatternArgs {
             output_file: Default::default(),
             cache: Default::default(),
             refresh_cache: Default::default(),
+            index: Default::default(),
             ai: Default::default(),
             language: Default::default(),
             stdin: Default::default(),
+            tsserver: Default::default(),
+            explain: Default::default(),
+            fix_label: Default::default(),
+            shared_limits: Default::default(),
         }
     }
 }
@@ -213,7 +244,11 @@ pub(crate) async fn run_apply_pattern(
         .await
         .unwrap()
         .get_context()
-        .unwrap();
+        .unwrap()
+        .with_limits(arg.shared_limits.execution_limits());
+    if let Some(tsserver) = &arg.tsserver {
+        context = context.with_type_provider(Arc::new(TsServerTypeProvider::spawn(tsserver)?));
+    }
 
     let format = OutputFormat::from_flags(
         format_flags,
@@ -225,6 +260,10 @@ pub(crate) async fn run_apply_pattern(
     );
 
     let default_lang = default_lang.or(arg.language);
+    let paths = match &arg.explain {
+        Some(target) => vec![target.path.clone()],
+        None => paths,
+    };
 
     let default_lang = if !arg.stdin {
         default_lang
@@ -495,7 +534,12 @@ pub(crate) async fn run_apply_pattern(
     let CompilationResult {
         problem: compiled,
         compilation_warnings,
-    } = match pattern.compile(final_input.pattern_libs(), lang, filter_range, arg.limit) {
+    } = match pattern.compile(
+        final_input.pattern_libs(),
+        lang,
+        filter_range.clone(),
+        arg.limit,
+    ) {
         Ok(c) => c,
         Err(e) => {
             let log = match e.downcast::<grit_util::AnalysisLog>() {
@@ -531,6 +575,11 @@ pub(crate) async fn run_apply_pattern(
             .unwrap();
     }
 
+    if let Some(target) = &arg.explain {
+        emitter.flush().await?;
+        return explain_pattern(&compiled, target, &context, format_flags);
+    }
+
     let warn_uncommitted = !arg.dry_run && !arg.force && has_uncommitted_changes(cwd.clone()).await;
     if warn_uncommitted && has_rewrite(&compiled.pattern, &compiled.definitions()) {
         let term = console::Term::stderr();
@@ -548,31 +597,84 @@ pub(crate) async fn run_apply_pattern(
         }
     }
 
+    // Named patterns may offer alternative fixes for their matches
+    let alternative_fixes = match &details.named_pattern {
+        Some(name) => {
+            let (resolved, _) = flushable_unwrap!(emitter, resolve_from_cwd(&Source::All).await);
+            let fixes = resolved
+                .into_iter()
+                .find(|p| &p.local_name == name)
+                .map(|p| p.alternative_fixes().to_vec())
+                .unwrap_or_default();
+            flushable_unwrap!(
+                emitter,
+                compile_alternative_fixes(&fixes, final_input.pattern_libs(), lang, filter_range)
+            )
+        }
+        None => vec![],
+    };
+    if let Some(label) = &arg.fix_label {
+        if !alternative_fixes.iter().any(|fix| &fix.label == label) {
+            emitter.flush().await?;
+            bail!("The pattern does not offer a fix labeled {label}");
+        }
+        details.fix_label = Some(label.clone());
+    }
+
     let processed = AtomicI32::new(0);
+    let context = context.with_cancellation(cancel_on_ctrl_c());
 
-    let mut emitter = par_apply_pattern(
+    // Effects are staged and only written once every file has been processed
+    let staged = par_apply_pattern(
         multi,
         compiled,
         final_input,
-        emitter,
+        TransactionalMessenger::new(emitter),
         &processed,
         details,
         &arg,
         &context,
         &format,
+        &alternative_fixes,
     )
     .await;
 
+    let mut emitter = if context.is_cancelled() {
+        staged.discard()
+    } else {
+        let (mut emitter, committed) = staged.commit(&find_journal_dir(cwd).await);
+        if let Err(e) = committed {
+            let log = AnalysisLog::floating_error(format!("Failed to apply changes: {:#}", e));
+            emitter
+                .emit(&MatchResult::AnalysisLog(log.clone()))
+                .unwrap();
+            emitter.flush().await?;
+            bail!(GoodError::new_with_message(log.message));
+        }
+        emitter
+    };
+
     let all_done = MatchResult::AllDone(AllDone {
         processed: processed.load(Ordering::SeqCst),
         found: details.matched,
-        reason: AllDoneReason::AllMatchesFound,
+        reason: if context.is_cancelled() {
+            AllDoneReason::Aborted
+        } else {
+            AllDoneReason::AllMatchesFound
+        },
     });
 
     emitter.emit(&all_done).unwrap();
 
     emitter.flush().await?;
 
+    if context.is_cancelled() {
+        bail!(
+            "Interrupted after processing {} files, no files were changed",
+            processed.load(Ordering::SeqCst)
+        );
+    }
+
     match emitter.get_fatal_error() {
         Some(e) => match format.is_always_ok() {
             (true, _) => return Ok(()),
@@ -1,15 +1,18 @@
 use anyhow::{bail, Result};
 use clap::Args;
 use dashmap::DashMap;
-use grit_cache::paths::cache_for_cwd;
+use grit_cache::paths::{cache_for_cwd, trigram_index_for_cwd};
+use grit_util::RequiredText;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use log::info;
 use marzano_core::{
     api::{
-        is_match, AllDone, AllDoneReason, EnforcementLevel, MatchReason, MatchResult, RewriteSource,
+        is_match, AllDone, AllDoneReason, EnforcementLevel, FixEdit, FixSafety, MatchReason,
+        MatchResult, Rewrite, RewriteSource,
     },
+    fixes::{apply_fix_edits, attach_alternative_fixes, AlternativeFixPattern},
     fs::apply_rewrite,
-    problem::Problem,
+    problem::{CombinedProblems, Problem},
 };
 use marzano_gritmodule::{config::ResolvedGritDefinition, utils::extract_path};
 use marzano_language::target_language::{expand_paths, PatternLanguage};
@@ -31,6 +34,7 @@ use marzano_messenger::emit::Messager;
 use cli_server::check::CheckMessenger;
 
 use crate::{
+    analyze::compile_alternative_fixes,
     error::GoodError,
     flags::{GlobalFormatFlags, OutputFormat},
     github::{log_check_annotations, write_check_summary},
@@ -44,16 +48,25 @@ use crate::{
     ux::{get_check_summary, log_file, print_config, CheckResult},
 };
 
-use super::filters::{extract_filter_ranges, SharedFilterArgs};
+use super::{
+    filters::{extract_filter_ranges, SharedFilterArgs},
+    interrupt::cancel_on_ctrl_c,
+    limits::SharedLimitArgs,
+};
 
 #[derive(Args, Serialize, Debug)]
 pub struct CheckArg {
     /// The target paths to apply the checks to
     #[clap(value_parser, default_value = ".")]
     pub paths: Vec<PathBuf>,
-    /// Apply fixes to all rewrites
-    #[clap(long = "fix")]
-    pub fix: bool,
+    /// Apply fixes to rewrites. Only safe fixes are applied unless a less safe level (suggestion or unsafe) is given, as in `--fix=unsafe`
+    #[clap(
+        long = "fix",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "safe"
+    )]
+    pub fix: Option<FixSafety>,
     /// Show verbose output
     #[clap(long = "verbose")]
     pub verbose: bool,
@@ -66,11 +79,16 @@ pub struct CheckArg {
     /// Clear cache before running check
     #[clap(long = "refresh-cache", conflicts_with = "no_cache")]
     pub refresh_cache: bool,
+    /// Skip files which cannot contain the text a pattern requires, using a trigram index stored in the cache directory
+    #[clap(long = "index")]
+    pub index: bool,
     /// Output annotations for a GitHub actions workflow
     #[clap(long = "github-actions")]
     pub github_actions: bool,
     #[clap(flatten)]
     pub shared_filters: SharedFilterArgs,
+    #[clap(flatten)]
+    pub shared_limits: SharedLimitArgs,
 }
 
 pub(crate) async fn run_check(
@@ -84,7 +102,11 @@ pub(crate) async fn run_check(
         bail!("--github-actions is not compatible with --json");
     }
 
-    let context = Updater::from_current_bin().await?.get_context()?;
+    let context = Updater::from_current_bin()
+        .await?
+        .get_context()?
+        .with_limits(arg.shared_limits.execution_limits())
+        .with_cancellation(cancel_on_ctrl_c());
 
     let (cache, manager) = cache_for_cwd(arg.refresh_cache, arg.no_cache).await?;
 
@@ -128,6 +150,7 @@ pub(crate) async fn run_check(
     let resolver = GritModuleResolver::new();
 
     let mut pattern_lookup: HashMap<[u8; 32], &ResolvedGritDefinition> = HashMap::new();
+    let mut fixes_lookup: HashMap<[u8; 32], Vec<AlternativeFixPattern>> = HashMap::new();
     let compile_tasks: Result<HashMap<String, Problem>, _> = enforced
         .iter()
         .map(|p| {
@@ -140,6 +163,15 @@ pub(crate) async fn run_check(
             let lang = PatternLanguage::get_language(&p.body);
             match rich_pattern.compile(&grit_files, lang, filter_range.clone(), None) {
                 Ok(c) => {
+                    let fixes = compile_alternative_fixes(
+                        p.alternative_fixes(),
+                        &grit_files,
+                        lang,
+                        filter_range.clone(),
+                    )?;
+                    if !fixes.is_empty() {
+                        fixes_lookup.insert(c.problem.hash, fixes);
+                    }
                     pattern_lookup.insert(c.problem.hash, p);
                     Ok((p.local_name.clone(), c.problem))
                 }
@@ -176,7 +208,47 @@ pub(crate) async fn run_check(
         found_files.insert(language.to_string(), input_files);
     }
 
-    let pg: ProgressBar = multi.add(ProgressBar::new(compiled_map.len().try_into()?));
+    let index = if arg.index {
+        let mut index = trigram_index_for_cwd(arg.refresh_cache).await?;
+        let all_paths: Vec<PathBuf> = found_files
+            .iter()
+            .flat_map(|files| {
+                files
+                    .value()
+                    .iter()
+                    .map(|file| file.path.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        index.update(&all_paths)?;
+        index.save()?;
+        Some(index)
+    } else {
+        None
+    };
+
+    // Single-file patterns for the same language are executed together, so
+    // every file is only parsed once
+    let mut combined_by_language: HashMap<String, Vec<&Problem>> = HashMap::new();
+    let mut multifile_problems = Vec::new();
+    for problem in problems.iter() {
+        if problem.is_multifile {
+            multifile_problems.push(*problem);
+        } else {
+            combined_by_language
+                .entry(problem.language.to_string())
+                .or_default()
+                .push(*problem);
+        }
+    }
+    let combined_file_count: usize = combined_by_language
+        .keys()
+        .filter_map(|language| found_files.get(language).map(|files| files.len()))
+        .sum();
+
+    let pg: ProgressBar = multi.add(ProgressBar::new(
+        (combined_file_count + multifile_problems.len()).try_into()?,
+    ));
     let style = ProgressStyle::with_template(
         "\n{prefix:.bold.dim} {wide_msg:.bold.dim}\n{wide_bar} {pos:}/{len}",
     )
@@ -184,7 +256,66 @@ pub(crate) async fn run_check(
     pg.set_style(style);
     pg.set_prefix("Checking");
 
-    problems.par_iter().for_each(|pattern| {
+    let may_match = |pattern: &Problem, required: &RequiredText, path: &RichPath| {
+        if let Some(index) = &index {
+            if !index.may_match(&path.path, required) {
+                return false;
+            }
+        }
+        let Some(hash) = path.hash else { return true };
+        !cache.has_no_matches(hash, pattern.hash)
+    };
+
+    for (language, language_problems) in combined_by_language {
+        let language_files = match found_files.get(&language) {
+            Some(files) => files,
+            None => continue,
+        };
+        pg.set_message(language);
+        let required: HashMap<[u8; 32], RequiredText> = language_problems
+            .iter()
+            .map(|pattern| (pattern.hash, pattern.required_text()))
+            .collect();
+        let combined = CombinedProblems::new(language_problems)?;
+        language_files.par_iter().for_each(|path| {
+            let to_run: HashSet<[u8; 32]> = combined
+                .problems()
+                .iter()
+                .filter(|pattern| may_match(pattern, &required[&pattern.hash], path))
+                .map(|pattern| pattern.hash)
+                .collect();
+            let mut file_results: HashMap<[u8; 32], Vec<MatchResult>> = HashMap::new();
+            for tagged in
+                combined.execute_file(path, &context, |pattern| to_run.contains(&pattern.hash))
+            {
+                file_results
+                    .entry(tagged.pattern_hash)
+                    .or_default()
+                    .push(tagged.result);
+            }
+            // The file was not checked, so it must not be cached as having no matches
+            if context.is_cancelled() {
+                return;
+            }
+            for pattern_hash in to_run {
+                let mut result = file_results.remove(&pattern_hash).unwrap_or_default();
+                if !result.iter().any(is_match) {
+                    if let Some(hash) = path.hash {
+                        cache.put_no_matches(hash, pattern_hash).unwrap();
+                    }
+                    continue;
+                }
+                if let Some(fixes) = fixes_lookup.get(&pattern_hash) {
+                    attach_alternative_fixes(&mut result, fixes, &context);
+                }
+                let mut entry = results.entry(pattern_hash).or_default();
+                entry.extend(result.into_iter().filter(is_match));
+            }
+            pg.inc(1);
+        });
+    }
+
+    multifile_problems.par_iter().for_each(|pattern| {
         if let Some(name) = &pattern.name {
             pg.set_message(name.to_string());
         }
@@ -192,25 +323,33 @@ pub(crate) async fn run_check(
             Some(files) => files,
             None => return,
         };
-        let un_cached_input_files: Vec<_> = language_files
-            .iter()
-            .filter(|path| {
-                let Some(hash) = path.hash else { return true };
-                !cache.has_no_matches(hash, pattern.hash)
-            })
-            .collect();
-        let (result, no_match) = pattern.execute_paths(un_cached_input_files, &context);
-        if !no_match.is_empty() {
-            for path in no_match.into_iter() {
-                let hash = path.hash.unwrap();
-                cache.put_no_matches(hash, pattern.hash).unwrap();
-            }
+        let un_cached_input_files: Vec<_> = language_files.iter().collect();
+        let (mut result, _) = pattern.execute_paths(un_cached_input_files, &context);
+        if let Some(fixes) = fixes_lookup.get(&pattern.hash) {
+            attach_alternative_fixes(&mut result, fixes, &context);
         }
         let mut entry = results.entry(pattern.hash).or_default();
         entry.extend(result.into_iter().filter(is_match));
         pg.inc(1);
     });
 
+    if context.is_cancelled() {
+        pg.finish_and_clear();
+        // Keep what was cached for the files that were fully checked
+        drop(cache);
+        if let Some(manager) = manager {
+            if let Err(e) = manager.join() {
+                bail!("Error joining cache manager: {:?}", e);
+            }
+        }
+        bail!("Check was interrupted before all files were checked");
+    }
+
+    // Files are checked in parallel, so put the results back in order
+    for mut entry in results.iter_mut() {
+        entry.value_mut().sort();
+    }
+
     let mut check_results: HashMap<String, Vec<CheckResult>> = HashMap::new();
 
     for result in results.iter() {
@@ -362,14 +501,51 @@ pub(crate) async fn run_check(
     let mut sorted_results: Vec<(&String, &Vec<CheckResult<'_>>)> = check_results.iter().collect();
     sorted_results.sort_by_key(|(k, _)| *k);
 
+    let mut fixed_files = 0;
+    let mut skipped_fixes = 0;
     for (file, check_results) in sorted_results.iter() {
-        if arg.fix {
-            let rewrites = check_results
+        if let Some(max_safety) = arg.fix {
+            let (rewrites, skipped): (Vec<_>, Vec<_>) = check_results
                 .iter()
                 .filter(|r| matches!(r.result, MatchResult::Rewrite(_)))
-                .collect::<Vec<_>>();
+                .partition(|r| r.pattern.fix_safety() <= max_safety);
+            // Matches with alternative fixes are only fixed automatically if one of them is preferred
+            let (preferred, skipped_preferred): (Vec<_>, Vec<_>) = check_results
+                .iter()
+                .filter(|r| {
+                    matches!(&r.result, MatchResult::Match(m) if m.fixes.iter().any(|f| f.preferred))
+                })
+                .partition(|r| r.pattern.fix_safety() <= max_safety);
+            skipped_fixes += skipped.len() + skipped_preferred.len();
+            if !rewrites.is_empty() || !preferred.is_empty() {
+                fixed_files += 1;
+            }
 
-            if rewrites.len() == 1 {
+            // Preferred fixes only edit the ranges of their own matches, and go first since
+            // their edits are relative to the checked content
+            let preferred_edits: Vec<&FixEdit> = preferred
+                .iter()
+                .filter_map(|r| match &r.result {
+                    MatchResult::Match(m) => Some(m),
+                    _ => None,
+                })
+                .flat_map(|m| {
+                    m.ranges.iter().filter_map(|range| {
+                        m.fixes
+                            .iter()
+                            .find(|fix| fix.preferred && &fix.range == range)
+                    })
+                })
+                .flat_map(|fix| &fix.edits)
+                .collect();
+            let fixed_preferred = !preferred_edits.is_empty();
+            if fixed_preferred {
+                let src = fs_err::read_to_string(file)?;
+                let fixed = apply_fix_edits(&src, preferred_edits)?;
+                apply_rewrite(&MatchResult::Rewrite(Rewrite::for_file(file, &src, &fixed)))?;
+            }
+
+            if rewrites.len() == 1 && !fixed_preferred {
                 apply_rewrite(&rewrites[0].result).unwrap();
             } else {
                 let applicable_patterns = rewrites
@@ -388,7 +564,7 @@ pub(crate) async fn run_check(
                 }
             }
         }
-        log_file(file, check_results, arg.fix);
+        log_file(file, check_results, arg.fix.as_ref());
     }
 
     drop(cache);
@@ -402,8 +578,19 @@ pub(crate) async fn run_check(
     }
 
     let files = check_results.len();
-    if arg.fix {
-        info!("{} files fixed.", files);
+    if let Some(max_safety) = arg.fix {
+        info!("{} files fixed.", fixed_files);
+        if skipped_fixes > 0 {
+            let next_level = if max_safety == FixSafety::Safe {
+                FixSafety::Suggestion
+            } else {
+                FixSafety::Unsafe
+            };
+            info!(
+                "{} fixes were not applied because they are less safe than {}. Run grit check --fix={} to apply more of them.",
+                skipped_fixes, max_safety, next_level
+            );
+        }
         Ok(())
     } else {
         let msg = format!(
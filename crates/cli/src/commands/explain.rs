@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use colored::Colorize;
+use grit_util::{MatchTrace, TraceNode};
+use marzano_core::problem::Problem;
+use marzano_util::rich_path::RichFile;
+use marzano_util::runtime::ExecutionContext;
+use serde::Serialize;
+
+use crate::flags::GlobalFormatFlags;
+
+/// Longest snippet of matched text shown for each traced operator.
+const MAX_TEXT_LENGTH: usize = 60;
+
+/// A line of a file to explain the evaluation of a pattern at, given as `<file>:<line>`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExplainTarget {
+    pub path: PathBuf,
+    pub line: u32,
+}
+
+impl FromStr for ExplainTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, line) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Expected <file>:<line>, got {}", s))?;
+        let line = line
+            .parse::<u32>()
+            .ok()
+            .filter(|line| *line > 0)
+            .ok_or_else(|| format!("Invalid line number {} in {}", line, s))?;
+        Ok(Self {
+            path: PathBuf::from(path),
+            line,
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Explanation {
+    file: String,
+    line: u32,
+    trace: Vec<TraceNode>,
+}
+
+/// Runs `compiled` on the target file, without applying any changes, and
+/// prints every operator tried against the nodes on the target line.
+pub(crate) fn explain_pattern(
+    compiled: &Problem,
+    target: &ExplainTarget,
+    context: &ExecutionContext,
+    flags: &GlobalFormatFlags,
+) -> Result<()> {
+    let content = std::fs::read_to_string(&target.path)?;
+    let file = target.path.to_string_lossy().to_string();
+    let trace = Arc::new(MatchTrace::new(target.line));
+    let context = context.clone().with_match_trace(trace.clone());
+    compiled.execute_file(&RichFile::new(file.clone(), content), &context);
+
+    let explanation = Explanation {
+        file,
+        line: target.line,
+        trace: trace.take(),
+    };
+    if flags.json || flags.jsonl {
+        println!("{}", serde_json::to_string(&explanation)?);
+        return Ok(());
+    }
+    if explanation.trace.is_empty() {
+        log::warn!(
+            "{}",
+            format!(
+                "The pattern did not try any nodes on line {} of {}",
+                explanation.line, explanation.file
+            )
+            .yellow()
+        );
+        return Ok(());
+    }
+    for root in &explanation.trace {
+        print_node(root, root.first_failure(), 0);
+    }
+    Ok(())
+}
+
+fn print_node(node: &TraceNode, failure: Option<&TraceNode>, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let status = if node.matched {
+        "✓".green()
+    } else {
+        "✗".red()
+    };
+    let mut parts = vec![
+        format!("{}{}", indent, status),
+        node.operator.bold().to_string(),
+    ];
+    if let Some(range) = &node.range {
+        parts.push(
+            format!("{}:{}", range.start.line, range.start.column)
+                .dimmed()
+                .to_string(),
+        );
+    }
+    if let Some(text) = &node.text {
+        parts.push(summarize(text));
+    }
+    if failure.is_some_and(|failure| std::ptr::eq(failure, node)) {
+        parts.push("<- first failure".red().bold().to_string());
+    }
+    log::info!("{}", parts.join(" "));
+    for binding in &node.bindings {
+        log::info!(
+            "{}  {} = {}",
+            indent,
+            binding.name.cyan(),
+            summarize(&binding.value)
+        );
+    }
+    for child in &node.children {
+        print_node(child, failure, depth + 1);
+    }
+}
+
+fn summarize(text: &str) -> String {
+    let first_line = text.lines().next().unwrap_or_default();
+    let mut summary: String = first_line.chars().take(MAX_TEXT_LENGTH).collect();
+    if summary.len() < text.len() {
+        summary.push_str("...");
+    }
+    format!("`{}`", summary)
+}
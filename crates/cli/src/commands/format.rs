@@ -74,6 +74,7 @@ pub async fn run_format(arg: &FormatGritArgs, flags: &GlobalFormatFlags) -> Resu
         matched: 0,
         rewritten: 0,
         named_pattern: None,
+        fix_label: None,
     };
     let dry_run = !arg.write;
 
@@ -0,0 +1,22 @@
+use grit_util::CancellationToken;
+use log::warn;
+
+/// Returns a token which is cancelled when the user presses Ctrl-C, so the
+/// command can stop processing files and wrap up cleanly.
+///
+/// Pressing Ctrl-C a second time exits immediately.
+pub(crate) fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancellation = CancellationToken::new();
+    let token = cancellation.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        token.cancel();
+        warn!("Interrupted, stopping after the current files. Press Ctrl-C again to exit immediately.");
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+    cancellation
+}
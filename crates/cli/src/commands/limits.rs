@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use clap::Args;
+use grit_util::ExecutionLimits;
+use serde::Serialize;
+
+#[derive(Args, Clone, Debug, Serialize, Default)]
+/// Shared arguments for limiting the work apply and check commands do per file.
+pub struct SharedLimitArgs {
+    /// Stop running a pattern on a file after this many milliseconds, and report the file as skipped
+    #[clap(long = "file-timeout")]
+    pub(crate) file_timeout: Option<u64>,
+    /// Stop running a pattern on a file after this many matching steps, and report the file as skipped
+    #[clap(long = "max-steps")]
+    pub(crate) max_steps: Option<u64>,
+}
+
+impl SharedLimitArgs {
+    pub(crate) fn execution_limits(&self) -> ExecutionLimits {
+        ExecutionLimits {
+            timeout: self.file_timeout.map(Duration::from_millis),
+            max_steps: self.max_steps,
+        }
+    }
+}
@@ -19,9 +19,12 @@ pub(crate) mod check;
 pub(crate) mod format;
 pub(crate) mod parse;
 pub(crate) mod patterns;
+pub(crate) mod patterns_infer;
+pub(crate) mod patterns_lint;
 pub(crate) mod patterns_list;
 pub(crate) mod patterns_test;
 pub(crate) mod plumbing;
+pub(crate) mod serve;
 pub(crate) mod version;
 
 #[cfg(feature = "workflows_v2")]
@@ -66,7 +69,10 @@ use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
 
 #[cfg(feature = "docgen")]
 pub(crate) mod docgen;
+mod explain;
 mod filters;
+mod interrupt;
+mod limits;
 
 use crate::{
     analytics::{
@@ -96,6 +102,7 @@ use parse::ParseArgs;
 use patterns::{PatternCommands, Patterns};
 use plumbing::PlumbingArgs;
 use serde::Serialize;
+use serve::ServeArgs;
 use std::io::Write;
 use std::process::{ChildStdin, Command, Stdio};
 use std::time::Instant;
@@ -128,9 +135,12 @@ use self::{
     lsp::run_lsp,
     parse::run_parse,
     patterns::{run_patterns_describe, run_patterns_edit},
+    patterns_infer::run_patterns_infer,
+    patterns_lint::run_patterns_lint,
     patterns_list::run_patterns_list,
     patterns_test::run_patterns_test,
     plumbing::run_plumbing,
+    serve::run_serve,
     version::run_version,
 };
 
@@ -171,6 +181,8 @@ pub enum Commands {
     /// Plumbing subcommands for easy machine integration
     #[clap(subcommand, name = "plumbing", hide = true)]
     Plumbing(PlumbingArgs),
+    /// Serve apply, check, parse and list requests over JSON-RPC, keeping modules and compiled patterns in memory
+    Serve(ServeArgs),
     /// Display version information about the CLI and agents
     Version(VersionArgs),
     /// Format grit files under current directory
@@ -206,6 +218,8 @@ impl fmt::Display for Commands {
                 PatternCommands::Test(_) => write!(f, "patterns test"),
                 PatternCommands::Edit(_) => write!(f, "patterns edit"),
                 PatternCommands::Describe(_) => write!(f, "patterns describe"),
+                PatternCommands::Infer(_) => write!(f, "patterns infer"),
+                PatternCommands::Lint(_) => write!(f, "patterns lint"),
             },
             #[cfg(feature = "workflows_v2")]
             Commands::Blueprints(arg) => match arg.blueprint_commands {
@@ -220,6 +234,7 @@ impl fmt::Display for Commands {
                 WorkflowCommands::Upload(_) => write!(f, "workflows upload"),
             },
             Commands::Plumbing(_) => write!(f, "plumbing"),
+            Commands::Serve(_) => write!(f, "serve"),
             Commands::Version(_) => write!(f, "version"),
             Commands::Format(_) => write!(f, "format"),
             #[cfg(feature = "docgen")]
@@ -327,6 +342,7 @@ fn setup_env_logger(app: &App, multi: &MultiProgress) {
     let log_level = app.format_flags.log_level.unwrap_or(match &app.command {
         Commands::Lsp(_) => LevelFilter::Off,
         Commands::Plumbing(_) => LevelFilter::Off,
+        Commands::Serve(_) => LevelFilter::Off,
         _ => LevelFilter::Info,
     });
 
@@ -397,6 +413,7 @@ async fn run_command(_use_tracing: bool) -> Result<()> {
         matched: 0,
         rewritten: 0,
         named_pattern: None,
+        fix_label: None,
     };
     let start = Instant::now();
 
@@ -408,6 +425,7 @@ async fn run_command(_use_tracing: bool) -> Result<()> {
     match &app.command {
         Commands::Install(_) => {}
         Commands::Plumbing(_) => {}
+        Commands::Serve(_) => {}
         _ => {
             updater.check_for_update().await?;
         }
@@ -444,6 +462,8 @@ async fn run_command(_use_tracing: bool) -> Result<()> {
                 PatternCommands::Test(arg) => run_patterns_test(arg, app.format_flags).await,
                 PatternCommands::Edit(arg) => run_patterns_edit(arg).await,
                 PatternCommands::Describe(arg) => run_patterns_describe(arg).await,
+                PatternCommands::Infer(arg) => run_patterns_infer(arg, app.format_flags).await,
+                PatternCommands::Lint(arg) => run_patterns_lint(arg, app.format_flags).await,
             },
             #[cfg(feature = "workflows_v2")]
             Commands::Blueprints(arg) => match arg.blueprint_commands {
@@ -462,6 +482,7 @@ async fn run_command(_use_tracing: bool) -> Result<()> {
             Commands::Plumbing(arg) => {
                 run_plumbing(arg, multi, &mut apply_details, app.format_flags).await
             }
+            Commands::Serve(arg) => run_serve(arg).await,
             Commands::Version(arg) => run_version(arg).await,
             Commands::Format(arg) => run_format(&arg, &app.format_flags).await,
             #[cfg(feature = "docgen")]
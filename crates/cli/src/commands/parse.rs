@@ -1,9 +1,11 @@
 use crate::{flags::GlobalFormatFlags, jsonl::JSONLineMessenger, resolver::GritModuleResolver};
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use clap::Args;
+use colored::Colorize;
 use grit_util::Position;
 use marzano_core::{
     api::{AnalysisLog, MatchResult, PatternInfo},
+    node_introspection::{node_kinds, node_patterns, NodeKind, NodePattern},
     parse::parse_input_file,
 };
 use marzano_language::target_language::{PatternLanguage, TargetLanguage};
@@ -20,6 +22,15 @@ use tokio::fs;
 pub struct ParseArgs {
     #[clap(value_parser)]
     paths: Vec<PathBuf>,
+    /// List the node kinds of a language, with the fields AST-node patterns can match on
+    #[clap(long = "node-types", conflicts_with = "node_patterns")]
+    node_types: bool,
+    /// Show the AST-node pattern which exactly matches each node of the given files
+    #[clap(long = "node-patterns")]
+    node_patterns: bool,
+    /// The language to introspect, files are otherwise parsed in the language of their extension
+    #[clap(long = "language", alias = "lang")]
+    language: Option<PatternLanguage>,
 }
 
 #[derive(Deserialize)]
@@ -30,7 +41,12 @@ pub struct ParseInput {
 
 impl From<ParseInput> for ParseArgs {
     fn from(input: ParseInput) -> Self {
-        Self { paths: input.paths }
+        Self {
+            paths: input.paths,
+            node_types: false,
+            node_patterns: false,
+            language: None,
+        }
     }
 }
 
@@ -39,6 +55,14 @@ pub(crate) async fn run_parse(
     parent: GlobalFormatFlags,
     pattern_body: Option<String>,
 ) -> Result<()> {
+    if arg.node_types {
+        let lang: TargetLanguage = arg.language.unwrap_or_default().try_into()?;
+        return print_items(&node_kinds(&lang), &parent, print_node_kind);
+    }
+    if arg.node_patterns {
+        return run_node_patterns(arg, &parent).await;
+    }
+
     if !parent.jsonl {
         bail!("Only JSONL output is supported for parse command");
     }
@@ -47,9 +71,25 @@ pub(crate) async fn run_parse(
 
     let mut emitter = JSONLineMessenger::new(io::stdout(), OutputMode::default(), visibility);
 
+    for result in parse_paths(pattern_body, arg.paths).await? {
+        emitter.emit(&result)?;
+    }
+
+    Ok(())
+}
+
+/// Parses `paths` into their syntax trees, and the pattern body too if one is given.
+///
+/// `.grit` files are compiled as patterns, while other files are parsed in the
+/// pattern's language. Files which fail to parse are skipped.
+pub(crate) async fn parse_paths(
+    pattern_body: Option<String>,
+    paths: Vec<PathBuf>,
+) -> Result<Vec<MatchResult>> {
+    let mut results = Vec::new();
     let parse_input = ParseInput {
         pattern_body: pattern_body.to_owned().unwrap_or_default(),
-        paths: arg.paths,
+        paths,
     };
 
     // we should be reading the default from a config
@@ -58,8 +98,7 @@ pub(crate) async fn run_parse(
         .try_into()?;
 
     if let Some(body) = pattern_body {
-        let result = parse_one_pattern(body, None).await?;
-        emitter.emit(&result)?;
+        results.push(parse_one_pattern(body, None).await?);
     }
 
     for path in parse_input.paths {
@@ -76,12 +115,99 @@ pub(crate) async fn run_parse(
             };
             MatchResult::InputFile(input_file)
         };
-        emitter.emit(&match_result)?;
+        results.push(match_result);
     }
 
+    Ok(results)
+}
+
+/// A node pattern, along with the file it was found in.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileNodePattern {
+    source_file: String,
+    #[serde(flatten)]
+    pattern: NodePattern,
+}
+
+async fn run_node_patterns(arg: ParseArgs, format: &GlobalFormatFlags) -> Result<()> {
+    let mut patterns = Vec::new();
+    for path in arg.paths {
+        let language = match arg.language {
+            Some(language) => language,
+            None => path
+                .extension()
+                .and_then(|ext| PatternLanguage::from_extension(&ext.to_string_lossy()))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Unable to determine the language of {}, use --language to set it",
+                        path.display()
+                    )
+                })?,
+        };
+        let lang: TargetLanguage = language.try_into()?;
+        let input = fs::read_to_string(&path).await?;
+        let source_file = path.to_string_lossy().to_string();
+        patterns.extend(
+            node_patterns(&lang, &input, &path)?
+                .into_iter()
+                .map(|pattern| FileNodePattern {
+                    source_file: source_file.clone(),
+                    pattern,
+                }),
+        );
+    }
+    print_items(&patterns, format, print_node_pattern)
+}
+
+fn print_items<T: Serialize>(
+    items: &[T],
+    format: &GlobalFormatFlags,
+    print: impl Fn(&T),
+) -> Result<()> {
+    if format.json {
+        println!("{}", serde_json::to_string(items)?);
+    } else if format.jsonl {
+        for item in items {
+            println!("{}", serde_json::to_string(item)?);
+        }
+    } else {
+        items.iter().for_each(print);
+    }
     Ok(())
 }
 
+fn print_node_kind(kind: &NodeKind) {
+    log::info!("{}", kind.name.bold());
+    for field in &kind.fields {
+        let name = if field.multiple {
+            format!("{}[]", field.name)
+        } else {
+            field.name.clone()
+        };
+        let required = if field.required { " (required)" } else { "" };
+        log::info!(
+            "  {}{}: {}",
+            name.blue(),
+            required.dimmed(),
+            field.sorts.join(" | ")
+        );
+    }
+}
+
+fn print_node_pattern(file_pattern: &FileNodePattern) {
+    let FileNodePattern {
+        source_file,
+        pattern,
+    } = file_pattern;
+    let location = format!(
+        "{}:{}:{}",
+        source_file, pattern.range.start.line, pattern.range.start.column
+    );
+    log::info!("{} {}", location.dimmed(), pattern.kind.bold());
+    log::info!("  {}", pattern.pattern);
+}
+
 #[allow(deprecated)]
 async fn parse_one_pattern(body: String, path: Option<&PathBuf>) -> Result<MatchResult> {
     let resolver = GritModuleResolver::new();
@@ -10,6 +10,7 @@ use marzano_util::{base64, url};
 use std::io::prelude::*;
 
 use marzano_gritmodule::searcher::collect_from_file;
+use marzano_language::target_language::PatternLanguage;
 use serde::Serialize;
 
 use crate::resolver;
@@ -34,6 +35,10 @@ pub enum PatternCommands {
     Edit(PatternsEditArgs),
     /// Describe a pattern
     Describe(PatternsDescribeArgs),
+    /// Infer a rewrite pattern from a before and after example
+    Infer(PatternsInferArgs),
+    /// Check patterns for mistakes the compiler accepts silently
+    Lint(PatternsLintArgs),
 }
 
 #[derive(Args, Debug, Serialize, Clone)]
@@ -56,6 +61,29 @@ pub struct PatternsTestArgs {
     /// Enable watch mode on .grit dir
     #[clap(long = "watch")]
     pub watch: bool,
+    /// Report which clauses of each pattern definition were reached by the samples
+    #[clap(long = "coverage")]
+    pub coverage: bool,
+}
+
+#[derive(Args, Debug, Serialize)]
+pub struct PatternsLintArgs {
+    /// Regex of a specific pattern to lint
+    #[clap(long = "filter")]
+    pub filter: Option<String>,
+}
+
+#[derive(Args, Debug, Serialize)]
+pub struct PatternsInferArgs {
+    /// File with the code before the change
+    #[clap(long = "before")]
+    pub before: PathBuf,
+    /// File with the code after the change
+    #[clap(long = "after")]
+    pub after: PathBuf,
+    /// Language of the examples, if it cannot be told from the extension of the before file
+    #[clap(long = "language", alias = "lang")]
+    pub language: Option<PatternLanguage>,
 }
 
 #[derive(Args, Debug, Serialize)]
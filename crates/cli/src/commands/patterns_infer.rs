@@ -0,0 +1,60 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use marzano_core::pattern_inference::infer_rewrite;
+use marzano_language::target_language::{PatternLanguage, TargetLanguage};
+use tokio::fs;
+
+use crate::flags::GlobalFormatFlags;
+
+use super::patterns::PatternsInferArgs;
+
+pub(crate) async fn run_patterns_infer(
+    arg: PatternsInferArgs,
+    parent: GlobalFormatFlags,
+) -> Result<()> {
+    let language = match arg.language {
+        Some(language) => language,
+        None => arg
+            .before
+            .extension()
+            .and_then(|ext| PatternLanguage::from_extension(&ext.to_string_lossy()))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unable to determine the language of {}, use --language to set it",
+                    arg.before.display()
+                )
+            })?,
+    };
+    let lang: TargetLanguage = language.try_into()?;
+    let before = fs::read_to_string(&arg.before).await?;
+    let after = fs::read_to_string(&arg.after).await?;
+
+    let inferred = infer_rewrite(&lang, &arg.before, &before, &after)?;
+
+    if parent.json || parent.jsonl {
+        println!("{}", serde_json::to_string(&inferred)?);
+        return Ok(());
+    }
+    log::info!("{}", inferred.pattern);
+    if inferred.validated {
+        log::info!(
+            "{}",
+            format!(
+                "Verified: the pattern rewrites {} into {}",
+                arg.before.display(),
+                arg.after.display()
+            )
+            .green()
+        );
+    } else {
+        log::warn!(
+            "{}",
+            format!(
+                "The pattern does not reproduce {}, it may need to be adjusted by hand",
+                arg.after.display()
+            )
+            .yellow()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use marzano_core::{
+    api::AnalysisLog,
+    lint::{lint_pattern, LINT_WARNING_LEVEL},
+};
+use marzano_gritmodule::config::DefinitionKind;
+use marzano_language::target_language::{PatternLanguage, TargetLanguage};
+use serde::Serialize;
+
+use crate::flags::GlobalFormatFlags;
+use crate::resolver::{get_grit_files_from_flags_or_cwd, resolve_from_cwd, Source};
+
+use super::patterns::PatternsLintArgs;
+use super::patterns_test::filter_patterns_by_regex;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PatternLint {
+    name: String,
+    source_file: String,
+    logs: Vec<AnalysisLog>,
+}
+
+pub(crate) async fn run_patterns_lint(
+    arg: PatternsLintArgs,
+    flags: GlobalFormatFlags,
+) -> Result<()> {
+    let (mut patterns, _) = resolve_from_cwd(&Source::Local).await?;
+    let libs = get_grit_files_from_flags_or_cwd(&flags).await?;
+
+    if let Some(filter) = &arg.filter {
+        patterns = filter_patterns_by_regex(patterns, filter)?;
+    }
+
+    let mut lints = Vec::new();
+    for pattern in patterns {
+        if !matches!(pattern.kind, DefinitionKind::Pattern) {
+            continue;
+        }
+        let lang = PatternLanguage::get_language(&pattern.body);
+        let chosen_lang = lang.unwrap_or_default();
+        if let PatternLanguage::Universal = chosen_lang {
+            continue;
+        }
+        let pattern_libs = libs.get_language_directory_or_default(lang)?;
+        let target_lang: TargetLanguage = chosen_lang.try_into()?;
+        let logs = match lint_pattern(
+            &pattern.body,
+            &pattern_libs,
+            target_lang,
+            Some(pattern.local_name.clone()),
+        ) {
+            Ok(logs) => logs.logs().into_iter().map(AnalysisLog::from).collect(),
+            Err(e) => vec![AnalysisLog::new_error(
+                format!("Failed to compile: {}", e),
+                &pattern.config.path,
+            )],
+        };
+        lints.push(PatternLint {
+            name: pattern.local_name,
+            source_file: pattern.config.path,
+            logs,
+        });
+    }
+
+    if flags.json {
+        println!("{}", serde_json::to_string(&lints)?);
+    } else if flags.jsonl {
+        for lint in &lints {
+            println!("{}", serde_json::to_string(lint)?);
+        }
+    } else {
+        lints
+            .iter()
+            .filter(|lint| !lint.logs.is_empty())
+            .for_each(print_lint);
+    }
+
+    let findings: usize = lints.iter().map(|lint| lint.logs.len()).sum();
+    if findings > 0 {
+        let patterns = lints.iter().filter(|lint| !lint.logs.is_empty()).count();
+        bail!(
+            "Found {} problems in {} of {} patterns",
+            findings,
+            patterns,
+            lints.len()
+        );
+    }
+    log::info!("No problems found in {} patterns", lints.len());
+    Ok(())
+}
+
+fn print_lint(lint: &PatternLint) {
+    log::info!(
+        "{} {}",
+        lint.name.bold(),
+        format!("({})", lint.source_file).dimmed()
+    );
+    for log in &lint.logs {
+        let position = format!("{}:{}", log.position.line, log.position.column);
+        let message = if log.level == LINT_WARNING_LEVEL {
+            log.message.yellow()
+        } else {
+            log.message.red()
+        };
+        log::info!("  {} {}", position.dimmed(), message);
+    }
+}
@@ -1,5 +1,7 @@
 use colored::Colorize;
-use dashmap::{DashMap, ReadOnlyView};
+use dashmap::{mapref::entry::Entry, DashMap, ReadOnlyView};
+use grit_pattern_matcher::coverage::DefinitionCoverage;
+use grit_util::ClauseCoverage;
 use log::{debug, info};
 
 use marzano_core::analysis::get_dependents_of_target_patterns_by_traversal_from_src;
@@ -24,7 +26,7 @@ use crate::resolver::{
 };
 use crate::result_formatting::FormattedResult;
 use crate::updater::Updater;
-use crate::ux::{indent, log_test_diff};
+use crate::ux::{heading, indent, log_test_diff};
 use marzano_messenger::emit::{get_visibility, VisibilityLevels};
 
 use super::patterns::PatternsTestArgs;
@@ -32,7 +34,7 @@ use super::patterns::PatternsTestArgs;
 use anyhow::{anyhow, bail, Context as _, Result};
 use std::collections::HashMap;
 
-use std::{path::Path, time::Duration};
+use std::{path::Path, sync::Arc, time::Duration};
 
 use marzano_gritmodule::searcher::collect_from_file;
 use notify::{self, RecursiveMode};
@@ -53,6 +55,8 @@ pub async fn get_marzano_pattern_test_results(
 
     let final_results: DashMap<String, Vec<WrappedResult>> = DashMap::new();
     let unformatted_results: DashMap<PatternLanguage, Vec<WrappedResult>> = DashMap::new();
+    let coverage_reports: DashMap<String, DefinitionCoverage> = DashMap::new();
+    let pattern_coverage: DashMap<String, Vec<DefinitionCoverage>> = DashMap::new();
 
     let runtime = Updater::from_current_bin().await?.get_context()?;
 
@@ -82,6 +86,13 @@ pub async fn get_marzano_pattern_test_results(
                         .unwrap_or(format!("<unknown pattern {}>", index));
 
                     if let Some(samples) = &pattern.config.samples {
+                        let coverage = args.coverage.then(|| Arc::new(ClauseCoverage::new()));
+                        let runtime = match &coverage {
+                            Some(coverage) => {
+                                runtime.clone().with_clause_coverage(coverage.clone())
+                            }
+                            None => runtime.clone(),
+                        };
                         let mut results = Vec::with_capacity(samples.len());
                         for sample in samples {
                             let result = test_pattern_sample(&compiled, sample, runtime.clone());
@@ -110,6 +121,13 @@ pub async fn get_marzano_pattern_test_results(
                                 results.push(wrapped);
                             }
                         }
+                        if let Some(coverage) = &coverage {
+                            let reports = compiled.coverage_report(coverage);
+                            for report in reports.iter() {
+                                merge_coverage_report(&coverage_reports, report.clone());
+                            }
+                            pattern_coverage.insert(pattern_name.clone(), reports);
+                        }
                         final_results.insert(pattern_name, results);
                     }
                     Ok(None)
@@ -120,6 +138,7 @@ pub async fn get_marzano_pattern_test_results(
                             outcome: TestOutcome::CompilationFailure,
                             message: Some(e.to_string()),
                             samples: vec![],
+                            coverage: None,
                         };
                         return Ok(Some(report));
                     }
@@ -204,6 +223,9 @@ pub async fn get_marzano_pattern_test_results(
 
     let final_results = final_results.into_read_only();
     log_test_results(&final_results, args.verbose)?;
+    if args.coverage && output == OutputFormat::Standard {
+        log_coverage_reports(coverage_reports)?;
+    }
     let total = final_results.values().flatten().count();
     match output {
         OutputFormat::Standard => {
@@ -226,8 +248,8 @@ pub async fn get_marzano_pattern_test_results(
         OutputFormat::Json => {
             // Collect the test reports
             let mut sample_results = final_results
-                .values()
-                .map(|r| {
+                .iter()
+                .map(|(name, r)| {
                     let all_pass = r.iter().all(|r| r.result.is_pass());
                     TestReport {
                         outcome: if all_pass {
@@ -241,6 +263,9 @@ pub async fn get_marzano_pattern_test_results(
                             Some("One or more samples failed".to_string())
                         },
                         samples: r.iter().map(|r| r.result.clone()).collect(),
+                        coverage: pattern_coverage
+                            .get(name)
+                            .map(|reports| reports.iter().map(CoverageReport::from).collect()),
                     }
                 })
                 .collect::<Vec<_>>();
@@ -558,6 +583,9 @@ struct TestReport {
     message: Option<String>,
     /// Sample test details
     samples: Vec<SampleTestResult>,
+    /// Clause coverage of the pattern and the definitions it uses, with `--coverage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coverage: Option<Vec<CoverageReport>>,
 }
 
 #[derive(Debug)]
@@ -703,3 +731,78 @@ fn log_test_results(
 
     Ok(())
 }
+
+/// Definitions are compiled into every pattern that uses them, so a clause
+/// counts as reached if any pattern's samples reached it.
+fn merge_coverage_report(
+    reports: &DashMap<String, DefinitionCoverage>,
+    report: DefinitionCoverage,
+) {
+    match reports.entry(report.name.clone()) {
+        Entry::Occupied(mut existing) => {
+            for (clause, other) in existing.get_mut().clauses.iter_mut().zip(report.clauses) {
+                clause.reached |= other.reached;
+            }
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(report);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CoverageReport {
+    name: String,
+    reached: usize,
+    total: usize,
+    unreached: Vec<String>,
+}
+
+impl From<&DefinitionCoverage> for CoverageReport {
+    fn from(report: &DefinitionCoverage) -> Self {
+        Self {
+            name: report.name.clone(),
+            reached: report.reached_count(),
+            total: report.clauses.len(),
+            unreached: report
+                .clauses
+                .iter()
+                .filter(|c| !c.reached)
+                .map(|c| c.description.clone())
+                .collect(),
+        }
+    }
+}
+
+fn log_coverage_reports(reports: DashMap<String, DefinitionCoverage>) -> Result<()> {
+    let mut reports = reports.into_iter().map(|(_, r)| r).collect::<Vec<_>>();
+    reports.sort_by(|a, b| a.name.cmp(&b.name));
+
+    info!("{}", heading("Clause coverage"));
+    for report in reports.iter() {
+        let reached = report.reached_count();
+        let total = report.clauses.len();
+        if reached == total {
+            info!(
+                "{} {} - {}/{} clauses reached",
+                '✓', report.name, reached, total
+            );
+            continue;
+        }
+        info!(
+            "{} {} - {}/{} clauses reached",
+            '✗', report.name, reached, total
+        );
+        for clause in report.clauses.iter().filter(|c| !c.reached) {
+            info!(
+                "{}",
+                indent(&format!("never reached: {}", clause.description), 4).bright_red()
+            );
+        }
+    }
+    let reached = reports.iter().map(|r| r.reached_count()).sum::<usize>();
+    let total = reports.iter().map(|r| r.clauses.len()).sum::<usize>();
+    info!("{} of {} clauses reached by samples.", reached, total);
+    Ok(())
+}
@@ -283,6 +283,7 @@ pub(crate) async fn run_plumbing(
                     watch: false,
                     filter: None,
                     exclude: vec![],
+                    coverage: false,
                 },
                 parent.into(),
             )
@@ -0,0 +1,779 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use marzano_core::{
+    api::{is_match, AnalysisLog, EnforcementLevel, MatchResult},
+    problem::{CombinedProblems, Problem},
+    transaction::Transaction,
+};
+use marzano_gritmodule::{
+    config::ResolvedGritDefinition, patterns_directory::PatternsDirectory,
+    searcher::find_journal_dir, utils::infer_pattern,
+};
+use marzano_language::target_language::{expand_paths, PatternLanguage};
+use marzano_messenger::format::format_result;
+use marzano_util::{finder::get_input_files, rich_path::RichPath, runtime::ExecutionContext};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::{watch, RwLock},
+    task::spawn_blocking,
+    try_join,
+};
+
+use crate::{
+    resolver::{get_grit_files_from, resolve_from, GritModuleResolver, Source},
+    updater::Updater,
+};
+
+use super::parse::parse_paths;
+
+#[derive(Args, Debug, Serialize)]
+#[clap(group(clap::ArgGroup::new("transport").required(true).args(["stdio", "socket"])))]
+pub struct ServeArgs {
+    /// Read requests from stdin and write responses to stdout
+    #[clap(long = "stdio")]
+    stdio: bool,
+    /// Listen for connections on a Unix socket at this path instead of stdio
+    #[clap(long = "socket")]
+    socket: Option<PathBuf>,
+}
+
+// Error codes defined by the JSON-RPC 2.0 specification
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(INTERNAL_ERROR, format!("{:#}", e))
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::new(INTERNAL_ERROR, e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApplyParams {
+    pattern: String,
+    /// Defaults to the root
+    #[serde(default)]
+    paths: Vec<PathBuf>,
+    root: Option<PathBuf>,
+    /// Return the rewrites without writing them to disk
+    #[serde(default)]
+    dry_run: bool,
+    /// Format the rewritten files, like `grit apply --format`
+    #[serde(default)]
+    format: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckParams {
+    /// Defaults to the root
+    #[serde(default)]
+    paths: Vec<PathBuf>,
+    root: Option<PathBuf>,
+    level: Option<EnforcementLevel>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParseParams {
+    pattern: Option<String>,
+    #[serde(default)]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListParams {
+    root: Option<PathBuf>,
+}
+
+/// A match found by `check`, along with the enforced pattern which found it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckMatch {
+    pattern: String,
+    level: EnforcementLevel,
+    result: MatchResult,
+}
+
+/// The patterns available from a root directory, resolved once per root.
+struct Modules {
+    definitions: Vec<ResolvedGritDefinition>,
+    grit_files: PatternsDirectory,
+}
+
+struct CheckPattern {
+    name: String,
+    level: EnforcementLevel,
+    problem: Problem,
+}
+
+/// Everything that is expensive to compute between requests: the modules
+/// resolved for each root, and the patterns compiled from them.
+#[derive(Default)]
+struct Caches {
+    modules: HashMap<PathBuf, Arc<Modules>>,
+    problems: HashMap<(PathBuf, Option<PatternLanguage>, String), Arc<Problem>>,
+    checks: HashMap<(PathBuf, EnforcementLevel), Arc<Vec<CheckPattern>>>,
+}
+
+/// Server answers requests, sharing its caches between all of its clones, so
+/// every connection can be served concurrently.
+///
+/// Nothing is invalidated automatically, clients send `reload` after changing
+/// `.grit` files.
+#[derive(Clone)]
+struct Server {
+    context: ExecutionContext,
+    default_root: PathBuf,
+    caches: Arc<RwLock<Caches>>,
+    shutdown: Arc<watch::Sender<bool>>,
+}
+
+impl Server {
+    fn new(context: ExecutionContext, default_root: PathBuf) -> Self {
+        Self {
+            context,
+            default_root,
+            caches: Arc::default(),
+            shutdown: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    fn is_shut_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Handles one line of input, returning the response to send unless the
+    /// line was a notification.
+    async fn handle_line(&self, line: &str) -> Option<Response> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, e.to_string());
+                return Some(Response::new(Value::Null, Err(error)));
+            }
+        };
+        let id = message.get("id").cloned().unwrap_or_default();
+        let request: Request = match serde_json::from_value(message) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            Ok(_) => {
+                let error = RpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported");
+                return Some(Response::new(id, Err(error)));
+            }
+            Err(e) => {
+                let error = RpcError::new(INVALID_REQUEST, e.to_string());
+                return Some(Response::new(id, Err(error)));
+            }
+        };
+        let outcome = self.handle(&request.method, request.params).await;
+        request.id.map(|id| Response::new(id, outcome))
+    }
+
+    async fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let result = match method {
+            "apply" => serde_json::to_value(self.apply(parse_params(params)?).await?)?,
+            "check" => serde_json::to_value(self.check(parse_params(params)?).await?)?,
+            "parse" => {
+                let params: ParseParams = parse_params(params)?;
+                serde_json::to_value(parse_paths(params.pattern, params.paths).await?)?
+            }
+            "list" => {
+                let params: ListParams = parse_params(params)?;
+                let root = self.root(params.root);
+                serde_json::to_value(&self.modules(&root).await?.definitions)?
+            }
+            "reload" => {
+                *self.caches.write().await = Caches::default();
+                Value::Null
+            }
+            "shutdown" => {
+                self.shutdown.send_replace(true);
+                Value::Null
+            }
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("Unknown method: {}", method),
+                ))
+            }
+        };
+        Ok(result)
+    }
+
+    fn root(&self, root: Option<PathBuf>) -> PathBuf {
+        match root {
+            Some(root) => self.default_root.join(root),
+            None => self.default_root.clone(),
+        }
+    }
+
+    async fn modules(&self, root: &Path) -> Result<Arc<Modules>> {
+        if let Some(modules) = self.caches.read().await.modules.get(root) {
+            return Ok(modules.clone());
+        }
+        let ((definitions, _), mut grit_files, global_files) = try_join![
+            resolve_from(root.to_path_buf(), &Source::All),
+            get_grit_files_from(Some(root.to_path_buf())),
+            get_grit_files_from(None),
+        ]?;
+        grit_files.merge(global_files);
+        let modules = Arc::new(Modules {
+            definitions,
+            grit_files,
+        });
+        // Another request may have resolved the same root in the meantime
+        let mut caches = self.caches.write().await;
+        Ok(caches
+            .modules
+            .entry(root.to_path_buf())
+            .or_insert(modules)
+            .clone())
+    }
+
+    /// Compiles `pattern` against the modules of `root`, or returns the log
+    /// explaining why it does not compile.
+    async fn compile(
+        &self,
+        root: &Path,
+        pattern: &str,
+    ) -> Result<Result<Arc<Problem>, AnalysisLog>> {
+        let modules = self.modules(root).await?;
+        let (lang, name, body) = infer_pattern(pattern, &modules.grit_files);
+        let key = (root.to_path_buf(), lang, body);
+        if let Some(problem) = self.caches.read().await.problems.get(&key) {
+            return Ok(Ok(problem.clone()));
+        }
+        let pattern_libs = modules.grit_files.get_language_directory_or_default(lang)?;
+        let resolver = GritModuleResolver::new();
+        let compiled = resolver
+            .make_pattern(&key.2, name.map(str::to_string))
+            .and_then(|pattern| pattern.compile(&pattern_libs, lang, None, None));
+        let problem = match compiled {
+            Ok(compiled) => Arc::new(compiled.problem),
+            Err(e) => {
+                return Ok(Err(match e.downcast::<grit_util::AnalysisLog>() {
+                    Ok(log) => AnalysisLog::from(log),
+                    Err(e) => AnalysisLog::floating_error(e.to_string()),
+                }))
+            }
+        };
+        let mut caches = self.caches.write().await;
+        Ok(Ok(caches.problems.entry(key).or_insert(problem).clone()))
+    }
+
+    async fn apply(&self, params: ApplyParams) -> Result<Vec<MatchResult>> {
+        let root = self.root(params.root);
+        let problem = match self.compile(&root, &params.pattern).await? {
+            Ok(problem) => problem,
+            Err(log) => return Ok(vec![MatchResult::AnalysisLog(log)]),
+        };
+        let paths = absolute_paths(&root, &params.paths);
+        let journal_dir = find_journal_dir(root).await;
+        let (dry_run, format) = (params.dry_run, params.format);
+        let context = self.context.clone();
+
+        // Execution and writing the results block, so they must not hold up other connections
+        spawn_blocking(move || -> Result<Vec<MatchResult>> {
+            let files = find_files(&paths, (&problem.language).into())?;
+            let (results, _) = problem.execute_paths(files.iter().collect(), &context);
+            if !dry_run {
+                let mut transaction = Transaction::new();
+                for result in &results {
+                    transaction.stage(result)?;
+                }
+                transaction.commit(&journal_dir)?;
+                if format {
+                    for result in &results {
+                        format_result(result.clone())?;
+                    }
+                }
+            }
+            Ok(results)
+        })
+        .await?
+    }
+
+    async fn check(&self, params: CheckParams) -> Result<Vec<CheckMatch>> {
+        let root = self.root(params.root);
+        let level = params.level.unwrap_or(EnforcementLevel::Warn);
+        let patterns = self.check_patterns(&root, level).await?;
+        let paths = absolute_paths(&root, &params.paths);
+        let context = self.context.clone();
+        spawn_blocking(move || run_checks(&patterns, &paths, &context)).await?
+    }
+
+    /// Compiles the patterns enforced in `root` at or above `level`, like `grit check`.
+    async fn check_patterns(
+        &self,
+        root: &Path,
+        level: EnforcementLevel,
+    ) -> Result<Arc<Vec<CheckPattern>>> {
+        let key = (root.to_path_buf(), level);
+        if let Some(patterns) = self.caches.read().await.checks.get(&key) {
+            return Ok(patterns.clone());
+        }
+        let modules = self.modules(root).await?;
+        let resolver = GritModuleResolver::new();
+        let mut patterns = Vec::new();
+        for definition in modules
+            .definitions
+            .iter()
+            .filter(|p| p.level() >= key.1 && !matches!(p.language, PatternLanguage::Universal))
+        {
+            let body = format!("{}()", definition.local_name);
+            let lang = PatternLanguage::get_language(&definition.body);
+            let pattern_libs = modules.grit_files.get_language_directory_or_default(lang)?;
+            let compiled = resolver
+                .make_pattern(&body, Some(definition.local_name.to_string()))?
+                .compile(&pattern_libs, lang, None, None);
+            match compiled {
+                Ok(compiled) => patterns.push(CheckPattern {
+                    name: definition.local_name.clone(),
+                    level: definition.level(),
+                    problem: compiled.problem,
+                }),
+                Err(e) => bail!(
+                    "Unable to compile pattern {}:\n{}",
+                    definition.local_name,
+                    e
+                ),
+            }
+        }
+        let mut caches = self.caches.write().await;
+        Ok(caches
+            .checks
+            .entry(key)
+            .or_insert_with(|| Arc::new(patterns))
+            .clone())
+    }
+}
+
+/// Runs the enforced `patterns` on `paths`, executing the single-file patterns
+/// of each language together.
+fn run_checks(
+    patterns: &[CheckPattern],
+    paths: &[PathBuf],
+    context: &ExecutionContext,
+) -> Result<Vec<CheckMatch>> {
+    let mut by_hash = HashMap::new();
+    let mut combined_by_language: HashMap<PatternLanguage, Vec<&Problem>> = HashMap::new();
+    let mut multifile_problems = Vec::new();
+    for pattern in patterns.iter() {
+        by_hash.insert(pattern.problem.hash, pattern);
+        if pattern.problem.is_multifile {
+            multifile_problems.push(&pattern.problem);
+        } else {
+            combined_by_language
+                .entry((&pattern.problem.language).into())
+                .or_default()
+                .push(&pattern.problem);
+        }
+    }
+
+    let mut matches = Vec::new();
+    let mut push_matches = |hash: &[u8; 32], results: Vec<MatchResult>| {
+        let Some(pattern) = by_hash.get(hash) else {
+            return;
+        };
+        matches.extend(
+            results
+                .into_iter()
+                .filter(is_match)
+                .map(|result| CheckMatch {
+                    pattern: pattern.name.clone(),
+                    level: pattern.level.clone(),
+                    result,
+                }),
+        );
+    };
+    for (language, problems) in combined_by_language {
+        let files = find_files(paths, language)?;
+        let combined = CombinedProblems::new(problems)?;
+        for tagged in combined.execute_paths(files.iter().collect(), context) {
+            push_matches(&tagged.pattern_hash, vec![tagged.result]);
+        }
+    }
+    for problem in multifile_problems {
+        let files = find_files(paths, (&problem.language).into())?;
+        let (results, _) = problem.execute_paths(files.iter().collect(), context);
+        push_matches(&problem.hash, results);
+    }
+    Ok(matches)
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    // Methods whose parameters are all optional may be called without any
+    let params = match params {
+        Value::Null => Value::Object(Default::default()),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn absolute_paths(root: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+    if paths.is_empty() {
+        return vec![root.to_path_buf()];
+    }
+    paths.iter().map(|path| root.join(path)).collect()
+}
+
+fn find_files(paths: &[PathBuf], language: PatternLanguage) -> Result<Vec<RichPath>> {
+    let mut language_paths = Vec::new();
+    for file in expand_paths(paths, Some(&[language]))? {
+        let file = file?;
+        if file.file_type().is_some_and(|t| t.is_dir()) {
+            continue;
+        }
+        language_paths.push(file.path().to_path_buf());
+    }
+    Ok(get_input_files(&language_paths))
+}
+
+/// Answers newline-delimited JSON-RPC requests from `reader` until it is
+/// closed or a `shutdown` request is received, on any connection.
+async fn serve<R, W>(server: &Server, reader: R, mut writer: W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut shutdown = server.shutdown.subscribe();
+    let mut lines = reader.lines();
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line?,
+            _ = shutdown.wait_for(|shutdown| *shutdown) => break,
+        };
+        let Some(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_line(&line).await {
+            let mut message = serde_json::to_vec(&response)?;
+            message.push(b'\n');
+            writer.write_all(&message).await?;
+            writer.flush().await?;
+        }
+        if server.is_shut_down() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn serve_socket(server: &Server, path: &Path) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    use tokio::net::UnixListener;
+
+    // A socket left behind by a server which did not shut down cleanly can be reused
+    if let Ok(metadata) = fs_err::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            bail!("{} already exists and is not a socket", path.display());
+        }
+        fs_err::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let mut shutdown = server.shutdown.subscribe();
+    // Every connection gets its own task, so an idle client does not hold up the others
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => accepted?.0,
+            _ = shutdown.wait_for(|shutdown| *shutdown) => break,
+        };
+        let server = server.clone();
+        tokio::spawn(async move {
+            let (reader, writer) = stream.into_split();
+            if let Err(e) = serve(&server, BufReader::new(reader), writer).await {
+                log::info!("Connection closed with an error: {:#}", e);
+            }
+        });
+    }
+    fs_err::remove_file(path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn serve_socket(_server: &Server, _path: &Path) -> Result<()> {
+    bail!("--socket is only supported on Unix, use --stdio instead")
+}
+
+pub(crate) async fn run_serve(arg: ServeArgs) -> Result<()> {
+    let context = Updater::from_current_bin().await?.get_context()?;
+    let server = Server::new(context, std::env::current_dir()?);
+    match &arg.socket {
+        Some(path) => serve_socket(&server, path).await,
+        None => {
+            serve(
+                &server,
+                BufReader::new(tokio::io::stdin()),
+                tokio::io::stdout(),
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn server() -> Server {
+        Server::new(ExecutionContext::default(), std::env::temp_dir())
+    }
+
+    async fn respond(server: &Server, line: &str) -> Value {
+        let response = server.handle_line(line).await.unwrap();
+        serde_json::to_value(response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reports_protocol_errors() {
+        let server = server();
+        let response = respond(&server, "{not json").await;
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = respond(&server, r#"{"jsonrpc":"1.0","id":1,"method":"list"}"#).await;
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        assert_eq!(response["id"], 1);
+
+        let response = respond(&server, r#"{"jsonrpc":"2.0","id":2,"method":"explode"}"#).await;
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response = respond(
+            &server,
+            r#"{"jsonrpc":"2.0","id":3,"method":"apply","params":{"paths":[]}}"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn answers_requests_until_shutdown() {
+        let server = server();
+        let input = [
+            r#"{"jsonrpc":"2.0","method":"reload"}"#,
+            r#"{"jsonrpc":"2.0","id":"a","method":"reload"}"#,
+            r#"{"jsonrpc":"2.0","id":"b","method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","id":"c","method":"reload"}"#,
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+        serve(&server, input.as_bytes(), &mut output).await.unwrap();
+
+        // The notification is not answered, and nothing is read after the shutdown
+        let responses: Vec<Value> = output
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], "a");
+        assert_eq!(responses[0]["result"], Value::Null);
+        assert_eq!(responses[1]["id"], "b");
+        assert!(server.is_shut_down());
+    }
+
+    fn request(id: u32, method: &str, params: Value) -> String {
+        serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn applies_patterns_with_the_cached_compilation() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("index.js");
+        fs_err::write(&file, "console.log('hello');\n").unwrap();
+        let server = Server::new(ExecutionContext::default(), dir.path().to_path_buf());
+        let pattern = "`console.log($msg)` => `console.warn($msg)`";
+
+        let dry_run = request(
+            1,
+            "apply",
+            serde_json::json!({"pattern": pattern, "dryRun": true}),
+        );
+        let rewrites = |response: &Value| {
+            response["result"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|result| result["__typename"] == "Rewrite")
+                .count()
+        };
+        let response = respond(&server, &dry_run).await;
+        assert_eq!(rewrites(&response), 1);
+        assert_eq!(
+            fs_err::read_to_string(&file).unwrap(),
+            "console.log('hello');\n"
+        );
+        let compiled = server.caches.read().await.problems.values().next().cloned();
+
+        // The second request reuses the pattern compiled for the first one
+        let response = respond(&server, &dry_run).await;
+        assert_eq!(rewrites(&response), 1);
+        let caches = server.caches.read().await;
+        assert_eq!(caches.problems.len(), 1);
+        assert!(Arc::ptr_eq(
+            &compiled.unwrap(),
+            caches.problems.values().next().unwrap()
+        ));
+        drop(caches);
+
+        let apply = request(2, "apply", serde_json::json!({"pattern": pattern}));
+        let response = respond(&server, &apply).await;
+        assert!(response["error"].is_null());
+        assert_eq!(
+            fs_err::read_to_string(&file).unwrap(),
+            "console.warn('hello');\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn checks_enforced_patterns() {
+        let dir = tempdir().unwrap();
+        fs_err::create_dir(dir.path().join(".grit")).unwrap();
+        fs_err::write(
+            dir.path().join(".grit/grit.yaml"),
+            r#"version: 0.0.1
+patterns:
+  - name: no_console
+    level: error
+    body: |
+      `console.log($msg)`
+"#,
+        )
+        .unwrap();
+        fs_err::write(dir.path().join("index.js"), "console.log('hello');\n").unwrap();
+        let server = Server::new(ExecutionContext::default(), dir.path().to_path_buf());
+
+        let check = request(1, "check", serde_json::json!({}));
+        for _ in 0..2 {
+            let response = respond(&server, &check).await;
+            let matches = response["result"].as_array().unwrap();
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0]["pattern"], "no_console");
+            assert_eq!(matches[0]["result"]["__typename"], "Match");
+        }
+        assert_eq!(server.caches.read().await.checks.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn serves_connections_concurrently() {
+        use std::time::Duration;
+        use tokio::{net::UnixStream, time::timeout};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("grit.sock");
+        let server = server();
+        let serving = tokio::spawn({
+            let server = server.clone();
+            let path = path.clone();
+            async move { serve_socket(&server, &path).await }
+        });
+        let connect = || async {
+            loop {
+                match UnixStream::connect(&path).await {
+                    Ok(stream) => return stream,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        };
+
+        // The first client stays connected without sending anything
+        let _idle = timeout(Duration::from_secs(5), connect()).await.unwrap();
+        let (reader, mut writer) = timeout(Duration::from_secs(5), connect())
+            .await
+            .unwrap()
+            .into_split();
+        let mut lines = BufReader::new(reader).lines();
+        for (id, method) in [(1, "reload"), (2, "shutdown")] {
+            let line = request(id, method, Value::Null) + "\n";
+            writer.write_all(line.as_bytes()).await.unwrap();
+            let response = timeout(Duration::from_secs(5), lines.next_line())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            let response: Value = serde_json::from_str(&response).unwrap();
+            assert_eq!(response["id"], id);
+            assert_eq!(response["result"], Value::Null);
+        }
+
+        timeout(Duration::from_secs(5), serving)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(!path.exists());
+    }
+}
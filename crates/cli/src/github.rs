@@ -63,7 +63,16 @@ pub fn log_check_annotations(check_results: &Vec<&CheckResult<'_>>) {
                     print_one(file, None, message, title, &level);
                 } else {
                     for range in ranges {
-                        print_one(file, Some(*range), message, title, &level);
+                        match pattern.message(result, range) {
+                            Some(rendered) => print_one(
+                                file,
+                                Some(*range),
+                                &rendered,
+                                Some(pattern.name()),
+                                &level,
+                            ),
+                            None => print_one(file, Some(*range), message, title, &level),
+                        }
                     }
                 }
             }
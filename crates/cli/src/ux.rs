@@ -4,7 +4,7 @@ use core::fmt;
 use grit_util::{Position, Range};
 use log::info;
 use marzano_core::{
-    api::{EnforcementLevel, MatchResult},
+    api::{EnforcementLevel, FixSafety, MatchResult},
     fs::extract_ranges,
 };
 use marzano_gritmodule::{config::ResolvedGritDefinition, testing::SampleTestResult};
@@ -107,25 +107,41 @@ pub struct CheckResult<'a> {
     pub result: MatchResult,
 }
 
-fn log_check_result(range: &Range, result: &CheckResult, fix: bool) {
+fn log_check_result(range: &Range, result: &CheckResult, fix: Option<&FixSafety>) {
     let location = format!("{}:{}", range.start.line, range.start.column);
     let kind = match result.result {
         MatchResult::Rewrite(_) => "rewrite",
         _ => "match",
     };
 
-    let fix_text = if fix {
+    let alternative_fixes: Vec<_> = match &result.result {
+        MatchResult::Match(m) => m.fixes.iter().filter(|f| &f.range == range).collect(),
+        _ => vec![],
+    };
+    let has_fix = kind == "rewrite" || alternative_fixes.iter().any(|f| f.preferred);
+
+    let safety = result.pattern.fix_safety();
+    let fix_text = if has_fix && fix.is_some_and(|max_safety| safety <= *max_safety) {
         "Fixed! ✓    ".to_string().green()
-    } else {
+    } else if alternative_fixes.len() > 1 {
+        format!("{} fixes available.    ", alternative_fixes.len()).blue()
+    } else if safety == FixSafety::Safe {
         "Fix available.    ".to_string().blue()
+    } else {
+        format!("Fix available ({}).    ", safety).yellow()
     };
 
+    let message = result.pattern.message(&result.result, range);
     info!(
         "  {}    {}    {}    {}{}",
         location.dimmed(),
         kind.red(),
-        result.pattern.description().map(|s| s.trim()).unwrap_or(""),
-        if kind == "rewrite" {
+        message
+            .as_deref()
+            .or(result.pattern.description())
+            .map(|s| s.trim())
+            .unwrap_or(""),
+        if kind == "rewrite" || !alternative_fixes.is_empty() {
             fix_text.to_string()
         } else {
             "".to_string()
@@ -134,7 +150,7 @@ fn log_check_result(range: &Range, result: &CheckResult, fix: bool) {
     );
 }
 
-pub fn log_file(file: &str, results: &[CheckResult], fix: bool) {
+pub fn log_file(file: &str, results: &[CheckResult], fix: Option<&FixSafety>) {
     if results.is_empty() {
         return;
     }
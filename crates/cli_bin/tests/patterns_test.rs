@@ -326,6 +326,31 @@ fn tests_match_only_with_file_name() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn includes_coverage_in_json_report() -> Result<()> {
+    let (_temp_dir, dir) = get_fixture("match_filename", false)?;
+
+    let mut test = get_test_cmd()?;
+    test.arg("patterns")
+        .arg("test")
+        .arg("--json")
+        .arg("--coverage")
+        .current_dir(dir);
+
+    let output = test.output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let reports: Vec<serde_json::Value> = serde_json::from_str(stdout.trim())?;
+    assert_eq!(reports.len(), 1);
+    let coverage = reports[0]["coverage"]
+        .as_array()
+        .expect("report should include coverage");
+    assert!(!coverage.is_empty());
+
+    Ok(())
+}
+
 #[test]
 fn tests_python_pattern_with_file_name() -> Result<()> {
     let (_temp_dir, dir) = get_fixture("python_filename", false)?;
@@ -461,6 +461,31 @@ mod tests {
         assert!(has_rewrite(&problem.pattern, &problem.definitions()));
     }
 
+    #[test]
+    fn test_is_not_rewrite_with_recursive_pattern() {
+        let pattern_src = r#"
+            pattern nested_call() {
+                `$_($args)` where { $args <: contains nested_call() }
+            }
+            nested_call()
+        "#
+        .to_string();
+        let libs = BTreeMap::new();
+        let problem = src_to_problem_libs(
+            pattern_src.to_string(),
+            &libs,
+            TargetLanguage::default(),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+        .problem;
+
+        assert!(!has_rewrite(&problem.pattern, &problem.definitions()));
+    }
+
     #[test]
     fn test_is_rewrite_with_predicate() {
         let pattern_src = r#"
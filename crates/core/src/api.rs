@@ -254,6 +254,41 @@ pub fn make_suppress_comment<'a>(
     }
 }
 
+/// Renders a diagnostic message template for the match site at `range`, replacing each
+/// `$name` with the source text the variable was bound to within that site.
+/// Variables which were not bound within the site are left as written.
+pub fn render_message_template(template: &str, result: &MatchResult, range: &Range) -> String {
+    let (variables, source) = match result {
+        MatchResult::Match(m) => (&m.variables, m.content().ok()),
+        MatchResult::Rewrite(r) => (&r.original.variables, r.original.source()),
+        MatchResult::RemoveFile(r) => (&r.original.variables, r.original.source()),
+        _ => return template.to_string(),
+    };
+    let Some(source) = source else {
+        return template.to_string();
+    };
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('$') {
+        rendered.push_str(&rest[..start]);
+        let name_end = rest[start + 1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .map_or(rest.len(), |len| start + 1 + len);
+        let name = &rest[start..name_end];
+        let value = variables
+            .iter()
+            .filter(|v| v.name == name)
+            .flat_map(|v| v.ranges.iter())
+            .find(|r| r.start_byte >= range.start_byte && r.end_byte <= range.end_byte)
+            .and_then(|r| source.get(r.start_byte as usize..r.end_byte as usize));
+        rendered.push_str(value.unwrap_or(name));
+        rest = &rest[name_end..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
 fn split_string_at_indices(s: &str, indices: Vec<usize>) -> Vec<&str> {
     let mut result = Vec::new();
     let mut prev = 0;
@@ -362,6 +397,13 @@ pub struct Match {
     pub reason: Option<MatchReason>,
     #[serde(default)]
     pub id: Uuid,
+    /// Labeled alternative fixes for the match, for the user to choose from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<AlternativeFix>,
+    /// Paths along which values flowed from a source into a sink, each from
+    /// the source to the sink
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flows: Vec<Vec<Range>>,
     /// Parsed content of the file, if a source map was used
     #[serde(skip)]
     inner_content: Option<String>,
@@ -377,11 +419,34 @@ impl From<EntireFile> for Match {
             reason: None,
             content: file_match.content,
             id: Uuid::new_v4(),
+            fixes: vec![],
+            flows: file_match.flows,
             inner_content: file_match.inner_content,
         }
     }
 }
 
+/// One of several labeled fixes which can be applied to a single range of a match
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct AlternativeFix {
+    pub label: String,
+    /// Preferred fixes are applied without asking, such as by `grit check --fix`
+    pub preferred: bool,
+    /// The range of the match this fix applies to
+    pub range: Range,
+    /// The edits the fix makes around the range, in the original file
+    pub edits: Vec<FixEdit>,
+}
+
+/// Replaces a byte range of the original file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct FixEdit {
+    pub range: ByteRange,
+    pub replacement: String,
+}
+
 impl From<Match> for EntireFile {
     fn from(file_match: Match) -> Self {
         Self {
@@ -392,6 +457,7 @@ impl From<Match> for EntireFile {
             // TODO: fix this or drop byte_ranges entirely
             byte_ranges: None,
             content: file_match.content,
+            flows: file_match.flows,
             inner_content: file_match.inner_content,
         }
     }
@@ -436,12 +502,20 @@ pub struct EntireFile {
     pub byte_ranges: Option<Vec<ByteRange>>,
     #[serde(default)]
     pub ranges: Vec<Range>,
+    /// Paths along which values flowed from a source into a sink
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flows: Vec<Vec<Range>>,
     /// Inner (parsed) content of the file, if a source map was used
     #[serde(skip)]
     inner_content: Option<String>,
 }
 
 impl EntireFile {
+    /// The source the ranges refer to, which is the inner content if a source map was used
+    fn source(&self) -> Option<&str> {
+        self.inner_content.as_deref().or(self.content.as_deref())
+    }
+
     /// Create an entire file for cases where we don't really have an original file to reference
     /// When working with rewrites, `from_file` should be used instead
     fn file_to_entire_file(name: &str, body: &str, byte_range: Option<&Vec<ByteRange>>) -> Self {
@@ -452,6 +526,7 @@ impl EntireFile {
             messages: vec![],
             byte_ranges: byte_range.map(|r| r.to_owned()),
             ranges: vec![],
+            flows: vec![],
             inner_content: None,
         }
     }
@@ -479,6 +554,7 @@ impl EntireFile {
         if let Some(input_ranges) = file.matches.borrow().input_matches.as_ref() {
             basic.ranges = input_ranges.ranges.clone();
             basic.variables = input_ranges.variables.clone();
+            basic.flows = input_ranges.flows.clone();
         };
         Ok(basic)
     }
@@ -589,6 +665,43 @@ impl fmt::Display for EnforcementLevel {
     }
 }
 
+/// How safe it is to apply a pattern's rewrites without reviewing them
+#[derive(
+    Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, PartialOrd, Eq, Ord, Hash,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum FixSafety {
+    /// Preserves behavior, so it can be applied in bulk
+    #[default]
+    Safe = 0,
+    /// Likely what the author wants, but should be reviewed
+    Suggestion = 1,
+    /// May change behavior
+    Unsafe = 2,
+}
+
+impl FromStr for FixSafety {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "safe" => Ok(FixSafety::Safe),
+            "suggestion" => Ok(FixSafety::Suggestion),
+            "unsafe" => Ok(FixSafety::Unsafe),
+            _ => bail!("'{}' is not a valid fix safety", s),
+        }
+    }
+}
+
+impl fmt::Display for FixSafety {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixSafety::Safe => write!(f, "safe"),
+            FixSafety::Suggestion => write!(f, "suggestion"),
+            FixSafety::Unsafe => write!(f, "unsafe"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum RewriteSource {
@@ -856,6 +969,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_render_message_template() {
+        let source = "const a = oldApi(1);\nconst b = oldApi(2);\n";
+        let site = |start: u32, end: u32| {
+            Range::new(
+                Position::new(1, start + 1),
+                Position::new(1, end + 1),
+                start,
+                end,
+            )
+        };
+        let mut result = Match::from(EntireFile::file_to_entire_file("test.js", source, None));
+        result.variables = vec![VariableMatch::new(
+            "$old".to_string(),
+            "0_1_$old".to_string(),
+            vec![site(10, 16), site(31, 37)],
+        )];
+        let result = MatchResult::Match(result);
+
+        let template = "Use newApi instead of $old in $unbound, it costs $5";
+        assert_eq!(
+            render_message_template(template, &result, &site(10, 19)),
+            "Use newApi instead of oldApi in $unbound, it costs $5"
+        );
+        assert_eq!(
+            render_message_template("$old is deprecated", &result, &site(31, 40)),
+            "oldApi is deprecated"
+        );
+    }
+
     #[test]
     fn test_normalize_path_in_project_already_normalized() {
         let path = "src/main.rs";
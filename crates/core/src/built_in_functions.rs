@@ -8,15 +8,22 @@ use grit_pattern_matcher::{
     constant::Constant,
     context::ExecContext,
     pattern::{
-        get_absolute_file_name, get_file_name, CallBuiltIn, CallbackPattern, JoinFn, LazyBuiltIn,
-        Pattern, ResolvedPattern, ResolvedSnippet, State,
+        get_absolute_file_name, get_file_name, AstNodePattern, CallBuiltIn, CallbackPattern,
+        JoinFn, LazyBuiltIn, Matcher, Pattern, ResolvedPattern, ResolvedSnippet, State,
+        StateCheckpoint,
     },
 };
-use grit_util::{AnalysisLogBuilder, AnalysisLogs, CodeRange, Language};
+use grit_util::{
+    error::GritResult, AnalysisLogBuilder, AnalysisLogs, AstNode, CodeRange, FlowRole, Language,
+};
 use itertools::Itertools;
+use marzano_language::target_language::TargetLanguage;
+use marzano_util::node_with_source::NodeWithSource;
 use rand::prelude::SliceRandom;
 use rand::Rng;
 use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
 
 // todo we can probably use a macro to generate a function that takes a vec and
 // and calls the input function with the vec args unpacked.
@@ -43,6 +50,16 @@ pub type CallbackFn = dyn for<'a, 'b> Fn(
     + Send
     + Sync;
 
+/// Computes the text a binding should be rewritten to.
+pub type ReplacementFn = dyn for<'a, 'b> Fn(
+        &'b MarzanoResolvedPattern<'a>,
+        &'a MarzanoContext<'a>,
+        &mut State<'a, MarzanoQueryContext>,
+        &mut AnalysisLogs,
+    ) -> Result<String>
+    + Send
+    + Sync;
+
 pub struct BuiltInFunction {
     pub name: &'static str,
     pub params: Vec<&'static str>,
@@ -109,7 +126,9 @@ impl BuiltInFunctionPosition {
 
 pub struct BuiltIns {
     built_ins: Vec<BuiltInFunction>,
-    callbacks: Vec<Box<CallbackFn>>,
+    /// Callbacks are shared, so patterns composed against one registry can be
+    /// built any number of times.
+    callbacks: Vec<Arc<CallbackFn>>,
 }
 
 impl std::fmt::Debug for BuiltIns {
@@ -154,17 +173,49 @@ impl BuiltIns {
         state: &mut State<'a, MarzanoQueryContext>,
         logs: &mut AnalysisLogs,
     ) -> Result<bool> {
-        (self.callbacks[call.callback_index])(binding, context, state, logs)
+        let callback = self.callbacks.get(call.callback_index).ok_or_else(|| {
+            anyhow!(
+                "callback {} was not registered with the built-ins this pattern was built with",
+                call.callback_index
+            )
+        })?;
+        callback(binding, context, state, logs)
     }
 
     /// Add an anonymous built-in, used for callbacks
     /// Returns a pattern that can be used to call the callback
     pub fn add_callback(&mut self, func: Box<CallbackFn>) -> Pattern<MarzanoQueryContext> {
-        self.callbacks.push(func);
+        self.callbacks.push(Arc::from(func));
         let index = self.callbacks.len() - 1;
         Pattern::CallbackPattern(Box::new(CallbackPattern::new(index)))
     }
 
+    /// Replaces the callbacks with the ones registered with `registry`, so
+    /// further callbacks are registered on top of them.
+    pub(crate) fn share_callbacks(&mut self, registry: &BuiltIns) {
+        self.callbacks = registry.callbacks.clone();
+    }
+
+    /// Makes the callbacks registered with `registry` callable by patterns run
+    /// with these built-ins.
+    ///
+    /// Callbacks already registered here must have been registered on top of
+    /// the same callbacks, since patterns refer to callbacks by index.
+    pub(crate) fn inherit_callbacks(&mut self, registry: &BuiltIns) -> Result<()> {
+        let consistent = self
+            .callbacks
+            .iter()
+            .zip(&registry.callbacks)
+            .all(|(own, registered)| Arc::ptr_eq(own, registered));
+        if !consistent {
+            bail!("callbacks were registered with different built-ins than the ones the pattern is built with");
+        }
+        if registry.callbacks.len() > self.callbacks.len() {
+            self.callbacks = registry.callbacks.clone();
+        }
+        Ok(())
+    }
+
     pub fn add_built_in(&mut self, built_in: BuiltInFunction) {
         self.built_ins.push(built_in);
     }
@@ -211,6 +262,38 @@ impl BuiltIns {
             BuiltInFunction::new("split", vec!["string", "separator"], Box::new(split_fn)),
             BuiltInFunction::new("log", vec!["message", "variable"], Box::new(log_fn))
                 .as_predicate_or_pattern(),
+            BuiltInFunction::new(
+                "declaration_of",
+                vec!["identifier"],
+                Box::new(declaration_of_fn),
+            )
+            .as_predicate_or_pattern(),
+            BuiltInFunction::new(
+                "references_of",
+                vec!["declaration"],
+                Box::new(references_of_fn),
+            ),
+            BuiltInFunction::new(
+                "flows_to",
+                vec!["source", "sink", "sanitizer"],
+                Box::new(flows_to_fn),
+            )
+            .as_predicate_or_pattern(),
+            BuiltInFunction::new(
+                "always_before",
+                vec!["first", "second"],
+                Box::new(always_before_fn),
+            )
+            .as_predicate_or_pattern(),
+            BuiltInFunction::new("is_unreachable", vec!["node"], Box::new(is_unreachable_fn))
+                .as_predicate_or_pattern(),
+            BuiltInFunction::new(
+                "always_followed_by",
+                vec!["node", "target"],
+                Box::new(always_followed_by_fn),
+            )
+            .as_predicate_or_pattern(),
+            BuiltInFunction::new("type_of", vec!["node"], Box::new(type_of_fn)),
         ]
         .into()
     }
@@ -556,6 +639,325 @@ fn length_fn<'a>(
     }
 }
 
+/// Resolve an identifier to the identifier which declares the binding it refers to
+fn declaration_of_fn<'a>(
+    args: &'a [Option<Pattern<MarzanoQueryContext>>],
+    context: &'a MarzanoContext<'a>,
+    state: &mut State<'a, MarzanoQueryContext>,
+    logs: &mut AnalysisLogs,
+) -> Result<MarzanoResolvedPattern<'a>> {
+    let args = MarzanoResolvedPattern::from_patterns(args, state, context, logs)?;
+
+    let identifier = scope_node_arg(&args, "declaration_of", context.language())?;
+    match context.language().declaration_of(&identifier) {
+        Some(declaration) => Ok(ResolvedPattern::from_node_binding(declaration)),
+        None => Ok(ResolvedPattern::from_constant(Constant::Boolean(false))),
+    }
+}
+
+/// List every identifier which refers to the binding of a declaration
+fn references_of_fn<'a>(
+    args: &'a [Option<Pattern<MarzanoQueryContext>>],
+    context: &'a MarzanoContext<'a>,
+    state: &mut State<'a, MarzanoQueryContext>,
+    logs: &mut AnalysisLogs,
+) -> Result<MarzanoResolvedPattern<'a>> {
+    let args = MarzanoResolvedPattern::from_patterns(args, state, context, logs)?;
+
+    let declaration = scope_node_arg(&args, "references_of", context.language())?;
+    let references = context.references_of(&declaration);
+    Ok(ResolvedPattern::from_list_parts(
+        references
+            .into_iter()
+            .map(ResolvedPattern::from_node_binding),
+    ))
+}
+
+fn scope_node_arg<'a>(
+    args: &[Option<MarzanoResolvedPattern<'a>>],
+    name: &str,
+    language: &TargetLanguage,
+) -> Result<NodeWithSource<'a>> {
+    if !language.supports_scope_analysis() {
+        bail!(
+            "{name} is not supported for {}, since it has no scope analysis",
+            language.language_name()
+        );
+    }
+    args.first()
+        .and_then(Option::as_ref)
+        .and_then(|arg| arg.get_last_binding())
+        .and_then(|binding| binding.as_node())
+        .ok_or_else(|| anyhow!("{name} takes an identifier node as its first argument"))
+}
+
+/// Test a pattern against a candidate node, then roll the state back to
+/// `checkpoint` so bindings made while testing do not leak into the match
+fn matches_candidate<'a>(
+    pattern: &'a Pattern<MarzanoQueryContext>,
+    candidate: NodeWithSource<'a>,
+    checkpoint: &StateCheckpoint<'a, MarzanoQueryContext>,
+    state: &mut State<'a, MarzanoQueryContext>,
+    context: &'a MarzanoContext<'a>,
+    logs: &mut AnalysisLogs,
+) -> GritResult<bool> {
+    let binding = MarzanoResolvedPattern::from_node_binding(candidate);
+    let matches = pattern.execute(&binding, state, context, logs);
+    state.rollback(checkpoint);
+    matches
+}
+
+/// Trace a value from a source into the sink, within the function enclosing
+/// the sink, and record the path it takes on the match
+fn flows_to_fn<'a>(
+    args: &'a [Option<Pattern<MarzanoQueryContext>>],
+    context: &'a MarzanoContext<'a>,
+    state: &mut State<'a, MarzanoQueryContext>,
+    logs: &mut AnalysisLogs,
+) -> Result<MarzanoResolvedPattern<'a>> {
+    let language = context.language();
+    if !language.supports_data_flow() {
+        bail!(
+            "flows_to is not supported for {}, since it has no data-flow analysis",
+            language.language_name()
+        );
+    }
+    let Some(source) = args.first().and_then(Option::as_ref) else {
+        bail!("flows_to requires a source pattern");
+    };
+    let sink = match args.get(1).and_then(Option::as_ref) {
+        Some(sink) => MarzanoResolvedPattern::from_pattern(sink, state, context, logs)?,
+        None => bail!("flows_to requires a sink"),
+    };
+    let sink = sink
+        .get_last_binding()
+        .and_then(Binding::as_node)
+        .ok_or_else(|| anyhow!("flows_to takes a node as its sink"))?;
+    let sanitizer = args.get(2).and_then(Option::as_ref);
+
+    let checkpoint = state.checkpoint();
+    let mut error = None;
+    let path = language.flow_path(&sink, &mut |node, role| {
+        let pattern = match (role, sanitizer) {
+            (FlowRole::Source, _) => source,
+            (FlowRole::Sanitizer, Some(sanitizer)) => sanitizer,
+            (FlowRole::Sanitizer, None) => return false,
+        };
+        if error.is_some() {
+            return false;
+        }
+        match matches_candidate(pattern, node.clone(), &checkpoint, state, context, logs) {
+            Ok(matches) => matches,
+            Err(e) => {
+                error = Some(e);
+                false
+            }
+        }
+    });
+    if let Some(error) = error {
+        return Err(error.into());
+    }
+
+    match path {
+        Some(path) => {
+            state
+                .flows
+                .push(path.iter().map(NodeWithSource::range).collect());
+            Ok(ResolvedPattern::from_list_parts(
+                path.into_iter().map(ResolvedPattern::from_node_binding),
+            ))
+        }
+        None => Ok(ResolvedPattern::from_constant(Constant::Boolean(false))),
+    }
+}
+
+/// Check whether the first node runs before the second on every path through
+/// their function
+fn always_before_fn<'a>(
+    args: &'a [Option<Pattern<MarzanoQueryContext>>],
+    context: &'a MarzanoContext<'a>,
+    state: &mut State<'a, MarzanoQueryContext>,
+    logs: &mut AnalysisLogs,
+) -> Result<MarzanoResolvedPattern<'a>> {
+    let args = MarzanoResolvedPattern::from_patterns(args, state, context, logs)?;
+
+    let first = control_flow_node_arg(&args, 0, "always_before", context.language())?;
+    let second = control_flow_node_arg(&args, 1, "always_before", context.language())?;
+    let always_before = context
+        .language()
+        .control_flow_graph(&second)
+        .is_some_and(|graph| graph.always_before(&first, &second));
+    Ok(ResolvedPattern::from_constant(Constant::Boolean(
+        always_before,
+    )))
+}
+
+/// Check whether no path through its function runs a node
+fn is_unreachable_fn<'a>(
+    args: &'a [Option<Pattern<MarzanoQueryContext>>],
+    context: &'a MarzanoContext<'a>,
+    state: &mut State<'a, MarzanoQueryContext>,
+    logs: &mut AnalysisLogs,
+) -> Result<MarzanoResolvedPattern<'a>> {
+    let args = MarzanoResolvedPattern::from_patterns(args, state, context, logs)?;
+
+    let node = control_flow_node_arg(&args, 0, "is_unreachable", context.language())?;
+    let is_unreachable = context
+        .language()
+        .control_flow_graph(&node)
+        .is_some_and(|graph| !graph.is_reachable(&node));
+    Ok(ResolvedPattern::from_constant(Constant::Boolean(
+        is_unreachable,
+    )))
+}
+
+/// Check whether every path from a node to a return runs a statement
+/// containing a match of the target pattern
+fn always_followed_by_fn<'a>(
+    args: &'a [Option<Pattern<MarzanoQueryContext>>],
+    context: &'a MarzanoContext<'a>,
+    state: &mut State<'a, MarzanoQueryContext>,
+    logs: &mut AnalysisLogs,
+) -> Result<MarzanoResolvedPattern<'a>> {
+    let node = match args.first().and_then(Option::as_ref) {
+        Some(node) => Some(MarzanoResolvedPattern::from_pattern(
+            node, state, context, logs,
+        )?),
+        None => None,
+    };
+    let node = control_flow_node_arg(&[node], 0, "always_followed_by", context.language())?;
+    let Some(target) = args.get(1).and_then(Option::as_ref) else {
+        bail!("always_followed_by requires a target pattern");
+    };
+    let Some(graph) = context.language().control_flow_graph(&node) else {
+        return Ok(ResolvedPattern::from_constant(Constant::Boolean(false)));
+    };
+
+    let language = context.language();
+    let checkpoint = state.checkpoint();
+    let mut error = None;
+    let always_followed = graph.always_followed_by(&node, |step| {
+        // Nested functions and lambdas are only defined by the step, so code
+        // inside their bodies does not run as part of it
+        let mut candidates = vec![step.clone()];
+        while let Some(candidate) = candidates.pop() {
+            if error.is_some() {
+                return false;
+            }
+            if !language.is_function_definition(&candidate) {
+                let children: Vec<_> = candidate.children().collect();
+                candidates.extend(children.into_iter().rev());
+            }
+            if !could_match_kind(target, &candidate) {
+                continue;
+            }
+            match matches_candidate(target, candidate, &checkpoint, state, context, logs) {
+                Ok(true) => return true,
+                Ok(false) => {}
+                Err(e) => error = Some(e),
+            }
+        }
+        false
+    });
+    if let Some(error) = error {
+        return Err(error.into());
+    }
+    Ok(ResolvedPattern::from_constant(Constant::Boolean(
+        always_followed,
+    )))
+}
+
+/// Look up the static type of a node through the type provider of the runtime,
+/// warning instead of failing when no type is available
+fn type_of_fn<'a>(
+    args: &'a [Option<Pattern<MarzanoQueryContext>>],
+    context: &'a MarzanoContext<'a>,
+    state: &mut State<'a, MarzanoQueryContext>,
+    logs: &mut AnalysisLogs,
+) -> Result<MarzanoResolvedPattern<'a>> {
+    let language = context.language();
+    if !matches!(
+        language,
+        TargetLanguage::JavaScript(_) | TargetLanguage::TypeScript(_) | TargetLanguage::Tsx(_)
+    ) {
+        bail!(
+            "type_of is not supported for {}, since types are only available for JavaScript and TypeScript",
+            language.language_name()
+        );
+    }
+
+    let args = MarzanoResolvedPattern::from_patterns(args, state, context, logs)?;
+
+    let node = args
+        .first()
+        .and_then(Option::as_ref)
+        .and_then(|arg| arg.get_last_binding())
+        .and_then(|binding| binding.as_node())
+        .ok_or_else(|| anyhow!("type_of takes a node as its argument"))?;
+    let file = get_absolute_file_name(state, context.language())?;
+    let range = node.range();
+    let type_text = match context.runtime.type_provider() {
+        Some(provider) => provider.type_of(Path::new(&file), node.source, &range),
+        None => Err(anyhow!("no type provider is configured")),
+    };
+    let reason = match type_text {
+        Ok(Some(type_text)) => return Ok(ResolvedPattern::from_string(type_text)),
+        Ok(None) => "the type provider has no type for it".to_owned(),
+        Err(error) => error.to_string(),
+    };
+
+    let text = node.text()?;
+    let log = AnalysisLogBuilder::default()
+        .level(339_u16)
+        .file(get_file_name(state, context.language())?)
+        .source(text.as_ref())
+        .position(range.start)
+        .range(range)
+        .message(format!(
+            "Warning: no type information for `{text}`: {reason}"
+        ))
+        .build()?;
+    logs.push(log);
+    Ok(ResolvedPattern::from_constant(Constant::Boolean(false)))
+}
+
+/// Whether `pattern` could match `node`, judging only by the kinds of node
+/// it matches. Patterns which are not tied to a kind may match any node.
+fn could_match_kind(pattern: &Pattern<MarzanoQueryContext>, node: &NodeWithSource) -> bool {
+    if let Pattern::AstNode(ast_node) = pattern {
+        ast_node.matches_kind_of(node)
+    } else if let Pattern::CodeSnippet(snippet) = pattern {
+        snippet.pattern_for_kind(node.node.kind_id()).is_some()
+    } else if let Pattern::Where(where_) = pattern {
+        could_match_kind(&where_.pattern, node)
+    } else if let Pattern::And(and) = pattern {
+        and.patterns.iter().all(|p| could_match_kind(p, node))
+    } else if let Pattern::Or(or) = pattern {
+        or.patterns.iter().any(|p| could_match_kind(p, node))
+    } else {
+        true
+    }
+}
+
+fn control_flow_node_arg<'a>(
+    args: &[Option<MarzanoResolvedPattern<'a>>],
+    index: usize,
+    name: &str,
+    language: &TargetLanguage,
+) -> Result<NodeWithSource<'a>> {
+    if !language.supports_control_flow() {
+        bail!(
+            "{name} is not supported for {}, since it has no control-flow analysis",
+            language.language_name()
+        );
+    }
+    args.get(index)
+        .and_then(Option::as_ref)
+        .and_then(|arg| arg.get_last_binding())
+        .and_then(|binding| binding.as_node())
+        .ok_or_else(|| anyhow!("{name} takes nodes as its arguments"))
+}
+
 pub fn get_ai_placeholder_functions() -> Option<BuiltIns> {
     Some(
         vec![
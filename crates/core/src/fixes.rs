@@ -0,0 +1,191 @@
+use crate::{
+    api::{AlternativeFix, FixEdit, MatchResult, Rewrite},
+    problem::Problem,
+};
+use anyhow::{bail, Result};
+use grit_util::{ByteRange, Range};
+use marzano_util::{rich_path::RichFile, runtime::ExecutionContext};
+use similar::{Algorithm, DiffTag, TextDiff};
+use std::collections::HashMap;
+
+/// A compiled, labeled fix pattern, offered as one of several alternatives for
+/// the matches of another pattern.
+pub struct AlternativeFixPattern {
+    pub label: String,
+    pub preferred: bool,
+    pub problem: Problem,
+}
+
+/// Executes each alternative fix on the files of the matches in `results`, and
+/// attaches the edits of every fix which overlap a match range to that range,
+/// as an [`AlternativeFix`].
+pub fn attach_alternative_fixes(
+    results: &mut [MatchResult],
+    fixes: &[AlternativeFixPattern],
+    context: &ExecutionContext,
+) {
+    if fixes.is_empty() {
+        return;
+    }
+    // Each fix runs once per file, and its changes are shared by every match in that file
+    let mut changes: HashMap<String, Vec<Vec<Changes>>> = HashMap::new();
+    for result in results.iter() {
+        let MatchResult::Match(m) = result else {
+            continue;
+        };
+        let Some(content) = &m.content else {
+            continue;
+        };
+        if changes.contains_key(&m.source_file) {
+            continue;
+        }
+        let file = RichFile::new(m.source_file.clone(), content.clone());
+        let file_changes = fixes
+            .iter()
+            .map(|fix| {
+                fix.problem
+                    .execute_file(&file, context)
+                    .iter()
+                    .filter_map(|r| match r {
+                        MatchResult::Rewrite(r) => Changes::from_rewrite(r),
+                        MatchResult::AnalysisLog(_)
+                        | MatchResult::Match(_)
+                        | MatchResult::InputFile(_)
+                        | MatchResult::CreateFile(_)
+                        | MatchResult::RemoveFile(_)
+                        | MatchResult::DoneFile(_)
+                        | MatchResult::AllDone(_)
+                        | MatchResult::PatternInfo(_) => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        changes.insert(m.source_file.clone(), file_changes);
+    }
+    for result in results.iter_mut() {
+        let MatchResult::Match(m) = result else {
+            continue;
+        };
+        let Some(file_changes) = changes.get(&m.source_file) else {
+            continue;
+        };
+        for range in &m.ranges {
+            for (fix, fix_changes) in fixes.iter().zip(file_changes) {
+                let scoped: Vec<FixEdit> = fix_changes
+                    .iter()
+                    .filter_map(|changes| changes.edit_within(range))
+                    .collect();
+                if !scoped.is_empty() {
+                    m.fixes.push(AlternativeFix {
+                        label: fix.label.clone(),
+                        preferred: fix.preferred,
+                        range: *range,
+                        edits: scoped,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Applies `edits` to the `content` they were computed for. Edits shared by
+/// several matches are only applied once.
+pub fn apply_fix_edits<'a>(
+    content: &str,
+    edits: impl IntoIterator<Item = &'a FixEdit>,
+) -> Result<String> {
+    let mut edits: Vec<&FixEdit> = edits.into_iter().collect();
+    edits.sort();
+    edits.dedup();
+    let mut fixed = String::with_capacity(content.len());
+    let mut end = 0;
+    for edit in edits {
+        if edit.range.start < end {
+            bail!("Overlapping fixes cannot be applied together");
+        }
+        let Some(unchanged) = content.get(end..edit.range.start) else {
+            bail!("Fix edit is out of bounds of the file");
+        };
+        fixed.push_str(unchanged);
+        fixed.push_str(&edit.replacement);
+        end = edit.range.end;
+    }
+    let Some(rest) = content.get(end..) else {
+        bail!("Fix edit is out of bounds of the file");
+    };
+    fixed.push_str(rest);
+    Ok(fixed)
+}
+
+/// The changes a rewrite makes, as pairs of byte ranges in the original and
+/// rewritten content.
+struct Changes {
+    rewritten: String,
+    ranges: Vec<(ByteRange, ByteRange)>,
+}
+
+impl Changes {
+    fn from_rewrite(rewrite: &Rewrite) -> Option<Self> {
+        let original = rewrite.original.content.as_deref()?;
+        let rewritten = rewrite.rewritten.content.clone()?;
+        let original_offsets = char_offsets(original);
+        let rewritten_offsets = char_offsets(&rewritten);
+        let diff = TextDiff::configure()
+            .algorithm(Algorithm::Myers)
+            .diff_chars(original, rewritten.as_str());
+
+        // Adjacent changes, such as a deletion followed by an insertion, make up a single range
+        let mut ranges: Vec<(ByteRange, ByteRange)> = Vec::new();
+        let mut changing = false;
+        for op in diff.ops() {
+            let (tag, old, new) = op.as_tag_tuple();
+            let old = ByteRange::new(original_offsets[old.start], original_offsets[old.end]);
+            let new = ByteRange::new(rewritten_offsets[new.start], rewritten_offsets[new.end]);
+            if tag == DiffTag::Equal {
+                changing = false;
+                continue;
+            }
+            if let Some(last) = ranges.last_mut().filter(|_| changing) {
+                last.0.end = old.end;
+                last.1.end = new.end;
+            } else {
+                ranges.push((old, new));
+                changing = true;
+            }
+        }
+        Some(Self { rewritten, ranges })
+    }
+
+    /// Combines the changes which touch `range` into a single edit, so the
+    /// fix of one match leaves every other match alone.
+    fn edit_within(&self, range: &Range) -> Option<FixEdit> {
+        let mut touching = self
+            .ranges
+            .iter()
+            .filter(|(original, _)| overlaps(original, range));
+        let first = touching.next()?;
+        let last = touching.last().unwrap_or(first);
+        Some(FixEdit {
+            range: ByteRange::new(first.0.start, last.0.end),
+            replacement: self.rewritten[first.1.start..last.1.end].to_string(),
+        })
+    }
+}
+
+/// The byte offset of every character of `text`, followed by its length.
+fn char_offsets(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .collect()
+}
+
+/// Whether an edit touches a match range. Insertions count at either end of the range.
+fn overlaps(edit: &ByteRange, range: &Range) -> bool {
+    let (start, end) = (range.start_byte as usize, range.end_byte as usize);
+    if edit.start == edit.end {
+        start <= edit.start && edit.start <= end
+    } else {
+        edit.start < end && start < edit.end
+    }
+}
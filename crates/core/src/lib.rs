@@ -12,6 +12,7 @@ mod clean;
 pub mod compact_api;
 pub mod constants;
 mod equivalence;
+pub mod fixes;
 mod foreign_function_definition;
 pub mod fs;
 mod inline_snippets;
@@ -19,23 +20,27 @@ mod inline_snippets;
 pub mod sdk;
 
 mod limits;
+pub mod lint;
 pub mod marzano_binding;
 pub mod marzano_code_snippet;
 pub mod marzano_context;
 pub mod marzano_resolved_pattern;
+pub mod node_introspection;
 mod optimizer;
 pub mod parse;
 mod paths;
 pub mod pattern_compiler;
+pub mod pattern_inference;
 pub mod problem;
 mod smart_insert;
 mod split_snippet;
 mod suppress;
 mod text_unparser;
+pub mod transaction;
 pub mod tree_sitter_serde;
+pub mod type_provider;
 mod variables;
 
-#[cfg(any(feature = "napi", feature = "wasm_core"))]
 pub use sdk::UncompiledPatternBuilder;
 
 // getrandom is a deeply nested dependency used by many things eg. uuid
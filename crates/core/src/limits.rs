@@ -30,3 +30,23 @@ pub(crate) fn is_file_too_big(file: &RichFile) -> Option<AnalysisLog> {
         None
     }
 }
+
+/// Reports that `pattern` was stopped on `file` for exceeding its execution
+/// limits, as described by `reason`.
+pub(crate) fn limit_exceeded_log(pattern: Option<&str>, file: &str, reason: &str) -> AnalysisLog {
+    AnalysisLog {
+        level: Some(310),
+        message: format!(
+            "Skipped {} for pattern {}, it {}.",
+            file,
+            pattern.unwrap_or("<pattern>"),
+            reason
+        ),
+        file: Some(file.to_owned().into()),
+        engine_id: Some("marzano".to_owned()),
+        position: Some(Position::first()),
+        syntax_tree: None,
+        range: None,
+        source: None,
+    }
+}
@@ -0,0 +1,447 @@
+use crate::{
+    equivalence::are_equivalent,
+    pattern_compiler::{src_to_problem_libs, CompilationResult},
+    problem::Problem,
+};
+use anyhow::{anyhow, Result};
+use grit_pattern_matcher::constants::{DEFAULT_FILE_NAME, GLOBAL_VARS_SCOPE_INDEX};
+use grit_util::{
+    traverse, AnalysisLog, AnalysisLogBuilder, AnalysisLogs, Ast, AstNode, ByteRange, Language,
+    Order, Range,
+};
+use marzano_language::{
+    grit_parser::MarzanoGritParser,
+    language::{nodes_from_indices, MarzanoLanguage},
+    target_language::{PatternLanguage, TargetLanguage},
+};
+use marzano_util::node_with_source::NodeWithSource;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+/// The level of warnings about patterns which compile, but are unlikely to do
+/// what the author intended.
+pub const LINT_WARNING_LEVEL: u16 = 441;
+
+/// Checks the GritQL file `src` for mistakes which compile, but are unlikely
+/// to do what the author intended.
+///
+/// The warnings raised while compiling the file itself are included, so the
+/// result is every warning about `src`, ordered by position. Files which only
+/// hold definitions are linted as if each of their patterns was called.
+pub fn lint_pattern(
+    src: &str,
+    libs: &BTreeMap<String, String>,
+    default_lang: TargetLanguage,
+    name: Option<String>,
+) -> Result<AnalysisLogs> {
+    let mut parser = MarzanoGritParser::new()?;
+    let tree = parser.parse_file(src, Some(Path::new(DEFAULT_FILE_NAME)))?;
+    let root = tree.root_node();
+
+    let mut defined = BTreeSet::new();
+    let mut patterns = Vec::new();
+    for definition in root.named_children_by_field_name("definitions") {
+        for node in definition.named_children() {
+            let Some(name) = node.child_by_field_name("name") else {
+                continue;
+            };
+            let name = name.text()?.trim().to_string();
+            if node.node.kind() == "patternDefinition" {
+                patterns.push(name.clone());
+            }
+            defined.insert(name);
+        }
+    }
+    // The libraries hold a copy of the definitions in `src`, which would be defined twice
+    let libs: BTreeMap<String, String> = libs
+        .iter()
+        .filter(|(file, _)| !defined.contains(file.strip_suffix(".grit").unwrap_or(file.as_str())))
+        .map(|(file, body)| (file.clone(), body.clone()))
+        .collect();
+
+    let src = if root.child_by_field_name("pattern").is_some() {
+        src.to_string()
+    } else if patterns.is_empty() {
+        return Ok(AnalysisLogs::default());
+    } else {
+        let calls = patterns
+            .iter()
+            .map(|pattern| format!("{}()", pattern))
+            .collect::<Vec<_>>();
+        format!("{}\n\nor {{ {} }}\n", src, calls.join(", "))
+    };
+    lint_compiled(src, &libs, default_lang, name)
+}
+
+fn lint_compiled(
+    src: String,
+    libs: &BTreeMap<String, String>,
+    default_lang: TargetLanguage,
+    name: Option<String>,
+) -> Result<AnalysisLogs> {
+    let CompilationResult {
+        problem,
+        compilation_warnings,
+    } = src_to_problem_libs(src, libs, default_lang, name, None, None, None)?;
+    // Warnings about libraries are reported when the libraries are linted themselves
+    let mut logs: Vec<AnalysisLog> = compilation_warnings
+        .logs()
+        .into_iter()
+        .filter(|log| {
+            log.file
+                .as_ref()
+                .map_or(true, |file| file == Path::new(DEFAULT_FILE_NAME))
+        })
+        .collect();
+    let Some(tree) = &problem.tree else {
+        return Ok(logs.into());
+    };
+    let root = tree.root_node();
+
+    logs.extend(unused_variables(&problem, &tree.source)?);
+    for node in traverse(root.walk(), Order::Pre) {
+        match node.node.kind().as_ref() {
+            "patternOr" => logs.extend(subsumed_branches(&node, "patterns")?),
+            "predicateOr" => logs.extend(subsumed_branches(&node, "predicates")?),
+            "rewrite" | "predicateRewrite" => {
+                logs.extend(underscore_replacements(&node, &problem.language)?)
+            }
+            "backtickSnippet" => logs.extend(text_snippet(&node, &problem.language)?),
+            _ => {}
+        }
+    }
+    logs.extend(unused_definitions(&root, libs)?);
+
+    logs.sort_by_key(|log| log.range.as_ref().map(|range| range.start_byte));
+    Ok(logs.into())
+}
+
+fn warning(source: &str, range: Range, message: String) -> Result<AnalysisLog> {
+    Ok(AnalysisLogBuilder::default()
+        .level(LINT_WARNING_LEVEL)
+        .file(DEFAULT_FILE_NAME)
+        .source(source)
+        .position(range.start)
+        .range(range)
+        .message(message)
+        .build()?)
+}
+
+/// Metavariables which appear only once are bound without being used, or
+/// used without ever being bound.
+fn unused_variables(problem: &Problem, source: &str) -> Result<Vec<AnalysisLog>> {
+    let underscore = format!("{}_", problem.language.metavariable_prefix());
+    let mut logs = Vec::new();
+    for (scope_index, scope) in problem.variables.locations.iter().enumerate() {
+        // Globals such as $filename are bound by the engine
+        if scope_index == GLOBAL_VARS_SCOPE_INDEX as usize {
+            continue;
+        }
+        for variable in scope {
+            // Replacements using $_ are reported on their own
+            if variable.name() == underscore {
+                continue;
+            }
+            let [location] = variable.get_main_locations()[..] else {
+                continue;
+            };
+            logs.push(warning(
+                source,
+                Range::from_byte_range(source, &location),
+                format!(
+                    "Warning: {} is only used once, so its value is never used. Use $_ if it does not need to be bound",
+                    variable.name()
+                ),
+            )?);
+        }
+    }
+    Ok(logs)
+}
+
+/// Branches of an `or` are tried in order, so a branch is dead if an earlier
+/// one matches everything it matches.
+fn subsumed_branches(node: &NodeWithSource, field: &str) -> Result<Vec<AnalysisLog>> {
+    let branches: Vec<_> = node.named_children_by_field_name(field).collect();
+    let mut logs = Vec::new();
+    for (index, branch) in branches.iter().enumerate() {
+        let Some(earlier) = branches[..index]
+            .iter()
+            .find(|earlier| subsumes(earlier, branch))
+        else {
+            continue;
+        };
+        logs.push(warning(
+            node.source,
+            branch.range(),
+            format!(
+                "Warning: this branch of the or can never match, since the earlier branch `{}` already matches everything it does",
+                earlier.text()?.trim()
+            ),
+        )?);
+    }
+    Ok(logs)
+}
+
+fn subsumes(earlier: &NodeWithSource, later: &NodeWithSource) -> bool {
+    let matches_anything = match earlier.node.kind().as_ref() {
+        "underscore" | "top" => true,
+        "variable" => earlier.text().is_ok_and(|text| text.trim() == "$_"),
+        "booleanConstant" => earlier.text().is_ok_and(|text| text.trim() == "true"),
+        _ => false,
+    };
+    if matches_anything || are_equivalent(earlier, later) {
+        return true;
+    }
+    // A where clause only narrows the pattern it is attached to
+    later.node.kind() == "patternWhere"
+        && later
+            .child_by_field_name("pattern")
+            .is_some_and(|pattern| are_equivalent(earlier, &pattern))
+}
+
+/// `$_` is never bound, so it cannot be substituted into a replacement.
+fn underscore_replacements(
+    node: &NodeWithSource,
+    lang: &TargetLanguage,
+) -> Result<Vec<AnalysisLog>> {
+    let Some(right) = node.child_by_field_name("right") else {
+        return Ok(vec![]);
+    };
+    let underscore = format!("{}_", lang.metavariable_prefix());
+    let message = "Warning: $_ is never bound, so it cannot be used on the right hand side of a rewrite. Bind the code to a named metavariable instead";
+    let mut logs = Vec::new();
+    for child in traverse(right.walk(), Order::Pre) {
+        let content_start = match child.node.kind().as_ref() {
+            "variable" => {
+                if child.text()?.trim() == "$_" {
+                    logs.push(warning(node.source, child.range(), message.to_string())?);
+                }
+                continue;
+            }
+            "backtickSnippet" => 1,
+            "rawBacktickSnippet" => 4,
+            _ => continue,
+        };
+        let text = child.text()?;
+        let Some(content) = text.get(content_start..text.len().saturating_sub(1)) else {
+            continue;
+        };
+        let offset = child.byte_range().start + content_start;
+        for found in lang.metavariable_regex().find_iter(content) {
+            if found.as_str() == underscore {
+                let range = ByteRange::new(offset + found.start(), offset + found.end());
+                logs.push(warning(
+                    node.source,
+                    Range::from_byte_range(node.source, &range),
+                    message.to_string(),
+                )?);
+            }
+        }
+    }
+    Ok(logs)
+}
+
+/// Snippets which do not parse as the target language are silently matched
+/// as plain text instead.
+fn text_snippet(node: &NodeWithSource, lang: &TargetLanguage) -> Result<Vec<AnalysisLog>> {
+    if is_replacement(node) {
+        return Ok(vec![]);
+    }
+    let text = node.text()?;
+    let Some(content) = text
+        .strip_prefix('`')
+        .and_then(|text| text.strip_suffix('`'))
+    else {
+        return Ok(vec![]);
+    };
+    if content.trim().is_empty()
+        || lang.exact_variable_regex().is_match(content.trim())
+        || lang.metavariable_bracket_regex().is_match(content)
+    {
+        return Ok(vec![]);
+    }
+    let snippet_trees = lang.parse_snippet_contexts(content);
+    if !nodes_from_indices(&snippet_trees).is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(vec![warning(
+        node.source,
+        node.range(),
+        format!(
+            "Warning: `{}` does not parse as {}, so it will be matched as plain text",
+            content,
+            PatternLanguage::from(lang)
+        ),
+    )?])
+}
+
+/// Whether the snippet is used as a value rather than matched against, in
+/// which case it is expected to be text.
+fn is_replacement(snippet: &NodeWithSource) -> bool {
+    let Some(code_snippet) = snippet.parent() else {
+        return false;
+    };
+    let Some(parent) = code_snippet.parent() else {
+        return false;
+    };
+    let field = match parent.node.kind().as_ref() {
+        "rewrite" | "predicateRewrite" | "patternAccumulate" | "predicateAccumulate" => "right",
+        "assignmentAsPattern" | "predicateAssignment" | "predicateReturn" => "pattern",
+        "predicateEqual" | "like" => return true,
+        // Named arguments of calls
+        _ if parent.child_by_field_name("name").is_some() => "pattern",
+        _ => return false,
+    };
+    parent
+        .child_by_field_name(field)
+        .is_some_and(|value| value.byte_range() == code_snippet.byte_range())
+}
+
+/// Pattern definitions which are not called from `root` nor from any library.
+fn unused_definitions(
+    root: &NodeWithSource,
+    libs: &BTreeMap<String, String>,
+) -> Result<Vec<AnalysisLog>> {
+    let mut definitions = Vec::new();
+    for definition in root.named_children_by_field_name("definitions") {
+        let Some(pattern) = definition.child_by_field_name("pattern") else {
+            continue;
+        };
+        let name = pattern
+            .child_by_field_name("name")
+            .ok_or_else(|| anyhow!("missing name of patternDefinition"))?;
+        definitions.push((name.text()?.trim().to_string(), name));
+    }
+    if definitions.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut called = called_patterns(root)?;
+    let mut parser = MarzanoGritParser::new()?;
+    for (file, body) in libs {
+        let unresolved = definitions
+            .iter()
+            .any(|(name, _)| !called.contains(name) && body.contains(name.as_str()));
+        if unresolved {
+            let tree = parser.parse_file(body, Some(Path::new(file)))?;
+            called.extend(called_patterns(&tree.root_node())?);
+        }
+    }
+
+    definitions
+        .into_iter()
+        .filter(|(name, _)| !called.contains(name))
+        .map(|(name, node)| {
+            warning(
+                root.source,
+                node.range(),
+                format!("Warning: pattern {} is defined but never used", name),
+            )
+        })
+        .collect()
+}
+
+fn called_patterns(root: &NodeWithSource) -> Result<BTreeSet<String>> {
+    let mut called = BTreeSet::new();
+    for node in traverse(root.walk(), Order::Pre) {
+        if node.node.kind() != "nodeLike" {
+            continue;
+        }
+        let name = node
+            .child_by_field_name("name")
+            .ok_or_else(|| anyhow!("missing name of nodeLike"))?;
+        called.insert(name.text()?.trim().to_string());
+    }
+    Ok(called)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(src: &str) -> Vec<String> {
+        let lang = TargetLanguage::from_string("js", None).unwrap();
+        lint_pattern(src, &BTreeMap::new(), lang, None)
+            .unwrap()
+            .logs()
+            .into_iter()
+            .map(|log| log.message)
+            .collect()
+    }
+
+    #[test]
+    fn warns_about_variables_used_once() {
+        let messages = lint("language js\n\n`console.log($message, $level)` => `log($level)`");
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("$message is only used once"));
+    }
+
+    #[test]
+    fn warns_about_subsumed_or_branches() {
+        let messages = lint(
+            "language js\n\nor {\n  `foo()`,\n  `bar()`,\n  `foo()` where { $filename <: r\".*\\.ts\" }\n}",
+        );
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("earlier branch `foo()`"));
+    }
+
+    #[test]
+    fn warns_about_snippets_matched_as_text() {
+        let messages = lint("language js\n\n`foo(`");
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("does not parse as"));
+    }
+
+    #[test]
+    fn warns_about_unused_definitions() {
+        let messages = lint(
+            "language js\n\npattern used() { `foo()` }\npattern unused() { `bar()` }\n\nused()",
+        );
+        assert_eq!(
+            messages,
+            vec!["Warning: pattern unused is defined but never used"]
+        );
+    }
+
+    #[test]
+    fn warns_about_underscore_in_replacements() {
+        let messages = lint("language js\n\n`foo($_)` => `bar($_)`");
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("$_ is never bound"));
+    }
+
+    #[test]
+    fn warns_about_rewrites_inside_not() {
+        let lang = TargetLanguage::from_string("js", None).unwrap();
+        let logs = lint_pattern(
+            "language js\n\n`foo($x)` where { $x <: not and { `bar` => `baz` } }",
+            &BTreeMap::new(),
+            lang,
+            None,
+        )
+        .unwrap()
+        .logs();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(
+            logs[0].message,
+            "Warning: rewrites inside of a not will never be applied"
+        );
+        assert_eq!(logs[0].level, Some(LINT_WARNING_LEVEL));
+    }
+
+    #[test]
+    fn lints_files_of_definitions() {
+        let messages = lint("language js\n\npattern helper() { `foo($x)` }\n");
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("$x is only used once"));
+    }
+
+    #[test]
+    fn accepts_clean_patterns() {
+        assert!(
+            lint("language js\n\n`console.log($message)` => `logger.info($message)`").is_empty()
+        );
+    }
+}
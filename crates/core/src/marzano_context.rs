@@ -12,7 +12,7 @@ use grit_pattern_matcher::{
     binding::Binding,
     constants::{GLOBAL_VARS_SCOPE_INDEX, NEW_FILES_INDEX},
     context::ExecContext,
-    file_owners::FileOwners,
+    file_owners::{FileOwner, FileOwners},
     pattern::{
         CallBuiltIn, CallbackPattern, File, FilePtr, GritFunctionDefinition, Matcher, Pattern,
         PatternDefinition, PredicateDefinition, ResolvedPattern, State,
@@ -20,17 +20,26 @@ use grit_pattern_matcher::{
 };
 use grit_util::{
     error::{GritPatternError, GritResult},
-    AnalysisLogs, Ast, FileOrigin, InputRanges, MatchRanges,
+    AnalysisLogs, Ast, AstNode, ClauseCoverage, ExecutionBudget, FileOrigin, InputRanges,
+    MatchRanges, MatchTrace,
 };
 use marzano_language::{
     language::{MarzanoLanguage, Tree},
     target_language::TargetLanguage,
+    Declarations,
 };
 use marzano_util::{
+    node_with_source::NodeWithSource,
     rich_path::{LoadableFile, RichFile},
     runtime::ExecutionContext,
 };
-use std::{borrow::Cow, path::PathBuf};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    path::PathBuf,
+    sync::OnceLock,
+};
 
 pub struct MarzanoContext<'a> {
     pub pattern_definitions: &'a Vec<PatternDefinition<MarzanoQueryContext>>,
@@ -43,6 +52,28 @@ pub struct MarzanoContext<'a> {
     pub language: &'a TargetLanguage,
     pub runtime: &'a ExecutionContext,
     pub name: Option<String>,
+    parsed_files: Option<&'a ParsedFiles>,
+    budget: Option<ExecutionBudget>,
+    /// The declarations of each tree queried by `references_of()`, by the id of its root
+    declarations: RefCell<HashMap<usize, Declarations>>,
+}
+
+/// Files parsed by one of several problems executed together, so the other
+/// problems can reuse the parse instead of reading and parsing the file again.
+///
+/// Every problem still gets its own copy of the file, since matches are
+/// recorded on it.
+pub(crate) struct ParsedFiles(Vec<OnceLock<ParsedFile>>);
+
+enum ParsedFile {
+    TooBig,
+    Parsed(Option<FileOwner<Tree>>),
+}
+
+impl ParsedFiles {
+    pub(crate) fn new(file_count: usize) -> Self {
+        Self((0..file_count).map(|_| OnceLock::new()).collect())
+    }
 }
 
 impl<'a> MarzanoContext<'a> {
@@ -70,9 +101,61 @@ impl<'a> MarzanoContext<'a> {
             language,
             runtime,
             name,
+            parsed_files: None,
+            budget: ExecutionBudget::start(runtime.limits(), runtime.cancellation().cloned()),
+            declarations: RefCell::new(HashMap::new()),
         }
     }
 
+    pub(crate) fn with_parsed_files(mut self, parsed_files: &'a ParsedFiles) -> Self {
+        self.parsed_files = Some(parsed_files);
+        self
+    }
+
+    /// Lists every identifier which refers to the binding of `declaration`,
+    /// indexing the declarations of its tree the first time the tree is queried
+    pub(crate) fn references_of<'b>(
+        &self,
+        declaration: &NodeWithSource<'b>,
+    ) -> Vec<NodeWithSource<'b>> {
+        let Some(root) = declaration.ancestors().last() else {
+            return Vec::new();
+        };
+        let mut declarations = self.declarations.borrow_mut();
+        let declarations = match declarations.entry(root.node.id()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match self.language.declarations(&root) {
+                Some(tree_declarations) => entry.insert(tree_declarations),
+                None => return Vec::new(),
+            },
+        };
+        self.language.references_in(declarations, declaration)
+    }
+
+    fn parse_file(&self, index: usize, logs: &mut AnalysisLogs) -> GritResult<ParsedFile> {
+        let cow: Cow<RichFile> = self.lazy_files[index]
+            .try_into_cow()
+            .map_err(|e| GritPatternError::new(e.to_string()))?;
+
+        if let Some(log) = is_file_too_big(&cow) {
+            logs.push(log);
+            return Ok(ParsedFile::TooBig);
+        }
+
+        let owned = cow.into_owned();
+
+        let file = FileOwnerCompiler::from_matches(
+            owned.path,
+            owned.content,
+            None,
+            FileOrigin::Fresh,
+            None,
+            self.language,
+            logs,
+        )?;
+        Ok(ParsedFile::Parsed(file))
+    }
+
     #[cfg(all(
         feature = "network_requests_external",
         feature = "external_functions_ffi",
@@ -149,28 +232,29 @@ impl<'a> ExecContext<'a, MarzanoQueryContext> for MarzanoContext<'a> {
                 if state.files.is_loaded(ptr) {
                     return Ok(true);
                 }
-                let index = ptr.file;
-
-                let cow: Cow<RichFile> = self.lazy_files[index as usize]
-                    .try_into_cow()
-                    .map_err(|e| GritPatternError::new(e.to_string()))?;
-
-                if let Some(log) = is_file_too_big(&cow) {
-                    logs.push(log);
-                    return Ok(false);
-                }
-
-                let owned = cow.into_owned();
+                let index = ptr.file as usize;
 
-                let file = FileOwnerCompiler::from_matches(
-                    owned.path,
-                    owned.content,
-                    None,
-                    FileOrigin::Fresh,
-                    None,
-                    self.language,
-                    logs,
-                )?;
+                let file = match self.parsed_files.and_then(|parsed| parsed.0.get(index)) {
+                    Some(shared) => {
+                        if shared.get().is_none() {
+                            let _ = shared.set(self.parse_file(index, logs)?);
+                        }
+                        match shared.get() {
+                            Some(ParsedFile::Parsed(file)) => file.as_ref().map(|file| FileOwner {
+                                name: file.name.clone(),
+                                absolute_path: file.absolute_path.clone(),
+                                tree: file.tree.clone(),
+                                matches: Default::default(),
+                                new: file.new,
+                            }),
+                            Some(ParsedFile::TooBig) | None => return Ok(false),
+                        }
+                    }
+                    None => match self.parse_file(index, logs)? {
+                        ParsedFile::Parsed(file) => file,
+                        ParsedFile::TooBig => return Ok(false),
+                    },
+                };
                 if let Some(file) = file {
                     self.files.push(file);
                     state.files.load_file(ptr, self.files.last().unwrap());
@@ -231,6 +315,7 @@ impl<'a> ExecContext<'a, MarzanoQueryContext> for MarzanoContext<'a> {
             ranges,
             variables,
             suppressed,
+            flows: state.flows.clone(),
         };
         for file_ptr in files {
             let file = state.files.get_file_owner(file_ptr);
@@ -386,4 +471,16 @@ impl<'a> ExecContext<'a, MarzanoQueryContext> for MarzanoContext<'a> {
     fn name(&self) -> Option<&str> {
         self.name.as_deref()
     }
+
+    fn clause_coverage(&self) -> Option<&ClauseCoverage> {
+        self.runtime.clause_coverage()
+    }
+
+    fn match_trace(&self) -> Option<&MatchTrace> {
+        self.runtime.match_trace()
+    }
+
+    fn execution_budget(&self) -> Option<&ExecutionBudget> {
+        self.budget.as_ref()
+    }
 }
@@ -0,0 +1,194 @@
+use anyhow::{Context, Result};
+use grit_util::{traverse, Ast, AstNode, FileOrigin, Order, Range};
+use marzano_language::language::{Field, MarzanoLanguage, SortId};
+use marzano_util::node_with_source::NodeWithSource;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tree_sitter::Language as TSLanguage;
+
+/// A kind of syntax node, with the fields AST-node patterns can match on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeKind {
+    pub name: String,
+    pub fields: Vec<NodeField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeField {
+    pub name: String,
+    pub required: bool,
+    /// Multiple fields are matched with a list pattern
+    pub multiple: bool,
+    /// The kinds of node the field may hold. Anonymous tokens are quoted.
+    pub sorts: Vec<String>,
+}
+
+/// A node of a code sample, with the AST-node pattern which matches it exactly.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodePattern {
+    pub kind: String,
+    pub range: Range,
+    pub pattern: String,
+}
+
+/// Lists the named node kinds of `lang`, in the order of their sort ids.
+pub fn node_kinds<'a>(lang: &impl MarzanoLanguage<'a>) -> Vec<NodeKind> {
+    let ts_lang = lang.get_ts_language();
+    let mut kinds = Vec::new();
+    for (sort, fields) in lang.node_types().iter().enumerate() {
+        let Ok(sort) = SortId::try_from(sort) else {
+            break;
+        };
+        let Some(name) = named_kind(ts_lang, sort) else {
+            continue;
+        };
+        kinds.push(NodeKind {
+            name,
+            fields: fields
+                .iter()
+                .map(|field| node_field(ts_lang, field))
+                .collect(),
+        });
+    }
+    kinds
+}
+
+fn node_field(ts_lang: &TSLanguage, field: &Field) -> NodeField {
+    let sorts = field
+        .sorts()
+        .iter()
+        .filter_map(|sort| {
+            named_kind(ts_lang, *sort).or_else(|| {
+                let name = ts_lang.node_kind_for_id(*sort)?;
+                Some(format!("\"{}\"", name))
+            })
+        })
+        .collect();
+    NodeField {
+        name: field.name().to_string(),
+        required: field.required(),
+        multiple: field.multiple(),
+        sorts,
+    }
+}
+
+/// The name of a named, visible kind, or `None` for anonymous tokens and hidden rules.
+fn named_kind(ts_lang: &TSLanguage, sort: SortId) -> Option<String> {
+    let name = ts_lang.node_kind_for_id(sort)?.to_string();
+    // Looking a kind up by name only finds the named kind, and tokens may share its name
+    if name.is_empty() || name.starts_with('_') || ts_lang.id_for_node_kind(&name, true) != sort {
+        return None;
+    }
+    Some(name)
+}
+
+/// Parses `input` and returns the AST-node pattern for each of its named nodes,
+/// outermost first.
+pub fn node_patterns<'a>(
+    lang: &impl MarzanoLanguage<'a>,
+    input: &str,
+    path: &Path,
+) -> Result<Vec<NodePattern>> {
+    let mut parser = lang.get_parser();
+    let tree = parser
+        .parse_file(input, Some(path), &mut vec![].into(), FileOrigin::Fresh)
+        .context("Parsed tree is empty")?;
+    let mut patterns = Vec::new();
+    for node in traverse(tree.root_node().walk(), Order::Pre) {
+        if !node.node.is_named() {
+            continue;
+        }
+        patterns.push(NodePattern {
+            kind: node.node.kind().to_string(),
+            range: node.range(),
+            pattern: node_pattern(lang, &node)?,
+        });
+    }
+    Ok(patterns)
+}
+
+/// Builds the AST-node pattern which matches `node` exactly.
+///
+/// Every field is given, so absent fields are matched with `.` or `[]`. Nodes
+/// which expose no fields can only be matched by their text.
+pub fn node_pattern<'a>(lang: &impl MarzanoLanguage<'a>, node: &NodeWithSource) -> Result<String> {
+    let fields = match lang.node_types().get(usize::from(node.node.kind_id())) {
+        Some(fields) if !node.node.is_error() && !fields.is_empty() => fields,
+        _ => return Ok(string_literal(&node.text()?)),
+    };
+    let mut args = Vec::with_capacity(fields.len());
+    for field in fields {
+        let value = if field.multiple() {
+            let items = node
+                .named_children_by_field_id(field.id())
+                .map(|child| node_pattern(lang, &child))
+                .collect::<Result<Vec<_>>>()?;
+            format!("[{}]", items.join(", "))
+        } else {
+            match node.child_by_field_id(field.id()) {
+                Some(child) => node_pattern(lang, &child)?,
+                None => ".".to_string(),
+            }
+        };
+        args.push(format!("{} = {}", field.name(), value));
+    }
+    Ok(format!("{}({})", node.node.kind(), args.join(", ")))
+}
+
+fn string_literal(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use marzano_language::target_language::TargetLanguage;
+
+    #[test]
+    fn lists_fields_of_node_kinds() {
+        let lang = TargetLanguage::from_string("js", None).unwrap();
+        let kinds = node_kinds(&lang);
+        let call = kinds
+            .iter()
+            .find(|kind| kind.name == "call_expression")
+            .unwrap();
+        let function = call
+            .fields
+            .iter()
+            .find(|field| field.name == "function")
+            .unwrap();
+        assert!(function.required);
+        assert!(!function.multiple);
+        assert!(function.sorts.contains(&"member_expression".to_string()));
+        // Supertypes and hidden rules are not kinds which can be matched
+        assert!(!kinds.iter().any(|kind| kind.name.starts_with('_')));
+    }
+
+    #[test]
+    fn builds_exact_node_patterns() {
+        let lang = TargetLanguage::from_string("js", None).unwrap();
+        let patterns = node_patterns(&lang, "console.log(\"hi\");", Path::new("test.js")).unwrap();
+        let member = patterns
+            .iter()
+            .find(|pattern| pattern.kind == "member_expression")
+            .unwrap();
+        // The optional chain is absent, so the pattern requires it to be
+        assert_eq!(
+            member.pattern,
+            r#"member_expression(chain = ., object = "console", property = "log")"#
+        );
+        let string = patterns
+            .iter()
+            .find(|pattern| pattern.kind == "string")
+            .unwrap();
+        assert_eq!(string.pattern, r#"string(fragment = ["hi"])"#);
+    }
+
+    #[test]
+    fn escapes_text_of_leaf_nodes() {
+        assert_eq!(string_literal(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+    }
+}
@@ -1,3 +1,4 @@
+use super::Inliner;
 use anyhow::Result;
 use grit_pattern_matcher::{
     context::QueryContext,
@@ -5,13 +6,14 @@ use grit_pattern_matcher::{
 };
 
 trait FilenamePatternExtractor<Q: QueryContext> {
-    fn extract_filename_pattern(&self) -> Result<Option<Pattern<Q>>>;
+    fn extract_filename_pattern(&self, inliner: &mut Inliner<Q>) -> Result<Option<Pattern<Q>>>;
 }
 
 /// Given a pattern, construct a new pattern that reflects any filename predicates found
 /// If analysis cannot be done reliably, returns None
 pub fn extract_filename_pattern<Q: QueryContext>(
     pattern: &Pattern<Q>,
+    inliner: &mut Inliner<Q>,
 ) -> Result<Option<Pattern<Q>>> {
     match pattern {
         // Once we hit a leaf node that is *not* matched against the filename, we can't go any further
@@ -27,83 +29,90 @@ pub fn extract_filename_pattern<Q: QueryContext>(
         | Pattern::Bottom => Ok(Some(Pattern::Top)),
 
         // Traversing downwards, collecting patterns
-        Pattern::Contains(c) => c.extract_filename_pattern(),
-        Pattern::Bubble(b) => b.extract_filename_pattern(),
-        Pattern::Where(w) => w.extract_filename_pattern(),
-        Pattern::Rewrite(rw) => extract_filename_pattern(&rw.left),
-        Pattern::Includes(inc) => extract_filename_pattern(&inc.includes),
-        Pattern::Every(every) => extract_filename_pattern(&every.pattern),
-        Pattern::Within(within) => extract_filename_pattern(&within.pattern),
-        Pattern::After(a) => extract_filename_pattern(&a.after),
-        Pattern::Before(b) => extract_filename_pattern(&b.before),
+        Pattern::Contains(c) => c.extract_filename_pattern(inliner),
+        Pattern::Bubble(b) => b.extract_filename_pattern(inliner),
+        Pattern::Where(w) => w.extract_filename_pattern(inliner),
+        Pattern::Rewrite(rw) => extract_filename_pattern(&rw.left, inliner),
+        Pattern::Includes(inc) => extract_filename_pattern(&inc.includes, inliner),
+        Pattern::Every(every) => extract_filename_pattern(&every.pattern, inliner),
+        Pattern::Within(within) => extract_filename_pattern(&within.pattern, inliner),
+        Pattern::After(a) => extract_filename_pattern(&a.after, inliner),
+        Pattern::Before(b) => extract_filename_pattern(&b.before, inliner),
 
         // Mirror existing logic
         Pattern::Maybe(_) => Ok(Some(Pattern::Top)),
         Pattern::And(target) => {
-            let Some(patterns) = extract_filename_patterns_from_patterns(&target.patterns)? else {
+            let Some(patterns) =
+                extract_filename_patterns_from_patterns(&target.patterns, inliner)?
+            else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(patterns)))))
         }
         Pattern::Or(target) => {
-            let Some(patterns) = extract_filename_patterns_from_patterns(&target.patterns)? else {
+            let Some(patterns) =
+                extract_filename_patterns_from_patterns(&target.patterns, inliner)?
+            else {
                 return Ok(None);
             };
             Ok(Some(Pattern::Or(Box::new(Or::new(patterns)))))
         }
         Pattern::Any(target) => {
-            let Some(patterns) = extract_filename_patterns_from_patterns(&target.patterns)? else {
+            let Some(patterns) =
+                extract_filename_patterns_from_patterns(&target.patterns, inliner)?
+            else {
                 return Ok(None);
             };
             Ok(Some(Pattern::Any(Box::new(Any::new(patterns)))))
         }
-        Pattern::Some(some) => extract_filename_pattern(&some.pattern),
+        Pattern::Some(some) => extract_filename_pattern(&some.pattern, inliner),
+        Pattern::Call(call) => inliner.inline_pattern(call.index, extract_filename_pattern),
 
         Pattern::CallBuiltIn(call_built_in) if call_built_in.name == "log" => {
             Ok(Some(Pattern::Top))
         }
 
         Pattern::Add(add) => {
-            let Some(lhs) = extract_filename_pattern(&add.lhs)? else {
+            let Some(lhs) = extract_filename_pattern(&add.lhs, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_filename_pattern(&add.rhs)? else {
+            let Some(rhs) = extract_filename_pattern(&add.rhs, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
         }
         Pattern::Subtract(sub) => {
-            let Some(lhs) = extract_filename_pattern(&sub.lhs)? else {
+            let Some(lhs) = extract_filename_pattern(&sub.lhs, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_filename_pattern(&sub.rhs)? else {
+            let Some(rhs) = extract_filename_pattern(&sub.rhs, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
         }
         Pattern::Multiply(target) => {
-            let Some(lhs) = extract_filename_pattern(&target.lhs)? else {
+            let Some(lhs) = extract_filename_pattern(&target.lhs, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_filename_pattern(&target.rhs)? else {
+            let Some(rhs) = extract_filename_pattern(&target.rhs, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
         }
         Pattern::Divide(target) => {
-            let Some(lhs) = extract_filename_pattern(&target.lhs)? else {
+            let Some(lhs) = extract_filename_pattern(&target.lhs, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_filename_pattern(&target.rhs)? else {
+            let Some(rhs) = extract_filename_pattern(&target.rhs, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
         }
         Pattern::Modulo(target) => {
-            let Some(lhs) = extract_filename_pattern(&target.lhs)? else {
+            let Some(lhs) = extract_filename_pattern(&target.lhs, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_filename_pattern(&target.rhs)? else {
+            let Some(rhs) = extract_filename_pattern(&target.rhs, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
@@ -118,7 +127,6 @@ pub fn extract_filename_pattern<Q: QueryContext>(
         | Pattern::ListIndex(_)
         | Pattern::Map(_)
         | Pattern::Accessor(_)
-        | Pattern::Call(_)
         | Pattern::Regex(_)
         | Pattern::File(_)
         | Pattern::Files(_)
@@ -138,29 +146,29 @@ pub fn extract_filename_pattern<Q: QueryContext>(
 }
 
 impl<Q: QueryContext> FilenamePatternExtractor<Q> for Bubble<Q> {
-    fn extract_filename_pattern(&self) -> Result<Option<Pattern<Q>>> {
-        extract_filename_pattern(self.pattern_def.pattern())
+    fn extract_filename_pattern(&self, inliner: &mut Inliner<Q>) -> Result<Option<Pattern<Q>>> {
+        extract_filename_pattern(self.pattern_def.pattern(), inliner)
     }
 }
 
 impl<Q: QueryContext> FilenamePatternExtractor<Q> for Contains<Q> {
-    fn extract_filename_pattern(&self) -> Result<Option<Pattern<Q>>> {
-        extract_filename_pattern(&self.contains)
+    fn extract_filename_pattern(&self, inliner: &mut Inliner<Q>) -> Result<Option<Pattern<Q>>> {
+        extract_filename_pattern(&self.contains, inliner)
     }
 }
 
 impl<Q: QueryContext> FilenamePatternExtractor<Q> for Includes<Q> {
-    fn extract_filename_pattern(&self) -> Result<Option<Pattern<Q>>> {
-        extract_filename_pattern(&self.includes)
+    fn extract_filename_pattern(&self, inliner: &mut Inliner<Q>) -> Result<Option<Pattern<Q>>> {
+        extract_filename_pattern(&self.includes, inliner)
     }
 }
 
 impl<Q: QueryContext> FilenamePatternExtractor<Q> for Where<Q> {
-    fn extract_filename_pattern(&self) -> Result<Option<Pattern<Q>>> {
-        let pattern = extract_filename_pattern(&self.pattern)?.unwrap_or(Pattern::Top);
+    fn extract_filename_pattern(&self, inliner: &mut Inliner<Q>) -> Result<Option<Pattern<Q>>> {
+        let pattern = extract_filename_pattern(&self.pattern, inliner)?.unwrap_or(Pattern::Top);
         let predicate_pattern = self
             .side_condition
-            .extract_filename_pattern()?
+            .extract_filename_pattern(inliner)?
             .unwrap_or(Pattern::Top);
         Ok(Some(Pattern::And(Box::new(And::new(vec![
             pattern,
@@ -172,10 +180,11 @@ impl<Q: QueryContext> FilenamePatternExtractor<Q> for Where<Q> {
 /// Given a list of patterns, extract the filename patterns from each of them
 fn extract_filename_patterns_from_patterns<Q: QueryContext>(
     predicates: &[Pattern<Q>],
+    inliner: &mut Inliner<Q>,
 ) -> Result<Option<Vec<Pattern<Q>>>> {
     let mut patterns = vec![];
     for p in predicates {
-        let pattern = extract_filename_pattern(p)?;
+        let pattern = extract_filename_pattern(p, inliner)?;
         if let Some(pattern) = pattern {
             patterns.push(pattern);
         } else {
@@ -188,10 +197,11 @@ fn extract_filename_patterns_from_patterns<Q: QueryContext>(
 /// Given a list of predicates, extract the filename patterns from each of them
 fn extract_patterns_from_predicates<Q: QueryContext>(
     predicates: &[Predicate<Q>],
+    inliner: &mut Inliner<Q>,
 ) -> Result<Option<Vec<Pattern<Q>>>> {
     let mut patterns = vec![];
     for p in predicates {
-        let pattern = p.extract_filename_pattern()?;
+        let pattern = p.extract_filename_pattern(inliner)?;
         if let Some(pattern) = pattern {
             patterns.push(pattern);
         } else {
@@ -202,22 +212,25 @@ fn extract_patterns_from_predicates<Q: QueryContext>(
 }
 
 impl<Q: QueryContext> FilenamePatternExtractor<Q> for Predicate<Q> {
-    fn extract_filename_pattern(&self) -> Result<Option<Pattern<Q>>> {
+    fn extract_filename_pattern(&self, inliner: &mut Inliner<Q>) -> Result<Option<Pattern<Q>>> {
         match self {
             Predicate::And(target) => {
-                let Some(patterns) = extract_patterns_from_predicates(&target.predicates)? else {
+                let Some(patterns) = extract_patterns_from_predicates(&target.predicates, inliner)?
+                else {
                     return Ok(None);
                 };
                 Ok(Some(Pattern::And(Box::new(And::new(patterns)))))
             }
             Predicate::Or(target) => {
-                let Some(patterns) = extract_patterns_from_predicates(&target.predicates)? else {
+                let Some(patterns) = extract_patterns_from_predicates(&target.predicates, inliner)?
+                else {
                     return Ok(None);
                 };
                 Ok(Some(Pattern::Or(Box::new(Or::new(patterns)))))
             }
             Predicate::Any(target) => {
-                let Some(patterns) = extract_patterns_from_predicates(&target.predicates)? else {
+                let Some(patterns) = extract_patterns_from_predicates(&target.predicates, inliner)?
+                else {
                     return Ok(None);
                 };
                 Ok(Some(Pattern::Any(Box::new(Any::new(patterns)))))
@@ -228,7 +241,7 @@ impl<Q: QueryContext> FilenamePatternExtractor<Q> for Predicate<Q> {
                         if var.is_file_name() {
                             if let Some(pattern) = &m.pattern {
                                 // This is the key line of this entire file
-                                if is_safe_to_hoist(pattern)? {
+                                if is_safe_to_hoist(pattern, inliner)? {
                                     return Ok(Some(pattern.clone()));
                                 } else {
                                     return Ok(None);
@@ -242,7 +255,7 @@ impl<Q: QueryContext> FilenamePatternExtractor<Q> for Predicate<Q> {
                 };
 
                 if let Some(pattern) = &m.pattern {
-                    extract_filename_pattern(pattern)
+                    extract_filename_pattern(pattern, inliner)
                 } else {
                     // TODO: is this right? Why do we ever have an empty pattern?
                     Ok(None)
@@ -252,7 +265,7 @@ impl<Q: QueryContext> FilenamePatternExtractor<Q> for Predicate<Q> {
                 Ok(Some(Pattern::Top))
             }
 
-            Predicate::Rewrite(rw) => extract_filename_pattern(&rw.left),
+            Predicate::Rewrite(rw) => extract_filename_pattern(&rw.left, inliner),
             Predicate::CallBuiltIn(call_built_in) if call_built_in.name == "log" => {
                 Ok(Some(Pattern::Top))
             }
@@ -268,13 +281,13 @@ impl<Q: QueryContext> FilenamePatternExtractor<Q> for Predicate<Q> {
             // Either we need both the condition and the left to be true
             // OR we need the right to be true
             Predicate::If(target) => {
-                let Some(condition) = target.if_.extract_filename_pattern()? else {
+                let Some(condition) = target.if_.extract_filename_pattern(inliner)? else {
                     return Ok(None);
                 };
-                let Some(then) = target.then.extract_filename_pattern()? else {
+                let Some(then) = target.then.extract_filename_pattern(inliner)? else {
                     return Ok(None);
                 };
-                let Some(else_) = target.else_.extract_filename_pattern()? else {
+                let Some(else_) = target.else_.extract_filename_pattern(inliner)? else {
                     return Ok(None);
                 };
                 Ok(Some(Pattern::Or(Box::new(Or::new(vec![
@@ -283,11 +296,12 @@ impl<Q: QueryContext> FilenamePatternExtractor<Q> for Predicate<Q> {
                 ])))))
             }
 
+            Predicate::Call(call) => inliner.inline_predicate(call.index, |predicate, inliner| {
+                predicate.extract_filename_pattern(inliner)
+            }),
+
             // These are more complicated, implement carefully
-            Predicate::Call(_)
-            | Predicate::CallBuiltIn(_)
-            | Predicate::Not(_)
-            | Predicate::Equal(_) => Ok(None),
+            Predicate::CallBuiltIn(_) | Predicate::Not(_) | Predicate::Equal(_) => Ok(None),
         }
     }
 }
@@ -296,11 +310,26 @@ impl<Q: QueryContext> FilenamePatternExtractor<Q> for Predicate<Q> {
 // This is not a great implementation, but it's a start.
 // I think a better approach will actually be to introduce a Pattern::FailOpen idea where if any errors are encountered when resolving
 // a pattern, we can just assume it's true. This will allow us to hoist more patterns without worrying about unbound variables.
-pub(crate) fn is_safe_to_hoist<Q: QueryContext>(pattern: &Pattern<Q>) -> Result<bool> {
+pub(crate) fn is_safe_to_hoist<Q: QueryContext>(
+    pattern: &Pattern<Q>,
+    inliner: &mut Inliner<Q>,
+) -> Result<bool> {
     match pattern {
-        Pattern::Includes(inc) => is_safe_to_hoist(&inc.includes),
+        Pattern::Includes(inc) => is_safe_to_hoist(&inc.includes, inliner),
         Pattern::StringConstant(_) => Ok(true),
-        Pattern::Contains(c) => is_safe_to_hoist(&c.contains),
+        Pattern::Contains(c) => is_safe_to_hoist(&c.contains, inliner),
+        Pattern::Call(call) => {
+            for arg in call.args.iter().flatten() {
+                if !is_safe_to_hoist(arg, inliner)? {
+                    return Ok(false);
+                }
+            }
+            Ok(inliner
+                .inline_pattern(call.index, |pattern, inliner| {
+                    is_safe_to_hoist(pattern, inliner).map(Some)
+                })?
+                .unwrap_or(false))
+        }
 
         // We probably want to do some deeper analysis here
         Pattern::CodeSnippet(_snippet) => Ok(true),
@@ -311,7 +340,6 @@ pub(crate) fn is_safe_to_hoist<Q: QueryContext>(pattern: &Pattern<Q>) -> Result<
         | Pattern::ListIndex(_)
         | Pattern::Map(_)
         | Pattern::Accessor(_)
-        | Pattern::Call(_)
         | Pattern::Regex(_)
         | Pattern::File(_)
         | Pattern::Files(_)
@@ -5,8 +5,9 @@ use grit_pattern_matcher::{
         And, Any, Bubble, Contains, Includes, Or, Pattern, Predicate, StringConstant, Where,
     },
 };
+use grit_util::RequiredText;
 
-use super::hoist_files::is_safe_to_hoist;
+use super::{hoist_files::is_safe_to_hoist, Inliner};
 use grit_pattern_matcher::pattern::AstLeafNodePattern;
 use grit_pattern_matcher::pattern::CodeSnippet;
 
@@ -20,7 +21,11 @@ trait BodyPatternExtractor<Q: QueryContext> {
     // Look at pattern and attempt to extract the body pattern
     // If we are already matching against body, then we just need to extract the pattern itself
     // If we are looking at side conditions, we need to first find a `$program <: _` predicate
-    fn extract_body_pattern(&self, matching_body: bool) -> Result<Option<Pattern<Q>>>;
+    fn extract_body_pattern(
+        &self,
+        matching_body: bool,
+        inliner: &mut Inliner<Q>,
+    ) -> Result<Option<Pattern<Q>>>;
 }
 
 /// Extracts the *text* patterns from a pattern
@@ -104,6 +109,7 @@ fn extract_pattern_text<Q: QueryContext>(pattern: &Pattern<Q>) -> Result<Option<
 pub fn extract_body_pattern<Q: QueryContext>(
     pattern: &Pattern<Q>,
     matching_body: bool,
+    inliner: &mut Inliner<Q>,
 ) -> Result<Option<Pattern<Q>>> {
     match pattern {
         Pattern::Variable(_)
@@ -131,22 +137,22 @@ pub fn extract_body_pattern<Q: QueryContext>(
                     return Ok(Some(Pattern::Includes(Box::new(Includes::new(text)))));
                 }
             }
-            extract_body_pattern(&c.contains, matching_body)
+            extract_body_pattern(&c.contains, matching_body, inliner)
         }
-        Pattern::Bubble(b) => b.extract_body_pattern(matching_body),
-        Pattern::Where(w) => w.extract_body_pattern(matching_body),
-        Pattern::Rewrite(rw) => extract_body_pattern(&rw.left, matching_body),
-        Pattern::Includes(inc) => extract_body_pattern(&inc.includes, matching_body),
-        Pattern::Every(every) => extract_body_pattern(&every.pattern, matching_body),
-        Pattern::Within(within) => extract_body_pattern(&within.pattern, matching_body),
-        Pattern::After(a) => extract_body_pattern(&a.after, matching_body),
-        Pattern::Before(b) => extract_body_pattern(&b.before, matching_body),
+        Pattern::Bubble(b) => b.extract_body_pattern(matching_body, inliner),
+        Pattern::Where(w) => w.extract_body_pattern(matching_body, inliner),
+        Pattern::Rewrite(rw) => extract_body_pattern(&rw.left, matching_body, inliner),
+        Pattern::Includes(inc) => extract_body_pattern(&inc.includes, matching_body, inliner),
+        Pattern::Every(every) => extract_body_pattern(&every.pattern, matching_body, inliner),
+        Pattern::Within(within) => extract_body_pattern(&within.pattern, matching_body, inliner),
+        Pattern::After(a) => extract_body_pattern(&a.after, matching_body, inliner),
+        Pattern::Before(b) => extract_body_pattern(&b.before, matching_body, inliner),
 
         // Mirror existing logic
         Pattern::Maybe(_) => Ok(Some(Pattern::Top)),
         Pattern::And(target) => {
             let Some(patterns) =
-                extract_body_patterns_from_patterns(&target.patterns, matching_body)?
+                extract_body_patterns_from_patterns(&target.patterns, matching_body, inliner)?
             else {
                 return Ok(None);
             };
@@ -154,7 +160,7 @@ pub fn extract_body_pattern<Q: QueryContext>(
         }
         Pattern::Or(target) => {
             let Some(patterns) =
-                extract_body_patterns_from_patterns(&target.patterns, matching_body)?
+                extract_body_patterns_from_patterns(&target.patterns, matching_body, inliner)?
             else {
                 return Ok(None);
             };
@@ -162,59 +168,62 @@ pub fn extract_body_pattern<Q: QueryContext>(
         }
         Pattern::Any(target) => {
             let Some(patterns) =
-                extract_body_patterns_from_patterns(&target.patterns, matching_body)?
+                extract_body_patterns_from_patterns(&target.patterns, matching_body, inliner)?
             else {
                 return Ok(None);
             };
             Ok(Some(Pattern::Any(Box::new(Any::new(patterns)))))
         }
-        Pattern::Some(some) => extract_body_pattern(&some.pattern, matching_body),
+        Pattern::Some(some) => extract_body_pattern(&some.pattern, matching_body, inliner),
+        Pattern::Call(call) => inliner.inline_pattern(call.index, |pattern, inliner| {
+            extract_body_pattern(pattern, matching_body, inliner)
+        }),
 
         Pattern::CallBuiltIn(call_built_in) if call_built_in.name == "log" => {
             Ok(Some(Pattern::Top))
         }
 
         Pattern::Add(add) => {
-            let Some(lhs) = extract_body_pattern(&add.lhs, matching_body)? else {
+            let Some(lhs) = extract_body_pattern(&add.lhs, matching_body, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_body_pattern(&add.rhs, matching_body)? else {
+            let Some(rhs) = extract_body_pattern(&add.rhs, matching_body, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
         }
         Pattern::Subtract(sub) => {
-            let Some(lhs) = extract_body_pattern(&sub.lhs, matching_body)? else {
+            let Some(lhs) = extract_body_pattern(&sub.lhs, matching_body, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_body_pattern(&sub.rhs, matching_body)? else {
+            let Some(rhs) = extract_body_pattern(&sub.rhs, matching_body, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
         }
         Pattern::Multiply(target) => {
-            let Some(lhs) = extract_body_pattern(&target.lhs, matching_body)? else {
+            let Some(lhs) = extract_body_pattern(&target.lhs, matching_body, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_body_pattern(&target.rhs, matching_body)? else {
+            let Some(rhs) = extract_body_pattern(&target.rhs, matching_body, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
         }
         Pattern::Divide(target) => {
-            let Some(lhs) = extract_body_pattern(&target.lhs, matching_body)? else {
+            let Some(lhs) = extract_body_pattern(&target.lhs, matching_body, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_body_pattern(&target.rhs, matching_body)? else {
+            let Some(rhs) = extract_body_pattern(&target.rhs, matching_body, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
         }
         Pattern::Modulo(target) => {
-            let Some(lhs) = extract_body_pattern(&target.lhs, matching_body)? else {
+            let Some(lhs) = extract_body_pattern(&target.lhs, matching_body, inliner)? else {
                 return Ok(None);
             };
-            let Some(rhs) = extract_body_pattern(&target.rhs, matching_body)? else {
+            let Some(rhs) = extract_body_pattern(&target.rhs, matching_body, inliner)? else {
                 return Ok(None);
             };
             Ok(Some(Pattern::And(Box::new(And::new(vec![lhs, rhs])))))
@@ -229,7 +238,6 @@ pub fn extract_body_pattern<Q: QueryContext>(
         | Pattern::ListIndex(_)
         | Pattern::Map(_)
         | Pattern::Accessor(_)
-        | Pattern::Call(_)
         | Pattern::Regex(_)
         | Pattern::File(_)
         | Pattern::Files(_)
@@ -249,30 +257,47 @@ pub fn extract_body_pattern<Q: QueryContext>(
 }
 
 impl<Q: QueryContext> BodyPatternExtractor<Q> for Bubble<Q> {
-    fn extract_body_pattern(&self, matching_body: bool) -> Result<Option<Pattern<Q>>> {
-        extract_body_pattern(self.pattern_def.pattern(), matching_body)
+    fn extract_body_pattern(
+        &self,
+        matching_body: bool,
+        inliner: &mut Inliner<Q>,
+    ) -> Result<Option<Pattern<Q>>> {
+        extract_body_pattern(self.pattern_def.pattern(), matching_body, inliner)
     }
 }
 
 impl<Q: QueryContext> BodyPatternExtractor<Q> for Contains<Q> {
-    fn extract_body_pattern(&self, matching_body: bool) -> Result<Option<Pattern<Q>>> {
-        extract_body_pattern(&self.contains, matching_body)
+    fn extract_body_pattern(
+        &self,
+        matching_body: bool,
+        inliner: &mut Inliner<Q>,
+    ) -> Result<Option<Pattern<Q>>> {
+        extract_body_pattern(&self.contains, matching_body, inliner)
     }
 }
 
 impl<Q: QueryContext> BodyPatternExtractor<Q> for Includes<Q> {
-    fn extract_body_pattern(&self, matching_body: bool) -> Result<Option<Pattern<Q>>> {
-        extract_body_pattern(&self.includes, matching_body)
+    fn extract_body_pattern(
+        &self,
+        matching_body: bool,
+        inliner: &mut Inliner<Q>,
+    ) -> Result<Option<Pattern<Q>>> {
+        extract_body_pattern(&self.includes, matching_body, inliner)
     }
 }
 
 impl<Q: QueryContext> BodyPatternExtractor<Q> for Where<Q> {
-    fn extract_body_pattern(&self, matching_body: bool) -> Result<Option<Pattern<Q>>> {
-        let pattern = extract_body_pattern(&self.pattern, matching_body)?.unwrap_or(Pattern::Top);
+    fn extract_body_pattern(
+        &self,
+        matching_body: bool,
+        inliner: &mut Inliner<Q>,
+    ) -> Result<Option<Pattern<Q>>> {
+        let pattern =
+            extract_body_pattern(&self.pattern, matching_body, inliner)?.unwrap_or(Pattern::Top);
 
         let predicate_pattern = self
             .side_condition
-            .extract_body_pattern(false)?
+            .extract_body_pattern(false, inliner)?
             .unwrap_or(Pattern::Top);
 
         Ok(Some(Pattern::And(Box::new(And::new(vec![
@@ -285,10 +310,11 @@ impl<Q: QueryContext> BodyPatternExtractor<Q> for Where<Q> {
 fn extract_body_patterns_from_patterns<Q: QueryContext>(
     in_patterns: &[Pattern<Q>],
     matching_body: bool,
+    inliner: &mut Inliner<Q>,
 ) -> Result<Option<Vec<Pattern<Q>>>> {
     let mut patterns = vec![];
     for p in in_patterns {
-        let pattern = extract_body_pattern(p, matching_body)?;
+        let pattern = extract_body_pattern(p, matching_body, inliner)?;
         if let Some(pattern) = pattern {
             patterns.push(pattern);
         } else {
@@ -300,10 +326,11 @@ fn extract_body_patterns_from_patterns<Q: QueryContext>(
 
 fn extract_patterns_from_predicates<Q: QueryContext>(
     predicates: &[Predicate<Q>],
+    inliner: &mut Inliner<Q>,
 ) -> Result<Option<Vec<Pattern<Q>>>> {
     let mut patterns = vec![];
     for p in predicates {
-        let pattern = p.extract_body_pattern(false)?;
+        let pattern = p.extract_body_pattern(false, inliner)?;
         if let Some(pattern) = pattern {
             patterns.push(pattern);
         } else {
@@ -314,22 +341,29 @@ fn extract_patterns_from_predicates<Q: QueryContext>(
 }
 
 impl<Q: QueryContext> BodyPatternExtractor<Q> for Predicate<Q> {
-    fn extract_body_pattern(&self, _: bool) -> Result<Option<Pattern<Q>>> {
+    fn extract_body_pattern(
+        &self,
+        _: bool,
+        inliner: &mut Inliner<Q>,
+    ) -> Result<Option<Pattern<Q>>> {
         match self {
             Predicate::And(target) => {
-                let Some(patterns) = extract_patterns_from_predicates(&target.predicates)? else {
+                let Some(patterns) = extract_patterns_from_predicates(&target.predicates, inliner)?
+                else {
                     return Ok(None);
                 };
                 Ok(Some(Pattern::And(Box::new(And::new(patterns)))))
             }
             Predicate::Or(target) => {
-                let Some(patterns) = extract_patterns_from_predicates(&target.predicates)? else {
+                let Some(patterns) = extract_patterns_from_predicates(&target.predicates, inliner)?
+                else {
                     return Ok(None);
                 };
                 Ok(Some(Pattern::Or(Box::new(Or::new(patterns)))))
             }
             Predicate::Any(target) => {
-                let Some(patterns) = extract_patterns_from_predicates(&target.predicates)? else {
+                let Some(patterns) = extract_patterns_from_predicates(&target.predicates, inliner)?
+                else {
                     return Ok(None);
                 };
                 Ok(Some(Pattern::Any(Box::new(Any::new(patterns)))))
@@ -337,11 +371,12 @@ impl<Q: QueryContext> BodyPatternExtractor<Q> for Predicate<Q> {
             Predicate::Match(m) => {
                 match &m.val {
                     grit_pattern_matcher::pattern::Container::Variable(var) => {
-                        if var.is_program() || var.is_probably_match() {
+                        if var.is_program() || inliner.is_match(var) {
                             if let Some(pattern) = &m.pattern {
                                 // This is the key line of this entire file
-                                if is_safe_to_hoist(pattern)? {
-                                    let body_pattern = extract_body_pattern(pattern, true)?;
+                                if is_safe_to_hoist(pattern, inliner)? {
+                                    let body_pattern =
+                                        extract_body_pattern(pattern, true, inliner)?;
 
                                     if let Some(body_pattern) = body_pattern {
                                         return Ok(Some(body_pattern));
@@ -356,7 +391,7 @@ impl<Q: QueryContext> BodyPatternExtractor<Q> for Predicate<Q> {
                 };
 
                 if let Some(pattern) = &m.pattern {
-                    extract_body_pattern(pattern, false)
+                    extract_body_pattern(pattern, false, inliner)
                 } else {
                     // TODO: is this right? Why do we ever have an empty pattern?
                     Ok(None)
@@ -366,7 +401,7 @@ impl<Q: QueryContext> BodyPatternExtractor<Q> for Predicate<Q> {
                 Ok(Some(Pattern::Top))
             }
 
-            Predicate::Rewrite(rw) => extract_body_pattern(&rw.left, false),
+            Predicate::Rewrite(rw) => extract_body_pattern(&rw.left, false, inliner),
             Predicate::CallBuiltIn(call_built_in) if call_built_in.name == "log" => {
                 Ok(Some(Pattern::Top))
             }
@@ -382,13 +417,13 @@ impl<Q: QueryContext> BodyPatternExtractor<Q> for Predicate<Q> {
             // Either we need both the condition and the left to be true
             // OR we need the right to be true
             Predicate::If(target) => {
-                let Some(condition) = target.if_.extract_body_pattern(false)? else {
+                let Some(condition) = target.if_.extract_body_pattern(false, inliner)? else {
                     return Ok(None);
                 };
-                let Some(then) = target.then.extract_body_pattern(false)? else {
+                let Some(then) = target.then.extract_body_pattern(false, inliner)? else {
                     return Ok(None);
                 };
-                let Some(else_) = target.else_.extract_body_pattern(false)? else {
+                let Some(else_) = target.else_.extract_body_pattern(false, inliner)? else {
                     return Ok(None);
                 };
                 Ok(Some(Pattern::Or(Box::new(Or::new(vec![
@@ -397,12 +432,137 @@ impl<Q: QueryContext> BodyPatternExtractor<Q> for Predicate<Q> {
                 ])))))
             }
 
+            Predicate::Call(call) => inliner.inline_predicate(call.index, |predicate, inliner| {
+                predicate.extract_body_pattern(false, inliner)
+            }),
+
             // These are more complicated, implement carefully
-            Predicate::Call(_)
-            | Predicate::CallBuiltIn(_)
-            | Predicate::Not(_)
-            | Predicate::Equal(_) => Ok(None),
+            Predicate::CallBuiltIn(_) | Predicate::Not(_) | Predicate::Equal(_) => Ok(None),
+        }
+    }
+}
+
+/// Collects the literal text a file has to include for the wrapped
+/// `pattern` to match it, based on the `includes` checks hoisted by
+/// `extract_body_pattern()`.
+///
+/// Anything we don't recognize is assumed to match any file.
+pub fn required_text<Q: QueryContext>(pattern: &Pattern<Q>) -> RequiredText {
+    match pattern {
+        Pattern::Sequential(sequential) if sequential.0.len() == 1 => {
+            required_text(&sequential.0[0].pattern)
         }
+        Pattern::Sequential(_) => RequiredText::Anything,
+        Pattern::Limit(limit) => required_text(&limit.pattern),
+        Pattern::File(file) => required_text(&file.body),
+        Pattern::And(and) => RequiredText::all(and.patterns.iter().map(required_text)),
+        Pattern::Or(or) => RequiredText::one_of(or.patterns.iter().map(required_text)),
+        Pattern::Any(any) => RequiredText::one_of(any.patterns.iter().map(required_text)),
+        Pattern::Includes(includes) => included_text(&includes.includes),
+        Pattern::StringConstant(_)
+        | Pattern::CodeSnippet(_)
+        | Pattern::AstLeafNode(_)
+        | Pattern::AstNode(_)
+        | Pattern::List(_)
+        | Pattern::ListIndex(_)
+        | Pattern::Map(_)
+        | Pattern::Accessor(_)
+        | Pattern::Call(_)
+        | Pattern::Regex(_)
+        | Pattern::Files(_)
+        | Pattern::Bubble(_)
+        | Pattern::CallBuiltIn(_)
+        | Pattern::CallFunction(_)
+        | Pattern::CallForeignFunction(_)
+        | Pattern::CallbackPattern(_)
+        | Pattern::Assignment(_)
+        | Pattern::Accumulate(_)
+        | Pattern::Maybe(_)
+        | Pattern::Not(_)
+        | Pattern::If(_)
+        | Pattern::Undefined
+        | Pattern::Top
+        | Pattern::Bottom
+        | Pattern::Underscore
+        | Pattern::IntConstant(_)
+        | Pattern::FloatConstant(_)
+        | Pattern::BooleanConstant(_)
+        | Pattern::Dynamic(_)
+        | Pattern::Variable(_)
+        | Pattern::Rewrite(_)
+        | Pattern::Range(_)
+        | Pattern::Contains(_)
+        | Pattern::Within(_)
+        | Pattern::After(_)
+        | Pattern::Before(_)
+        | Pattern::Where(_)
+        | Pattern::Some(_)
+        | Pattern::Every(_)
+        | Pattern::Add(_)
+        | Pattern::Subtract(_)
+        | Pattern::Multiply(_)
+        | Pattern::Divide(_)
+        | Pattern::Modulo(_)
+        | Pattern::Dots
+        | Pattern::Like(_) => RequiredText::Anything,
+    }
+}
+
+fn included_text<Q: QueryContext>(pattern: &Pattern<Q>) -> RequiredText {
+    match pattern {
+        Pattern::StringConstant(constant) => RequiredText::literal(&constant.text),
+        Pattern::Or(or) => RequiredText::one_of(or.patterns.iter().map(included_text)),
+        Pattern::CodeSnippet(_)
+        | Pattern::AstLeafNode(_)
+        | Pattern::AstNode(_)
+        | Pattern::List(_)
+        | Pattern::ListIndex(_)
+        | Pattern::Map(_)
+        | Pattern::Accessor(_)
+        | Pattern::Call(_)
+        | Pattern::Regex(_)
+        | Pattern::File(_)
+        | Pattern::Files(_)
+        | Pattern::Bubble(_)
+        | Pattern::Limit(_)
+        | Pattern::CallBuiltIn(_)
+        | Pattern::CallFunction(_)
+        | Pattern::CallForeignFunction(_)
+        | Pattern::CallbackPattern(_)
+        | Pattern::Assignment(_)
+        | Pattern::Accumulate(_)
+        | Pattern::And(_)
+        | Pattern::Maybe(_)
+        | Pattern::Any(_)
+        | Pattern::Not(_)
+        | Pattern::If(_)
+        | Pattern::Undefined
+        | Pattern::Top
+        | Pattern::Bottom
+        | Pattern::Underscore
+        | Pattern::IntConstant(_)
+        | Pattern::FloatConstant(_)
+        | Pattern::BooleanConstant(_)
+        | Pattern::Dynamic(_)
+        | Pattern::Variable(_)
+        | Pattern::Rewrite(_)
+        | Pattern::Range(_)
+        | Pattern::Contains(_)
+        | Pattern::Includes(_)
+        | Pattern::Within(_)
+        | Pattern::After(_)
+        | Pattern::Before(_)
+        | Pattern::Where(_)
+        | Pattern::Some(_)
+        | Pattern::Every(_)
+        | Pattern::Add(_)
+        | Pattern::Subtract(_)
+        | Pattern::Multiply(_)
+        | Pattern::Divide(_)
+        | Pattern::Modulo(_)
+        | Pattern::Dots
+        | Pattern::Sequential(_)
+        | Pattern::Like(_) => RequiredText::Anything,
     }
 }
 
@@ -549,4 +709,102 @@ mod test {
         println!("{:?}", results);
         assert!(results.iter().any(|r| r.is_match()));
     }
+
+    #[test]
+    fn test_hoists_through_pattern_calls() {
+        let libs = BTreeMap::new();
+
+        let pattern = src_to_problem_libs(
+            r#"
+        pattern find_console() {
+            `console`
+        }
+
+        pattern wrapper() {
+            find_console()
+        }
+
+        wrapper()
+        "#
+            .to_string(),
+            &libs,
+            TargetLanguage::default(),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+        .problem;
+
+        // The text is hoisted just like in `test_basic_file_contains`
+        let formatted = format!("pattern: {:?}", pattern);
+        assert!(formatted.contains("includes: Or(Or { patterns: [StringConstant("));
+
+        let test_files = vec![
+            SyntheticFile::new(
+                "target.js".to_owned(),
+                "console.log(\"Hello, world!\");".to_owned(),
+                true,
+            ),
+            SyntheticFile::new("other.js".to_owned(), "funcify(42);".to_owned(), true),
+        ];
+        let results = run_on_test_files(&pattern, &test_files);
+        assert!(results.iter().any(|r| r.is_match()));
+    }
+
+    #[test]
+    fn test_does_not_follow_recursive_calls_forever() {
+        let libs = BTreeMap::new();
+
+        let pattern = src_to_problem_libs(
+            r#"
+        pattern nested_call() {
+            `$_($args)` where { $args <: contains nested_call() }
+        }
+
+        nested_call()
+        "#
+            .to_string(),
+            &libs,
+            TargetLanguage::default(),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+        .problem;
+
+        let formatted = format!("pattern: {:?}", pattern);
+        assert!(!formatted.contains("includes: Or(Or { patterns: [StringConstant("));
+    }
+
+    #[test]
+    fn test_required_text_of_hoisted_pattern() {
+        let libs = BTreeMap::new();
+
+        let pattern = src_to_problem_libs(
+            r#"
+        `funcify` where {
+            $program <: contains `console`
+        }
+        "#
+            .to_string(),
+            &libs,
+            TargetLanguage::default(),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+        .problem;
+
+        let required = pattern.required_text();
+        assert!(!required.may_be_satisfied(&mut |literal| "funcify()".contains(literal)));
+        assert!(
+            required.may_be_satisfied(&mut |literal| "console.log(1); funcify()".contains(literal))
+        );
+    }
 }
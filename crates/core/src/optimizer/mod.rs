@@ -1,2 +1,116 @@
+use anyhow::Result;
+use grit_pattern_matcher::{
+    context::{QueryContext, StaticDefinitions},
+    pattern::{Pattern, Predicate, Variable},
+};
+
 pub mod hoist_files;
 pub mod hoist_text;
+
+/// Follows calls into the pattern and predicate definitions they refer to,
+/// so patterns which merely wrap named sub-patterns are optimized as if the
+/// sub-patterns were written inline.
+pub struct Inliner<'a, Q: QueryContext> {
+    definitions: &'a StaticDefinitions<'a, Q>,
+    /// The definitions currently being inlined. These are not inlined again,
+    /// so recursive definitions are not followed forever.
+    inlining: Vec<Definition>,
+    /// The `$match` variable the main pattern was wrapped with, if any.
+    match_variable: Option<Variable>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Definition {
+    Pattern(usize),
+    Predicate(usize),
+}
+
+impl<'a, Q: QueryContext> Inliner<'a, Q> {
+    pub fn new(definitions: &'a StaticDefinitions<'a, Q>) -> Self {
+        Self {
+            definitions,
+            inlining: Vec::new(),
+            match_variable: None,
+        }
+    }
+
+    pub fn with_match_variable(mut self, match_variable: Option<Variable>) -> Self {
+        self.match_variable = match_variable;
+        self
+    }
+
+    /// Whether we are inside of a definition, where variables may refer to
+    /// parameters rather than to nodes of the file.
+    pub fn is_inlining(&self) -> bool {
+        !self.inlining.is_empty()
+    }
+
+    /// Whether `variable` is bound to the nodes matched by the main pattern,
+    /// so that anything it matches is part of the program text.
+    ///
+    /// Without a known `$match` variable, we fall back to guessing by scope.
+    /// This is never the case inside of definitions, which have scopes of
+    /// their own.
+    pub fn is_match(&self, variable: &Variable) -> bool {
+        if self.is_inlining() {
+            return false;
+        }
+        match &self.match_variable {
+            Some(match_variable) => {
+                let location = |variable: &Variable| {
+                    Some((variable.try_scope().ok()?, variable.try_index().ok()?))
+                };
+                location(variable).is_some() && location(variable) == location(match_variable)
+            }
+            None => variable.is_probably_match(),
+        }
+    }
+
+    /// Runs `analyze()` on the body of the pattern definition at `index`.
+    ///
+    /// Returns `None` if the definition is unknown or already being inlined.
+    pub fn inline_pattern<T>(
+        &mut self,
+        index: usize,
+        analyze: impl FnOnce(&'a Pattern<Q>, &mut Self) -> Result<Option<T>>,
+    ) -> Result<Option<T>> {
+        let definitions = self.definitions;
+        let Some(definition) = definitions.get_pattern(index) else {
+            return Ok(None);
+        };
+        self.inline(Definition::Pattern(index), |inliner| {
+            analyze(definition.pattern(), inliner)
+        })
+    }
+
+    /// Runs `analyze()` on the body of the predicate definition at `index`.
+    ///
+    /// Returns `None` if the definition is unknown or already being inlined.
+    pub fn inline_predicate<T>(
+        &mut self,
+        index: usize,
+        analyze: impl FnOnce(&'a Predicate<Q>, &mut Self) -> Result<Option<T>>,
+    ) -> Result<Option<T>> {
+        let definitions = self.definitions;
+        let Some(definition) = definitions.get_predicate(index) else {
+            return Ok(None);
+        };
+        self.inline(Definition::Predicate(index), |inliner| {
+            analyze(&definition.predicate, inliner)
+        })
+    }
+
+    fn inline<T>(
+        &mut self,
+        definition: Definition,
+        analyze: impl FnOnce(&mut Self) -> Result<Option<T>>,
+    ) -> Result<Option<T>> {
+        if self.inlining.contains(&definition) {
+            return Ok(None);
+        }
+        self.inlining.push(definition);
+        let result = analyze(self);
+        self.inlining.pop();
+        result
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    optimizer::{hoist_files::extract_filename_pattern, hoist_text::extract_body_pattern},
+    optimizer::{hoist_files::extract_filename_pattern, hoist_text::extract_body_pattern, Inliner},
     problem::MarzanoQueryContext,
 };
 
@@ -7,10 +7,11 @@ use super::compiler::{DefinitionInfo, SnippetCompilationContext};
 use anyhow::Result;
 use grit_pattern_matcher::{
     constants::GRIT_RANGE_VAR,
+    context::StaticDefinitions,
     pattern::{
         And, Bubble, Call, Container, Contains, FilePattern, Includes, Limit, Match, Maybe,
-        Pattern, PatternDefinition, PrAnd, PrOr, Predicate, Range as PRange, Rewrite, Step,
-        StringConstant, Variable, Where,
+        Pattern, PatternDefinition, PrAnd, PrOr, Predicate, PredicateDefinition, Range as PRange,
+        Rewrite, Step, StringConstant, Variable, Where,
     },
 };
 use grit_util::FileRange;
@@ -19,6 +20,7 @@ use log::debug;
 pub(crate) fn auto_wrap_pattern(
     pattern: Pattern<MarzanoQueryContext>,
     pattern_definitions: &mut [PatternDefinition<MarzanoQueryContext>],
+    predicate_definitions: &[PredicateDefinition<MarzanoQueryContext>],
     is_not_multifile: bool,
     file_ranges: Option<Vec<FileRange>>,
     context: &mut dyn SnippetCompilationContext,
@@ -43,13 +45,16 @@ pub(crate) fn auto_wrap_pattern(
         } else {
             pattern
         };
-        let first_wrap = if should_wrap_in_contains {
+        let (first_wrap, match_variable) = if should_wrap_in_contains {
             wrap_pattern_in_contains(pattern, context)?
         } else {
-            pattern
+            (pattern, None)
         };
         let second_wrap = if should_wrap_in_file {
-            wrap_pattern_in_file(first_wrap)?
+            let definitions =
+                StaticDefinitions::new(pattern_definitions, predicate_definitions, &[]);
+            let inliner = Inliner::new(&definitions).with_match_variable(match_variable);
+            wrap_pattern_in_file(first_wrap, inliner)?
         } else {
             first_wrap
         };
@@ -410,11 +415,14 @@ fn wrap_pattern_in_range(
     Ok(pattern)
 }
 
+/// Wraps the pattern in a contains, binding each match to `$match` if the
+/// context supports it. Returns the wrapped pattern along with `$match`.
 fn wrap_pattern_in_contains(
     pattern: Pattern<MarzanoQueryContext>,
     context: &mut dyn SnippetCompilationContext,
-) -> Result<Pattern<MarzanoQueryContext>> {
-    let pattern = if let Ok(var) = context.register_match_variable() {
+) -> Result<(Pattern<MarzanoQueryContext>, Option<Variable>)> {
+    let match_variable = context.register_match_variable().ok();
+    let pattern = if let Some(var) = &match_variable {
         Pattern::Where(Box::new(Where::new(
             Pattern::Variable(var.clone()),
             Predicate::Match(Box::new(Match::new(
@@ -428,7 +436,10 @@ fn wrap_pattern_in_contains(
 
     let pattern_definition = context.register_ephemeral_pattern(pattern)?;
     let bubble = Pattern::Bubble(Box::new(Bubble::new(pattern_definition, vec![])));
-    Ok(Pattern::Contains(Box::new(Contains::new(bubble, None))))
+    Ok((
+        Pattern::Contains(Box::new(Contains::new(bubble, None))),
+        match_variable,
+    ))
 }
 
 /// Wraps the pattern in a file pattern, so it can match directly against files
@@ -436,6 +447,9 @@ fn wrap_pattern_in_contains(
 /// - avoid unnecessary file loading by hoisting $filename matches
 /// - attempt to inject pure text matches through "includes"
 ///
+/// Calls to pattern and predicate definitions are followed, so patterns that
+/// only wrap other patterns get the same optimizations.
+///
 /// For example:
 /// ```grit
 /// `console.log($foo)` where {
@@ -453,20 +467,22 @@ fn wrap_pattern_in_contains(
 /// ```
 fn wrap_pattern_in_file(
     pattern: Pattern<MarzanoQueryContext>,
+    mut inliner: Inliner<MarzanoQueryContext>,
 ) -> Result<Pattern<MarzanoQueryContext>> {
-    let filename_pattern = extract_filename_pattern(&pattern)?.unwrap_or_else(|| {
+    let filename_pattern = extract_filename_pattern(&pattern, &mut inliner)?.unwrap_or_else(|| {
         debug!("Optimization skipped: no filename pattern found, wrapping in top pattern");
         Pattern::Top
     });
 
-    let pattern = if let Some(file_body_pattern) = extract_body_pattern(&pattern, true)? {
-        Pattern::File(Box::new(FilePattern::new(
-            filename_pattern,
-            Pattern::And(Box::new(And::new(vec![file_body_pattern, pattern]))),
-        )))
-    } else {
-        Pattern::File(Box::new(FilePattern::new(filename_pattern, pattern)))
-    };
+    let pattern =
+        if let Some(file_body_pattern) = extract_body_pattern(&pattern, true, &mut inliner)? {
+            Pattern::File(Box::new(FilePattern::new(
+                filename_pattern,
+                Pattern::And(Box::new(And::new(vec![file_body_pattern, pattern]))),
+            )))
+        } else {
+            Pattern::File(Box::new(FilePattern::new(filename_pattern, pattern)))
+        };
     Ok(pattern)
 }
 
@@ -215,6 +215,7 @@ impl CompiledPatternBuilder {
         let pattern = auto_wrap_pattern(
             self.pattern,
             &mut self.pattern_definitions,
+            &self.predicate_definitions,
             !self.is_multifile,
             file_ranges,
             &mut node_context,
@@ -2,7 +2,7 @@ use super::{
     compiler::NodeCompilationContext, node_compiler::NodeCompiler,
     pattern_compiler::PatternCompiler, predicate_compiler::PredicateCompiler,
 };
-use crate::problem::MarzanoQueryContext;
+use crate::{lint::LINT_WARNING_LEVEL, problem::MarzanoQueryContext};
 use anyhow::{anyhow, Result};
 use grit_pattern_matcher::{
     context::StaticDefinitions,
@@ -34,7 +34,7 @@ impl NodeCompiler for NotCompiler {
             )
         }) {
             let log = AnalysisLogBuilder::default()
-                .level(441_u16)
+                .level(LINT_WARNING_LEVEL)
                 .file(context.compilation.file)
                 .source(node.source)
                 .position(range.start)
@@ -70,7 +70,7 @@ impl NodeCompiler for PrNotCompiler {
             )
         }) {
             let log = AnalysisLogBuilder::default()
-                .level(441_u16)
+                .level(LINT_WARNING_LEVEL)
                 .file(context.compilation.file)
                 .source(node.source)
                 .position(range.start)
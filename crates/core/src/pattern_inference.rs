@@ -0,0 +1,292 @@
+use crate::{
+    api::MatchResult,
+    pattern_compiler::{src_to_problem_libs, CompilationResult},
+};
+use anyhow::{bail, Context, Result};
+use grit_util::{traverse, Ast, AstNode, FileOrigin, Language, Order};
+use marzano_language::{
+    language::{MarzanoLanguage, Tree},
+    target_language::{PatternLanguage, TargetLanguage},
+};
+use marzano_util::{
+    node_with_source::NodeWithSource, rich_path::RichFile, runtime::ExecutionContext,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+/// A rewrite pattern inferred from a before and after example.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InferredPattern {
+    pub pattern: String,
+    /// The metavariables which code kept by the change was generalized into.
+    pub variables: Vec<String>,
+    /// Whether running the pattern on the before example reproduces the after example.
+    pub validated: bool,
+}
+
+/// Infers a rewrite pattern which turns `before` into `after`.
+///
+/// The pattern rewrites the smallest node enclosing the change. Leaves which
+/// are carried over from `before` to `after` are generalized into
+/// metavariables, while the leaves which changed are kept as they are. If the
+/// generalized pattern does not reproduce `after`, the literal one is returned.
+pub fn infer_rewrite(
+    lang: &TargetLanguage,
+    path: &Path,
+    before: &str,
+    after: &str,
+) -> Result<InferredPattern> {
+    if before == after {
+        bail!("The before and after examples are identical, there is no change to infer");
+    }
+    let before_tree = parse_example(lang, path, before, "before")?;
+    let after_tree = parse_example(lang, path, after, "after")?;
+    let (before_node, after_node) = changed_nodes(&before_tree, &after_tree);
+
+    for node in [&before_node, &after_node] {
+        let text = node.text()?;
+        if text.contains('`') {
+            bail!("The changed code contains a backtick, so it cannot be written as a snippet");
+        }
+        if lang.metavariable_regex().is_match(&text) {
+            bail!("The changed code would be read as containing metavariables");
+        }
+    }
+
+    let before_leaves = leaves(&before_node);
+    let after_leaves = leaves(&after_node);
+    let kept = after_leaves
+        .iter()
+        .map(|leaf| Ok(leaf.text()?.to_string()))
+        .collect::<Result<HashSet<_>>>()?;
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut variables = Vec::new();
+    for leaf in &before_leaves {
+        let text = leaf.text()?.to_string();
+        if kept.contains(&text) && !names.contains_key(&text) {
+            let name = variable_name(lang, leaf.node.kind().as_ref(), &variables);
+            variables.push(name.clone());
+            names.insert(text, name);
+        }
+    }
+
+    let language = PatternLanguage::from(lang);
+    if !variables.is_empty() {
+        let pattern = rewrite_pattern(
+            language,
+            &substitute(&before_node, &before_leaves, &names)?,
+            &substitute(&after_node, &after_leaves, &names)?,
+        );
+        if reproduces(&pattern, lang, path, before, after) {
+            return Ok(InferredPattern {
+                pattern,
+                variables,
+                validated: true,
+            });
+        }
+    }
+    let pattern = rewrite_pattern(language, &before_node.text()?, &after_node.text()?);
+    let validated = reproduces(&pattern, lang, path, before, after);
+    Ok(InferredPattern {
+        pattern,
+        variables: vec![],
+        validated,
+    })
+}
+
+fn parse_example(lang: &TargetLanguage, path: &Path, source: &str, name: &str) -> Result<Tree> {
+    let tree = lang
+        .get_parser()
+        .parse_file(source, Some(path), &mut vec![].into(), FileOrigin::Fresh)
+        .with_context(|| format!("Failed to parse the {} example", name))?;
+    if tree.root_node().node.has_error() {
+        bail!(
+            "The {} example does not parse as {}",
+            name,
+            PatternLanguage::from(lang)
+        );
+    }
+    Ok(tree)
+}
+
+/// Finds the smallest nodes enclosing the change, which are surrounded by the
+/// same code in both examples.
+fn changed_nodes<'t>(
+    before: &'t Tree,
+    after: &'t Tree,
+) -> (NodeWithSource<'t>, NodeWithSource<'t>) {
+    let (before_source, after_source) = (before.source.as_str(), after.source.as_str());
+    let mut start = common_prefix(before_source, after_source);
+    let mut suffix = common_suffix(before_source, after_source, start);
+    // The enclosing nodes of either example may begin or end further out than
+    // the other's, so widen both until they agree
+    loop {
+        let before_node = covering_node(before.root_node(), start, before_source.len() - suffix);
+        let after_node = covering_node(after.root_node(), start, after_source.len() - suffix);
+        let (before_range, after_range) = (before_node.byte_range(), after_node.byte_range());
+        let next_start = before_range.start.min(after_range.start);
+        let next_suffix =
+            (before_source.len() - before_range.end).min(after_source.len() - after_range.end);
+        if next_start == start && next_suffix == suffix {
+            return (before_node, after_node);
+        }
+        start = next_start;
+        suffix = next_suffix;
+    }
+}
+
+fn covering_node(root: NodeWithSource<'_>, start: usize, end: usize) -> NodeWithSource<'_> {
+    let mut node = root;
+    'descend: loop {
+        for child in node.named_children() {
+            let range = child.byte_range();
+            if range.start <= start && end <= range.end {
+                node = child;
+                continue 'descend;
+            }
+        }
+        return node;
+    }
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(a.len().min(b.len()), |((index, _), _)| index)
+}
+
+fn common_suffix(a: &str, b: &str, prefix: usize) -> usize {
+    a[prefix..]
+        .chars()
+        .rev()
+        .zip(b[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum()
+}
+
+/// The named nodes without named children under `node`, in source order.
+fn leaves<'t>(node: &NodeWithSource<'t>) -> Vec<NodeWithSource<'t>> {
+    traverse(node.walk(), Order::Pre)
+        .filter(|node| node.node.is_named() && node.named_children().next().is_none())
+        .collect()
+}
+
+/// Names a metavariable after the kind of node it stands for.
+fn variable_name(lang: &TargetLanguage, kind: &str, taken: &[String]) -> String {
+    let mut base: String = kind
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    if !base.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        base.insert_str(0, "var_");
+    }
+    let prefix = lang.metavariable_prefix();
+    let mut name = format!("{}{}", prefix, base);
+    let mut suffix = 2;
+    while taken.contains(&name) {
+        name = format!("{}{}_{}", prefix, base, suffix);
+        suffix += 1;
+    }
+    name
+}
+
+/// Returns the text of `node`, with the leaves named in `names` replaced by their metavariable.
+fn substitute(
+    node: &NodeWithSource,
+    leaves: &[NodeWithSource],
+    names: &HashMap<String, String>,
+) -> Result<String> {
+    let offset = node.byte_range().start;
+    let mut text = node.text()?.to_string();
+    // Leaves never overlap, so replacing the last one first keeps the earlier offsets valid
+    for leaf in leaves.iter().rev() {
+        let Some(name) = names.get(leaf.text()?.as_ref()) else {
+            continue;
+        };
+        let range = leaf.byte_range();
+        text.replace_range(range.start - offset..range.end - offset, name);
+    }
+    Ok(text)
+}
+
+fn rewrite_pattern(language: PatternLanguage, before: &str, after: &str) -> String {
+    format!("language {}\n\n`{}` => `{}`\n", language, before, after)
+}
+
+fn reproduces(
+    pattern: &str,
+    lang: &TargetLanguage,
+    path: &Path,
+    before: &str,
+    after: &str,
+) -> bool {
+    let Ok(CompilationResult { problem, .. }) = src_to_problem_libs(
+        pattern.to_string(),
+        &BTreeMap::new(),
+        lang.clone(),
+        None,
+        None,
+        None,
+        None,
+    ) else {
+        return false;
+    };
+    let file = RichFile::new(path.to_string_lossy().to_string(), before.to_string());
+    problem
+        .execute_file(&file, &ExecutionContext::default())
+        .iter()
+        .any(|result| {
+            if let MatchResult::Rewrite(rewrite) = result {
+                rewrite.content().is_ok_and(|content| content == after)
+            } else {
+                false
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(before: &str, after: &str) -> Result<InferredPattern> {
+        let lang = TargetLanguage::from_extension("ts").unwrap();
+        infer_rewrite(&lang, Path::new("example.ts"), before, after)
+    }
+
+    #[test]
+    fn generalizes_kept_leaves() {
+        let inferred = infer("foo(a, b);\n", "bar(b, a);\n").unwrap();
+        assert_eq!(
+            inferred.pattern,
+            "language js\n\n`foo($identifier, $identifier_2)` => `bar($identifier_2, $identifier)`\n"
+        );
+        assert_eq!(inferred.variables, vec!["$identifier", "$identifier_2"]);
+        assert!(inferred.validated);
+    }
+
+    #[test]
+    fn keeps_changed_code_literal() {
+        let inferred = infer(
+            "const x = 1;\nconsole.log(x);\n",
+            "const x = 1;\nlogger.info(x);\n",
+        )
+        .unwrap();
+        assert_eq!(
+            inferred.pattern,
+            "language js\n\n`console.log` => `logger.info`\n"
+        );
+        assert!(inferred.variables.is_empty());
+        assert!(inferred.validated);
+    }
+
+    #[test]
+    fn rejects_identical_examples() {
+        assert!(infer("foo();\n", "foo();\n").is_err());
+    }
+}
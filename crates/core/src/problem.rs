@@ -3,26 +3,32 @@ use crate::{
     ast_node::{ASTNode, AstLeafNode},
     built_in_functions::BuiltIns,
     foreign_function_definition::ForeignFunctionDefinition,
+    limits::limit_exceeded_log,
     marzano_binding::MarzanoBinding,
     marzano_code_snippet::MarzanoCodeSnippet,
-    marzano_context::MarzanoContext,
+    marzano_context::{MarzanoContext, ParsedFiles},
     marzano_resolved_pattern::{MarzanoFile, MarzanoResolvedPattern},
+    optimizer::hoist_text::required_text,
     pattern_compiler::compiler::VariableLocations,
 };
 use anyhow::{bail, Result};
 use grit_pattern_matcher::{
     constants::{GLOBAL_VARS_SCOPE_INDEX, NEW_FILES_INDEX},
     context::{QueryContext, StaticDefinitions},
+    coverage::{coverage_report, DefinitionCoverage},
     file_owners::FileOwners,
     pattern::{
         FilePtr, FileRegistry, GritFunctionDefinition, Matcher, Pattern, PatternDefinition,
         PredicateDefinition, ResolvedPattern, State,
     },
 };
-use grit_util::VariableMatch;
+use grit_util::{error::GritPatternError, ClauseCoverage, RequiredText, VariableMatch};
 
 use log::error;
-use marzano_language::{language::Tree, target_language::TargetLanguage};
+use marzano_language::{
+    language::Tree,
+    target_language::{PatternLanguage, TargetLanguage},
+};
 use marzano_util::{
     cache::{GritCache, NullCache},
     hasher::hash,
@@ -83,6 +89,27 @@ impl Problem {
         }
         defs
     }
+
+    /// Reports which clauses of the pattern and its definitions were reached
+    /// by executions that recorded into `coverage`.
+    ///
+    /// The executions must have used this problem, via an `ExecutionContext`
+    /// built with `with_clause_coverage`.
+    pub fn coverage_report(&self, coverage: &ClauseCoverage) -> Vec<DefinitionCoverage> {
+        let name = self.name.as_deref().unwrap_or("<pattern>");
+        coverage_report(name, &self.pattern, &self.definitions(), coverage)
+    }
+
+    /// The literal text a file needs to contain for the pattern to match it.
+    ///
+    /// Multifile patterns may match files regardless of their own contents,
+    /// so they never require any text.
+    pub fn required_text(&self) -> RequiredText {
+        if self.is_multifile {
+            return RequiredText::Anything;
+        }
+        required_text(&self.pattern)
+    }
 }
 
 enum FilePattern {
@@ -262,8 +289,22 @@ impl Problem {
         binding: FilePattern,
         owned_files: &FileOwners<Tree>,
         context: &ExecutionContext,
-        mut done_files: HashMap<String, DoneFile>,
+        done_files: HashMap<String, DoneFile>,
     ) {
+        let outputs =
+            self.execute_and_collect(files, binding, owned_files, context, done_files, None);
+        send(tx, outputs);
+    }
+
+    fn execute_and_collect(
+        &self,
+        files: Vec<impl LoadableFile>,
+        binding: FilePattern,
+        owned_files: &FileOwners<Tree>,
+        context: &ExecutionContext,
+        mut done_files: HashMap<String, DoneFile>,
+        parsed_files: Option<&ParsedFiles>,
+    ) -> Vec<MatchResult> {
         let file_names: Vec<PathBuf> = files
             .iter()
             .map(|f| PathBuf::from_str(&f.name()).unwrap())
@@ -274,37 +315,65 @@ impl Problem {
             .map(|file| Box::new(file) as Box<dyn LoadableFile>)
             .collect();
 
-        let mut outputs =
-            match self.execute(binding, lazy_files, borrowed_names, owned_files, context) {
-                Result::Err(err) => file_names
-                    .iter()
-                    .map(|file| {
-                        MatchResult::AnalysisLog(AnalysisLog::new_error(
-                            err.to_string(),
-                            &file.to_string_lossy(),
-                        ))
-                    })
-                    .collect(),
-                Result::Ok(messages) => {
-                    // For each message, mark the DoneFile as having results
-                    for message in &messages {
-                        if !is_match(message) {
-                            continue;
-                        }
-                        if let Some(name) = message.file_name() {
-                            // .unwrap() is safe, because from_str is infallible
-                            let path = PathBuf::from_str(name).unwrap();
-                            if let Some(done_file) =
-                                done_files.get_mut(path.to_string_lossy().as_ref())
-                            {
-                                done_file.has_results = Some(true);
-                            }
+        let mut outputs = match self.execute(
+            binding,
+            lazy_files,
+            borrowed_names,
+            owned_files,
+            context,
+            parsed_files,
+        ) {
+            // Cancelled files are left out entirely, as if they were never
+            // visited, so they are neither reported nor cached as done
+            Result::Err(err) if matches!(err.downcast_ref(), Some(GritPatternError::Cancelled)) => {
+                return vec![];
+            }
+            Result::Err(err) => {
+                if let Some(GritPatternError::LimitExceeded(reason)) = err.downcast_ref() {
+                    file_names
+                        .iter()
+                        .map(|file| {
+                            MatchResult::AnalysisLog(
+                                limit_exceeded_log(
+                                    self.name.as_deref(),
+                                    &file.to_string_lossy(),
+                                    reason,
+                                )
+                                .into(),
+                            )
+                        })
+                        .collect()
+                } else {
+                    file_names
+                        .iter()
+                        .map(|file| {
+                            MatchResult::AnalysisLog(AnalysisLog::new_error(
+                                err.to_string(),
+                                &file.to_string_lossy(),
+                            ))
+                        })
+                        .collect()
+                }
+            }
+            Result::Ok(messages) => {
+                // For each message, mark the DoneFile as having results
+                for message in &messages {
+                    if !is_match(message) {
+                        continue;
+                    }
+                    if let Some(name) = message.file_name() {
+                        // .unwrap() is safe, because from_str is infallible
+                        let path = PathBuf::from_str(name).unwrap();
+                        if let Some(done_file) = done_files.get_mut(path.to_string_lossy().as_ref())
+                        {
+                            done_file.has_results = Some(true);
                         }
                     }
-
-                    messages
                 }
-            };
+
+                messages
+            }
+        };
 
         outputs.extend(done_files.into_values().map(MatchResult::DoneFile));
 
@@ -312,7 +381,7 @@ impl Problem {
             // to keep snapshot tests happy, not ideal;
             outputs.sort();
         }
-        send(tx, outputs);
+        outputs
     }
 
     pub fn execute_files(
@@ -388,6 +457,9 @@ impl Problem {
     }
 
     /// Given a vec of paths, execute the problem on each path and stream the results
+    ///
+    /// Once the context is cancelled, files which have not been fully executed
+    /// are skipped, so no results or `DoneFile` messages are sent for them
     pub fn execute_paths_streaming(
         &self,
         files: Vec<PathBuf>,
@@ -403,6 +475,8 @@ impl Problem {
     /// Files that match from the input channel are executed by this pattern
     /// All other message types are simply forwarded to the output channel
     ///
+    /// Once the context is cancelled, incoming files are no longer executed,
+    /// but other messages are still forwarded
     pub fn execute_streaming_relay(
         &self,
         incoming_rx: Receiver<Vec<MatchResult>>,
@@ -482,6 +556,9 @@ impl Problem {
         #[cfg(feature = "grit_tracing")]
         let parent_cx = parent_span.context();
 
+        if context.is_cancelled() {
+            return;
+        }
         if self.is_multifile {
             self.build_and_execute_resolved_pattern(&tx, files, context, &NullCache::new());
         } else {
@@ -498,6 +575,9 @@ impl Problem {
                     event!(Level::INFO, "spawn execute_shared_body");
 
                     files.into_par_iter().for_each_with(tx, |sender, f| {
+                        if context.is_cancelled() {
+                            return;
+                        }
                         let vec = vec![f];
                         self.build_and_execute_resolved_pattern(sender, vec, context, cache);
                     });
@@ -541,6 +621,7 @@ impl Problem {
         file_names: Vec<&Path>,
         owned_files: &FileOwners<Tree>,
         context: &ExecutionContext,
+        parsed_files: Option<&ParsedFiles>,
     ) -> Result<Vec<MatchResult>> {
         let mut user_logs = vec![].into();
 
@@ -558,6 +639,10 @@ impl Problem {
             context,
             self.name.clone(),
         );
+        let context = match parsed_files {
+            Some(parsed_files) => context.with_parsed_files(parsed_files),
+            None => context,
+        };
 
         let bindings = self.variables.initial_bindings();
 
@@ -593,6 +678,104 @@ impl Problem {
     }
 }
 
+/// A result of one of several problems executed together, tagged with the
+/// problem which produced it.
+#[derive(Clone, Debug)]
+pub struct TaggedMatchResult {
+    pub pattern_name: Option<String>,
+    pub pattern_hash: [u8; 32],
+    pub result: MatchResult,
+}
+
+/// Several single-file problems for the same language, executed in a single
+/// pass over the files.
+///
+/// Each file is read and parsed only once, after which every problem is
+/// matched against the same tree.
+pub struct CombinedProblems<'p> {
+    problems: Vec<&'p Problem>,
+}
+
+impl<'p> CombinedProblems<'p> {
+    pub fn new(problems: Vec<&'p Problem>) -> Result<Self> {
+        if let Some(problem) = problems.iter().find(|problem| problem.is_multifile) {
+            bail!(
+                "Cannot combine multifile pattern {} with other patterns",
+                problem.name.as_deref().unwrap_or("<pattern>")
+            );
+        }
+        if let Some((first, rest)) = problems.split_first() {
+            let language: PatternLanguage = (&first.language).into();
+            if rest
+                .iter()
+                .any(|problem| PatternLanguage::from(&problem.language) != language)
+            {
+                bail!("Only patterns for the same language can be combined");
+            }
+        }
+        Ok(Self { problems })
+    }
+
+    pub fn problems(&self) -> &[&'p Problem] {
+        &self.problems
+    }
+
+    /// Executes every problem for which `should_run()` holds against `file`,
+    /// sharing a single parse of the file between them.
+    ///
+    /// If the execution is cancelled, nothing is returned for the file, so it
+    /// is never reported as checked by only some of the problems.
+    pub fn execute_file(
+        &self,
+        file: impl LoadableFile + Clone,
+        context: &ExecutionContext,
+        should_run: impl Fn(&Problem) -> bool,
+    ) -> Vec<TaggedMatchResult> {
+        let parsed_files = ParsedFiles::new(1);
+        let mut results = Vec::new();
+        for problem in self.problems.iter().filter(|problem| should_run(problem)) {
+            let path = file.name();
+            let done_files = HashMap::from([(
+                path.clone(),
+                DoneFile {
+                    file_hash: Some(hash(&path)),
+                    relative_file_path: path,
+                    ..Default::default()
+                },
+            )]);
+            let outputs = problem.execute_and_collect(
+                vec![file.clone()],
+                FilePtr::new(0, 0).into(),
+                &FileOwners::new(),
+                context,
+                done_files,
+                Some(&parsed_files),
+            );
+            results.extend(outputs.into_iter().map(|result| TaggedMatchResult {
+                pattern_name: problem.name.clone(),
+                pattern_hash: problem.hash,
+                result,
+            }));
+        }
+        if context.is_cancelled() {
+            return vec![];
+        }
+        results
+    }
+
+    /// Executes every problem against `files`, processing files in parallel.
+    pub fn execute_paths(
+        &self,
+        files: Vec<&RichPath>,
+        context: &ExecutionContext,
+    ) -> Vec<TaggedMatchResult> {
+        files
+            .into_par_iter()
+            .flat_map_iter(|file| self.execute_file(file, context, |_| true))
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct MarzanoQueryContext;
 
@@ -0,0 +1,132 @@
+use anyhow::{bail, Result};
+use grit_pattern_matcher::{
+    context::ExecContext,
+    effects::{insert_effect, Effect},
+    pattern::{PatternOrResolved, ResolvedPattern, State, Variable},
+};
+use grit_util::{EffectKind, Range};
+use std::sync::Arc;
+
+use crate::{
+    built_in_functions::{CallbackFn, ReplacementFn},
+    marzano_context::MarzanoContext,
+    marzano_resolved_pattern::MarzanoResolvedPattern,
+    problem::MarzanoQueryContext,
+};
+
+/// CallbackBinding is the view of a matched binding handed to native callbacks.
+///
+/// It is the Rust counterpart of the napi `ResultBinding`, but borrows the
+/// execution state instead of extending its lifetime, so it is safe to use.
+pub struct CallbackBinding<'r, 'a> {
+    binding: &'r MarzanoResolvedPattern<'a>,
+    context: &'a MarzanoContext<'a>,
+    state: &'r mut State<'a, MarzanoQueryContext>,
+}
+
+impl<'r, 'a> CallbackBinding<'r, 'a> {
+    pub(crate) fn new(
+        binding: &'r MarzanoResolvedPattern<'a>,
+        context: &'a MarzanoContext<'a>,
+        state: &'r mut State<'a, MarzanoQueryContext>,
+    ) -> Self {
+        Self {
+            binding,
+            context,
+            state,
+        }
+    }
+
+    /// Retrieves the stringified representation of the binding (ie. the actual source code)
+    pub fn text(&self) -> Result<String> {
+        let text = self
+            .binding
+            .text(&self.state.files, self.context.language())?;
+        Ok(text.into_owned())
+    }
+
+    /// If the binding was found in a source file, return the position of the binding.
+    pub fn range(&self) -> Option<Range> {
+        self.binding.position(self.context.language())
+    }
+
+    /// Retrieves the absolute file name of the file containing the current binding.
+    pub fn filename(&self) -> Result<String> {
+        match self.resolve(&Variable::file_name())? {
+            Some(filename) => Ok(filename),
+            None => bail!("No file name found for the binding"),
+        }
+    }
+
+    /// Retrieves the text bound to a variable in the current scope, such as `$name`,
+    /// or `None` if the variable is not bound.
+    pub fn variable_text(&self, name: &str) -> Result<Option<String>> {
+        let Some(var) = self.state.find_var(name) else {
+            return Ok(None);
+        };
+        self.resolve(&var)
+    }
+
+    /// Inserts the provided text after the binding.
+    pub fn append(&mut self, text: String) -> Result<()> {
+        let left = PatternOrResolved::Resolved::<MarzanoQueryContext>(self.binding);
+        let replacement = ResolvedPattern::from_string(text);
+        insert_effect(&left, replacement, self.state, self.context)?;
+        Ok(())
+    }
+
+    fn resolve(&self, var: &Variable) -> Result<Option<String>> {
+        let Some(candidate) = var.get_pattern_or_resolved(self.state)? else {
+            return Ok(None);
+        };
+        let PatternOrResolved::Resolved(resolved) = candidate else {
+            return Ok(None);
+        };
+        let text = resolved.text(&self.state.files, self.context.language())?;
+        Ok(Some(text.into_owned()))
+    }
+}
+
+pub(crate) fn filter_callback<F>(callback: F) -> Box<CallbackFn>
+where
+    F: Fn(&mut CallbackBinding<'_, '_>) -> Result<bool> + Send + Sync + 'static,
+{
+    Box::new(move |binding, context, state, _logs| {
+        callback(&mut CallbackBinding::new(binding, context, state))
+    })
+}
+
+pub(crate) fn replacement_callback<F>(callback: F) -> Box<ReplacementFn>
+where
+    F: Fn(&mut CallbackBinding<'_, '_>) -> Result<String> + Send + Sync + 'static,
+{
+    Box::new(move |binding, context, state, _logs| {
+        callback(&mut CallbackBinding::new(binding, context, state))
+    })
+}
+
+/// Turns a replacement into a callback which rewrites every binding it is called on.
+pub(crate) fn rewrite_callback(replacement: Arc<ReplacementFn>) -> Box<CallbackFn> {
+    Box::new(move |binding, context, state, logs| {
+        let text = replacement(binding, context, state, logs)?;
+        rewrite_binding(binding, text, state)
+    })
+}
+
+/// Rewrites the code bound to `binding` to `text`, like the right-hand side of a rewrite would
+fn rewrite_binding<'a>(
+    binding: &MarzanoResolvedPattern<'a>,
+    text: String,
+    state: &mut State<'a, MarzanoQueryContext>,
+) -> Result<bool> {
+    let Some(bindings) = binding.get_bindings() else {
+        bail!("Only code bound by a pattern can be rewritten");
+    };
+    let replacement = MarzanoResolvedPattern::from_string(text);
+    state.effects.extend(bindings.map(|binding| Effect {
+        binding,
+        pattern: replacement.clone(),
+        kind: EffectKind::Rewrite,
+    }));
+    Ok(true)
+}
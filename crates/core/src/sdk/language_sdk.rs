@@ -1,5 +1,5 @@
 use anyhow::Result;
-use grit_pattern_matcher::pattern::{Pattern, VariableSource};
+use grit_pattern_matcher::pattern::{And, Pattern, VariableSource};
 use grit_util::AnalysisLogs;
 use marzano_language::target_language::TargetLanguage;
 
@@ -11,7 +11,10 @@ use crate::{
     problem::{MarzanoQueryContext, Problem},
 };
 
-use super::StatelessCompilerContext;
+use super::{
+    callback::{filter_callback, replacement_callback, rewrite_callback, CallbackBinding},
+    StatelessCompilerContext,
+};
 
 /// GlobalBuilder provides a higher level interface for building and composing patterns
 ///
@@ -37,8 +40,12 @@ impl LanguageSdk {
         }
     }
 
+    /// Returns a compiler which registers its callbacks on top of the ones
+    /// registered with this SDK, so patterns from both can be built together.
     pub fn compiler(&self) -> StatelessCompilerContext {
-        StatelessCompilerContext::new(self.language)
+        let mut compiler = StatelessCompilerContext::new(self.language);
+        compiler.built_ins.share_callbacks(&self.compiler.built_ins);
+        compiler
     }
 
     pub fn snippet(&self, snippet: &str) -> Result<Pattern<MarzanoQueryContext>> {
@@ -46,16 +53,56 @@ impl LanguageSdk {
         compiler.parse_snippet(snippet)
     }
 
+    /// Filter the pattern to only match instances for which the native callback returns `true`
+    ///
+    /// The callback is registered with this SDK, so the pattern must be built by it.
+    pub fn filter<F>(
+        &mut self,
+        pattern: Pattern<MarzanoQueryContext>,
+        callback: F,
+    ) -> Pattern<MarzanoQueryContext>
+    where
+        F: Fn(&mut CallbackBinding<'_, '_>) -> Result<bool> + Send + Sync + 'static,
+    {
+        let callback = self
+            .compiler
+            .built_ins
+            .add_callback(filter_callback(callback));
+        Pattern::And(Box::new(And::new(vec![pattern, callback])))
+    }
+
+    /// Rewrite each instance of the pattern to the string returned by the native callback
+    ///
+    /// The callback is registered with this SDK, so the pattern must be built by it.
+    pub fn rewrite<F>(
+        &mut self,
+        pattern: Pattern<MarzanoQueryContext>,
+        callback: F,
+    ) -> Pattern<MarzanoQueryContext>
+    where
+        F: Fn(&mut CallbackBinding<'_, '_>) -> Result<String> + Send + Sync + 'static,
+    {
+        let replacement = replacement_callback(callback);
+        let callback = self
+            .compiler
+            .built_ins
+            .add_callback(rewrite_callback(replacement.into()));
+        Pattern::And(Box::new(And::new(vec![pattern, callback])))
+    }
+
     pub fn build(&mut self, pattern: Pattern<MarzanoQueryContext>) -> Result<Problem> {
         let built_ins = BuiltIns::get_built_in_functions();
         self.build_custom(built_ins, pattern)
     }
 
+    /// Build the pattern with custom built-ins, which also get every callback
+    /// registered with this SDK
     pub fn build_custom(
         &mut self,
-        built_ins: BuiltIns,
+        mut built_ins: BuiltIns,
         pattern: Pattern<MarzanoQueryContext>,
     ) -> Result<Problem> {
+        built_ins.inherit_callbacks(&self.compiler.built_ins)?;
         let _logs: AnalysisLogs = vec![].into();
         let global_vars = build_standard_global_vars();
         let mut pattern_definitions = vec![];
@@ -65,6 +112,7 @@ impl LanguageSdk {
         let pattern = auto_wrap_pattern(
             pattern,
             &mut pattern_definitions,
+            &[],
             !is_multifile,
             None,
             &mut self.compiler,
@@ -90,3 +138,46 @@ impl LanguageSdk {
         Ok(problem)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::MatchResult;
+    use marzano_util::{rich_path::RichFile, runtime::ExecutionContext};
+
+    fn matches(problem: &Problem, content: &str) -> bool {
+        problem
+            .execute_file(
+                &RichFile::new("test.js".to_string(), content.to_string()),
+                &ExecutionContext::default(),
+            )
+            .iter()
+            .any(MatchResult::is_match)
+    }
+
+    #[test]
+    fn builds_filtered_patterns_more_than_once() -> Result<()> {
+        let mut sdk = LanguageSdk::default();
+        let log = sdk.snippet("console.log($msg)")?;
+        let kept_logs = sdk.filter(log, |binding| {
+            Ok(binding.variable_text("$msg")?.as_deref() == Some("'keep'"))
+        });
+        let warn = sdk.snippet("console.warn($msg)")?;
+        let kept_warnings = sdk.filter(warn, |binding| {
+            Ok(binding.variable_text("$msg")?.as_deref() != Some("''"))
+        });
+
+        // Both patterns were composed before either of them was built
+        let logs = sdk.build(kept_logs.clone())?;
+        let warnings = sdk.build(kept_warnings)?;
+        let logs_again = sdk.build(kept_logs)?;
+
+        for problem in [&logs, &logs_again] {
+            assert!(matches(problem, "console.log('keep');"));
+            assert!(!matches(problem, "console.log('drop');"));
+        }
+        assert!(matches(&warnings, "console.warn('keep');"));
+        assert!(!matches(&warnings, "console.warn('');"));
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+mod callback;
 mod compiler;
 mod language_sdk;
 mod pattern_sdk;
@@ -7,6 +8,7 @@ mod test_yaml;
 #[cfg(feature = "napi")]
 mod binding;
 
+pub use callback::CallbackBinding;
 pub(crate) use compiler::StatelessCompilerContext;
 pub use language_sdk::LanguageSdk;
 pub use pattern_sdk::UncompiledPatternBuilder;
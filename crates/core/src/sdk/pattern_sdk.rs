@@ -1,5 +1,8 @@
-use anyhow::Result;
-use grit_pattern_matcher::pattern::{And, Contains, Pattern};
+use anyhow::{bail, Result};
+use grit_pattern_matcher::pattern::{
+    And, Bubble, Container, Contains, FilePattern, Limit, Match, Maybe, Not, Or, Pattern,
+    Predicate, Where, Within,
+};
 #[allow(unused_imports)]
 use marzano_util::{rich_path::RichFile, runtime::ExecutionContext};
 
@@ -10,11 +13,15 @@ use std::sync::Arc;
 use crate::api::{FileMatchResult, MatchResult};
 
 use crate::{
-    built_in_functions::CallbackFn,
+    built_in_functions::{CallbackFn, ReplacementFn},
+    pattern_compiler::compiler::SnippetCompilationContext,
     problem::{MarzanoQueryContext, Problem},
 };
 
-use super::{LanguageSdk, StatelessCompilerContext};
+use super::{
+    callback::{filter_callback, replacement_callback, rewrite_callback, CallbackBinding},
+    LanguageSdk, StatelessCompilerContext,
+};
 
 #[cfg(feature = "wasm_core")]
 use wasm_bindgen::prelude::*;
@@ -42,6 +49,24 @@ impl SimpleCallback {
     }
 }
 
+#[derive(Clone)]
+struct SimpleReplacement {
+    replacement: Arc<ReplacementFn>,
+}
+
+impl Debug for SimpleReplacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SimpleReplacement")
+    }
+}
+
+impl SimpleReplacement {
+    #[allow(unused)]
+    fn new(replacement: Arc<ReplacementFn>) -> Self {
+        SimpleReplacement { replacement }
+    }
+}
+
 /// UncompiledPattern is used to build up complex patterns *before* building them
 /// Late compilation allows us to reuse the same pattern across languages (where a snippet will ultimately be parsed differently
 /// It also allows the pattern to be used as a root pattern, or dynamically inside a function callback
@@ -60,6 +85,40 @@ enum UncompiledPattern {
     And {
         patterns: Vec<UncompiledPatternBuilder>,
     },
+    Or {
+        patterns: Vec<UncompiledPatternBuilder>,
+    },
+    Within {
+        within: Box<UncompiledPatternBuilder>,
+    },
+    Not {
+        pattern: Box<UncompiledPatternBuilder>,
+    },
+    Maybe {
+        pattern: Box<UncompiledPatternBuilder>,
+    },
+    Bubble {
+        pattern: Box<UncompiledPatternBuilder>,
+    },
+    /// Matches `pattern`, as long as `variable` then matches `value`
+    Where {
+        pattern: Box<UncompiledPatternBuilder>,
+        variable: String,
+        value: Box<UncompiledPatternBuilder>,
+    },
+    /// Rewrites each match of `pattern` to the text computed by `replacement`
+    Rewrite {
+        pattern: Box<UncompiledPatternBuilder>,
+        replacement: SimpleReplacement,
+    },
+    Limit {
+        pattern: Box<UncompiledPatternBuilder>,
+        limit: usize,
+    },
+    /// Matches files whose body matches `body`
+    File {
+        body: Box<UncompiledPatternBuilder>,
+    },
 }
 
 #[cfg_attr(feature = "wasm_core", wasm_bindgen)]
@@ -81,11 +140,7 @@ impl UncompiledPatternBuilder {
         context: &mut StatelessCompilerContext,
     ) -> Result<Pattern<MarzanoQueryContext>> {
         match self.pattern {
-            UncompiledPattern::Snippet { text } => {
-                let sdk = LanguageSdk::default();
-                let pattern = sdk.snippet(&text)?;
-                Ok(pattern)
-            }
+            UncompiledPattern::Snippet { text } => context.parse_snippet(&text),
             UncompiledPattern::And { patterns } => {
                 let mut compiled: Vec<Pattern<MarzanoQueryContext>> = vec![];
                 for pattern in patterns {
@@ -107,11 +162,80 @@ impl UncompiledPatternBuilder {
                 ));
                 Ok(built_in)
             }
+            UncompiledPattern::Or { patterns } => {
+                let mut compiled: Vec<Pattern<MarzanoQueryContext>> = vec![];
+                for pattern in patterns {
+                    compiled.push(pattern.compile(context)?);
+                }
+                Ok(Pattern::Or(Box::new(Or::new(compiled))))
+            }
+            UncompiledPattern::Within { within } => {
+                let compiled = within.compile(context)?;
+                Ok(Pattern::Within(Box::new(Within::new(compiled, None))))
+            }
+            UncompiledPattern::Not { pattern } => {
+                let compiled = pattern.compile(context)?;
+                Ok(Pattern::Not(Box::new(Not::new(compiled))))
+            }
+            UncompiledPattern::Maybe { pattern } => {
+                let compiled = pattern.compile(context)?;
+                Ok(Pattern::Maybe(Box::new(Maybe::new(compiled))))
+            }
+            UncompiledPattern::Bubble { pattern } => {
+                let compiled = pattern.compile(context)?;
+                let definition = context.register_ephemeral_pattern(compiled)?;
+                Ok(Pattern::Bubble(Box::new(Bubble::new(definition, vec![]))))
+            }
+            UncompiledPattern::Where {
+                pattern,
+                variable,
+                value,
+            } => {
+                if !variable.starts_with('$') {
+                    bail!("Variable names must start with `$`, found `{}`", variable);
+                }
+                let compiled = pattern.compile(context)?;
+                let variable = context.register_variable(&variable, None)?;
+                let value = value.compile(context)?;
+                let side_condition = Predicate::Match(Box::new(Match::new(
+                    Container::Variable(variable),
+                    Some(value),
+                )));
+                Ok(Pattern::Where(Box::new(Where::new(
+                    compiled,
+                    side_condition,
+                ))))
+            }
+            UncompiledPattern::Rewrite {
+                pattern,
+                replacement,
+            } => {
+                let compiled = pattern.compile(context)?;
+                let rewrite = context
+                    .built_ins
+                    .add_callback(rewrite_callback(replacement.replacement));
+                Ok(Pattern::And(Box::new(And::new(vec![compiled, rewrite]))))
+            }
+            UncompiledPattern::Limit { pattern, limit } => {
+                let compiled = pattern.compile(context)?;
+                Ok(Pattern::Limit(Box::new(Limit::new(compiled, limit))))
+            }
+            UncompiledPattern::File { body } => {
+                let compiled = body.compile(context)?;
+                Ok(Pattern::File(Box::new(FilePattern::new(
+                    Pattern::Top,
+                    compiled,
+                ))))
+            }
         }
     }
 
     pub fn build(self) -> Result<Problem> {
-        let mut sdk = LanguageSdk::default();
+        self.build_with(&mut LanguageSdk::default())
+    }
+
+    /// Build the pattern for the language of the provided SDK
+    pub fn build_with(self, sdk: &mut LanguageSdk) -> Result<Problem> {
         let mut compiler = sdk.compiler();
 
         let compiled = self.compile(&mut compiler)?;
@@ -119,6 +243,32 @@ impl UncompiledPatternBuilder {
         Ok(built)
     }
 
+    /// Create a pattern from a code snippet, such as `console.log($message)`
+    pub fn from_snippet(text: impl Into<String>) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::Snippet { text: text.into() })
+    }
+
+    /// Filter the pattern to only match instances for which the native callback returns `true`
+    pub fn filter_with<F>(&self, callback: F) -> Self
+    where
+        F: Fn(&mut CallbackBinding<'_, '_>) -> Result<bool> + Send + Sync + 'static,
+    {
+        let callback_pattern = UncompiledPatternBuilder::new(UncompiledPattern::Callback {
+            callback: SimpleCallback::new(Arc::from(filter_callback(callback))),
+        });
+        self.and_internal(&callback_pattern)
+    }
+
+    /// Rewrite each instance of this pattern to the string returned by the native callback
+    pub fn rewrite_with<F>(&self, callback: F) -> Self
+    where
+        F: Fn(&mut CallbackBinding<'_, '_>) -> Result<String> + Send + Sync + 'static,
+    {
+        self.rewrite_internal(SimpleReplacement::new(Arc::from(replacement_callback(
+            callback,
+        ))))
+    }
+
     // We need indirection to deal with some Napi limitations
     #[inline]
     #[allow(unused)]
@@ -133,6 +283,81 @@ impl UncompiledPatternBuilder {
             },
         }
     }
+
+    #[allow(unused)]
+    fn within_internal(&self, other: &UncompiledPatternBuilder) -> Self {
+        let within = UncompiledPatternBuilder::new(UncompiledPattern::Within {
+            within: Box::new(other.clone()),
+        });
+        self.and_internal(&within)
+    }
+
+    #[allow(unused)]
+    fn and_internal(&self, other: &UncompiledPatternBuilder) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::And {
+            patterns: vec![self.clone(), other.clone()],
+        })
+    }
+
+    #[allow(unused)]
+    fn or_internal(&self, other: &UncompiledPatternBuilder) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::Or {
+            patterns: vec![self.clone(), other.clone()],
+        })
+    }
+
+    #[allow(unused)]
+    fn not_internal(&self) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::Not {
+            pattern: Box::new(self.clone()),
+        })
+    }
+
+    #[allow(unused)]
+    fn maybe_internal(&self) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::Maybe {
+            pattern: Box::new(self.clone()),
+        })
+    }
+
+    #[allow(unused)]
+    fn bubble_internal(&self) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::Bubble {
+            pattern: Box::new(self.clone()),
+        })
+    }
+
+    #[allow(unused)]
+    fn where_internal(&self, variable: &str, value: &UncompiledPatternBuilder) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::Where {
+            pattern: Box::new(self.clone()),
+            variable: variable.to_string(),
+            value: Box::new(value.clone()),
+        })
+    }
+
+    #[allow(unused)]
+    fn rewrite_internal(&self, replacement: SimpleReplacement) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::Rewrite {
+            pattern: Box::new(self.clone()),
+            replacement,
+        })
+    }
+
+    #[allow(unused)]
+    fn limit_internal(&self, limit: usize) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::Limit {
+            pattern: Box::new(self.clone()),
+            limit,
+        })
+    }
+
+    #[allow(unused)]
+    fn file_internal(body: &UncompiledPatternBuilder) -> Self {
+        UncompiledPatternBuilder::new(UncompiledPattern::File {
+            body: Box::new(body.clone()),
+        })
+    }
 }
 
 /// This implements the wasm version of the API
@@ -149,6 +374,51 @@ impl UncompiledPatternBuilder {
     pub fn contains(&self, other: &UncompiledPatternBuilder) -> Self {
         self.contains_internal(other)
     }
+
+    /// Filter this pattern to only match instances inside the other pattern
+    pub fn within(&self, other: &UncompiledPatternBuilder) -> Self {
+        self.within_internal(other)
+    }
+
+    /// Match instances that match both this pattern and the other pattern
+    pub fn and(&self, other: &UncompiledPatternBuilder) -> Self {
+        self.and_internal(other)
+    }
+
+    /// Match instances that match either this pattern or the other pattern
+    pub fn or(&self, other: &UncompiledPatternBuilder) -> Self {
+        self.or_internal(other)
+    }
+
+    /// Match instances that do not match this pattern
+    pub fn not(&self) -> Self {
+        self.not_internal()
+    }
+
+    /// Match this pattern if possible, but succeed either way
+    pub fn maybe(&self) -> Self {
+        self.maybe_internal()
+    }
+
+    /// Give this pattern its own scope for variables
+    pub fn bubble(&self) -> Self {
+        self.bubble_internal()
+    }
+
+    /// Filter this pattern to only match instances where `variable` matches the other pattern
+    pub fn where_variable(&self, variable: &str, pattern: &UncompiledPatternBuilder) -> Self {
+        self.where_internal(variable, pattern)
+    }
+
+    /// Stop matching after `limit` files have matched
+    pub fn limit(&self, limit: usize) -> Self {
+        self.limit_internal(limit)
+    }
+
+    /// Match files whose body matches the given pattern
+    pub fn new_file(body: &UncompiledPatternBuilder) -> Self {
+        Self::file_internal(body)
+    }
 }
 
 /// This implements features that should only be used from Napi
@@ -203,6 +473,89 @@ impl UncompiledPatternBuilder {
         })
     }
 
+    /// Rewrite each instance of this pattern to the string returned by the provided callback
+    #[napi]
+    pub fn rewrite(
+        &self,
+        callback: napi::threadsafe_function::ThreadsafeFunction<
+            ResultBinding,
+            napi::threadsafe_function::ErrorStrategy::Fatal,
+        >,
+    ) -> Self {
+        let replacement =
+            SimpleReplacement::new(Arc::new(move |binding, context, state, _logs| {
+                let runtime = context
+                    .runtime
+                    .handle
+                    .as_ref()
+                    .ok_or(anyhow::anyhow!("Async runtime required"))?;
+
+                let foreign_binding = ResultBinding::new_unsafe(binding, context, state);
+
+                let val = runtime
+                    .block_on(async { callback.call_async::<String>(foreign_binding).await })?;
+
+                Ok(val)
+            }));
+
+        self.rewrite_internal(replacement)
+    }
+
+    /// Filter this pattern to only match instances inside the other pattern
+    #[napi]
+    pub fn within(&self, other: &UncompiledPatternBuilder) -> Self {
+        self.within_internal(other)
+    }
+
+    /// Match instances that match both this pattern and the other pattern
+    #[napi]
+    pub fn and(&self, other: &UncompiledPatternBuilder) -> Self {
+        self.and_internal(other)
+    }
+
+    /// Match instances that match either this pattern or the other pattern
+    #[napi]
+    pub fn or(&self, other: &UncompiledPatternBuilder) -> Self {
+        self.or_internal(other)
+    }
+
+    /// Match instances that do not match this pattern
+    #[napi]
+    pub fn not(&self) -> Self {
+        self.not_internal()
+    }
+
+    /// Match this pattern if possible, but succeed either way
+    #[napi]
+    pub fn maybe(&self) -> Self {
+        self.maybe_internal()
+    }
+
+    /// Give this pattern its own scope for variables
+    #[napi]
+    pub fn bubble(&self) -> Self {
+        self.bubble_internal()
+    }
+
+    /// Filter this pattern to only match instances where `variable` matches the other pattern
+    /// @param variable The name of the variable, including the leading `$`
+    #[napi(js_name = "where")]
+    pub fn where_variable(&self, variable: String, pattern: &UncompiledPatternBuilder) -> Self {
+        self.where_internal(&variable, pattern)
+    }
+
+    /// Stop matching after `limit` files have matched
+    #[napi]
+    pub fn limit(&self, limit: u32) -> Self {
+        self.limit_internal(limit as usize)
+    }
+
+    /// Match files whose body matches the given pattern
+    #[napi(factory, js_name = "new_file")]
+    pub fn new_file(body: &UncompiledPatternBuilder) -> Self {
+        Self::file_internal(body)
+    }
+
     async fn run_inner(&self, files: Vec<RichFile>) -> Result<Vec<MatchResult>> {
         let problem = self.clone().build()?;
         let context = ExecutionContext::default();
@@ -258,3 +611,129 @@ impl UncompiledPatternBuilder {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grit_pattern_matcher::context::ExecContext;
+
+    fn snippet(text: &str) -> UncompiledPatternBuilder {
+        UncompiledPatternBuilder::from_snippet(text)
+    }
+
+    fn rewritten_content(results: &[MatchResult]) -> Option<String> {
+        results.iter().find_map(|result| {
+            if let MatchResult::Rewrite(rewrite) = result {
+                Some(rewrite.content().unwrap().to_string())
+            } else {
+                None
+            }
+        })
+    }
+
+    fn run(pattern: UncompiledPatternBuilder, content: &str) -> Vec<MatchResult> {
+        let problem = pattern.build().unwrap();
+        problem.execute_files(
+            vec![RichFile::new("test.js".to_string(), content.to_string())],
+            &ExecutionContext::default(),
+        )
+    }
+
+    fn matches(pattern: UncompiledPatternBuilder, content: &str) -> bool {
+        run(pattern, content).iter().any(MatchResult::is_match)
+    }
+
+    #[test]
+    fn test_within() {
+        let pattern = snippet("console.log($msg)").within_internal(&snippet("function $name() {}"));
+        assert!(matches(
+            pattern.clone(),
+            "function foo() { console.log('hello'); }"
+        ));
+        assert!(!matches(pattern, "console.log('hello');"));
+    }
+
+    #[test]
+    fn test_or_and_not() {
+        let pattern = snippet("console.log($msg)")
+            .or_internal(&snippet("console.warn($msg)"))
+            .and_internal(&snippet("console.$method('skip')").not_internal());
+        assert!(matches(pattern.clone(), "console.warn('hello');"));
+        assert!(!matches(pattern, "console.log('skip');"));
+    }
+
+    #[test]
+    fn test_where() {
+        let pattern = snippet("console.log($msg)").where_internal("$msg", &snippet("'hello'"));
+        assert!(matches(pattern.clone(), "console.log('hello');"));
+        assert!(!matches(pattern, "console.log('goodbye');"));
+    }
+
+    #[test]
+    fn test_where_requires_variable_name() {
+        let pattern = snippet("console.log($msg)").where_internal("msg", &snippet("'hello'"));
+        assert!(pattern.build().is_err());
+    }
+
+    #[test]
+    fn test_rewrite_with_callback() {
+        let replacement = SimpleReplacement::new(Arc::new(|binding, context, state, _logs| {
+            let text = binding.text(&state.files, context.language())?;
+            Ok(text.replace("log", "warn"))
+        }));
+        let pattern = snippet("console.log($msg)").rewrite_internal(replacement);
+        let results = run(pattern, "console.log('hello');");
+        assert_eq!(
+            rewritten_content(&results).unwrap(),
+            "console.warn('hello');"
+        );
+    }
+
+    #[test]
+    fn test_native_filter() {
+        let pattern = snippet("console.log($msg)").filter_with(|binding| {
+            assert_eq!(binding.filename()?, "test.js");
+            Ok(binding.variable_text("$msg")?.as_deref() == Some("'keep'"))
+        });
+        assert!(matches(pattern.clone(), "console.log('keep');"));
+        assert!(!matches(pattern, "console.log('drop');"));
+    }
+
+    #[test]
+    fn test_native_rewrite() {
+        let pattern = snippet("console.log($msg)").rewrite_with(|binding| {
+            let range = binding.range().unwrap();
+            assert_eq!(range.start.line, 2);
+            let msg = binding.variable_text("$msg")?.unwrap_or_default();
+            Ok(format!("logger.info({})", msg))
+        });
+        let results = run(pattern, "foo();\nconsole.log('hello');");
+        assert_eq!(
+            rewritten_content(&results).unwrap(),
+            "foo();\nlogger.info('hello');"
+        );
+    }
+
+    #[test]
+    fn test_file_and_limit() {
+        let pattern = UncompiledPatternBuilder::file_internal(
+            &snippet("console.log($msg)").within_internal(&snippet("function $name() {}")),
+        )
+        .limit_internal(1);
+        let problem = pattern.build().unwrap();
+        let results = problem.execute_files(
+            vec![
+                RichFile::new(
+                    "first.js".to_string(),
+                    "function foo() { console.log('hello'); }".to_string(),
+                ),
+                RichFile::new(
+                    "second.js".to_string(),
+                    "function bar() { console.log('hello'); }".to_string(),
+                ),
+            ],
+            &ExecutionContext::default(),
+        );
+        assert_eq!(results.iter().filter(|r| r.is_match()).count(), 1);
+    }
+}
@@ -13,7 +13,7 @@ use marzano_context::MarzanoContext;
 use marzano_language::language::MarzanoLanguage;
 use marzano_language::target_language::{PatternLanguage, TargetLanguage};
 use marzano_resolved_pattern::MarzanoResolvedPattern;
-use marzano_util::rich_path::RichFile;
+use marzano_util::rich_path::{RichFile, RichPath};
 use marzano_util::runtime::{ExecutionContext, LanguageModelAPI};
 use problem::{MarzanoQueryContext, Problem};
 use similar::{ChangeTag, TextDiff};
@@ -16790,3 +16790,590 @@ fn csharp_throw_exception() {
     })
     .unwrap();
 }
+
+#[test]
+fn reports_unreached_clause_coverage() {
+    let pattern = r#"
+        |language js
+        |
+        |or {
+        |    `console.log($msg)` => `logger.info($msg)`,
+        |    `console.error($msg)` => `logger.error($msg)`
+        |}
+        |"#
+    .trim_margin()
+    .unwrap();
+    let source = "console.log('hello');".to_owned();
+
+    let coverage = std::sync::Arc::new(grit_util::ClauseCoverage::new());
+    let context = ExecutionContext::default().with_clause_coverage(coverage.clone());
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let pattern = src_to_problem(pattern, js_lang).unwrap();
+    pattern.execute_file(&RichFile::new("test-file.js".to_owned(), source), &context);
+
+    let report = pattern.coverage_report(&coverage);
+    assert_eq!(report.len(), 1);
+    let reached: Vec<_> = report[0]
+        .clauses
+        .iter()
+        .map(|clause| (clause.description.as_str(), clause.reached))
+        .collect();
+    assert_eq!(
+        reached,
+        vec![
+            ("or branch 1 of 2", true),
+            ("or branch 2 of 2", false),
+            ("rewrite #1", true),
+            ("rewrite #2", false),
+        ]
+    );
+}
+
+#[test]
+fn reports_data_flow_paths() {
+    let pattern = r#"
+        |language js
+        |
+        |`db.query($query)` where {
+        |    flows_to(source=`req.query`, sink=$query, sanitizer=`sanitize($_)`)
+        |}
+        |"#
+    .trim_margin()
+    .unwrap();
+    let source = r#"function handler(req, db) {
+  const filter = req.query;
+  db.query(filter);
+  db.query(sanitize(filter));
+}"#
+    .to_owned();
+
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let pattern = src_to_problem(pattern, js_lang).unwrap();
+    let results = pattern.execute_file(
+        &RichFile::new("test-file.js".to_owned(), source),
+        &ExecutionContext::default(),
+    );
+    let flows: Vec<_> = results
+        .iter()
+        .filter_map(|result| match result {
+            MatchResult::Match(m) => Some(&m.flows),
+            _ => None,
+        })
+        .flatten()
+        .map(|flow| {
+            flow.iter()
+                .map(|range| range.start.line)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(flows, vec![vec![2, 2, 3]]);
+}
+
+#[test]
+fn finds_locks_not_always_released() {
+    let pattern = r#"
+        |language js
+        |
+        |`$mutex.lock()` as $lock where {
+        |    not always_followed_by(node=$lock, target=`$mutex.unlock()`)
+        |}
+        |"#
+    .trim_margin()
+    .unwrap();
+    let source = r#"function update(first, second, done) {
+  first.lock();
+  if (done) {
+    return;
+  }
+  first.unlock();
+  second.lock();
+  second.unlock();
+}"#
+    .to_owned();
+
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let pattern = src_to_problem(pattern, js_lang).unwrap();
+    let results = pattern.execute_file(
+        &RichFile::new("test-file.js".to_owned(), source),
+        &ExecutionContext::default(),
+    );
+    let lines: Vec<_> = results
+        .iter()
+        .filter_map(|result| match result {
+            MatchResult::Match(m) => Some(&m.ranges),
+            _ => None,
+        })
+        .flatten()
+        .map(|range| range.start.line)
+        .collect();
+    assert_eq!(lines, vec![2]);
+}
+
+#[test]
+fn ignores_targets_inside_nested_closures() {
+    let pattern = r#"
+        |language js
+        |
+        |`$mutex.lock()` as $lock where {
+        |    not always_followed_by(node=$lock, target=`$mutex.unlock()`)
+        |}
+        |"#
+    .trim_margin()
+    .unwrap();
+    let source = r#"function update(mutex, schedule) {
+  mutex.lock();
+  schedule(() => mutex.unlock());
+}"#
+    .to_owned();
+
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let pattern = src_to_problem(pattern, js_lang).unwrap();
+    let results = pattern.execute_file(
+        &RichFile::new("test-file.js".to_owned(), source),
+        &ExecutionContext::default(),
+    );
+    let lines: Vec<_> = results
+        .iter()
+        .filter_map(|result| match result {
+            MatchResult::Match(m) => Some(&m.ranges),
+            _ => None,
+        })
+        .flatten()
+        .map(|range| range.start.line)
+        .collect();
+    assert_eq!(lines, vec![2]);
+}
+
+#[derive(Debug)]
+struct FixedTypes;
+
+impl type_provider::TypeProvider for FixedTypes {
+    fn type_of(&self, _path: &Path, source: &str, range: &Range) -> Result<Option<String>> {
+        let text = &source[range.start_byte as usize..range.end_byte as usize];
+        Ok((text == "events").then(|| "Observable<Event>".to_owned()))
+    }
+}
+
+#[test]
+fn matches_on_provided_types() {
+    let pattern = r#"
+        |language js
+        |
+        |`$source.map($_)` where {
+        |    $type = type_of(node=$source),
+        |    $type <: r"Observable<.*>"
+        |}
+        |"#
+    .trim_margin()
+    .unwrap();
+    let source = r#"const events = fromEvent(button, 'click');
+const names = ['a', 'b'];
+events.map(toName);
+names.map(toName);"#
+        .to_owned();
+
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let pattern = src_to_problem(pattern, js_lang).unwrap();
+    let file = RichFile::new("test-file.ts".to_owned(), source);
+
+    let context = ExecutionContext::default().with_type_provider(std::sync::Arc::new(FixedTypes));
+    let lines: Vec<_> = pattern
+        .execute_file(&file, &context)
+        .iter()
+        .filter_map(|result| match result {
+            MatchResult::Match(m) => Some(&m.ranges),
+            _ => None,
+        })
+        .flatten()
+        .map(|range| range.start.line)
+        .collect();
+    assert_eq!(lines, vec![3]);
+
+    let results = pattern.execute_file(&file, &ExecutionContext::default());
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, MatchResult::Match(_))));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        MatchResult::AnalysisLog(log) if log.message.contains("no type provider is configured")
+    )));
+}
+
+#[test]
+fn rejects_type_of_outside_javascript() {
+    let pattern = r#"
+        |language python
+        |
+        |`$source.map($_)` where {
+        |    $type = type_of(node=$source),
+        |    $type <: r"list.*"
+        |}
+        |"#
+    .trim_margin()
+    .unwrap();
+    let source = "names.map(to_name)".to_owned();
+
+    let python_lang: TargetLanguage = PatternLanguage::Python.try_into().unwrap();
+    let pattern = src_to_problem(pattern, python_lang).unwrap();
+    let context = ExecutionContext::default().with_type_provider(std::sync::Arc::new(FixedTypes));
+    let results = pattern.execute_file(&RichFile::new("test-file.py".to_owned(), source), &context);
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, MatchResult::Match(_))));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        MatchResult::AnalysisLog(log) if log.message.contains("type_of is not supported for")
+    )));
+}
+
+#[test]
+fn combined_problems_share_a_parse_without_sharing_matches() {
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let mut rewrite = src_to_problem("`console.log($x)` => .".to_owned(), js_lang).unwrap();
+    rewrite.name = Some("no_console".to_owned());
+    let mut find = src_to_problem("`foo($x)`".to_owned(), js_lang).unwrap();
+    find.name = Some("find_foo".to_owned());
+    let mut unrelated = src_to_problem("`bar($x)`".to_owned(), js_lang).unwrap();
+    unrelated.name = Some("find_bar".to_owned());
+
+    let combined = problem::CombinedProblems::new(vec![&rewrite, &find, &unrelated]).unwrap();
+    let source = "console.log(1);\nfoo(2);\n".to_owned();
+    let file = RichFile::new("test-file.tsx".to_owned(), source.clone());
+    let context = ExecutionContext::default();
+    let results = combined.execute_file(&file, &context, |problem| {
+        problem.name.as_deref() != Some("find_bar")
+    });
+
+    let rewrites: Vec<_> = results
+        .iter()
+        .filter(|tagged| matches!(tagged.result, MatchResult::Rewrite(_)))
+        .collect();
+    assert_eq!(rewrites.len(), 1);
+    assert_eq!(rewrites[0].pattern_name.as_deref(), Some("no_console"));
+    assert_eq!(rewrites[0].pattern_hash, rewrite.hash);
+
+    // The match of the second pattern is made against the original source
+    let matches: Vec<_> = results
+        .iter()
+        .filter_map(|tagged| match &tagged.result {
+            MatchResult::Match(m) => Some((tagged.pattern_name.as_deref(), m)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0, Some("find_foo"));
+    assert_eq!(matches[0].1.ranges[0].start.line, 2);
+
+    // Skipped patterns do not run at all
+    assert!(!results
+        .iter()
+        .any(|tagged| tagged.pattern_name.as_deref() == Some("find_bar")));
+
+    assert!(problem::CombinedProblems::new(vec![
+        &rewrite,
+        &src_to_problem("language python\n`foo`".to_owned(), js_lang).unwrap(),
+    ])
+    .is_err());
+}
+
+#[test]
+fn combined_problems_execute_paths() {
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let mut rewrite = src_to_problem("`console.log($x)` => .".to_owned(), js_lang).unwrap();
+    rewrite.name = Some("no_console".to_owned());
+    let mut find = src_to_problem("`foo($x)`".to_owned(), js_lang).unwrap();
+    find.name = Some("find_foo".to_owned());
+    let combined = problem::CombinedProblems::new(vec![&rewrite, &find]).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs.tsx");
+    let calls = dir.path().join("calls.tsx");
+    fs_err::write(&logs, "console.log(1);\n").unwrap();
+    fs_err::write(&calls, "foo(2);\nfoo(3);\n").unwrap();
+    let paths = [
+        RichPath::new(logs.clone(), None),
+        RichPath::new(calls.clone(), None),
+    ];
+
+    let results = combined.execute_paths(paths.iter().collect(), &ExecutionContext::default());
+    let found: Vec<_> = results
+        .iter()
+        .filter(|tagged| api::is_match(&tagged.result))
+        .map(|tagged| {
+            (
+                tagged.pattern_name.as_deref(),
+                tagged.result.file_name().map(str::to_owned),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (Some("no_console"), Some(logs.to_string_lossy().to_string())),
+            (Some("find_foo"), Some(calls.to_string_lossy().to_string())),
+        ]
+    );
+}
+
+#[test]
+fn reports_patterns_exceeding_their_step_limit() {
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let mut pattern = src_to_problem("`foo($x)`".to_owned(), js_lang).unwrap();
+    pattern.name = Some("find_foo".to_owned());
+    let source = (0..100).map(|i| format!("foo({i});\n")).collect::<String>();
+    let file = RichFile::new("bundle.min.js".to_owned(), source);
+
+    let context = ExecutionContext::default().with_limits(grit_util::ExecutionLimits {
+        timeout: None,
+        max_steps: Some(50),
+    });
+    let results = pattern.execute_file(&file, &context);
+    assert!(!results.iter().any(api::is_match));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        MatchResult::AnalysisLog(log)
+            if log.message == "Skipped bundle.min.js for pattern find_foo, it exceeded the limit of 50 steps."
+                && log.file == "bundle.min.js"
+    )));
+
+    let unlimited = pattern.execute_file(&file, &ExecutionContext::default());
+    assert_eq!(
+        unlimited
+            .iter()
+            .filter(|result| matches!(result, MatchResult::Match(_)))
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn skips_files_once_cancelled() {
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let pattern = src_to_problem("`foo($x)`".to_owned(), js_lang).unwrap();
+    let files = vec![
+        RichFile::new("first.js".to_owned(), "foo(1);".to_owned()),
+        RichFile::new("second.js".to_owned(), "foo(2);".to_owned()),
+    ];
+
+    let cancellation = grit_util::CancellationToken::new();
+    let context = ExecutionContext::default().with_cancellation(cancellation.clone());
+    let results = pattern.execute_files(files.clone(), &context);
+    assert_eq!(
+        results
+            .iter()
+            .filter(|result| api::is_match(result))
+            .count(),
+        2
+    );
+
+    cancellation.cancel();
+    let results = pattern.execute_files(files, &context);
+    assert!(results.is_empty());
+}
+
+#[test]
+fn traces_operators_tried_on_a_line() {
+    let pattern = r#"
+        |language js
+        |
+        |`console.log($msg)` where { $msg <: `'bye'` }
+        |"#
+    .trim_margin()
+    .unwrap();
+    let source = "foo();\nconsole.log('hello');".to_owned();
+
+    let trace = std::sync::Arc::new(grit_util::MatchTrace::new(2));
+    let context = ExecutionContext::default().with_match_trace(trace.clone());
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let pattern = src_to_problem(pattern, js_lang).unwrap();
+    pattern.execute_file(&RichFile::new("test-file.js".to_owned(), source), &context);
+
+    fn bindings(node: &grit_util::TraceNode) -> Vec<String> {
+        node.bindings
+            .iter()
+            .map(|binding| binding.value.clone())
+            .chain(node.children.iter().flat_map(bindings))
+            .collect()
+    }
+
+    let roots = trace.take();
+    assert!(!roots.is_empty());
+    assert!(roots
+        .iter()
+        .all(|root| root.range.map(|range| range.start.line) == Some(2)));
+    assert!(roots
+        .iter()
+        .any(|root| !root.matched && bindings(root).contains(&"'hello'".to_owned())));
+}
+
+#[test]
+fn attaches_alternative_fixes_to_every_match_in_a_file() {
+    let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+    let pattern = src_to_problem("language js\n`console.log($msg)`".to_owned(), js_lang).unwrap();
+    let fix = |label: &str, method: &str| crate::fixes::AlternativeFixPattern {
+        label: label.to_owned(),
+        preferred: false,
+        problem: src_to_problem(
+            format!("language js\n`console.log($msg)` => `logger.{method}($msg)`"),
+            js_lang,
+        )
+        .unwrap(),
+    };
+    let fixes = [fix("Log info", "info"), fix("Log a warning", "warn")];
+    let source = "console.log('a');\nconsole.log('b');";
+    let context = ExecutionContext::default();
+    let mut results = pattern.execute_file(
+        &RichFile::new("test-file.js".to_owned(), source.to_owned()),
+        &context,
+    );
+    // Several patterns may match the same file, as in `grit check`
+    results.extend(results.clone());
+    crate::fixes::attach_alternative_fixes(&mut results, &fixes, &context);
+
+    let matches: Vec<_> = results
+        .iter()
+        .filter_map(|result| {
+            if let MatchResult::Match(m) = result {
+                Some(m)
+            } else {
+                None
+            }
+        })
+        .collect();
+    assert_eq!(matches.len(), 2);
+    for m in &matches {
+        assert_eq!(m.ranges.len(), 2);
+        // Each range is offered both fixes, which only edit that range
+        for range in &m.ranges {
+            let labels: Vec<_> = m
+                .fixes
+                .iter()
+                .filter(|fix| &fix.range == range)
+                .map(|fix| fix.label.as_str())
+                .collect();
+            assert_eq!(labels, ["Log info", "Log a warning"]);
+        }
+    }
+
+    // The two sites take different fixes
+    let fixes = &matches[0].fixes;
+    let chosen = [
+        fixes
+            .iter()
+            .find(|fix| fix.range == matches[0].ranges[0] && fix.label == "Log info")
+            .unwrap(),
+        fixes
+            .iter()
+            .find(|fix| fix.range == matches[0].ranges[1] && fix.label == "Log a warning")
+            .unwrap(),
+    ];
+    let fixed =
+        crate::fixes::apply_fix_edits(source, chosen.iter().flat_map(|fix| &fix.edits)).unwrap();
+    assert_eq!(fixed, "logger.info('a');\nlogger.warn('b');");
+}
+
+#[test]
+fn renames_references_of_a_declaration() {
+    run_test_expected(TestArgExpected {
+        pattern: r#"
+            |language js
+            |
+            |`const $x = $_` where {
+            |    $x <: `data`,
+            |    $refs = references_of(declaration = $x),
+            |    $refs <: every $ref where { $ref => `result` }
+            |}
+            |"#
+        .trim_margin()
+        .unwrap(),
+        source: r#"
+            |const data = load();
+            |function process(data) {
+            |  return data.map((item) => item);
+            |}
+            |console.log(data);
+            |"#
+        .trim_margin()
+        .unwrap(),
+        expected: r#"
+            |const result = load();
+            |function process(data) {
+            |  return data.map((item) => item);
+            |}
+            |console.log(result);
+            |"#
+        .trim_margin()
+        .unwrap(),
+    })
+    .unwrap();
+}
+
+#[test]
+fn resolves_calls_to_their_declaration() {
+    run_test_expected(TestArgExpected {
+        pattern: r#"
+            |language js
+            |
+            |`$f($args)` where {
+            |    $f <: `foo`,
+            |    $decl = declaration_of(identifier = $f),
+            |    $decl <: within import_statement()
+            |} => `bar($args)`
+            |"#
+        .trim_margin()
+        .unwrap(),
+        source: r#"
+            |import { foo } from "lib";
+            |foo(1);
+            |function local() {
+            |  const foo = () => 2;
+            |  foo(3);
+            |}
+            |"#
+        .trim_margin()
+        .unwrap(),
+        expected: r#"
+            |import { foo } from "lib";
+            |bar(1);
+            |function local() {
+            |  const foo = () => 2;
+            |  foo(3);
+            |}
+            |"#
+        .trim_margin()
+        .unwrap(),
+    })
+    .unwrap();
+}
+
+#[derive(serde::Deserialize)]
+struct LanguageParityCase {
+    pattern: String,
+    file: String,
+    source: String,
+    expected: String,
+}
+
+/// The WASM bindings run the same cases, so every language must rewrite the
+/// same way in both.
+#[test]
+fn rewrites_language_parity_cases() {
+    let cases: Vec<LanguageParityCase> =
+        serde_json::from_str(include_str!("../fixtures/language_parity.json")).unwrap();
+    let mut covered = std::collections::HashSet::new();
+    for case in cases {
+        covered.insert(PatternLanguage::get_language(&case.pattern).unwrap());
+        let js_lang: TargetLanguage = PatternLanguage::Tsx.try_into().unwrap();
+        let result = match_pattern_one_file(case.pattern, &case.file, &case.source, js_lang)
+            .with_context(|| format!("failed to run the parity case for {}", case.file))
+            .unwrap();
+        validate_execution_result(result, case.expected).unwrap();
+    }
+    for language in PatternLanguage::enumerate() {
+        assert!(
+            covered.contains(&language),
+            "missing a parity case for {:?}",
+            language
+        );
+    }
+}
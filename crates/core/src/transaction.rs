@@ -0,0 +1,416 @@
+use crate::api::{FileMatchResult, MatchResult};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{self, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const JOURNAL_VERSION: u32 = 2;
+const MANIFEST_NAME: &str = "manifest.json";
+const ORIGINALS_DIR: &str = "originals";
+const TEMPORARY_SUFFIX: &str = ".grit-tmp";
+
+/// Only the most recent journals are kept, since every journal holds a copy
+/// of each file it changed.
+const JOURNALS_KEPT: usize = 10;
+
+/// Transaction stages the effects of applying a pattern in memory, so they can
+/// be written to disk all at once when the apply completes.
+/// - Later effects on a file replace earlier ones, exactly as if they had been
+///   written to disk one after the other
+/// - Committing records the previous contents of every changed file in a
+///   journal first, so the whole transaction can be undone later
+#[derive(Debug, Default)]
+pub struct Transaction {
+    /// The final contents of every affected file, or `None` if it is removed.
+    staged: BTreeMap<PathBuf, Option<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    path: PathBuf,
+    /// Name of the copy of the previous contents in the originals directory,
+    /// unless the file did not exist before.
+    original: Option<String>,
+    /// Hash of the contents written by the transaction, unless the file was
+    /// removed.
+    committed: Option<String>,
+}
+
+struct Change {
+    path: PathBuf,
+    original: Option<Vec<u8>>,
+    content: Option<String>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// Stages the effects of `result`, mirroring [`crate::fs::apply_rewrite`].
+    pub fn stage(&mut self, result: &MatchResult) -> Result<()> {
+        match result {
+            MatchResult::CreateFile(f) => {
+                let content = f.content()?.to_owned();
+                self.staged
+                    .insert(PathBuf::from(&f.rewritten.source_file), Some(content));
+            }
+            MatchResult::Rewrite(r) => {
+                let content = r.content()?.to_owned();
+                if r.rewritten.source_file != r.original.source_file {
+                    self.staged
+                        .insert(PathBuf::from(&r.original.source_file), None);
+                }
+                self.staged
+                    .insert(PathBuf::from(&r.rewritten.source_file), Some(content));
+            }
+            MatchResult::RemoveFile(f) => {
+                self.staged
+                    .insert(PathBuf::from(&f.original.source_file), None);
+            }
+            MatchResult::AnalysisLog(_)
+            | MatchResult::Match(_)
+            | MatchResult::InputFile(_)
+            | MatchResult::DoneFile(_)
+            | MatchResult::AllDone(_)
+            | MatchResult::PatternInfo(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Writes all staged effects to disk, after recording the previous
+    /// contents of the affected files in a new journal under `journal_dir`.
+    ///
+    /// New contents are written next to their targets before any file is
+    /// touched, and files changed before a failure are restored, so the tree
+    /// is either fully changed or left as it was.
+    ///
+    /// Returns the number of files which changed.
+    pub fn commit(self, journal_dir: &Path) -> Result<usize> {
+        let mut changes = Vec::new();
+        for (path, content) in self.staged {
+            let path = path::absolute(&path)?;
+            let original = read_if_exists(&path)?;
+            if original.as_deref() == content.as_deref().map(str::as_bytes) {
+                continue;
+            }
+            changes.push(Change {
+                path,
+                original,
+                content,
+            });
+        }
+        if changes.is_empty() {
+            return Ok(0);
+        }
+
+        let journal = record_journal(journal_dir, &changes)?;
+
+        let mut temporaries = Vec::new();
+        for change in &changes {
+            let Some(content) = &change.content else {
+                continue;
+            };
+            let temporary = temporary_path(&change.path);
+            if let Err(e) = write_creating_parents(&temporary, content.as_bytes()) {
+                for temporary in temporaries {
+                    let _ = fs_err::remove_file(temporary);
+                }
+                let _ = fs_err::remove_dir_all(&journal);
+                return Err(e).context("Failed to stage changes, no files were modified");
+            }
+            temporaries.push(temporary);
+        }
+
+        for change in &changes {
+            let moved = match &change.content {
+                Some(_) => fs_err::rename(temporary_path(&change.path), &change.path),
+                None => remove_if_exists(&change.path),
+            };
+            if let Err(e) = moved {
+                for temporary in temporaries {
+                    let _ = fs_err::remove_file(temporary);
+                }
+                restore_journal(&journal, true)
+                    .context("Failed to restore files after an incomplete apply")?;
+                return Err(e).context("Failed to apply changes, all files were restored");
+            }
+        }
+
+        prune_journals(journal_dir)?;
+        Ok(changes.len())
+    }
+}
+
+/// Restores the files changed by the most recent transaction committed to
+/// `journal_dir`, and removes its journal.
+///
+/// Unless `force` is set, nothing is restored if any of the files changed
+/// since the transaction was committed, so later edits are never lost.
+///
+/// Returns the number of restored files, or `None` if there was nothing to
+/// undo.
+pub fn undo_last_transaction(journal_dir: &Path, force: bool) -> Result<Option<usize>> {
+    let Some(journal) = list_journals(journal_dir)?.pop() else {
+        return Ok(None);
+    };
+    restore_journal(&journal, force).map(Some)
+}
+
+fn record_journal(journal_dir: &Path, changes: &[Change]) -> Result<PathBuf> {
+    if !journal_dir.exists() {
+        fs_err::create_dir_all(journal_dir)?;
+        fs_err::write(journal_dir.join(".gitignore"), "*\n")?;
+    }
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    // Zero-padded, so journals sort in the order they were committed
+    let journal = journal_dir.join(format!("{:020}-{}", timestamp, std::process::id()));
+    let originals = journal.join(ORIGINALS_DIR);
+    fs_err::create_dir_all(&originals)?;
+
+    let mut entries = Vec::with_capacity(changes.len());
+    for (index, change) in changes.iter().enumerate() {
+        let original = match &change.original {
+            Some(bytes) => {
+                let name = index.to_string();
+                fs_err::write(originals.join(&name), bytes)?;
+                Some(name)
+            }
+            None => None,
+        };
+        entries.push(ManifestEntry {
+            path: change.path.clone(),
+            original,
+            committed: change
+                .content
+                .as_deref()
+                .map(str::as_bytes)
+                .map(hash_content),
+        });
+    }
+
+    // The manifest is written last, so only complete journals are ever read
+    let manifest = Manifest {
+        version: JOURNAL_VERSION,
+        entries,
+    };
+    let temporary = journal.join(format!("{}{}", MANIFEST_NAME, TEMPORARY_SUFFIX));
+    fs_err::write(&temporary, serde_json::to_vec(&manifest)?)?;
+    fs_err::rename(&temporary, journal.join(MANIFEST_NAME))?;
+    Ok(journal)
+}
+
+fn restore_journal(journal: &Path, force: bool) -> Result<usize> {
+    let manifest: Manifest = serde_json::from_slice(&fs_err::read(journal.join(MANIFEST_NAME))?)
+        .context("Failed to read apply journal")?;
+    if manifest.version != JOURNAL_VERSION {
+        bail!(
+            "Unsupported apply journal version {} in {}",
+            manifest.version,
+            journal.display()
+        );
+    }
+    if !force {
+        let mut modified = Vec::new();
+        for entry in &manifest.entries {
+            let current = read_if_exists(&entry.path)?;
+            if current.as_deref().map(hash_content) != entry.committed {
+                modified.push(format!("  - {}", entry.path.display()));
+            }
+        }
+        if !modified.is_empty() {
+            bail!(
+                "These files changed since the last apply, use --force to undo it anyway:\n{}",
+                modified.join("\n")
+            );
+        }
+    }
+    for entry in &manifest.entries {
+        match &entry.original {
+            Some(name) => {
+                let bytes = fs_err::read(journal.join(ORIGINALS_DIR).join(name))?;
+                let temporary = temporary_path(&entry.path);
+                write_creating_parents(&temporary, &bytes)?;
+                fs_err::rename(&temporary, &entry.path)?;
+            }
+            None => remove_if_exists(&entry.path)?,
+        }
+    }
+    fs_err::remove_dir_all(journal)?;
+    Ok(manifest.entries.len())
+}
+
+/// Lists the complete journals in `journal_dir`, oldest first.
+fn list_journals(journal_dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs_err::read_dir(journal_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut journals = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.join(MANIFEST_NAME).is_file() {
+            journals.push(path);
+        }
+    }
+    journals.sort();
+    Ok(journals)
+}
+
+fn prune_journals(journal_dir: &Path) -> Result<()> {
+    let journals = list_journals(journal_dir)?;
+    let outdated = journals.len().saturating_sub(JOURNALS_KEPT);
+    for journal in &journals[..outdated] {
+        fs_err::remove_dir_all(journal)?;
+    }
+    Ok(())
+}
+
+fn hash_content(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(TEMPORARY_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs_err::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs_err::remove_file(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn write_creating_parents(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs_err::create_dir_all(parent)?;
+    }
+    fs_err::write(path, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Rewrite;
+
+    #[test]
+    fn commits_and_undoes_transactions() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = tempdir.path();
+        let journal_dir = dir.join(".grit").join("journal");
+        let rewritten = dir.join("rewritten.js");
+        let removed = dir.join("removed.js");
+        let created = dir.join("nested").join("created.js");
+        fs_err::write(&rewritten, "console.log('before');")?;
+        fs_err::write(&removed, "remove(me);")?;
+
+        let mut transaction = Transaction::new();
+        transaction.stage(&MatchResult::Rewrite(Rewrite::for_file(
+            &rewritten.to_string_lossy(),
+            "console.log('before');",
+            "console.log('after');",
+        )))?;
+        // Moving a file removes the original and creates the new one
+        transaction.stage(&MatchResult::Rewrite(Rewrite {
+            original: Rewrite::for_file(&removed.to_string_lossy(), "remove(me);", "").original,
+            rewritten: Rewrite::for_file(&created.to_string_lossy(), "", "created();").rewritten,
+            reason: None,
+            id: Default::default(),
+        }))?;
+
+        // Nothing is written until the transaction is committed
+        assert_eq!(
+            fs_err::read_to_string(&rewritten)?,
+            "console.log('before');"
+        );
+        assert!(!created.exists());
+
+        assert_eq!(transaction.commit(&journal_dir)?, 3);
+        assert_eq!(fs_err::read_to_string(&rewritten)?, "console.log('after');");
+        assert!(!removed.exists());
+        assert_eq!(fs_err::read_to_string(&created)?, "created();");
+
+        assert_eq!(undo_last_transaction(&journal_dir, false)?, Some(3));
+        assert_eq!(
+            fs_err::read_to_string(&rewritten)?,
+            "console.log('before');"
+        );
+        assert_eq!(fs_err::read_to_string(&removed)?, "remove(me);");
+        assert!(!created.exists());
+        assert_eq!(undo_last_transaction(&journal_dir, false)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_to_undo_over_later_edits() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = tempdir.path();
+        let journal_dir = dir.join(".grit").join("journal");
+        let rewritten = dir.join("rewritten.js");
+        let created = dir.join("created.js");
+        fs_err::write(&rewritten, "console.log('before');")?;
+
+        let mut transaction = Transaction::new();
+        transaction.stage(&MatchResult::Rewrite(Rewrite::for_file(
+            &rewritten.to_string_lossy(),
+            "console.log('before');",
+            "console.log('after');",
+        )))?;
+        transaction.stage(&MatchResult::Rewrite(Rewrite::for_file(
+            &created.to_string_lossy(),
+            "",
+            "created();",
+        )))?;
+        assert_eq!(transaction.commit(&journal_dir)?, 2);
+
+        fs_err::write(&rewritten, "console.log('edited');")?;
+        fs_err::write(&created, "edited();")?;
+        let error = undo_last_transaction(&journal_dir, false).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains(&rewritten.display().to_string()));
+        assert!(message.contains(&created.display().to_string()));
+        // Nothing was restored, and the journal is kept
+        assert_eq!(
+            fs_err::read_to_string(&rewritten)?,
+            "console.log('edited');"
+        );
+        assert_eq!(fs_err::read_to_string(&created)?, "edited();");
+
+        assert_eq!(undo_last_transaction(&journal_dir, true)?, Some(2));
+        assert_eq!(
+            fs_err::read_to_string(&rewritten)?,
+            "console.log('before');"
+        );
+        assert!(!created.exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use grit_util::Range;
+pub use marzano_util::runtime::TypeProvider;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use tsserver::TsServerTypeProvider;
+
+/// Extracts the type from a tsserver display string, such as
+/// `const items: Observable<Item>` or `(property) Foo.bar: string`.
+///
+/// The type is whatever follows the first colon outside of any brackets.
+/// Display strings without such a colon, like `class Foo`, are returned as is.
+pub fn type_from_display_string(display: &str) -> &str {
+    let mut depth = 0_usize;
+    let mut previous = None;
+    for (index, c) in display.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            '>' if previous == Some('=') => {}
+            ')' | ']' | '}' | '>' => depth = depth.saturating_sub(1),
+            ':' if depth == 0 => return display[index + 1..].trim(),
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    display.trim()
+}
+
+/// Returns the 1-based line and UTF-16 column of the last character of
+/// `range`, which is how tsserver addresses positions.
+pub fn tsserver_location(source: &str, range: &Range) -> (u32, u32) {
+    let end = (range.end_byte as usize).min(source.len());
+    let start = (range.start_byte as usize).min(end);
+    let last = source[..end]
+        .char_indices()
+        .next_back()
+        .map_or(start, |(index, _)| index.max(start));
+    let line_start = source[..last].rfind('\n').map_or(0, |index| index + 1);
+    let line = source[..last].matches('\n').count() + 1;
+    let column = source[line_start..last].encode_utf16().count() + 1;
+    (line as u32, column as u32)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod tsserver {
+    use super::{tsserver_location, type_from_display_string};
+    use anyhow::{anyhow, bail, Context, Result};
+    use grit_util::Range;
+    use marzano_util::runtime::TypeProvider;
+    use serde_json::{json, Value};
+    use std::{
+        collections::{hash_map::DefaultHasher, HashMap},
+        hash::{Hash, Hasher},
+        io::{BufRead, BufReader, Read, Write},
+        path::{self, Path, PathBuf},
+        process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+        sync::Mutex,
+    };
+
+    /// Looks up types through a `tsserver` process, which is started once and
+    /// shared by all files.
+    #[derive(Debug)]
+    pub struct TsServerTypeProvider {
+        server: Mutex<TsServer>,
+    }
+
+    impl TsServerTypeProvider {
+        /// Starts the tsserver at `executable`, such as
+        /// `node_modules/.bin/tsserver`.
+        pub fn spawn(executable: &Path) -> Result<Self> {
+            let mut process = Command::new(executable)
+                .arg("--disableAutomaticTypingAcquisition")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .with_context(|| format!("failed to start tsserver at {}", executable.display()))?;
+            let stdin = process
+                .stdin
+                .take()
+                .ok_or_else(|| anyhow!("tsserver has no stdin"))?;
+            let stdout = process
+                .stdout
+                .take()
+                .ok_or_else(|| anyhow!("tsserver has no stdout"))?;
+            Ok(Self {
+                server: Mutex::new(TsServer {
+                    process,
+                    stdin,
+                    stdout: BufReader::new(stdout),
+                    seq: 0,
+                    opened: HashMap::new(),
+                }),
+            })
+        }
+    }
+
+    impl TypeProvider for TsServerTypeProvider {
+        fn type_of(&self, path: &Path, source: &str, range: &Range) -> Result<Option<String>> {
+            let path = path::absolute(path)?;
+            let mut server = self
+                .server
+                .lock()
+                .map_err(|_| anyhow!("tsserver is unavailable after an earlier failure"))?;
+            server.open(&path, source)?;
+
+            // Ask about the last character of the node, so member accesses
+            // report the type of the accessed property.
+            let (line, offset) = tsserver_location(source, range);
+            let seq = server.send(
+                "quickinfo",
+                json!({
+                    "file": path,
+                    "line": line,
+                    "offset": offset,
+                }),
+            )?;
+            let response = server.response(seq)?;
+            if response["success"] != true {
+                return Ok(None);
+            }
+            Ok(response["body"]["displayString"]
+                .as_str()
+                .map(|display| type_from_display_string(display).to_owned()))
+        }
+    }
+
+    #[derive(Debug)]
+    struct TsServer {
+        process: Child,
+        stdin: ChildStdin,
+        stdout: BufReader<ChildStdout>,
+        seq: u64,
+        /// Hashes of the contents each open file was last sent with.
+        opened: HashMap<PathBuf, u64>,
+    }
+
+    impl TsServer {
+        fn send(&mut self, command: &str, arguments: Value) -> Result<u64> {
+            self.seq += 1;
+            let request = json!({
+                "seq": self.seq,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            });
+            writeln!(self.stdin, "{request}")?;
+            self.stdin.flush()?;
+            Ok(self.seq)
+        }
+
+        /// Skips events and other responses until the response to `seq`.
+        fn response(&mut self, seq: u64) -> Result<Value> {
+            loop {
+                let message = self.read_message()?;
+                if message["type"] == "response" && message["request_seq"] == seq {
+                    return Ok(message);
+                }
+            }
+        }
+
+        fn read_message(&mut self) -> Result<Value> {
+            let mut length = None;
+            loop {
+                let mut line = String::new();
+                if self.stdout.read_line(&mut line)? == 0 {
+                    bail!("tsserver exited unexpectedly");
+                }
+                let line = line.trim_end();
+                if let Some(value) = line.strip_prefix("Content-Length:") {
+                    length = Some(value.trim().parse::<usize>()?);
+                } else if line.is_empty() && length.is_some() {
+                    break;
+                }
+            }
+            let mut body = vec![0; length.unwrap_or_default()];
+            self.stdout.read_exact(&mut body)?;
+            Ok(serde_json::from_slice(&body)?)
+        }
+
+        /// Makes sure tsserver sees the current `source` of the file.
+        fn open(&mut self, path: &Path, source: &str) -> Result<()> {
+            let mut hasher = DefaultHasher::new();
+            source.hash(&mut hasher);
+            let hash = hasher.finish();
+            match self.opened.get(path) {
+                Some(opened) if *opened == hash => return Ok(()),
+                Some(_) => {
+                    self.send("close", json!({ "file": path }))?;
+                }
+                None => {}
+            }
+            // Neither request is answered with a response, only with events.
+            self.send("open", json!({ "file": path, "fileContent": source }))?;
+            self.opened.insert(path.to_owned(), hash);
+            Ok(())
+        }
+    }
+
+    impl Drop for TsServer {
+        fn drop(&mut self) {
+            let _ = self.process.kill();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grit_util::ByteRange;
+
+    #[test]
+    fn extracts_types_from_display_strings() {
+        assert_eq!(
+            type_from_display_string("const items: Observable<Item>"),
+            "Observable<Item>"
+        );
+        assert_eq!(
+            type_from_display_string("(property) Foo.bar: Map<string, number[]>"),
+            "Map<string, number[]>"
+        );
+        assert_eq!(
+            type_from_display_string(
+                "(method) Array<number>.map<U>(callbackfn: (value: number) => U): U[]"
+            ),
+            "U[]"
+        );
+        assert_eq!(type_from_display_string("class Foo"), "class Foo");
+    }
+
+    #[test]
+    fn addresses_tsserver_positions_in_utf16() {
+        let source = "const name = \"héllo 👋\"; items.map(f);";
+        let start = source.find("items.map").unwrap();
+        let end = start + "items.map".len();
+        let range = Range::from_byte_range(source, &ByteRange::new(start, end));
+        // `é` takes one UTF-16 unit and `👋` takes two, while both take more bytes
+        assert_eq!(tsserver_location(source, &range), (1, 34));
+
+        let source = "a;\n// ü\nfoo.bar";
+        let start = source.find("foo.bar").unwrap();
+        let range = Range::from_byte_range(source, &ByteRange::new(start, source.len()));
+        assert_eq!(tsserver_location(source, &range), (3, 7));
+    }
+}
@@ -5,7 +5,8 @@ use crate::{
 
 /// Determine if a provided pattern has a rewrite anywhere inside of it
 ///
-/// Note this does not yet walk inside predicates and function calls
+/// Calls are followed into the pattern, predicate, and function definitions
+/// they refer to, visiting each definition once.
 pub fn has_rewrite<Q: QueryContext>(
     current_pattern: &Pattern<Q>,
     definitions: &StaticDefinitions<Q>,
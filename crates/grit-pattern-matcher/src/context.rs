@@ -7,7 +7,10 @@ use crate::{
         State,
     },
 };
-use grit_util::{error::GritResult, AnalysisLogs, Ast, AstNode, Language};
+use grit_util::{
+    error::GritResult, AnalysisLogs, Ast, AstNode, ClauseCoverage, ClauseId, ExecutionBudget,
+    Language, MatchTrace,
+};
 
 /// Contains various kinds of context about the query being executed.
 pub trait QueryContext: Clone + std::fmt::Debug + Sized + 'static {
@@ -75,6 +78,36 @@ pub trait ExecContext<'a, Q: QueryContext> {
     ) -> GritResult<bool>;
 
     fn name(&self) -> Option<&str>;
+
+    /// Returns the recorder for clause coverage, if coverage is being collected.
+    fn clause_coverage(&self) -> Option<&ClauseCoverage> {
+        None
+    }
+
+    /// Records that a clause was reached, if coverage is being collected.
+    fn record_clause<T>(&self, clause: &T) {
+        if let Some(coverage) = self.clause_coverage() {
+            coverage.record(ClauseId::of(clause));
+        }
+    }
+
+    /// Returns the recorder for the evaluation trace, if one is being collected.
+    fn match_trace(&self) -> Option<&MatchTrace> {
+        None
+    }
+
+    /// Returns the budget of the current execution, if its work is limited.
+    fn execution_budget(&self) -> Option<&ExecutionBudget> {
+        None
+    }
+
+    /// Counts a step of execution, failing once the budget is exhausted.
+    fn step(&self) -> GritResult<()> {
+        match self.execution_budget() {
+            Some(budget) => budget.step(),
+            None => Ok(()),
+        }
+    }
 }
 
 /// Static information used for a pattern
@@ -109,6 +142,19 @@ impl<'a, Q: QueryContext> StaticDefinitions<'a, Q> {
         self.pattern_definitions.get(index)
     }
 
+    /// Iterates over the pattern definitions that are not skipped during analysis.
+    pub fn patterns(&self) -> impl Iterator<Item = &PatternDefinition<Q>> {
+        self.pattern_definitions
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !self.skippable_indexes.contains(index))
+            .map(|(_, definition)| definition)
+    }
+
+    pub fn predicates(&self) -> impl Iterator<Item = &PredicateDefinition<Q>> {
+        self.predicate_definitions.iter()
+    }
+
     pub fn get_predicate(&self, index: usize) -> Option<&PredicateDefinition<Q>> {
         self.predicate_definitions.get(index)
     }
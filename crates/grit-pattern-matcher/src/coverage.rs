@@ -0,0 +1,202 @@
+//! Clause coverage, used by `grit patterns test --coverage`.
+//!
+//! A clause is a point where a pattern can take a different path: each `or`
+//! branch, `where` side condition, rewrite, and call to a named pattern.
+//! Matchers record the clauses they reach with
+//! [`ExecContext::record_clause`](crate::context::ExecContext::record_clause),
+//! and [`coverage_report`] maps those back onto the pattern definitions the
+//! clauses were written in.
+
+use crate::{
+    context::{QueryContext, StaticDefinitions},
+    pattern::{Container, Pattern, PatternOrPredicate, Predicate, Where},
+};
+use grit_util::{ClauseCoverage, ClauseId};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClauseKind {
+    OrBranch,
+    Where,
+    Rewrite,
+    Call,
+}
+
+impl fmt::Display for ClauseKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClauseKind::OrBranch => write!(f, "or branch"),
+            ClauseKind::Where => write!(f, "where clause"),
+            ClauseKind::Rewrite => write!(f, "rewrite"),
+            ClauseKind::Call => write!(f, "pattern call"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Clause {
+    pub id: ClauseId,
+    pub kind: ClauseKind,
+    pub description: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClauseReport {
+    pub kind: ClauseKind,
+    pub description: String,
+    pub reached: bool,
+}
+
+/// Coverage of the clauses written in a single pattern or predicate definition.
+#[derive(Debug, Clone)]
+pub struct DefinitionCoverage {
+    pub name: String,
+    pub clauses: Vec<ClauseReport>,
+}
+
+impl DefinitionCoverage {
+    pub fn reached_count(&self) -> usize {
+        self.clauses.iter().filter(|c| c.reached).count()
+    }
+}
+
+/// Lists the clauses written directly in `root`, in source order.
+///
+/// Calls are listed as clauses, but the definitions they call are not walked;
+/// those are reported separately by [`coverage_report`].
+pub fn collect_clauses<'a, Q: QueryContext>(
+    root: PatternOrPredicate<'a, Q>,
+    definitions: &StaticDefinitions<'a, Q>,
+) -> Vec<Clause> {
+    let unfollowed = StaticDefinitions::default();
+    let mut clauses = Vec::new();
+    let mut where_count = 0;
+    let mut rewrite_count = 0;
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        match node {
+            PatternOrPredicate::Pattern(Pattern::Or(or)) => {
+                clauses.extend(or.patterns.iter().enumerate().map(|(i, branch)| Clause {
+                    id: ClauseId::of(branch),
+                    kind: ClauseKind::OrBranch,
+                    description: format!("or branch {} of {}", i + 1, or.patterns.len()),
+                }))
+            }
+            PatternOrPredicate::Predicate(Predicate::Or(or)) => {
+                clauses.extend(or.predicates.iter().enumerate().map(|(i, branch)| Clause {
+                    id: ClauseId::of(branch),
+                    kind: ClauseKind::OrBranch,
+                    description: format!("or branch {} of {}", i + 1, or.predicates.len()),
+                }))
+            }
+            PatternOrPredicate::Pattern(Pattern::Where(w)) if !is_self_match(w) => {
+                where_count += 1;
+                clauses.push(Clause {
+                    id: ClauseId::of(&w.side_condition),
+                    kind: ClauseKind::Where,
+                    description: format!("where clause #{}", where_count),
+                });
+            }
+            PatternOrPredicate::Pattern(Pattern::Rewrite(r))
+            | PatternOrPredicate::Predicate(Predicate::Rewrite(r)) => {
+                rewrite_count += 1;
+                clauses.push(Clause {
+                    id: ClauseId::of(r.as_ref()),
+                    kind: ClauseKind::Rewrite,
+                    description: format!("rewrite #{}", rewrite_count),
+                });
+            }
+            PatternOrPredicate::Pattern(Pattern::Call(call)) => {
+                // Calls to skipped definitions, such as `before_each_file`, are not user clauses.
+                if let Some(definition) = definitions.get_pattern(call.index) {
+                    clauses.push(Clause {
+                        id: ClauseId::of(call.as_ref()),
+                        kind: ClauseKind::Call,
+                        description: format!("call to `{}`", definition.name),
+                    });
+                }
+            }
+            _ => {}
+        }
+        let mut children = match node {
+            // The generic walk skips over the root of a bubble's body, which may itself be a clause.
+            PatternOrPredicate::Pattern(Pattern::Bubble(bubble)) => bubble
+                .args
+                .iter()
+                .flatten()
+                .map(PatternOrPredicate::Pattern)
+                .chain(Some(PatternOrPredicate::Pattern(
+                    bubble.pattern_def.pattern(),
+                )))
+                .collect(),
+            _ => node.children(&unfollowed),
+        };
+        children.reverse();
+        stack.extend(children);
+    }
+    clauses
+}
+
+/// Builds a coverage report for `root` and every pattern and predicate
+/// definition it was compiled with.
+///
+/// Definitions without any clauses are omitted.
+pub fn coverage_report<Q: QueryContext>(
+    root_name: &str,
+    root: &Pattern<Q>,
+    definitions: &StaticDefinitions<Q>,
+    coverage: &ClauseCoverage,
+) -> Vec<DefinitionCoverage> {
+    let report = |name: &str, clauses: Vec<Clause>| DefinitionCoverage {
+        name: name.to_string(),
+        clauses: clauses
+            .into_iter()
+            .map(|clause| ClauseReport {
+                reached: coverage.is_reached(clause.id),
+                kind: clause.kind,
+                description: clause.description,
+            })
+            .collect(),
+    };
+
+    let mut reports = vec![report(
+        root_name,
+        collect_clauses(PatternOrPredicate::Pattern(root), definitions),
+    )];
+    for definition in definitions.patterns() {
+        reports.push(report(
+            &definition.name,
+            collect_clauses(
+                PatternOrPredicate::Pattern(definition.pattern()),
+                definitions,
+            ),
+        ));
+    }
+    for definition in definitions.predicates() {
+        reports.push(report(
+            &definition.name,
+            collect_clauses(
+                PatternOrPredicate::Predicate(&definition.predicate),
+                definitions,
+            ),
+        ));
+    }
+    reports.retain(|r| !r.clauses.is_empty());
+    reports
+}
+
+/// Auto-wrapping binds the whole pattern with `$match where { $match <: ... }`,
+/// which always holds when the inner pattern does and is not a clause of its own.
+fn is_self_match<Q: QueryContext>(w: &Where<Q>) -> bool {
+    let (Pattern::Variable(var), Predicate::Match(m)) = (&w.pattern, &w.side_condition) else {
+        return false;
+    };
+    let Container::Variable(matched) = &m.val else {
+        return false;
+    };
+    matches!(
+        (var.try_scope(), var.try_index(), matched.try_scope(), matched.try_index()),
+        (Ok(scope), Ok(index), Ok(other_scope), Ok(other_index))
+            if scope == other_scope && index == other_index
+    )
+}
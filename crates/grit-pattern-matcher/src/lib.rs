@@ -9,6 +9,7 @@ pub mod binding;
 pub mod constant;
 pub mod constants;
 pub mod context;
+pub mod coverage;
 pub mod effects;
 pub mod errors;
 pub mod file_owners;
@@ -112,7 +112,9 @@ pub use resolved_pattern::{File, JoinFn, LazyBuiltIn, ResolvedFile, ResolvedSnip
 pub use rewrite::Rewrite;
 pub use sequential::Sequential;
 pub use some::Some;
-pub use state::{get_top_level_effects, EffectRange, FilePtr, FileRegistry, State};
+pub use state::{
+    get_top_level_effects, EffectRange, FilePtr, FileRegistry, State, StateCheckpoint,
+};
 pub use step::Step;
 pub use string_constant::StringConstant;
 pub use subtract::Subtract;
@@ -40,6 +40,7 @@ impl<Q: QueryContext> Matcher<Q> for Call<Q> {
         logs: &mut AnalysisLogs,
     ) -> GritResult<bool> {
         let pattern_definition = &context.pattern_definitions()[self.index];
+        context.record_clause(self);
 
         pattern_definition.call(state, binding, context, logs, &self.args)
     }
@@ -47,7 +48,7 @@ impl<Q: QueryContext> Matcher<Q> for Call<Q> {
 
 #[derive(Debug, Clone)]
 pub struct PrCall<Q: QueryContext> {
-    pub(crate) index: usize,
+    pub index: usize,
     pub args: Vec<Option<Pattern<Q>>>,
 }
 
@@ -10,22 +10,28 @@ use crate::{
         patterns::CodeSnippet,
     },
 };
+use std::collections::HashSet;
 
 pub struct PatternOrPredicateIterator<'a, Q: QueryContext> {
     patterns: Vec<PatternOrPredicate<'a, Q>>,
     definitions: &'a StaticDefinitions<'a, Q>,
+    /// Definitions are shared by every call to them and may be recursive, so
+    /// we remember what was already visited to walk each of them only once.
+    visited: HashSet<(u8, *const ())>,
 }
 
 impl<'a, Q: QueryContext> Iterator for PatternOrPredicateIterator<'a, Q> {
     type Item = PatternOrPredicate<'a, Q>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(pattern) = self.patterns.pop() {
+        while let Some(pattern) = self.patterns.pop() {
+            if !self.visited.insert(pattern.identity()) {
+                continue;
+            }
             self.patterns.extend(pattern.children(self.definitions));
-            Some(pattern)
-        } else {
-            None
+            return Some(pattern);
         }
+        None
     }
 }
 
@@ -34,12 +40,14 @@ impl<'a, Q: QueryContext> PatternOrPredicateIterator<'a, Q> {
         Self {
             patterns: vec![PatternOrPredicate::Pattern(pattern)],
             definitions,
+            visited: HashSet::new(),
         }
     }
     fn from_predicate(predicate: &'a Predicate<Q>, definitions: &'a StaticDefinitions<Q>) -> Self {
         Self {
             patterns: vec![PatternOrPredicate::Predicate(predicate)],
             definitions,
+            visited: HashSet::new(),
         }
     }
 }
@@ -53,7 +61,21 @@ pub enum PatternOrPredicate<'a, Q: QueryContext> {
 }
 
 impl<'a, Q: QueryContext> PatternOrPredicate<'a, Q> {
-    fn children(&self, definitions: &'a StaticDefinitions<Q>) -> Vec<PatternOrPredicate<'a, Q>> {
+    /// Identifies the pattern or predicate by its kind and address.
+    fn identity(&self) -> (u8, *const ()) {
+        match self {
+            PatternOrPredicate::Pattern(p) => (0, *p as *const Pattern<Q> as *const ()),
+            PatternOrPredicate::Predicate(p) => (1, *p as *const Predicate<Q> as *const ()),
+            PatternOrPredicate::DynamicPattern(p) => {
+                (2, *p as *const DynamicPattern<Q> as *const ())
+            }
+        }
+    }
+
+    pub(crate) fn children(
+        &self,
+        definitions: &'a StaticDefinitions<Q>,
+    ) -> Vec<PatternOrPredicate<'a, Q>> {
         match self {
             PatternOrPredicate::Pattern(p) => p.children(definitions),
             PatternOrPredicate::Predicate(p) => p.children(definitions),
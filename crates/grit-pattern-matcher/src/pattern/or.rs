@@ -6,7 +6,10 @@ use super::{
     resolved_pattern::ResolvedPattern,
     State,
 };
-use crate::{binding::Binding, context::QueryContext};
+use crate::{
+    binding::Binding,
+    context::{ExecContext, QueryContext},
+};
 use core::fmt::Debug;
 use grit_util::{error::GritResult, AnalysisLogs};
 
@@ -44,6 +47,7 @@ impl<Q: QueryContext> Matcher<Q> for Or<Q> {
                         continue;
                     }
                 }
+                context.record_clause(p);
                 let mut state = init_state.clone();
                 let res = p.execute(resolved, &mut state, context, logs)?;
                 if res {
@@ -53,6 +57,7 @@ impl<Q: QueryContext> Matcher<Q> for Or<Q> {
             }
         } else {
             for p in self.patterns.iter() {
+                context.record_clause(p);
                 let mut state = init_state.clone();
                 let res = p.execute(resolved, &mut state, context, logs)?;
                 if res {
@@ -90,6 +95,7 @@ impl<Q: QueryContext> Evaluator<Q> for PrOr<Q> {
         logs: &mut AnalysisLogs,
     ) -> GritResult<FuncEvaluation<Q>> {
         for p in self.predicates.iter() {
+            context.record_clause(p);
             let mut state = init_state.clone();
             let res = p.execute_func(&mut state, context, logs)?;
             if res.predicator || res.ret_val.is_some() {
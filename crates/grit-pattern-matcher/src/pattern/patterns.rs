@@ -231,6 +231,8 @@ impl<Q: QueryContext> Matcher<Q> for Pattern<Q> {
         context: &'a Q::ExecContext<'a>,
         logs: &mut AnalysisLogs,
     ) -> GritResult<bool> {
+        context.step()?;
+
         if let Some(file) = binding.get_file() {
             state.bindings[GLOBAL_VARS_SCOPE_INDEX as usize]
                 .last_mut()
@@ -238,6 +240,32 @@ impl<Q: QueryContext> Matcher<Q> for Pattern<Q> {
                 .value = Some(file.name(&state.files));
         }
 
+        let language = context.language();
+        if let Some(trace) = context
+            .match_trace()
+            .filter(|trace| trace.is_traced(|| binding.position(language)))
+        {
+            let text = binding
+                .text(&state.files, language)
+                .ok()
+                .map(|text| text.into_owned());
+            trace.enter(self.name(), binding.position(language), text);
+            let result = self.execute_operator(binding, state, context, logs);
+            trace.exit(matches!(result, Ok(true)));
+            return result;
+        }
+        self.execute_operator(binding, state, context, logs)
+    }
+}
+
+impl<Q: QueryContext> Pattern<Q> {
+    fn execute_operator<'a>(
+        &'a self,
+        binding: &Q::ResolvedPattern<'a>,
+        state: &mut State<'a, Q>,
+        context: &'a Q::ExecContext<'a>,
+        logs: &mut AnalysisLogs,
+    ) -> GritResult<bool> {
         match self {
             Pattern::AstNode(ast_node) => ast_node.execute(binding, state, context, logs),
             Pattern::Some(some) => some.execute(binding, state, context, logs),
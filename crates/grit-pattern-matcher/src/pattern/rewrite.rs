@@ -6,7 +6,10 @@ use super::{
     variable_content::VariableContent,
     State,
 };
-use crate::{context::QueryContext, effects::Effect};
+use crate::{
+    context::{ExecContext, QueryContext},
+    effects::Effect,
+};
 use core::fmt::Debug;
 use grit_util::{
     error::{GritPatternError, GritResult},
@@ -108,6 +111,7 @@ impl<Q: QueryContext> Rewrite<Q> {
             kind: EffectKind::Rewrite,
         });
         state.effects.extend(effects);
+        context.record_clause(self);
         Ok(true)
     }
 }
@@ -160,6 +160,9 @@ pub struct State<'a, Q: QueryContext> {
     pub bindings: VarRegistry<'a, Q>,
     pub effects: Vec<Effect<'a, Q>>,
     pub files: FileRegistry<'a, Q>,
+    /// Paths along which values were found to flow from a source into a
+    /// sink, such as by `flows_to()`.
+    pub flows: Vec<Vec<Range>>,
     rng: rand::rngs::StdRng,
     current_scope: usize,
     // Track dynamic pattern scope names
@@ -246,6 +249,17 @@ pub struct ScopeTracker {
     previous_scope: usize,
 }
 
+/// The bindings and effects of a [`State`] at one point, for undoing whatever
+/// a pattern bound while it was tried against a candidate.
+pub struct StateCheckpoint<'a, Q: QueryContext> {
+    bindings: VarRegistry<'a, Q>,
+    effects: usize,
+    flows: usize,
+    rng: rand::rngs::StdRng,
+    current_scope: usize,
+    pattern_scopes: HashMap<String, usize>,
+}
+
 impl<'a, Q: QueryContext> State<'a, Q> {
     pub fn new(bindings: VarRegistry<'a, Q>, registry: FileRegistry<'a, Q>) -> Self {
         Self {
@@ -254,6 +268,7 @@ impl<'a, Q: QueryContext> State<'a, Q> {
             bindings,
             effects: vec![],
             files: registry,
+            flows: vec![],
             pattern_scopes: HashMap::new(),
         }
     }
@@ -270,6 +285,29 @@ impl<'a, Q: QueryContext> State<'a, Q> {
         &mut self.rng
     }
 
+    /// Records the bindings and effects of the state, to restore with [`State::rollback`]
+    pub fn checkpoint(&self) -> StateCheckpoint<'a, Q> {
+        StateCheckpoint {
+            bindings: self.bindings.clone(),
+            effects: self.effects.len(),
+            flows: self.flows.len(),
+            rng: self.rng.clone(),
+            current_scope: self.current_scope,
+            pattern_scopes: self.pattern_scopes.clone(),
+        }
+    }
+
+    /// Undoes the bindings and effects made since `checkpoint` was taken.
+    /// Loaded files are kept.
+    pub fn rollback(&mut self, checkpoint: &StateCheckpoint<'a, Q>) {
+        self.bindings.clone_from(&checkpoint.bindings);
+        self.effects.truncate(checkpoint.effects);
+        self.flows.truncate(checkpoint.flows);
+        self.rng.clone_from(&checkpoint.rng);
+        self.current_scope = checkpoint.current_scope;
+        self.pattern_scopes.clone_from(&checkpoint.pattern_scopes);
+    }
+
     /// Enter a scope by copying the current scope and adding the new variables
     /// When you are done with a scope, you *must* call exit_scope
     ///
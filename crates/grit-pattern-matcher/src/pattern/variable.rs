@@ -411,6 +411,11 @@ impl<Q: QueryContext> Matcher<Q> for Variable {
         variable_content
             .value_history
             .push(resolved_pattern.clone());
+        if let Some(trace) = context.match_trace() {
+            if let Ok(value) = resolved_pattern.text(&state.files, context.language()) {
+                trace.bind(&variable_content.name, value.into_owned());
+            }
+        }
         Ok(true)
     }
 }
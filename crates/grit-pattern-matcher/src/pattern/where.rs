@@ -4,7 +4,7 @@ use super::{
     predicates::Predicate,
     State,
 };
-use crate::context::QueryContext;
+use crate::context::{ExecContext, QueryContext};
 use core::fmt::Debug;
 use grit_util::{error::GritResult, AnalysisLogs};
 
@@ -46,6 +46,7 @@ impl<Q: QueryContext> Matcher<Q> for Where<Q> {
         {
             return Ok(false);
         }
+        context.record_clause(&self.side_condition);
         if self
             .side_condition
             .execute_func(&mut cur_state, context, logs)?
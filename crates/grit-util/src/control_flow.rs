@@ -0,0 +1,205 @@
+use crate::AstNode;
+use std::collections::HashSet;
+
+/// Identifies a vertex of a [`ControlFlowGraph`].
+pub type Vertex = usize;
+
+/// The control-flow graph of a function body.
+///
+/// Vertices are the steps of the function, such as simple statements and the
+/// conditions of branches and loops, along with a synthetic entry and exit.
+/// An edge leads from each vertex to every vertex that may run right after it.
+/// Returns and uncaught exceptions lead to the exit.
+#[derive(Clone, Debug)]
+pub struct ControlFlowGraph<N> {
+    nodes: Vec<Option<N>>,
+    successors: Vec<Vec<Vertex>>,
+}
+
+impl<N> Default for ControlFlowGraph<N> {
+    fn default() -> Self {
+        Self {
+            nodes: vec![None, None],
+            successors: vec![Vec::new(), Vec::new()],
+        }
+    }
+}
+
+impl<N> ControlFlowGraph<N> {
+    pub const ENTRY: Vertex = 0;
+    pub const EXIT: Vertex = 1;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a vertex for the step `node`, or a synthetic vertex if `None`.
+    pub fn add_vertex(&mut self, node: Option<N>) -> Vertex {
+        self.nodes.push(node);
+        self.successors.push(Vec::new());
+        self.nodes.len() - 1
+    }
+
+    pub fn add_edge(&mut self, from: Vertex, to: Vertex) {
+        if !self.successors[from].contains(&to) {
+            self.successors[from].push(to);
+        }
+    }
+
+    /// Returns the number of vertices, including the entry and exit.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the graph has no vertices besides the entry and exit.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() <= 2
+    }
+
+    pub fn node(&self, vertex: Vertex) -> Option<&N> {
+        self.nodes.get(vertex).and_then(Option::as_ref)
+    }
+
+    pub fn successors(&self, vertex: Vertex) -> &[Vertex] {
+        &self.successors[vertex]
+    }
+
+    /// Returns the vertices reachable from `start` without passing through a
+    /// vertex for which `is_blocked()` holds. Blocked vertices are included,
+    /// but not walked past.
+    fn reachable_from(
+        &self,
+        start: impl IntoIterator<Item = Vertex>,
+        mut is_blocked: impl FnMut(Vertex) -> bool,
+    ) -> HashSet<Vertex> {
+        let mut reached = HashSet::new();
+        let mut stack: Vec<Vertex> = start.into_iter().collect();
+        while let Some(vertex) = stack.pop() {
+            if !reached.insert(vertex) || is_blocked(vertex) {
+                continue;
+            }
+            stack.extend(self.successors(vertex));
+        }
+        reached
+    }
+
+    /// Whether `vertex` can run at all.
+    pub fn is_vertex_reachable(&self, vertex: Vertex) -> bool {
+        self.reachable_from([Self::ENTRY], |_| false)
+            .contains(&vertex)
+    }
+
+    /// Whether every path from the entry to `second` passes through `first`.
+    pub fn dominates(&self, first: Vertex, second: Vertex) -> bool {
+        first == second
+            || !self
+                .reachable_from([Self::ENTRY], |vertex| vertex == first)
+                .contains(&second)
+    }
+
+    /// Whether every path from `from` to the exit passes through a vertex for
+    /// which `is_target()` holds, not counting `from` itself.
+    ///
+    /// Holds trivially if the exit cannot be reached from `from`.
+    pub fn always_reaches(&self, from: Vertex, is_target: impl FnMut(Vertex) -> bool) -> bool {
+        !self
+            .reachable_from(self.successors(from).to_vec(), is_target)
+            .contains(&Self::EXIT)
+    }
+}
+
+impl<N: AstNode> ControlFlowGraph<N> {
+    /// Returns the vertex of the innermost step which contains `node`.
+    pub fn vertex_of(&self, node: &N) -> Option<Vertex> {
+        let range = node.byte_range();
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(vertex, step)| Some((vertex, step.as_ref()?.byte_range())))
+            .filter(|(_, step)| step.start <= range.start && range.end <= step.end)
+            .min_by_key(|(_, step)| step.end - step.start)
+            .map(|(vertex, _)| vertex)
+    }
+
+    /// Whether the step containing `node` can run at all.
+    ///
+    /// Nodes outside of any step, such as the function itself, are considered
+    /// reachable.
+    pub fn is_reachable(&self, node: &N) -> bool {
+        self.vertex_of(node)
+            .map_or(true, |vertex| self.is_vertex_reachable(vertex))
+    }
+
+    /// Whether `first` always runs before `second`: every path from the entry
+    /// to `second` passes through `first`.
+    ///
+    /// Within a single step, nodes are assumed to run in source order.
+    pub fn always_before(&self, first: &N, second: &N) -> bool {
+        let (Some(first_vertex), Some(second_vertex)) =
+            (self.vertex_of(first), self.vertex_of(second))
+        else {
+            return false;
+        };
+        if first_vertex == second_vertex {
+            first.byte_range().end <= second.byte_range().start
+        } else {
+            self.dominates(first_vertex, second_vertex)
+        }
+    }
+
+    /// Whether every path from `node` to the exit of the function runs a step
+    /// for which `is_target()` holds after the step containing `node`.
+    pub fn always_followed_by(&self, node: &N, mut is_target: impl FnMut(&N) -> bool) -> bool {
+        let Some(from) = self.vertex_of(node) else {
+            return false;
+        };
+        self.always_reaches(from, |vertex| self.node(vertex).is_some_and(&mut is_target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Graph = ControlFlowGraph<()>;
+
+    /// Builds `entry -> a -> (b | c) -> d -> exit`, where `c` may also return
+    /// early.
+    fn diamond() -> (Graph, [Vertex; 4]) {
+        let mut graph = Graph::new();
+        let [a, b, c, d] = [(); 4].map(|_| graph.add_vertex(Some(())));
+        for (from, to) in [
+            (Graph::ENTRY, a),
+            (a, b),
+            (a, c),
+            (b, d),
+            (c, d),
+            (c, Graph::EXIT),
+            (d, Graph::EXIT),
+        ] {
+            graph.add_edge(from, to);
+        }
+        (graph, [a, b, c, d])
+    }
+
+    #[test]
+    fn finds_dominators() {
+        let (graph, [a, b, c, d]) = diamond();
+        assert!(graph.dominates(a, d));
+        assert!(!graph.dominates(b, d));
+        assert!(!graph.dominates(c, b));
+        assert!(graph.dominates(d, d));
+    }
+
+    #[test]
+    fn finds_targets_on_every_path() {
+        let (mut graph, [a, b, c, d]) = diamond();
+        assert!(!graph.always_reaches(a, |vertex| vertex == d));
+        assert!(graph.always_reaches(a, |vertex| vertex == b || vertex == c));
+
+        let unreachable = graph.add_vertex(Some(()));
+        graph.add_edge(unreachable, d);
+        assert!(!graph.is_vertex_reachable(unreachable));
+        assert!(graph.is_vertex_reachable(d));
+    }
+}
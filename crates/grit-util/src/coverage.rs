@@ -0,0 +1,58 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Identifies a clause of a compiled pattern by its address.
+///
+/// Ids are only stable for as long as the compiled pattern they were taken
+/// from is alive, so a [`ClauseCoverage`] should not outlive or be shared
+/// between compiled patterns.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ClauseId(usize);
+
+impl ClauseId {
+    pub fn of<T>(clause: &T) -> Self {
+        Self(clause as *const T as usize)
+    }
+}
+
+/// Records which clauses of a pattern were reached during execution.
+///
+/// The recorder is shared between threads, since files are executed in
+/// parallel.
+#[derive(Debug, Default)]
+pub struct ClauseCoverage {
+    reached: Mutex<HashSet<ClauseId>>,
+}
+
+impl ClauseCoverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, clause: ClauseId) {
+        if let Ok(mut reached) = self.reached.lock() {
+            reached.insert(clause);
+        }
+    }
+
+    pub fn is_reached(&self, clause: ClauseId) -> bool {
+        self.reached
+            .lock()
+            .map(|reached| reached.contains(&clause))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_reached_clauses() {
+        let clauses = [1, 2];
+        let coverage = ClauseCoverage::new();
+        coverage.record(ClauseId::of(&clauses[0]));
+        assert!(coverage.is_reached(ClauseId::of(&clauses[0])));
+        assert!(!coverage.is_reached(ClauseId::of(&clauses[1])));
+    }
+}
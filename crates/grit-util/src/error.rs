@@ -33,6 +33,14 @@ pub enum GritPatternError {
 
     #[error("{0}")]
     Generic(String),
+
+    /// An execution ran out of the time or steps it was allowed.
+    #[error("{0}")]
+    LimitExceeded(String),
+
+    /// An execution was stopped through its cancellation token.
+    #[error("execution was cancelled")]
+    Cancelled,
 }
 
 impl GritPatternError {
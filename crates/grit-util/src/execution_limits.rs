@@ -0,0 +1,147 @@
+use crate::error::{GritPatternError, GritResult};
+use crate::CancellationToken;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// How often the clock and the cancellation token are consulted, in steps,
+/// since reading them is much more expensive than counting a step. Must be a
+/// power of two.
+const STEPS_PER_CHECK: u64 = 1024;
+
+/// Limits on the work done by a single execution of a pattern against a
+/// file, so pathological combinations cannot stall a whole run.
+///
+/// Timeouts rely on the system clock and are not supported on
+/// `wasm32-unknown-unknown`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    /// Wall-clock time after which the execution is aborted.
+    pub timeout: Option<Duration>,
+    /// Number of pattern steps after which the execution is aborted.
+    pub max_steps: Option<u64>,
+}
+
+impl ExecutionLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.timeout.is_none() && self.max_steps.is_none()
+    }
+}
+
+/// Tracks the work done by an execution against its [`ExecutionLimits`],
+/// and whether the execution has been cancelled.
+#[derive(Debug)]
+pub struct ExecutionBudget {
+    limits: ExecutionLimits,
+    deadline: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+    steps: Cell<u64>,
+}
+
+impl ExecutionBudget {
+    /// Starts the clock on a new execution, unless it is unlimited and cannot
+    /// be cancelled.
+    pub fn start(limits: ExecutionLimits, cancellation: Option<CancellationToken>) -> Option<Self> {
+        if limits.is_unlimited() && cancellation.is_none() {
+            return None;
+        }
+        Some(Self {
+            limits,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            cancellation,
+            steps: Cell::new(0),
+        })
+    }
+
+    /// Counts a step, failing once the execution has run out of steps or
+    /// time, or has been cancelled.
+    pub fn step(&self) -> GritResult<()> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+
+        if let Some(max_steps) = self.limits.max_steps {
+            if steps > max_steps {
+                return Err(GritPatternError::LimitExceeded(format!(
+                    "exceeded the limit of {max_steps} steps"
+                )));
+            }
+        }
+        if steps & (STEPS_PER_CHECK - 1) != 0 {
+            return Ok(());
+        }
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(GritPatternError::Cancelled);
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.limits.timeout) {
+            if Instant::now() > deadline {
+                return Err(GritPatternError::LimitExceeded(format!(
+                    "timed out after {}ms",
+                    timeout.as_millis()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_executions_have_no_budget() {
+        assert!(ExecutionBudget::start(ExecutionLimits::default(), None).is_none());
+    }
+
+    #[test]
+    fn fails_after_the_step_limit() {
+        let budget = ExecutionBudget::start(
+            ExecutionLimits {
+                timeout: None,
+                max_steps: Some(3),
+            },
+            None,
+        )
+        .unwrap();
+        for _ in 0..3 {
+            assert!(budget.step().is_ok());
+        }
+        let error = budget.step().unwrap_err();
+        assert!(matches!(error, GritPatternError::LimitExceeded(_)));
+        assert_eq!(error.to_string(), "exceeded the limit of 3 steps");
+        assert_eq!(budget.steps(), 4);
+    }
+
+    #[test]
+    fn fails_after_the_timeout() {
+        let budget = ExecutionBudget::start(
+            ExecutionLimits {
+                timeout: Some(Duration::ZERO),
+                max_steps: None,
+            },
+            None,
+        )
+        .unwrap();
+        std::thread::sleep(Duration::from_millis(1));
+        let result = (0..STEPS_PER_CHECK).try_for_each(|_| budget.step());
+        assert_eq!(result.unwrap_err().to_string(), "timed out after 0ms");
+    }
+
+    #[test]
+    fn fails_once_cancelled() {
+        let cancellation = CancellationToken::new();
+        let budget =
+            ExecutionBudget::start(ExecutionLimits::default(), Some(cancellation.clone())).unwrap();
+        assert!((0..STEPS_PER_CHECK).try_for_each(|_| budget.step()).is_ok());
+        cancellation.cancel();
+        let result = (0..STEPS_PER_CHECK).try_for_each(|_| budget.step());
+        assert!(matches!(result, Err(GritPatternError::Cancelled)));
+    }
+}
@@ -1,6 +1,8 @@
 use std::borrow::Cow;
 
-use crate::{constants::*, ranges::EffectRange, AstNode, ByteRange, CodeRange, Range};
+use crate::{
+    constants::*, ranges::EffectRange, AstNode, ByteRange, CodeRange, ControlFlowGraph, Range,
+};
 use regex::Regex;
 
 pub enum GritMetaValue {
@@ -133,6 +135,87 @@ pub trait Language: Sized {
     fn make_single_line_comment(&self, text: &str) -> String {
         format!("// {text}\n")
     }
+
+    /// Whether the language resolves identifiers to their declarations
+    /// through [`Self::declaration_of()`] and [`Self::references_of()`].
+    fn supports_scope_analysis(&self) -> bool {
+        false
+    }
+
+    /// Returns the identifier which declares the binding `identifier` refers
+    /// to, taking lexical scoping and shadowing into account.
+    ///
+    /// A declaring identifier resolves to itself, or to an earlier declaration
+    /// of the same binding. Returns `None` if the node is not an identifier, or
+    /// if the binding is not declared in the file, such as for globals.
+    #[allow(unused_variables)]
+    fn declaration_of<'a>(&self, identifier: &Self::Node<'a>) -> Option<Self::Node<'a>> {
+        None
+    }
+
+    /// Returns every identifier that refers to the binding declared by
+    /// `declaration`, including the declaration itself, in source order.
+    ///
+    /// If `declaration` is a reference, the binding it refers to is used.
+    #[allow(unused_variables)]
+    fn references_of<'a>(&self, declaration: &Self::Node<'a>) -> Vec<Self::Node<'a>> {
+        Vec::new()
+    }
+
+    /// Whether the language can trace values through a function with
+    /// [`Self::flow_path()`].
+    fn supports_data_flow(&self) -> bool {
+        false
+    }
+
+    /// Returns a path along which a value flows from a source into `sink`,
+    /// within the function enclosing `sink`.
+    ///
+    /// Sources and sanitizers are recognized by calling `is_role()` on the
+    /// nodes of the function. A value does not flow through a sanitizer. The
+    /// path starts at the source, continues with every binding the value is
+    /// assigned to along the way, and ends at `sink`. Returns `None` if no
+    /// source reaches `sink`.
+    #[allow(unused_variables)]
+    fn flow_path<'a>(
+        &self,
+        sink: &Self::Node<'a>,
+        is_role: &mut dyn FnMut(&Self::Node<'a>, FlowRole) -> bool,
+    ) -> Option<Vec<Self::Node<'a>>> {
+        None
+    }
+
+    /// Whether the language can build control-flow graphs with
+    /// [`Self::control_flow_graph()`].
+    fn supports_control_flow(&self) -> bool {
+        false
+    }
+
+    /// Returns the control-flow graph of the function enclosing `node`, or of
+    /// the top-level code if `node` is not inside a function.
+    #[allow(unused_variables)]
+    fn control_flow_graph<'a>(
+        &self,
+        node: &Self::Node<'a>,
+    ) -> Option<ControlFlowGraph<Self::Node<'a>>> {
+        None
+    }
+
+    /// Whether `node` defines a function or lambda, whose body runs when it
+    /// is called rather than where it is defined.
+    #[allow(unused_variables)]
+    fn is_function_definition(&self, node: &Self::Node<'_>) -> bool {
+        false
+    }
+}
+
+/// The role a node is tested for by [`Language::flow_path()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowRole {
+    /// The node produces a value that should be traced.
+    Source,
+    /// The node cleans any value that flows through it.
+    Sanitizer,
 }
 
 #[derive(Clone, Debug)]
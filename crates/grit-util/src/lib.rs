@@ -1,24 +1,36 @@
 mod analysis_logs;
 mod ast_node;
 mod ast_node_traversal;
+mod cancellation;
 mod code_range;
 pub mod constants;
+mod control_flow;
+mod coverage;
 mod effect_kind;
 pub mod error;
+mod execution_limits;
 mod language;
 mod parser;
 mod position;
 mod ranges;
+mod required_text;
+mod trace;
 
 pub use analysis_logs::{AnalysisLog, AnalysisLogBuilder, AnalysisLogs};
 pub use ast_node::AstNode;
 pub use ast_node_traversal::{traverse, AstCursor, Order};
+pub use cancellation::CancellationToken;
 pub use code_range::CodeRange;
+pub use control_flow::{ControlFlowGraph, Vertex};
+pub use coverage::{ClauseCoverage, ClauseId};
 pub use effect_kind::EffectKind;
-pub use language::{GritMetaValue, Language, Replacement};
+pub use execution_limits::{ExecutionBudget, ExecutionLimits};
+pub use language::{FlowRole, GritMetaValue, Language, Replacement};
 pub use parser::{Ast, FileOrigin, Parser, SnippetTree};
 pub use position::Position;
 pub use ranges::{
     ByteRange, EffectRange, FileRange, InputRanges, MatchRanges, Range, RangeWithoutByte,
     UtilRange, VariableBinding, VariableMatch,
 };
+pub use required_text::RequiredText;
+pub use trace::{MatchTrace, TraceBinding, TraceNode};
@@ -262,6 +262,8 @@ pub struct InputRanges {
     pub ranges: Vec<Range>,
     pub variables: Vec<VariableMatch>,
     pub suppressed: bool,
+    /// Paths along which values flowed from a source into a sink.
+    pub flows: Vec<Vec<Range>>,
 }
 
 #[derive(Debug, Clone, Default)]
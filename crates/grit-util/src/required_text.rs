@@ -0,0 +1,115 @@
+/// Describes the literal text a file needs to contain for a pattern to have
+/// any chance of matching it.
+///
+/// This is derived from the text a pattern hoists into `includes` checks, so
+/// files can be ruled out without reading or parsing them, for instance by
+/// looking them up in an index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RequiredText {
+    /// Any file may match.
+    Anything,
+    /// The file needs to contain the given text.
+    Literal(String),
+    /// The file needs to satisfy all of the requirements.
+    All(Vec<RequiredText>),
+    /// The file needs to satisfy at least one of the requirements.
+    OneOf(Vec<RequiredText>),
+}
+
+impl RequiredText {
+    pub fn literal(text: impl Into<String>) -> Self {
+        let text = text.into();
+        if text.is_empty() {
+            Self::Anything
+        } else {
+            Self::Literal(text)
+        }
+    }
+
+    /// Combines requirements which all need to be satisfied.
+    pub fn all(requirements: impl IntoIterator<Item = RequiredText>) -> Self {
+        let mut requirements: Vec<_> = requirements
+            .into_iter()
+            .filter(|requirement| !requirement.is_anything())
+            .collect();
+        match requirements.len() {
+            0 => Self::Anything,
+            1 => requirements.pop().unwrap_or(Self::Anything),
+            _ => Self::All(requirements),
+        }
+    }
+
+    /// Combines requirements of which at least one needs to be satisfied.
+    ///
+    /// If any of them can be satisfied by anything, so can the result.
+    pub fn one_of(requirements: impl IntoIterator<Item = RequiredText>) -> Self {
+        let mut combined = Vec::new();
+        for requirement in requirements {
+            if requirement.is_anything() {
+                return Self::Anything;
+            }
+            combined.push(requirement);
+        }
+        match combined.len() {
+            0 => Self::Anything,
+            1 => combined.pop().unwrap_or(Self::Anything),
+            _ => Self::OneOf(combined),
+        }
+    }
+
+    pub fn is_anything(&self) -> bool {
+        matches!(self, Self::Anything)
+    }
+
+    /// Returns whether the requirements may be satisfied by a file for which
+    /// `may_contain()` tells whether it may contain a given literal.
+    ///
+    /// `may_contain()` is allowed to give false positives, but not false
+    /// negatives.
+    pub fn may_be_satisfied(&self, may_contain: &mut impl FnMut(&str) -> bool) -> bool {
+        match self {
+            Self::Anything => true,
+            Self::Literal(text) => may_contain(text),
+            Self::All(requirements) => requirements
+                .iter()
+                .all(|requirement| requirement.may_be_satisfied(may_contain)),
+            Self::OneOf(requirements) => requirements
+                .iter()
+                .any(|requirement| requirement.may_be_satisfied(may_contain)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplifies_combinations() {
+        assert_eq!(RequiredText::literal(""), RequiredText::Anything);
+        assert_eq!(
+            RequiredText::all([RequiredText::Anything, RequiredText::literal("foo")]),
+            RequiredText::literal("foo")
+        );
+        assert_eq!(
+            RequiredText::one_of([RequiredText::Anything, RequiredText::literal("foo")]),
+            RequiredText::Anything
+        );
+        assert_eq!(RequiredText::one_of([]), RequiredText::Anything);
+    }
+
+    #[test]
+    fn checks_requirements_against_text() {
+        let required = RequiredText::all([
+            RequiredText::literal("console"),
+            RequiredText::one_of([RequiredText::literal("log"), RequiredText::literal("warn")]),
+        ]);
+        let contained_in = |text: &'static str| move |literal: &str| text.contains(literal);
+
+        assert!(required.may_be_satisfied(&mut contained_in("console.log(1)")));
+        assert!(required.may_be_satisfied(&mut contained_in("console.warn(1)")));
+        assert!(!required.may_be_satisfied(&mut contained_in("console.error(1)")));
+        assert!(!required.may_be_satisfied(&mut contained_in("logger.warn(1)")));
+        assert!(RequiredText::Anything.may_be_satisfied(&mut contained_in("")));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::Range;
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// A pattern operator tried against a value during execution.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceNode {
+    pub operator: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub matched: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bindings: Vec<TraceBinding>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TraceNode>,
+}
+
+/// A variable bound while evaluating a [`TraceNode`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TraceBinding {
+    pub name: String,
+    pub value: String,
+}
+
+impl TraceNode {
+    /// Returns the innermost operator which caused this one to fail, following
+    /// the first failing child at every level.
+    pub fn first_failure(&self) -> Option<&TraceNode> {
+        if self.matched {
+            return None;
+        }
+        self.children
+            .iter()
+            .find(|child| !child.matched)
+            .and_then(TraceNode::first_failure)
+            .or(Some(self))
+    }
+}
+
+#[derive(Debug, Default)]
+struct TraceState {
+    roots: Vec<TraceNode>,
+    stack: Vec<TraceNode>,
+}
+
+/// Records the evaluation of a pattern against the nodes starting on a
+/// single line.
+///
+/// Every operator tried against a node on the line starts a new root, and
+/// everything it evaluates in turn is recorded beneath it, whatever its
+/// location. Execution should be restricted to a single file, since the
+/// trace only keeps track of lines.
+#[derive(Debug)]
+pub struct MatchTrace {
+    line: u32,
+    state: Mutex<TraceState>,
+}
+
+impl MatchTrace {
+    /// Traces the nodes starting on the 1-based `line`.
+    pub fn new(line: u32) -> Self {
+        Self {
+            line,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Whether an operator tried against a value at `range` should be
+    /// recorded.
+    ///
+    /// The range is only computed if no operator is being recorded already.
+    pub fn is_traced(&self, range: impl FnOnce() -> Option<Range>) -> bool {
+        let recording = self
+            .state
+            .lock()
+            .map(|state| !state.stack.is_empty())
+            .unwrap_or(false);
+        recording || range().is_some_and(|range| range.start.line == self.line)
+    }
+
+    pub fn enter(&self, operator: &str, range: Option<Range>, text: Option<String>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.stack.push(TraceNode {
+                operator: operator.to_owned(),
+                range,
+                text,
+                matched: false,
+                bindings: Vec::new(),
+                children: Vec::new(),
+            });
+        }
+    }
+
+    /// Records a variable binding made by the operator being evaluated.
+    pub fn bind(&self, name: &str, value: String) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(node) = state.stack.last_mut() {
+                node.bindings.push(TraceBinding {
+                    name: name.to_owned(),
+                    value,
+                });
+            }
+        }
+    }
+
+    /// Completes the operator entered last.
+    pub fn exit(&self, matched: bool) {
+        if let Ok(mut state) = self.state.lock() {
+            let Some(mut node) = state.stack.pop() else {
+                return;
+            };
+            node.matched = matched;
+            match state.stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => state.roots.push(node),
+            }
+        }
+    }
+
+    /// Returns the recorded trees, in the order they were tried.
+    pub fn take(&self) -> Vec<TraceNode> {
+        self.state
+            .lock()
+            .map(|mut state| std::mem::take(&mut state.roots))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn range_on(line: u32) -> Option<Range> {
+        Some(Range::new(
+            Position::new(line, 1),
+            Position::new(line, 5),
+            0,
+            4,
+        ))
+    }
+
+    #[test]
+    fn records_nested_operators_on_the_traced_line() {
+        let trace = MatchTrace::new(2);
+        assert!(!trace.is_traced(|| range_on(1)));
+        assert!(trace.is_traced(|| range_on(2)));
+
+        trace.enter("AND", range_on(2), Some("foo".to_owned()));
+        assert!(trace.is_traced(|| None));
+        trace.enter("VARIABLE", None, None);
+        trace.bind("$x", "foo".to_owned());
+        trace.exit(true);
+        trace.enter("CODE_SNIPPET", None, None);
+        trace.exit(false);
+        trace.exit(false);
+
+        let roots = trace.take();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].children.len(), 2);
+        assert_eq!(roots[0].children[0].bindings[0].name, "$x");
+        assert_eq!(
+            roots[0].first_failure().map(|node| node.operator.as_str()),
+            Some("CODE_SNIPPET")
+        );
+        assert!(trace.take().is_empty());
+    }
+}
@@ -4,7 +4,7 @@ use anyhow::Result;
 use marzano_gritmodule::searcher::{find_global_grit_modules_dir, find_grit_modules_dir};
 use marzano_util::cache::NullCache;
 
-use crate::{dynamic::DynamicCache, new_cache::ThreadedCache};
+use crate::{dynamic::DynamicCache, new_cache::ThreadedCache, trigram_index::TrigramIndex};
 
 pub async fn cache_dir(current_path: PathBuf) -> Result<PathBuf> {
     // If GRIT_CACHE_DIR env var is set, use that
@@ -36,3 +36,9 @@ pub async fn cache_for_cwd(
         Ok((DynamicCache::Threaded(cache), Some(manager)))
     }
 }
+
+/// Open the trigram index for the current working directory
+pub async fn trigram_index_for_cwd(refresh: bool) -> Result<TrigramIndex> {
+    let cache_dir = cache_dir(std::env::current_dir()?).await?;
+    TrigramIndex::open(&cache_dir, refresh)
+}
@@ -0,0 +1,426 @@
+use anyhow::{anyhow, Context, Result};
+use grit_util::RequiredText;
+use marzano_util::hasher::hash;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::{self, Path, PathBuf};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TRIGRAM_INDEX_NAME: &str = "trigram_index";
+const TRIGRAM_INDEX_VERSION: u8 = 2;
+
+/// Every file gets a set of trigram bits sized to the number of distinct
+/// trigrams it contains, so lookups can give false positives but never false
+/// negatives. With this many bits per trigram, each trigram a file lacks has
+/// a chance of about 1 in 16 of being reported as present.
+const BITS_PER_TRIGRAM: usize = 16;
+const MIN_TRIGRAM_BITS: usize = 64;
+/// Trigrams are 24-bit values, so larger sets could not be any more precise.
+const MAX_TRIGRAM_BITS: usize = 1 << 24;
+
+/// TrigramIndex records which trigrams each file contains, so files that
+/// cannot contain the text a pattern requires can be skipped without reading
+/// or parsing them.
+/// - Entries are only re-read when the size or modification time of a file
+///   changed, or when the file was modified no earlier than the second it was
+///   indexed in, since an edit in that same second may not change either
+/// - Entries are only re-indexed when their content hash changed
+/// - Files that are unknown to the index, or could not be read, may always
+///   match
+pub struct TrigramIndex {
+    path: PathBuf,
+    entries: HashMap<PathBuf, Entry>,
+    changed: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    len: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs_err::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            len: metadata.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    stamp: Stamp,
+    /// Seconds since the epoch at which the file was read
+    indexed_secs: u64,
+    hash: [u8; 32],
+    trigrams: Trigrams,
+}
+
+impl Entry {
+    /// Whether the entry still describes a file with the given `stamp`.
+    ///
+    /// A file modified in the same second it was indexed may have changed
+    /// again without changing its stamp, so its contents must be checked.
+    fn is_fresh(&self, stamp: Stamp) -> bool {
+        self.stamp == stamp && stamp.modified_secs < self.indexed_secs
+    }
+
+    /// Indexes the file at `path`, reusing the trigrams of the `previous`
+    /// entry if the contents are unchanged.
+    fn index(path: &Path, stamp: Stamp, previous: Option<&Entry>) -> Option<Self> {
+        let indexed_secs = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let bytes = fs_err::read(path).ok()?;
+        let content = String::from_utf8(bytes).ok()?;
+        let hash = hash(&content);
+        let trigrams = match previous {
+            Some(previous) if previous.hash == hash => previous.trigrams.clone(),
+            _ => Trigrams::of(&content),
+        };
+        Some(Self {
+            stamp,
+            indexed_secs,
+            hash,
+            trigrams,
+        })
+    }
+}
+
+/// The trigram bits of a file. The number of words is always a power of two.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Trigrams(Vec<u64>);
+
+impl Trigrams {
+    fn of(text: &str) -> Self {
+        let distinct: HashSet<u32> = text.as_bytes().windows(3).map(Self::value).collect();
+        let bits = (distinct.len() * BITS_PER_TRIGRAM)
+            .next_power_of_two()
+            .clamp(MIN_TRIGRAM_BITS, MAX_TRIGRAM_BITS);
+        let mut words = vec![0; bits / 64];
+        for value in distinct {
+            let bit = Self::bit(value, bits);
+            words[bit / 64] |= 1 << (bit % 64);
+        }
+        Self(words)
+    }
+
+    /// Literals shorter than a trigram may be contained in any file.
+    fn may_contain(&self, literal: &str) -> bool {
+        let bits = self.0.len() * 64;
+        literal.as_bytes().windows(3).all(|trigram| {
+            let bit = Self::bit(Self::value(trigram), bits);
+            self.0[bit / 64] & (1 << (bit % 64)) != 0
+        })
+    }
+
+    fn value(trigram: &[u8]) -> u32 {
+        u32::from(trigram[0]) | (u32::from(trigram[1]) << 8) | (u32::from(trigram[2]) << 16)
+    }
+
+    /// Maps a trigram onto one of `bits` bits, which must be a power of two.
+    fn bit(value: u32, bits: usize) -> usize {
+        (value.wrapping_mul(0x9E37_79B1) >> (32 - bits.trailing_zeros())) as usize
+    }
+}
+
+impl TrigramIndex {
+    /// Opens the index stored in `dir`, or starts an empty one if `refresh`
+    /// is set or the stored index is missing or outdated.
+    pub fn open(dir: &Path, refresh: bool) -> Result<Self> {
+        let path = dir.join(TRIGRAM_INDEX_NAME);
+        let entries = if refresh {
+            HashMap::new()
+        } else {
+            Self::initialize(&path)?
+        };
+        Ok(Self {
+            path,
+            entries,
+            changed: refresh,
+        })
+    }
+
+    fn initialize(path: &Path) -> Result<HashMap<PathBuf, Entry>> {
+        let bytes = match fs_err::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    return Ok(HashMap::new());
+                } else {
+                    return Err(e).context(format!(
+                        "Failed to read trigram index {}",
+                        path.to_string_lossy()
+                    ));
+                }
+            }
+        };
+
+        match bytes.split_first() {
+            Some((version, entries)) if *version == TRIGRAM_INDEX_VERSION => {
+                // A truncated or corrupted index is simply rebuilt
+                Ok(Self::decode(entries).unwrap_or_default())
+            }
+            _ => Ok(HashMap::new()),
+        }
+    }
+
+    /// Makes sure the entries for `paths` are up to date.
+    ///
+    /// Files are only read if they changed since they were last indexed, and
+    /// those are read in parallel.
+    pub fn update(&mut self, paths: &[PathBuf]) -> Result<()> {
+        let stale: Vec<(PathBuf, Option<Stamp>)> = paths
+            .iter()
+            .filter_map(|path| {
+                let path = path::absolute(path).ok()?;
+                let stamp = Stamp::of(&path);
+                match (self.entries.get(&path), stamp) {
+                    (Some(entry), Some(stamp)) if entry.is_fresh(stamp) => None,
+                    _ => Some((path, stamp)),
+                }
+            })
+            .collect();
+        if stale.is_empty() {
+            return Ok(());
+        }
+
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk_size = stale.len().div_ceil(threads);
+        let previous = &self.entries;
+        let indexed = thread::scope(|scope| {
+            let handles: Vec<_> = stale
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|(path, stamp)| {
+                                let entry = stamp.and_then(|stamp| {
+                                    Entry::index(path, stamp, previous.get(path))
+                                });
+                                (path, entry)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().map_err(|_| anyhow!("Failed to index files")))
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        for (path, entry) in indexed.into_iter().flatten() {
+            match entry {
+                Some(entry) => self.entries.insert(path.clone(), entry),
+                None => self.entries.remove(path),
+            };
+        }
+        self.changed = true;
+        Ok(())
+    }
+
+    /// Returns whether the file at `path` may satisfy `required`.
+    ///
+    /// The entry for `path` should have been brought up to date with
+    /// `update()` first.
+    pub fn may_match(&self, path: &Path, required: &RequiredText) -> bool {
+        if required.is_anything() {
+            return true;
+        }
+        let Some(entry) = path::absolute(path)
+            .ok()
+            .and_then(|path| self.entries.get(&path))
+        else {
+            return true;
+        };
+        required.may_be_satisfied(&mut |literal| entry.trigrams.may_contain(literal))
+    }
+
+    /// Writes the index back to disk if it changed, dropping entries for
+    /// files which no longer exist.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        self.entries.retain(|path, _| path.exists());
+
+        let mut bytes = vec![TRIGRAM_INDEX_VERSION];
+        for (path, entry) in &self.entries {
+            let Some(path) = path.to_str() else {
+                continue;
+            };
+            bytes.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(path.as_bytes());
+            bytes.extend_from_slice(&entry.stamp.len.to_le_bytes());
+            bytes.extend_from_slice(&entry.stamp.modified_secs.to_le_bytes());
+            bytes.extend_from_slice(&entry.stamp.modified_nanos.to_le_bytes());
+            bytes.extend_from_slice(&entry.indexed_secs.to_le_bytes());
+            bytes.extend_from_slice(&entry.hash);
+            bytes.extend_from_slice(&(entry.trigrams.0.len() as u32).to_le_bytes());
+            for word in &entry.trigrams.0 {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+
+        // Write to a uniquely named temporary file first, so concurrent runs
+        // never read or clobber a partially written index
+        let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+        let temporary = tempfile::NamedTempFile::new_in(dir)
+            .and_then(|mut file| file.write_all(&bytes).map(|_| file))
+            .context(
+                "Failed to write trigram index, please run `grit init` or set GRIT_CACHE_DIR to use an index",
+            )?;
+        temporary.persist(&self.path)?;
+        self.changed = false;
+        Ok(())
+    }
+
+    fn decode(mut bytes: &[u8]) -> Option<HashMap<PathBuf, Entry>> {
+        fn take<'a>(bytes: &mut &'a [u8], count: usize) -> Option<&'a [u8]> {
+            if bytes.len() < count {
+                return None;
+            }
+            let (taken, rest) = bytes.split_at(count);
+            *bytes = rest;
+            Some(taken)
+        }
+        fn take_array<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+            take(bytes, N)?.try_into().ok()
+        }
+
+        let mut entries = HashMap::new();
+        while !bytes.is_empty() {
+            let path_len = u32::from_le_bytes(take_array(&mut bytes)?) as usize;
+            let path = std::str::from_utf8(take(&mut bytes, path_len)?).ok()?;
+            let stamp = Stamp {
+                len: u64::from_le_bytes(take_array(&mut bytes)?),
+                modified_secs: u64::from_le_bytes(take_array(&mut bytes)?),
+                modified_nanos: u32::from_le_bytes(take_array(&mut bytes)?),
+            };
+            let indexed_secs = u64::from_le_bytes(take_array(&mut bytes)?);
+            let hash = take_array(&mut bytes)?;
+            let words = u32::from_le_bytes(take_array(&mut bytes)?) as usize;
+            if !words.is_power_of_two() || words * 64 > MAX_TRIGRAM_BITS {
+                return None;
+            }
+            let trigrams = (0..words)
+                .map(|_| Some(u64::from_le_bytes(take_array(&mut bytes)?)))
+                .collect::<Option<Vec<_>>>()?;
+            entries.insert(
+                PathBuf::from(path),
+                Entry {
+                    stamp,
+                    indexed_secs,
+                    hash,
+                    trigrams: Trigrams(trigrams),
+                },
+            );
+        }
+        Some(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigram_index() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = tempdir.path();
+        let console = dir.join("console.js");
+        let funcify = dir.join("funcify.js");
+        let unknown = dir.join("unknown.js");
+        fs_err::write(&console, "console.log('Hello, world!');")?;
+        fs_err::write(&funcify, "funcify();")?;
+        let paths = vec![console.clone(), funcify.clone()];
+
+        let requires_console = RequiredText::literal("console");
+        let requires_log_or_funcify = RequiredText::one_of([
+            RequiredText::literal(".log("),
+            RequiredText::literal("funcify"),
+        ]);
+
+        let mut index = TrigramIndex::open(dir, true)?;
+        index.update(&paths)?;
+        assert!(index.may_match(&console, &requires_console));
+        assert!(!index.may_match(&funcify, &requires_console));
+        assert!(index.may_match(&unknown, &requires_console));
+        assert!(index.may_match(&console, &requires_log_or_funcify));
+        assert!(index.may_match(&funcify, &requires_log_or_funcify));
+        assert!(index.may_match(&funcify, &RequiredText::literal("co")));
+        index.save()?;
+
+        // Read the index back and pick up changes to the files
+        let mut index = TrigramIndex::open(dir, false)?;
+        assert!(!index.may_match(&funcify, &requires_console));
+        fs_err::write(&funcify, "console.info(funcify());")?;
+        index.update(&paths)?;
+        assert!(index.may_match(&funcify, &requires_console));
+
+        // Deleted files are dropped and may match again
+        fs_err::remove_file(&console)?;
+        index.update(&paths)?;
+        index.save()?;
+        let index = TrigramIndex::open(dir, false)?;
+        assert!(index.may_match(&console, &requires_console));
+        assert!(!index.may_match(&funcify, &RequiredText::literal("warn")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn prunes_large_files() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = tempdir.path();
+        let large = dir.join("large.js");
+        let content = (0..20_000)
+            .map(|i| format!("const value{} = {};\n", i, i * 7))
+            .collect::<String>();
+        fs_err::write(&large, content)?;
+
+        let mut index = TrigramIndex::open(dir, true)?;
+        index.update(std::slice::from_ref(&large))?;
+        assert!(index.may_match(&large, &RequiredText::literal("value19999")));
+        assert!(!index.may_match(&large, &RequiredText::literal("console.log")));
+        assert!(!index.may_match(&large, &RequiredText::literal("function")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rereads_files_modified_without_changing_their_stamp() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = tempdir.path();
+        let file = dir.join("file.js");
+        // A time in the future, so the file looks modified after it was indexed
+        let modified = SystemTime::now() + std::time::Duration::from_secs(3600);
+        let write = |content: &str| -> Result<()> {
+            fs_err::write(&file, content)?;
+            std::fs::File::options()
+                .write(true)
+                .open(&file)?
+                .set_modified(modified)?;
+            Ok(())
+        };
+
+        write("funcify();")?;
+        let mut index = TrigramIndex::open(dir, true)?;
+        index.update(std::slice::from_ref(&file))?;
+        assert!(!index.may_match(&file, &RequiredText::literal("console")));
+
+        // Same length and modification time, different content
+        write("console();")?;
+        index.update(std::slice::from_ref(&file))?;
+        assert!(index.may_match(&file, &RequiredText::literal("console")));
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::Context;
 use grit_util::Range;
 use log::info;
-use marzano_core::api::EnforcementLevel;
+use marzano_core::api::{render_message_template, EnforcementLevel, FixSafety, MatchResult};
 use marzano_language::{grit_parser::MarzanoGritParser, target_language::PatternLanguage};
 use serde::{Deserialize, Serialize};
 use std::{
@@ -79,6 +79,26 @@ pub struct GritPatternMetadata {
     pub title: Option<String>,
     pub description: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// How safe the pattern's rewrites are to apply without review, defaults to safe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<FixSafety>,
+    /// A diagnostic message template, where `$name` is replaced with what the variable matched at each site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Labeled alternative fixes, for matches which can be fixed in more than one way
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixes: Option<Vec<GritPatternFix>>,
+}
+
+/// A labeled alternative fix for the matches of a pattern
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GritPatternFix {
+    pub label: String,
+    /// A GritQL pattern which rewrites the code matched by the pattern
+    pub body: String,
+    /// A preferred fix is applied without asking, such as by `grit check --fix`
+    #[serde(default)]
+    pub preferred: bool,
 }
 
 /// This contains the raw pattern data
@@ -262,6 +282,23 @@ impl ResolvedGritDefinition {
             .to_owned()
     }
 
+    pub fn fix_safety(&self) -> FixSafety {
+        self.config.meta.fix.unwrap_or_default()
+    }
+
+    pub fn alternative_fixes(&self) -> &[GritPatternFix] {
+        self.config.meta.fixes.as_deref().unwrap_or_default()
+    }
+
+    /// Render the pattern's message template, if any, for the match site at `range`
+    pub fn message(&self, result: &MatchResult, range: &Range) -> Option<String> {
+        self.config
+            .meta
+            .message
+            .as_deref()
+            .map(|template| render_message_template(template, result, range))
+    }
+
     // Retrieve the description from the config, if any
     pub fn description(&self) -> Option<&str> {
         self.config.meta.description.as_deref()
@@ -351,6 +388,36 @@ pub struct GritPatternSample {
     pub output: Option<String>,
     pub input_range: Option<Range>,
     pub output_range: Option<Range>,
+    /// Named input files, for samples that span multiple files.
+    /// When present, these are used instead of `input`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_files: Vec<GritPatternSampleFile>,
+    /// Expected output files, including created and deleted files.
+    /// When present, these are used instead of `output`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_files: Vec<GritPatternSampleFile>,
+    /// The pattern is expected to produce no matches at all on this sample
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub must_not_match: bool,
+}
+
+impl GritPatternSample {
+    pub fn is_multifile(&self) -> bool {
+        !self.input_files.is_empty()
+    }
+}
+
+/// A single named file in a multi-file sample
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GritPatternSampleFile {
+    pub path: String,
+    #[serde(default)]
+    pub content: String,
+    /// Only meaningful for output files: the file is expected to be deleted
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
 }
 
 pub const GRIT_GLOBAL_DIR_ENV: &str = "GRIT_GLOBAL_DIR";
@@ -358,6 +425,7 @@ pub const REPO_CONFIG_DIR_NAME: &str = ".grit";
 pub const CONFIG_FILE_NAMES: [&str; 2] = ["grit.yml", "grit.yaml"];
 pub const REPO_CONFIG_PATTERNS_DIR: &str = "patterns";
 pub const GRIT_MODULE_DIR: &str = ".gritmodules";
+pub const GRIT_JOURNAL_DIR: &str = ".gritjournal";
 pub const NAMESPACE_IMPORT_INDICATOR: &str = "*";
 
 pub fn is_namespace_import(pattern: &ModuleGritPattern) -> bool {
@@ -1,6 +1,6 @@
 use crate::config::{DefinitionKind, GritPatternMetadata, RawGritDefinition};
 use crate::{
-    config::{GritDefinitionConfig, GritPatternSample, ModuleGritPattern},
+    config::{GritDefinitionConfig, GritPatternSample, GritPatternSampleFile, ModuleGritPattern},
     fetcher::ModuleRepo,
     parser::extract_relative_file_path,
     utils::is_pattern_name,
@@ -39,6 +39,137 @@ struct MarkdownBody {
     open_sample: Option<GritPatternSample>,
 }
 
+impl MarkdownBody {
+    fn close_sample(&mut self) {
+        if let Some(open_sample) = self.open_sample.take() {
+            self.samples.push(open_sample);
+        }
+    }
+
+    /// Add a code block following the GritQL body as part of a sample
+    fn add_sample_block(
+        &mut self,
+        name: Option<String>,
+        info: SampleBlockInfo,
+        content: String,
+        range: Range,
+    ) -> Result<()> {
+        let Some(path) = info.filename else {
+            // Unnamed blocks can't complete a multi-file sample, and "nomatch" blocks are samples on their own
+            if info.must_not_match || self.open_sample.as_ref().is_some_and(|s| s.is_multifile()) {
+                self.close_sample();
+            }
+            if let Some(mut open_sample) = self.open_sample.take() {
+                open_sample.output = Some(content);
+                open_sample.output_range = Some(range);
+                self.samples.push(open_sample);
+            } else {
+                // Start a new sample
+                let sample = GritPatternSample {
+                    name,
+                    input: content,
+                    output: None,
+                    input_range: Some(range),
+                    output_range: None,
+                    input_files: Vec::new(),
+                    output_files: Vec::new(),
+                    must_not_match: info.must_not_match,
+                };
+                if info.must_not_match {
+                    self.samples.push(sample);
+                } else {
+                    self.open_sample = Some(sample);
+                }
+            }
+            return Ok(());
+        };
+
+        let file = GritPatternSampleFile {
+            path,
+            content,
+            deleted: info.deleted,
+            range: Some(range),
+        };
+        if info.output {
+            match self.open_sample.as_mut() {
+                Some(sample) if sample.is_multifile() => sample.output_files.push(file),
+                _ => bail!(
+                    "Output file {} in markdown sample does not follow any input files. Add a code block with filename=<path> for each input file first.",
+                    file.path
+                ),
+            }
+            return Ok(());
+        }
+
+        // An input file following the outputs of a sample starts a new sample
+        if self
+            .open_sample
+            .as_ref()
+            .is_some_and(|s| !s.is_multifile() || !s.output_files.is_empty())
+        {
+            self.close_sample();
+        }
+        let sample = self.open_sample.get_or_insert_with(|| GritPatternSample {
+            name,
+            input: String::new(),
+            output: None,
+            input_range: None,
+            output_range: None,
+            input_files: Vec::new(),
+            output_files: Vec::new(),
+            must_not_match: false,
+        });
+        sample.must_not_match |= info.must_not_match;
+        sample.input_files.push(file);
+        Ok(())
+    }
+}
+
+/// Attributes following the language in the info string of a sample code block,
+/// for example ```` ```js filename=src/button.js output ````
+#[derive(Debug, Default)]
+struct SampleBlockInfo {
+    /// `filename=<path>` makes the block a named file in a multi-file sample
+    filename: Option<String>,
+    /// `output` marks a named file as an expected output rather than an input
+    output: bool,
+    /// `deleted` marks a named output file as expected to be deleted
+    deleted: bool,
+    /// `nomatch` marks the sample as one the pattern must not match
+    must_not_match: bool,
+}
+
+impl SampleBlockInfo {
+    fn parse(info_string: &str) -> Self {
+        let mut info = Self::default();
+        // The first word is the language
+        for word in info_string.split_whitespace().skip(1) {
+            if let Some(filename) = word.strip_prefix("filename=") {
+                info.filename = Some(filename.trim_matches('"').to_string());
+                continue;
+            }
+            match word {
+                "output" => info.output = true,
+                "deleted" => {
+                    info.output = true;
+                    info.deleted = true;
+                }
+                "nomatch" => info.must_not_match = true,
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+fn sample_name_from_heading(heading: &str) -> Option<String> {
+    if !heading.is_empty() {
+        Some(heading.to_string())
+    } else {
+        None
+    }
+}
+
 pub fn make_md_parser() -> Result<Parser> {
     let mut parser = Parser::new().unwrap();
     let language = marzano_language::markdown_block::MarkdownBlock::new(None);
@@ -92,6 +223,7 @@ pub fn get_patterns_from_md(
 
     // Track the current language block for when we hit the actual content
     let mut current_code_block_language = None;
+    let mut current_sample_info = SampleBlockInfo::default();
 
     // Track the current heading level, used for determining which patterns should be grouped together
     let mut current_heading = (1, "".to_string());
@@ -101,6 +233,25 @@ pub fn get_patterns_from_md(
     for n in traverse(cursor, Order::Pre) {
         if n.node.kind() == "language" {
             current_code_block_language = Some(n.node.utf8_text(src.as_bytes()).unwrap());
+        } else if n.node.kind() == "info_string" {
+            current_sample_info =
+                SampleBlockInfo::parse(&n.node.utf8_text(src.as_bytes()).unwrap());
+        } else if n.node.kind() == "fenced_code_block" {
+            current_code_block_language = None;
+            current_sample_info = SampleBlockInfo::default();
+            // Empty blocks have no content node, but may still name a file, such as a deleted output
+            if !n.children().any(|c| c.node.kind() == "code_fence_content") {
+                let info = n
+                    .children()
+                    .find(|c| c.node.kind() == "info_string")
+                    .map(|c| SampleBlockInfo::parse(&c.node.utf8_text(src.as_bytes()).unwrap()));
+                if let (Some(info), Some(last_config)) = (info, patterns.last_mut()) {
+                    if info.filename.is_some() {
+                        let name = sample_name_from_heading(&current_heading.1);
+                        last_config.add_sample_block(name, info, String::new(), n.range())?;
+                    }
+                }
+            }
         } else if n.node.kind() == "code_fence_content" {
             let content = n.node.utf8_text(src.as_bytes()).unwrap();
             let content = content.to_string();
@@ -116,28 +267,12 @@ pub fn get_patterns_from_md(
                 };
                 patterns.push(definition);
             } else if let Some(last_config) = patterns.last_mut() {
-                // Check if we have an open sample
-                if let Some(mut open_sample) = last_config.open_sample.take() {
-                    open_sample.output = Some(content.to_string());
-                    open_sample.output_range = Some(n.range());
-                    last_config.samples.push(open_sample);
-                } else {
-                    // Start a new sample
-                    let sample = GritPatternSample {
-                        name: if !current_heading.1.is_empty() {
-                            Some(current_heading.1.clone())
-                        } else {
-                            None
-                        },
-                        input: content.to_string(),
-                        output: None,
-                        input_range: Some(n.range()),
-                        output_range: None,
-                    };
-                    last_config.open_sample = Some(sample);
-                }
+                let name = sample_name_from_heading(&current_heading.1);
+                let info = std::mem::take(&mut current_sample_info);
+                last_config.add_sample_block(name, info, content, n.range())?;
             }
             current_code_block_language = None;
+            current_sample_info = SampleBlockInfo::default();
         } else if n.node.kind() == "atx_heading" {
             let heading_level = n.node.child_by_field_name("level").unwrap();
             let heading_level = match heading_level.kind() {
@@ -153,9 +288,7 @@ pub fn get_patterns_from_md(
             if let Some(last_config) = patterns.last_mut() {
                 // If the grit block started in an h1, then new samples are introduced with each h3
                 if heading_level <= last_config.section_level + 1 {
-                    if let Some(open_sample) = last_config.open_sample.take() {
-                        last_config.samples.push(open_sample);
-                    }
+                    last_config.close_sample();
                 }
             }
 
@@ -216,9 +349,7 @@ js"hello world"
             if meta_copy.title.is_none() {
                 meta_copy.title = Some(p.section_heading);
             }
-            if let Some(last_sample) = p.open_sample.take() {
-                p.samples.push(last_sample);
-            }
+            p.close_sample();
             let src_tree = grit_parser
                 .parse(&p.body)?;
             if defines_itself(&src_tree.root_node(), name)? {
@@ -280,6 +411,9 @@ pub fn replace_sample_in_md_file(
     file_path: &String,
     offset: isize,
 ) -> Result<isize> {
+    if sample.is_multifile() {
+        bail!("Updating multi-file samples is not supported, update the expected output files manually");
+    }
     let Some(range) = &sample.output_range else {
         bail!("Sample does not have an output range, cannot replace in file");
     };
@@ -315,6 +449,7 @@ pub fn replace_sample_in_md_file(
 #[cfg(test)]
 mod tests {
     use insta::{assert_snapshot, assert_yaml_snapshot};
+    use marzano_core::api::FixSafety;
 
     use super::*;
 
@@ -737,6 +872,79 @@ language js
         assert_eq!(patterns[0].config.meta.level, Some(EnforcementLevel::Error));
     }
 
+    #[test]
+    fn test_frontmatter_fix_safety() {
+        let module = Default::default();
+        let mut rich_file = RichFile {
+            path: "no_loose_equality.md".to_string(),
+            content: r#"---
+title: Use strict equality
+fix: unsafe
+---
+
+Loose equality coerces types, but switching to strict equality can change behavior.
+
+```grit
+engine marzano(0.1)
+language js
+
+`$a == $b` => `$a === $b`
+```
+
+"#
+            .to_string(),
+        };
+        let patterns = get_patterns_from_md(
+            &mut rich_file,
+            &module,
+            &None,
+            GritDefinitionOverrides::default(),
+        )
+        .unwrap();
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].config.meta.fix, Some(FixSafety::Unsafe));
+    }
+
+    #[test]
+    fn test_frontmatter_alternative_fixes() {
+        let module = Default::default();
+        let mut rich_file = RichFile {
+            path: "no_null_comparison.md".to_string(),
+            content: r#"---
+title: Compare with null explicitly
+fixes:
+  - label: Use strict equality
+    body: "`$x == null` => `$x === null`"
+    preferred: true
+  - label: Use nullish check
+    body: "`$x == null` => `$x === null || $x === undefined`"
+---
+
+```grit
+engine marzano(0.1)
+language js
+
+`$x == null`
+```
+
+"#
+            .to_string(),
+        };
+        let patterns = get_patterns_from_md(
+            &mut rich_file,
+            &module,
+            &None,
+            GritDefinitionOverrides::default(),
+        )
+        .unwrap();
+        assert_eq!(patterns.len(), 1);
+        let fixes = patterns[0].config.meta.fixes.as_ref().unwrap();
+        assert_eq!(fixes.len(), 2);
+        assert_eq!(fixes[0].label, "Use strict equality");
+        assert!(fixes[0].preferred);
+        assert!(!fixes[1].preferred);
+    }
+
     #[test]
     fn test_with_override() {
         let module = Default::default();
@@ -977,4 +1185,137 @@ $$ LANGUAGE plpgsql;
         assert_eq!(&output_content, sample.output.as_ref().unwrap());
         assert_snapshot!(output_content);
     }
+
+    #[test]
+    fn test_multifile_samples() {
+        let module = Default::default();
+        let mut rich_file = RichFile {
+            path: "move_button.md".to_string(),
+            content: r#"# Move the button component
+
+```grit
+language js
+
+`import { Button } from './button'` => `import { Button } from './components/button'`
+```
+
+## Moves the component
+
+```js filename=src/button.js
+export const Button = () => null;
+```
+
+```js filename=src/app.js
+import { Button } from './button';
+```
+
+Becomes:
+
+```js filename=src/components/button.js output
+export const Button = () => null;
+```
+
+```js filename=src/app.js output
+import { Button } from './components/button';
+```
+
+```js filename=src/button.js deleted
+```
+
+## Leaves other imports alone
+
+```js nomatch
+import { Link } from './link';
+```
+
+## Single file
+
+```js
+import { Button } from './button';
+```
+
+```js
+import { Button } from './components/button';
+```
+"#
+            .to_string(),
+        };
+        let patterns = get_patterns_from_md(
+            &mut rich_file,
+            &module,
+            &None,
+            GritDefinitionOverrides::default(),
+        )
+        .unwrap();
+        assert_eq!(patterns.len(), 1);
+        let samples = patterns[0].config.samples.as_ref().unwrap();
+        assert_eq!(samples.len(), 3);
+
+        let multifile = &samples[0];
+        assert_eq!(multifile.name.as_deref(), Some("Moves the component"));
+        assert!(multifile.is_multifile());
+        let inputs = multifile
+            .input_files
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(inputs, vec!["src/button.js", "src/app.js"]);
+        let outputs = multifile
+            .output_files
+            .iter()
+            .map(|f| (f.path.as_str(), f.deleted))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outputs,
+            vec![
+                ("src/components/button.js", false),
+                ("src/app.js", false),
+                ("src/button.js", true)
+            ]
+        );
+        assert_eq!(
+            multifile.output_files[1].content,
+            "import { Button } from './components/button';\n"
+        );
+
+        let no_match = &samples[1];
+        assert!(no_match.must_not_match);
+        assert_eq!(no_match.input, "import { Link } from './link';\n");
+        assert!(no_match.output.is_none());
+
+        let single = &samples[2];
+        assert!(!single.is_multifile());
+        assert!(!single.must_not_match);
+        assert!(single.output.is_some());
+    }
+
+    #[test]
+    fn test_output_file_without_inputs() {
+        let module = Default::default();
+        let mut rich_file = RichFile {
+            path: "orphan_output.md".to_string(),
+            content: r#"# Orphan output
+
+```grit
+language js
+
+`foo` => `bar`
+```
+
+## Sample
+
+```js filename=src/app.js output
+bar
+```
+"#
+            .to_string(),
+        };
+        let result = get_patterns_from_md(
+            &mut rich_file,
+            &module,
+            &None,
+            GritDefinitionOverrides::default(),
+        );
+        assert!(result.is_err());
+    }
 }
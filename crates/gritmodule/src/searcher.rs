@@ -10,8 +10,8 @@ use crate::markdown::GritDefinitionOverrides;
 use crate::resolver::find_user_grit_dir;
 use crate::{
     config::{
-        ModuleGritPattern, GRIT_GLOBAL_DIR_ENV, GRIT_MODULE_DIR, REPO_CONFIG_DIR_NAME,
-        REPO_CONFIG_PATTERNS_DIR,
+        ModuleGritPattern, GRIT_GLOBAL_DIR_ENV, GRIT_JOURNAL_DIR, GRIT_MODULE_DIR,
+        REPO_CONFIG_DIR_NAME, REPO_CONFIG_PATTERNS_DIR,
     },
     fetcher::ModuleRepo,
     parser::{get_patterns_from_file, PatternFileExt},
@@ -249,6 +249,15 @@ pub async fn find_grit_modules_dir(dir: PathBuf) -> Result<PathBuf> {
     bail!("Unable to find .gritmodules directory")
 }
 
+/// Finds the directory holding the journals of applied patterns, in the
+/// `.grit` directory of the enclosing repo or under `dir` if there is none.
+pub async fn find_journal_dir(dir: PathBuf) -> PathBuf {
+    find_grit_dir_from(dir.clone())
+        .await
+        .unwrap_or_else(|| dir.join(REPO_CONFIG_DIR_NAME))
+        .join(GRIT_JOURNAL_DIR)
+}
+
 pub async fn find_global_grit_dir() -> Result<PathBuf> {
     let global_grit_dir = std::env::var(GRIT_GLOBAL_DIR_ENV);
     if let Ok(global_grit_dir) = global_grit_dir {
@@ -62,6 +62,15 @@ pub fn get_sample_name(sample: &GritPatternSample) -> String {
         return name.clone();
     }
 
+    if sample.is_multifile() {
+        return sample
+            .input_files
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+
     let line_break_index = sample.input.find('\n');
     let max_length = match line_break_index {
         Some(index) => std::cmp::min(SAMPLE_NAME_LENGTH, index),
@@ -198,7 +207,17 @@ pub fn test_pattern_sample(
     sample: &GritPatternSample,
     runtime: ExecutionContext,
 ) -> SampleTestResult {
-    let inferred_inputs = infer_rich_files_from_content(&compiled.language, &sample.input);
+    let inferred_inputs = if sample.is_multifile() {
+        sample
+            .input_files
+            .iter()
+            .map(|f| RichFile::new(f.path.clone(), f.content.clone()))
+            .collect()
+    } else {
+        infer_rich_files_from_content(&compiled.language, &sample.input)
+    };
+    let is_multifile =
+        sample.is_multifile() || is_multifile_sample(&sample.input, &compiled.language);
 
     let mut matches: Vec<MatchResult> = Vec::new();
 
@@ -232,14 +251,39 @@ pub fn test_pattern_sample(
         }
     }
 
+    if sample.must_not_match {
+        let match_count = matches.iter().filter(|m| is_match(m)).count();
+        if match_count == 0 {
+            return SampleTestResult::new_passing(matches, false);
+        }
+        return SampleTestResult {
+            matches,
+            state: GritTestResultState::FailedMatch,
+            message: Some(format!("Expected no matches, but got {}", match_count)),
+            expected_output: None,
+            actual_output: None,
+            expected_outputs: None,
+            actual_outputs: None,
+        };
+    }
+
     let mut raw_actual_outputs: Vec<RichFile> = Vec::new();
+    let mut actual_deleted: Vec<String> = Vec::new();
 
     // We only want mutation results
     for item in &matches {
         match item {
             MatchResult::Rewrite(r) => {
+                // In multi-file samples, a file moved by the rewrite is expected at its new path
+                let path =
+                    if sample.is_multifile() && r.rewritten.source_file != r.original.source_file {
+                        actual_deleted.push(r.original.source_file.clone());
+                        r.rewritten.source_file.clone()
+                    } else {
+                        r.original.source_file.clone()
+                    };
                 raw_actual_outputs.push(RichFile {
-                    path: r.original.source_file.clone(),
+                    path,
                     content: r.content().unwrap_or_default().to_string(),
                 });
             }
@@ -249,8 +293,11 @@ pub fn test_pattern_sample(
                     content: r.content().unwrap_or_default().to_string(),
                 });
             }
+            MatchResult::RemoveFile(r) if sample.is_multifile() => {
+                actual_deleted.push(r.original.source_file.clone());
+            }
             MatchResult::Match(r) => {
-                if is_multifile_sample(&sample.input, &compiled.language) {
+                if is_multifile {
                     continue;
                 }
                 raw_actual_outputs.push(RichFile {
@@ -262,11 +309,13 @@ pub fn test_pattern_sample(
         }
     }
 
+    let expects_output = sample.output.is_some() || !sample.output_files.is_empty();
+
     // First handle the case where we have no output
-    if raw_actual_outputs.is_empty() {
-        if sample.output.is_none() {
+    if raw_actual_outputs.is_empty() && actual_deleted.is_empty() {
+        if !expects_output {
             return SampleTestResult::new_passing(matches, false);
-        } else if !is_multifile_sample(&sample.input, &compiled.language) {
+        } else if !is_multifile {
             return SampleTestResult {
                 matches,
                 state: GritTestResultState::FailedMatch,
@@ -279,9 +328,7 @@ pub fn test_pattern_sample(
         }
     }
 
-    let sample_output = if let Some(output) = sample.output.as_ref() {
-        output
-    } else {
+    if !expects_output {
         return SampleTestResult {
             matches,
             state: GritTestResultState::FailedMatch,
@@ -291,15 +338,43 @@ pub fn test_pattern_sample(
             expected_outputs: None,
             actual_outputs: None,
         };
-    };
+    }
 
-    let mut raw_expected_outputs = infer_rich_files_from_content(&compiled.language, sample_output);
+    let mut raw_expected_outputs = if sample.is_multifile() {
+        let expected_deleted = sample
+            .output_files
+            .iter()
+            .filter(|f| f.deleted)
+            .map(|f| f.path.as_str());
+        if let Some(message) = deleted_files_mismatch(expected_deleted, &actual_deleted) {
+            return SampleTestResult {
+                matches,
+                state: GritTestResultState::FailedOutput,
+                message: Some(message),
+                expected_output: None,
+                actual_output: None,
+                expected_outputs: None,
+                actual_outputs: None,
+            };
+        }
+        sample
+            .output_files
+            .iter()
+            .filter(|f| !f.deleted)
+            .map(|f| RichFile::new(f.path.clone(), f.content.clone()))
+            .collect()
+    } else {
+        infer_rich_files_from_content(
+            &compiled.language,
+            sample.output.as_deref().unwrap_or_default(),
+        )
+    };
 
-    if raw_actual_outputs.len() < raw_expected_outputs.len()
-        && is_multifile_sample(&sample.input, &compiled.language)
-    {
+    if raw_actual_outputs.len() < raw_expected_outputs.len() && is_multifile {
         for file in cloned_files.iter() {
-            if raw_actual_outputs.iter().any(|f| f.path == file.path) {
+            if raw_actual_outputs.iter().any(|f| f.path == file.path)
+                || actual_deleted.contains(&file.path)
+            {
                 continue;
             }
             raw_actual_outputs.push(RichFile {
@@ -356,6 +431,27 @@ pub fn test_pattern_sample(
     }
 }
 
+/// Compare the files a multi-file sample expects to be deleted with the files that actually were
+fn deleted_files_mismatch<'a>(
+    expected: impl Iterator<Item = &'a str>,
+    actual: &[String],
+) -> Option<String> {
+    let expected = expected.collect::<Vec<_>>();
+    if let Some(missing) = expected
+        .iter()
+        .find(|&&path| !actual.iter().any(|a| a == path))
+    {
+        return Some(format!(
+            "Expected {} to be deleted, but it was not",
+            missing
+        ));
+    }
+    actual
+        .iter()
+        .find(|path| !expected.contains(&path.as_str()))
+        .map(|unexpected| format!("Expected {} to be kept, but it was deleted", unexpected))
+}
+
 fn is_multifile_sample(input: &str, lang: &TargetLanguage) -> bool {
     lang.extract_single_line_comment(input)
         .is_some_and(|c| c.contains("@filename:"))
@@ -398,3 +494,142 @@ pub fn has_output_mismatch(
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GritPatternSampleFile;
+    use marzano_core::pattern_compiler::src_to_problem_libs;
+    use marzano_language::target_language::PatternLanguage;
+    use std::collections::BTreeMap;
+
+    fn compile(pattern: &str) -> Problem {
+        let language: TargetLanguage = PatternLanguage::JavaScript.try_into().unwrap();
+        src_to_problem_libs(
+            pattern.to_string(),
+            &BTreeMap::new(),
+            language,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+        .problem
+    }
+
+    fn sample(input: &str, output: Option<&str>) -> GritPatternSample {
+        GritPatternSample {
+            name: None,
+            input: input.to_string(),
+            output: output.map(str::to_string),
+            input_range: None,
+            output_range: None,
+            input_files: Vec::new(),
+            output_files: Vec::new(),
+            must_not_match: false,
+        }
+    }
+
+    fn sample_file(path: &str, content: &str) -> GritPatternSampleFile {
+        GritPatternSampleFile {
+            path: path.to_string(),
+            content: content.to_string(),
+            deleted: false,
+            range: None,
+        }
+    }
+
+    fn deleted_file(path: &str) -> GritPatternSampleFile {
+        GritPatternSampleFile {
+            deleted: true,
+            ..sample_file(path, "")
+        }
+    }
+
+    fn multifile_sample(
+        input_files: Vec<GritPatternSampleFile>,
+        output_files: Vec<GritPatternSampleFile>,
+    ) -> GritPatternSample {
+        GritPatternSample {
+            input_files,
+            output_files,
+            ..sample("", None)
+        }
+    }
+
+    #[test]
+    fn must_not_match_sample() {
+        let compiled = compile("language js\n\n`foo($x)` => `bar($x)`");
+
+        let untouched = GritPatternSample {
+            must_not_match: true,
+            ..sample("baz(1);\n", None)
+        };
+        let result = test_pattern_sample(&compiled, &untouched, ExecutionContext::default());
+        assert!(result.is_pass(), "{:?}", result.message);
+
+        let matching = GritPatternSample {
+            must_not_match: true,
+            ..sample("foo(1);\n", None)
+        };
+        let result = test_pattern_sample(&compiled, &matching, ExecutionContext::default());
+        assert_eq!(result.state, GritTestResultState::FailedMatch);
+        assert_eq!(
+            result.message.as_deref(),
+            Some("Expected no matches, but got 1")
+        );
+    }
+
+    #[test]
+    fn multifile_sample_outputs() {
+        let compiled = compile("language js\n\n`foo($x)` => `bar($x)`");
+        let inputs = vec![
+            sample_file("a.js", "foo(1);\n"),
+            sample_file("b.js", "baz(2);\n"),
+        ];
+
+        let expected = multifile_sample(
+            inputs.clone(),
+            vec![
+                sample_file("a.js", "bar(1);\n"),
+                sample_file("b.js", "baz(2);\n"),
+            ],
+        );
+        let result = test_pattern_sample(&compiled, &expected, ExecutionContext::default());
+        assert!(result.is_pass(), "{:?}", result.message);
+
+        let wrong_output = multifile_sample(
+            inputs,
+            vec![
+                sample_file("a.js", "bar(1);\n"),
+                sample_file("b.js", "bar(2);\n"),
+            ],
+        );
+        let result = test_pattern_sample(&compiled, &wrong_output, ExecutionContext::default());
+        assert_eq!(result.state, GritTestResultState::FailedOutput);
+        assert_eq!(result.expected_output.as_deref(), Some("bar(2);\n"));
+        assert_eq!(result.actual_output.as_deref(), Some("baz(2);\n"));
+    }
+
+    #[test]
+    fn multifile_sample_deletions() {
+        let compiled = compile("language js\n\n`foo($x)` where { $filename => `moved.js` }");
+        let inputs = vec![sample_file("old.js", "foo(1);\n")];
+
+        let moved = multifile_sample(
+            inputs.clone(),
+            vec![sample_file("moved.js", "foo(1);\n"), deleted_file("old.js")],
+        );
+        let result = test_pattern_sample(&compiled, &moved, ExecutionContext::default());
+        assert!(result.is_pass(), "{:?}", result.message);
+
+        let kept = multifile_sample(inputs, vec![sample_file("moved.js", "foo(1);\n")]);
+        let result = test_pattern_sample(&compiled, &kept, ExecutionContext::default());
+        assert_eq!(result.state, GritTestResultState::FailedOutput);
+        assert_eq!(
+            result.message.as_deref(),
+            Some("Expected old.js to be kept, but it was deleted")
+        );
+    }
+}
@@ -0,0 +1,636 @@
+//! Control-flow graph construction for function bodies.
+//!
+//! A language's [`ControlFlowRules`] classify the statements of a function,
+//! and the builder links them into a [`ControlFlowGraph`] whose vertices are
+//! simple statements and the conditions of branches and loops.
+//!
+//! Any statement inside a `try` body may throw. A `finally` block runs before
+//! returns and exceptions leave its `try`, while `break` and `continue` jump
+//! straight to their loop. A deferred call is the finalizer of the rest of its
+//! block. Labels are ignored, so labeled jumps target the innermost loop or
+//! switch.
+
+use grit_util::{AstNode, ControlFlowGraph, Vertex};
+use marzano_util::node_with_source::NodeWithSource;
+
+type Graph<'a> = ControlFlowGraph<NodeWithSource<'a>>;
+
+pub(crate) trait ControlFlowRules {
+    /// Whether `node` is a function, or the root of the file.
+    fn is_function(&self, node: &NodeWithSource) -> bool;
+
+    /// Returns the statements run by `function`.
+    fn function_body<'a>(&self, function: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>>;
+
+    /// Classifies `node` by how it moves control.
+    fn statement<'a>(&self, node: &NodeWithSource<'a>) -> Statement<'a>;
+}
+
+pub(crate) enum Statement<'a> {
+    /// Runs each of the statements in order.
+    Block(Vec<NodeWithSource<'a>>),
+    /// Runs `consequence` if `condition` holds. Otherwise, the alternatives
+    /// are tried in order: an alternative classified as an `If` without
+    /// alternatives of its own, such as `elif`, is tested in turn, while any
+    /// other alternative is the final `else`.
+    If {
+        condition: NodeWithSource<'a>,
+        consequence: NodeWithSource<'a>,
+        alternatives: Vec<NodeWithSource<'a>>,
+    },
+    Loop(Loop<'a>),
+    /// Runs one of `cases` depending on `subject`.
+    Switch {
+        subject: Option<NodeWithSource<'a>>,
+        cases: Vec<NodeWithSource<'a>>,
+        /// Whether one of the cases always runs, such as with a `default` case.
+        exhaustive: bool,
+        /// Whether a case continues into the next one unless it breaks.
+        falls_through: bool,
+    },
+    Try {
+        body: NodeWithSource<'a>,
+        handlers: Vec<NodeWithSource<'a>>,
+        /// The `else` block Python runs when the body does not throw.
+        otherwise: Option<NodeWithSource<'a>>,
+        finalizer: Option<NodeWithSource<'a>>,
+    },
+    /// Runs the deferred call once the statements after it in the same block
+    /// finish or return, like a `finally` block around them.
+    Defer(NodeWithSource<'a>),
+    Return(NodeWithSource<'a>),
+    Throw(NodeWithSource<'a>),
+    Break(NodeWithSource<'a>),
+    Continue(NodeWithSource<'a>),
+    /// Runs as a single step.
+    Simple(NodeWithSource<'a>),
+}
+
+pub(crate) struct Loop<'a> {
+    pub initializers: Vec<NodeWithSource<'a>>,
+    /// The condition or iterable checked before every iteration, if any.
+    pub header: Option<NodeWithSource<'a>>,
+    pub body: NodeWithSource<'a>,
+    pub updates: Vec<NodeWithSource<'a>>,
+    /// The `else` block Python runs when the loop ends without `break`.
+    pub otherwise: Option<NodeWithSource<'a>>,
+    /// Whether the loop only ends through a jump, such as `while (true)`.
+    pub infinite: bool,
+    /// Whether the body runs before the header is first checked, as in
+    /// `do ... while`.
+    pub checks_after_body: bool,
+}
+
+pub(crate) fn control_flow_graph<'a>(
+    rules: &impl ControlFlowRules,
+    node: &NodeWithSource<'a>,
+) -> Option<Graph<'a>> {
+    let function = node
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| rules.is_function(ancestor))?;
+    let body = rules.function_body(&function)?;
+    let mut builder = Builder {
+        rules,
+        graph: Graph::new(),
+        frames: Vec::new(),
+    };
+    let exits = builder.build(&body, vec![Graph::ENTRY]);
+    builder.connect(&exits, Graph::EXIT);
+    Some(builder.graph)
+}
+
+#[derive(Clone, Copy)]
+enum Jump {
+    Return,
+    Throw,
+    Break,
+    Continue,
+}
+
+/// A construct which jumps inside it may land in, collecting the vertices
+/// they jump from.
+enum Frame {
+    Loop {
+        breaks: Vec<Vertex>,
+        continues: Vec<Vertex>,
+    },
+    Switch {
+        breaks: Vec<Vertex>,
+    },
+    Try {
+        catches: bool,
+        finally: bool,
+        throws: Vec<Vertex>,
+        returns: Vec<Vertex>,
+    },
+}
+
+struct Builder<'a, 'r, R> {
+    rules: &'r R,
+    graph: Graph<'a>,
+    frames: Vec<Frame>,
+}
+
+impl<'a, 'r, R: ControlFlowRules> Builder<'a, 'r, R> {
+    /// Adds the vertices for `node`, entered from `preds`, and returns the
+    /// vertices which continue after it.
+    fn build(&mut self, node: &NodeWithSource<'a>, preds: Vec<Vertex>) -> Vec<Vertex> {
+        match self.rules.statement(node) {
+            Statement::Block(statements) => self.build_all(&statements, preds),
+            Statement::If {
+                condition,
+                consequence,
+                alternatives,
+            } => self.build_if(condition, &consequence, &alternatives, preds),
+            Statement::Loop(spec) => self.build_loop(spec, preds),
+            Statement::Switch {
+                subject,
+                cases,
+                exhaustive,
+                falls_through,
+            } => {
+                let subject = self.vertex(subject, &preds);
+                self.frames.push(Frame::Switch { breaks: Vec::new() });
+                let mut exits = Vec::new();
+                let mut previous = Vec::new();
+                for case in &cases {
+                    let mut case_preds = vec![subject];
+                    if falls_through {
+                        case_preds.append(&mut previous);
+                    }
+                    let case_exits = self.build(case, case_preds);
+                    if falls_through {
+                        previous = case_exits;
+                    } else {
+                        exits.extend(case_exits);
+                    }
+                }
+                exits.extend(previous);
+                if let Some(Frame::Switch { breaks }) = self.frames.pop() {
+                    exits.extend(breaks);
+                }
+                if !exhaustive {
+                    exits.push(subject);
+                }
+                exits
+            }
+            Statement::Try {
+                body,
+                handlers,
+                otherwise,
+                finalizer,
+            } => self.build_try(
+                &body,
+                &handlers,
+                otherwise.as_ref(),
+                finalizer.as_ref(),
+                preds,
+            ),
+            Statement::Defer(call) => self.build_deferred(&call, &[], preds),
+            Statement::Return(node) => self.build_jump(node, Jump::Return, &preds),
+            Statement::Throw(node) => self.build_jump(node, Jump::Throw, &preds),
+            Statement::Break(node) => self.build_jump(node, Jump::Break, &preds),
+            Statement::Continue(node) => self.build_jump(node, Jump::Continue, &preds),
+            Statement::Simple(node) => vec![self.vertex(Some(node), &preds)],
+        }
+    }
+
+    /// Builds each of `nodes` in order.
+    fn build_all(&mut self, nodes: &[NodeWithSource<'a>], preds: Vec<Vertex>) -> Vec<Vertex> {
+        let mut preds = preds;
+        for (i, node) in nodes.iter().enumerate() {
+            if let Statement::Defer(call) = self.rules.statement(node) {
+                return self.build_deferred(&call, &nodes[i + 1..], preds);
+            }
+            preds = self.build(node, preds);
+        }
+        preds
+    }
+
+    fn build_if(
+        &mut self,
+        condition: NodeWithSource<'a>,
+        consequence: &NodeWithSource<'a>,
+        alternatives: &[NodeWithSource<'a>],
+        preds: Vec<Vertex>,
+    ) -> Vec<Vertex> {
+        let condition = self.vertex(Some(condition), &preds);
+        let mut exits = self.build(consequence, vec![condition]);
+        match alternatives.split_first() {
+            None => exits.push(condition),
+            Some((alternative, rest)) => match self.rules.statement(alternative) {
+                Statement::If {
+                    condition: alternative_condition,
+                    consequence,
+                    alternatives,
+                } if alternatives.is_empty() => exits.extend(self.build_if(
+                    alternative_condition,
+                    &consequence,
+                    rest,
+                    vec![condition],
+                )),
+                _ => exits.extend(self.build(alternative, vec![condition])),
+            },
+        }
+        exits
+    }
+
+    fn build_loop(&mut self, spec: Loop<'a>, preds: Vec<Vertex>) -> Vec<Vertex> {
+        let preds = self.build_all(&spec.initializers, preds);
+        self.frames.push(Frame::Loop {
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+        let (header, latch) = if spec.checks_after_body {
+            let body_entry = self.graph.len();
+            let body_exits = self.build(&spec.body, preds);
+            let (breaks, continues) = self.pop_loop();
+            let header = self.vertex(spec.header, &[body_exits, continues].concat());
+            let body_entry = if body_entry < header {
+                body_entry
+            } else {
+                header
+            };
+            self.graph.add_edge(header, body_entry);
+            (header, breaks)
+        } else {
+            let header = self.vertex(spec.header, &preds);
+            let body_exits = self.build(&spec.body, vec![header]);
+            let (breaks, continues) = self.pop_loop();
+            let latch = [body_exits, continues].concat();
+            let latch = self.build_all(&spec.updates, latch);
+            self.connect(&latch, header);
+            (header, breaks)
+        };
+        let mut exits = latch;
+        if !spec.infinite {
+            match &spec.otherwise {
+                Some(otherwise) => exits.extend(self.build(otherwise, vec![header])),
+                None => exits.push(header),
+            }
+        }
+        exits
+    }
+
+    fn pop_loop(&mut self) -> (Vec<Vertex>, Vec<Vertex>) {
+        match self.frames.pop() {
+            Some(Frame::Loop { breaks, continues }) => (breaks, continues),
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    fn build_try(
+        &mut self,
+        body: &NodeWithSource<'a>,
+        handlers: &[NodeWithSource<'a>],
+        otherwise: Option<&NodeWithSource<'a>>,
+        finalizer: Option<&NodeWithSource<'a>>,
+        preds: Vec<Vertex>,
+    ) -> Vec<Vertex> {
+        let catches = !handlers.is_empty();
+        let finally = finalizer.is_some();
+        self.frames.push(Frame::Try {
+            catches,
+            finally,
+            throws: Vec::new(),
+            returns: Vec::new(),
+        });
+        let body_start = self.graph.len();
+        let mut exits = self.build(body, preds);
+        let body_end = self.graph.len();
+        let (body_throws, mut returns) = self.pop_try();
+        // Every statement of the body may throw
+        let raised: Vec<Vertex> = (body_start..body_end).chain(body_throws).collect();
+
+        // Throws and returns in handlers still run the finalizer
+        self.frames.push(Frame::Try {
+            catches: false,
+            finally,
+            throws: Vec::new(),
+            returns: Vec::new(),
+        });
+        if let Some(otherwise) = otherwise {
+            exits = self.build(otherwise, exits);
+        }
+        for handler in handlers {
+            exits.extend(self.build(handler, raised.clone()));
+        }
+        let (mut throws, handler_returns) = self.pop_try();
+        returns.extend(handler_returns);
+        if !catches {
+            throws.extend(raised);
+        }
+
+        match finalizer {
+            Some(finalizer) => self.build_finalizer(finalizer, exits, throws, returns),
+            None => exits,
+        }
+    }
+
+    /// Builds `rest` with `call` as its finalizer.
+    fn build_deferred(
+        &mut self,
+        call: &NodeWithSource<'a>,
+        rest: &[NodeWithSource<'a>],
+        preds: Vec<Vertex>,
+    ) -> Vec<Vertex> {
+        self.frames.push(Frame::Try {
+            catches: false,
+            finally: true,
+            throws: Vec::new(),
+            returns: Vec::new(),
+        });
+        let exits = self.build_all(rest, preds);
+        let (throws, returns) = self.pop_try();
+        self.build_finalizer(call, exits, throws, returns)
+    }
+
+    /// Builds a finalizer entered from the `exits` of its `try` and from the
+    /// `throws` and `returns` leaving it, which continue as they began once
+    /// the finalizer is done.
+    fn build_finalizer(
+        &mut self,
+        finalizer: &NodeWithSource<'a>,
+        exits: Vec<Vertex>,
+        throws: Vec<Vertex>,
+        returns: Vec<Vertex>,
+    ) -> Vec<Vertex> {
+        let completes = !exits.is_empty();
+        let finalizer_exits =
+            self.build(finalizer, [exits, throws.clone(), returns.clone()].concat());
+        for (jump, pending) in [(Jump::Throw, throws), (Jump::Return, returns)] {
+            if !pending.is_empty() {
+                for &exit in &finalizer_exits {
+                    self.jump(jump, exit);
+                }
+            }
+        }
+        if completes {
+            finalizer_exits
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Pops a `Try` frame, returning the vertices which throw and return out
+    /// of it.
+    fn pop_try(&mut self) -> (Vec<Vertex>, Vec<Vertex>) {
+        match self.frames.pop() {
+            Some(Frame::Try {
+                throws, returns, ..
+            }) => (throws, returns),
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    fn build_jump(
+        &mut self,
+        node: NodeWithSource<'a>,
+        jump: Jump,
+        preds: &[Vertex],
+    ) -> Vec<Vertex> {
+        let vertex = self.vertex(Some(node), preds);
+        self.jump(jump, vertex);
+        Vec::new()
+    }
+
+    /// Records a jump from `vertex` with the construct it lands in, or leads
+    /// it to the exit if it leaves the function.
+    fn jump(&mut self, jump: Jump, vertex: Vertex) {
+        for frame in self.frames.iter_mut().rev() {
+            let targets = match (jump, frame) {
+                (Jump::Break, Frame::Loop { breaks, .. } | Frame::Switch { breaks }) => breaks,
+                (Jump::Continue, Frame::Loop { continues, .. }) => continues,
+                (
+                    Jump::Return,
+                    Frame::Try {
+                        finally: true,
+                        returns,
+                        ..
+                    },
+                ) => returns,
+                (
+                    Jump::Throw,
+                    Frame::Try {
+                        catches: true,
+                        throws,
+                        ..
+                    }
+                    | Frame::Try {
+                        finally: true,
+                        throws,
+                        ..
+                    },
+                ) => throws,
+                _ => continue,
+            };
+            targets.push(vertex);
+            return;
+        }
+        if matches!(jump, Jump::Return | Jump::Throw) {
+            self.graph.add_edge(vertex, Graph::EXIT);
+        }
+    }
+
+    fn vertex(&mut self, node: Option<NodeWithSource<'a>>, preds: &[Vertex]) -> Vertex {
+        let vertex = self.graph.add_vertex(node);
+        self.connect(preds, vertex);
+        vertex
+    }
+
+    fn connect(&mut self, preds: &[Vertex], to: Vertex) {
+        for &pred in preds {
+            self.graph.add_edge(pred, to);
+        }
+    }
+}
+
+/// Returns the named children of `node` which are not comments.
+pub(crate) fn statements_of<'a>(node: &NodeWithSource<'a>) -> Vec<NodeWithSource<'a>> {
+    node.named_children()
+        .filter(|child| !child.node.kind().contains("comment"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        go::Go, java::Java, javascript::JavaScript, language::MarzanoLanguage, python::Python,
+    };
+    use grit_util::{traverse, Ast, FileOrigin, Language, Order};
+
+    fn find<'a>(root: &NodeWithSource<'a>, text: &str) -> NodeWithSource<'a> {
+        traverse(root.walk(), Order::Pre)
+            .find(|node| node.text().is_ok_and(|t| t == text))
+            .unwrap_or_else(|| panic!("no node with text {text}"))
+    }
+
+    fn is_call_to(node: &NodeWithSource, name: &str) -> bool {
+        traverse(node.walk(), Order::Pre).any(|n| n.text().is_ok_and(|t| t == name))
+    }
+
+    #[test]
+    fn follows_javascript_branches_and_finally() {
+        let code = r#"function update(mutex, item) {
+  mutex.lock();
+  if (!item) {
+    return;
+  }
+  try {
+    save(item);
+  } finally {
+    mutex.unlock();
+  }
+}
+"#;
+        let lang = JavaScript::new(None);
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let lock = find(&root, "mutex.lock()");
+        let save = find(&root, "save(item)");
+        let graph = lang.control_flow_graph(&lock).unwrap();
+        assert!(graph.always_before(&lock, &save));
+        assert!(!graph.always_before(&save, &lock));
+        // The early return skips the unlock
+        assert!(!graph.always_followed_by(&lock, |step| is_call_to(step, "mutex.unlock()")));
+        // But every path through the `try` unlocks
+        assert!(graph.always_followed_by(&save, |step| is_call_to(step, "mutex.unlock()")));
+    }
+
+    #[test]
+    fn finds_unreachable_python_statements() {
+        let code = r#"def process(items):
+    for item in items:
+        if item is None:
+            break
+        handle(item)
+        continue
+        skipped(item)
+    else:
+        finish()
+    while True:
+        poll()
+    never()
+"#;
+        let lang = Python::new(None);
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let graph = lang
+            .control_flow_graph(&find(&root, "handle(item)"))
+            .unwrap();
+        assert!(graph.is_reachable(&find(&root, "handle(item)")));
+        assert!(graph.is_reachable(&find(&root, "finish()")));
+        assert!(!graph.is_reachable(&find(&root, "skipped(item)")));
+        assert!(!graph.is_reachable(&find(&root, "never()")));
+    }
+
+    #[test]
+    fn runs_java_finally_blocks_before_returns() {
+        let code = r#"class Store {
+  int read() {
+    lock.lock();
+    try {
+      if (empty) {
+        return 0;
+      }
+      return value;
+    } finally {
+      lock.unlock();
+    }
+  }
+}
+"#;
+        let lang = Java::new(None);
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let lock = find(&root, "lock.lock()");
+        let graph = lang.control_flow_graph(&lock).unwrap();
+        assert!(graph.always_followed_by(&lock, |step| is_call_to(step, "lock.unlock()")));
+        assert!(graph.is_reachable(&find(&root, "lock.unlock()")));
+    }
+
+    #[test]
+    fn rethrows_from_finally_blocks_into_outer_catches() {
+        let code = r#"function run() {
+  try {
+    try {
+      work();
+    } finally {
+      cleanup();
+    }
+  } catch (e) {
+    report(e);
+  }
+  done();
+}
+"#;
+        let lang = JavaScript::new(None);
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let cleanup = find(&root, "cleanup()");
+        let graph = lang.control_flow_graph(&cleanup).unwrap();
+        // A throw leaving the finally block is caught, rather than returning
+        assert!(graph.always_followed_by(&cleanup, |step| is_call_to(step, "done()")));
+    }
+
+    #[test]
+    fn counts_go_defers_on_every_path() {
+        let code = r#"package store
+
+func (s *Store) Get(key string) string {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	if key == "" {
+		return ""
+	}
+	return s.items[key]
+}
+"#;
+        let lang = Go::new(None);
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let lock = find(&root, "s.mu.Lock()");
+        let graph = lang.control_flow_graph(&lock).unwrap();
+        assert!(graph.always_followed_by(&lock, |step| is_call_to(step, "s.mu.Unlock()")));
+    }
+
+    #[test]
+    fn runs_go_defers_when_the_function_ends() {
+        let code = r#"package store
+
+func (s *Store) Put(key string, value string) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	s.items[key] = value
+}
+"#;
+        let lang = Go::new(None);
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let store = find(&root, "s.items[key] = value");
+        let unlock = find(&root, "s.mu.Unlock()");
+        let graph = lang.control_flow_graph(&store).unwrap();
+        assert!(graph.always_before(&store, &unlock));
+        assert!(!graph.always_before(&unlock, &store));
+    }
+}
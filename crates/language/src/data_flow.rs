@@ -0,0 +1,422 @@
+//! Intra-procedural data flow, used to trace values from sources into sinks.
+//!
+//! The analysis builds a def-use graph of the function enclosing a sink: every
+//! assignment to a local binding is a definition, and every identifier which
+//! resolves to that binding through [`ScopeRules`] is a use. A definition is
+//! tainted if its value contains a source, or a use reached by a tainted
+//! definition, without a sanitizer in between.
+//!
+//! Definitions are ordered by their position in the source. A use is reached
+//! by the definitions before it, except for those overwritten by a later
+//! definition which always runs before the use. Loops are not iterated, and
+//! nested functions are analyzed on their own.
+
+use crate::scope::{declaration_of, ScopeRules};
+use grit_util::{traverse, AstNode, FlowRole, Order};
+use marzano_util::node_with_source::NodeWithSource;
+use std::collections::HashMap;
+
+pub(crate) trait FlowRules: ScopeRules {
+    /// Whether `node` is a function, or the root of the file.
+    fn is_function(&self, node: &NodeWithSource) -> bool;
+
+    /// Whether `node` is a block of statements which run in sequence.
+    fn is_block(&self, node: &NodeWithSource) -> bool;
+
+    /// Whether `node` always runs its children when it runs itself, such as
+    /// an expression statement.
+    fn is_sequential(&self, node: &NodeWithSource) -> bool;
+
+    /// Returns the assignment performed by `node`, if any.
+    fn assignment<'a>(&self, node: &NodeWithSource<'a>) -> Option<Assignment<'a>>;
+
+    /// Whether the identifiers within `node`, inside an assignment target, are
+    /// not assigned to, such as in member accesses or default values.
+    fn is_opaque_target(&self, node: &NodeWithSource) -> bool;
+}
+
+pub(crate) struct Assignment<'a> {
+    pub target: NodeWithSource<'a>,
+    pub value: NodeWithSource<'a>,
+    /// Whether the assignment replaces the previous value of the target, as
+    /// opposed to updating it, like `+=` does.
+    pub overwrites: bool,
+}
+
+struct Definition<'a> {
+    /// The node performing the definition.
+    node: NodeWithSource<'a>,
+    /// The identifier being assigned to.
+    target: NodeWithSource<'a>,
+    declaration: NodeWithSource<'a>,
+    /// The assigned value, or `None` if the target is a source itself, such as
+    /// a parameter.
+    value: Option<NodeWithSource<'a>>,
+    overwrites: bool,
+    /// The byte offset at which the new value becomes visible.
+    effective_from: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Origin {
+    Source(usize),
+    Definition(usize),
+}
+
+pub(crate) fn flow_path<'a>(
+    rules: &impl FlowRules,
+    sink: &NodeWithSource<'a>,
+    is_role: &mut dyn FnMut(&NodeWithSource<'a>, FlowRole) -> bool,
+) -> Option<Vec<NodeWithSource<'a>>> {
+    let function = sink
+        .ancestors()
+        .find(|node| rules.is_function(node))
+        .or_else(|| sink.ancestors().last())?;
+    let mut analysis = FlowAnalysis {
+        rules,
+        is_role,
+        sources: Vec::new(),
+        definitions: Vec::new(),
+        tainted: Vec::new(),
+        sanitizers: HashMap::new(),
+    };
+    analysis.collect(&function);
+    if analysis.sources.is_empty() {
+        return None;
+    }
+    analysis.propagate();
+    let origin = analysis.origin_within(sink)?;
+    Some(analysis.path(origin, sink))
+}
+
+struct FlowAnalysis<'a, 'r, R, F> {
+    rules: &'r R,
+    is_role: F,
+    sources: Vec<NodeWithSource<'a>>,
+    definitions: Vec<Definition<'a>>,
+    /// How each definition got tainted, indexed like `definitions`.
+    tainted: Vec<Option<Origin>>,
+    sanitizers: HashMap<(usize, usize, u16), bool>,
+}
+
+impl<'a, 'r, R, F> FlowAnalysis<'a, 'r, R, F>
+where
+    R: FlowRules,
+    F: FnMut(&NodeWithSource<'a>, FlowRole) -> bool,
+{
+    /// Collects the sources and definitions in `node`, skipping nested
+    /// functions.
+    fn collect(&mut self, node: &NodeWithSource<'a>) {
+        if (self.is_role)(node, FlowRole::Source) {
+            // A declaring source, such as a parameter, taints its binding
+            if self.rules.is_identifier(node)
+                && declaration_of(self.rules, node).is_some_and(|d| d == *node)
+            {
+                self.definitions.push(Definition {
+                    node: node.clone(),
+                    target: node.clone(),
+                    declaration: node.clone(),
+                    value: None,
+                    overwrites: true,
+                    effective_from: node.byte_range().end,
+                });
+                self.tainted.push(Some(Origin::Source(self.sources.len())));
+            }
+            self.sources.push(node.clone());
+            return;
+        }
+        if let Some(assignment) = self.rules.assignment(node) {
+            let effective_from = assignment
+                .target
+                .byte_range()
+                .end
+                .max(assignment.value.byte_range().end);
+            for target in self.targets(&assignment.target) {
+                let Some(declaration) = declaration_of(self.rules, &target) else {
+                    continue;
+                };
+                self.definitions.push(Definition {
+                    node: node.clone(),
+                    target,
+                    declaration,
+                    value: Some(assignment.value.clone()),
+                    overwrites: assignment.overwrites,
+                    effective_from,
+                });
+                self.tainted.push(None);
+            }
+        }
+        for child in node.named_children() {
+            if !self.rules.is_function(&child) {
+                self.collect(&child);
+            }
+        }
+    }
+
+    /// Returns the identifiers assigned to by the assignment target `target`.
+    fn targets(&self, target: &NodeWithSource<'a>) -> Vec<NodeWithSource<'a>> {
+        if self.rules.is_opaque_target(target) {
+            Vec::new()
+        } else if self.rules.is_identifier(target) {
+            vec![target.clone()]
+        } else {
+            target
+                .named_children()
+                .flat_map(|child| self.targets(&child))
+                .collect()
+        }
+    }
+
+    /// Taints definitions until a fixpoint is reached.
+    fn propagate(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..self.definitions.len() {
+                if self.tainted[index].is_some() {
+                    continue;
+                }
+                let Some(value) = self.definitions[index].value.clone() else {
+                    continue;
+                };
+                if let Some(origin) = self.origin_within(&value) {
+                    self.tainted[index] = Some(origin);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Returns where a tainted value within `expression` comes from, if any.
+    fn origin_within(&mut self, expression: &NodeWithSource<'a>) -> Option<Origin> {
+        for index in 0..self.sources.len() {
+            let source = self.sources[index].clone();
+            if contains(expression, &source) && !self.is_sanitized(&source, expression) {
+                return Some(Origin::Source(index));
+            }
+        }
+        let uses: Vec<_> = traverse(expression.walk(), Order::Pre)
+            .filter(|node| self.rules.is_identifier(node))
+            .collect();
+        for identifier in uses {
+            let Some(declaration) = declaration_of(self.rules, &identifier) else {
+                continue;
+            };
+            let reaching = (0..self.definitions.len()).find(|&index| {
+                self.tainted[index].is_some()
+                    && self.definitions[index].declaration == declaration
+                    && self.reaches(index, &identifier)
+            });
+            if let Some(index) = reaching {
+                if !self.is_sanitized(&identifier, expression) {
+                    return Some(Origin::Definition(index));
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether the value assigned by the definition at `index` may still be
+    /// held by the binding where `identifier` uses it.
+    fn reaches(&self, index: usize, identifier: &NodeWithSource<'a>) -> bool {
+        let definition = &self.definitions[index];
+        let used_at = identifier.byte_range().start;
+        definition.effective_from <= used_at
+            && !self.definitions.iter().any(|other| {
+                other.overwrites
+                    && other.declaration == definition.declaration
+                    && other.effective_from > definition.effective_from
+                    && other.effective_from <= used_at
+                    && self.always_runs_before(&other.node, identifier)
+            })
+    }
+
+    /// Whether `node` runs on every path which reaches `identifier`, because
+    /// it sits unconditionally in a block enclosing `identifier`.
+    fn always_runs_before(
+        &self,
+        node: &NodeWithSource<'a>,
+        identifier: &NodeWithSource<'a>,
+    ) -> bool {
+        let Some(block) = node
+            .ancestors()
+            .skip(1)
+            .find(|ancestor| !self.rules.is_sequential(ancestor))
+        else {
+            return false;
+        };
+        self.rules.is_block(&block) && contains(&block, identifier)
+    }
+
+    /// Whether a sanitizer encloses `node` within `outer`.
+    fn is_sanitized(&mut self, node: &NodeWithSource<'a>, outer: &NodeWithSource<'a>) -> bool {
+        for ancestor in node.ancestors() {
+            let range = ancestor.byte_range();
+            let key = (range.start, range.end, ancestor.node.kind_id());
+            let is_sanitizer = match self.sanitizers.get(&key) {
+                Some(is_sanitizer) => *is_sanitizer,
+                None => {
+                    let is_sanitizer = (self.is_role)(&ancestor, FlowRole::Sanitizer);
+                    self.sanitizers.insert(key, is_sanitizer);
+                    is_sanitizer
+                }
+            };
+            if is_sanitizer {
+                return true;
+            }
+            if ancestor == *outer {
+                break;
+            }
+        }
+        false
+    }
+
+    /// Follows `origin` back to its source, listing every binding on the way.
+    fn path(&self, origin: Origin, sink: &NodeWithSource<'a>) -> Vec<NodeWithSource<'a>> {
+        let mut path = vec![sink.clone()];
+        let mut next = Some(origin);
+        while let Some(origin) = next {
+            let node = match origin {
+                Origin::Source(index) => {
+                    next = None;
+                    &self.sources[index]
+                }
+                Origin::Definition(index) => {
+                    next = self.tainted[index];
+                    &self.definitions[index].target
+                }
+            };
+            if path.last() != Some(node) {
+                path.push(node.clone());
+            }
+        }
+        path.reverse();
+        path
+    }
+}
+
+fn contains(outer: &NodeWithSource, inner: &NodeWithSource) -> bool {
+    let (outer, inner) = (outer.byte_range(), inner.byte_range());
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        javascript::JavaScript,
+        js_like::JsLikeScopeRules,
+        language::MarzanoLanguage,
+        python::{Python, PythonScopeRules},
+    };
+    use grit_util::{Ast, FileOrigin};
+
+    fn trace(
+        lang: &impl for<'a> MarzanoLanguage<'a>,
+        rules: &impl FlowRules,
+        code: &str,
+        sink: &str,
+        source: &str,
+        sanitizer: &str,
+    ) -> Option<Vec<String>> {
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let sink = traverse(root.walk(), Order::Pre)
+            .find(|node| node.text().is_ok_and(|text| text == sink))
+            .unwrap();
+        let path = flow_path(rules, &sink, &mut |node, role| {
+            let text = node.text().unwrap();
+            match role {
+                FlowRole::Source => text == source,
+                FlowRole::Sanitizer => text.starts_with(sanitizer),
+            }
+        })?;
+        Some(
+            path.iter()
+                .map(|node| node.text().unwrap().to_string())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn traces_javascript_values_through_bindings() {
+        let code = r#"function handler(req, db) {
+  const { id } = req.query;
+  let filter = "id = " + id;
+  filter += "";
+  db.query(filter);
+  const safe = sanitize(id);
+  db.query(safe);
+}
+"#;
+        let lang = JavaScript::new(None);
+        assert_eq!(
+            trace(
+                &lang,
+                &JsLikeScopeRules,
+                code,
+                "db.query(filter)",
+                "req.query",
+                "sanitize"
+            ),
+            Some(vec![
+                "req.query".to_string(),
+                "id".to_string(),
+                "filter".to_string(),
+                "db.query(filter)".to_string()
+            ])
+        );
+        assert_eq!(
+            trace(
+                &lang,
+                &JsLikeScopeRules,
+                code,
+                "db.query(safe)",
+                "req.query",
+                "sanitize"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn stops_python_values_at_overwrites() {
+        let code = r#"def handler(request):
+    name = request.args
+    if name:
+        log(name)
+    name = clean(name)
+    cursor.execute(name)
+"#;
+        let lang = Python::new(None);
+        assert_eq!(
+            trace(
+                &lang,
+                &PythonScopeRules::default(),
+                code,
+                "log(name)",
+                "request.args",
+                "clean"
+            ),
+            Some(vec![
+                "request.args".to_string(),
+                "name".to_string(),
+                "log(name)".to_string()
+            ])
+        );
+        assert_eq!(
+            trace(
+                &lang,
+                &PythonScopeRules::default(),
+                code,
+                "cursor.execute(name)",
+                "request.args",
+                "clean"
+            ),
+            None
+        );
+    }
+}
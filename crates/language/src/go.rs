@@ -1,5 +1,9 @@
-use crate::language::{fields_for_nodes, Field, MarzanoLanguage, NodeTypes, SortId, TSLanguage};
-use grit_util::Language;
+use crate::{
+    control_flow::{self, statements_of, ControlFlowRules, Loop, Statement},
+    language::{fields_for_nodes, Field, MarzanoLanguage, NodeTypes, SortId, TSLanguage},
+    scope::is_kind,
+};
+use grit_util::{ControlFlowGraph, Language};
 use marzano_util::node_with_source::NodeWithSource;
 use std::sync::OnceLock;
 
@@ -66,6 +70,130 @@ impl Language for Go {
             ("func GRIT_FUNC(GRIT_ARG *GRIT_PACKAGE.", ") {}"),
         ]
     }
+
+    fn supports_control_flow(&self) -> bool {
+        true
+    }
+
+    fn control_flow_graph<'a>(
+        &self,
+        node: &NodeWithSource<'a>,
+    ) -> Option<ControlFlowGraph<NodeWithSource<'a>>> {
+        control_flow::control_flow_graph(&GoControlFlowRules, node)
+    }
+
+    fn is_function_definition(&self, node: &NodeWithSource) -> bool {
+        GoControlFlowRules.is_function(node)
+    }
+}
+
+/// Control-flow rules for Go. Switch cases do not fall through, and deferred
+/// calls run once the rest of their block finishes or returns.
+struct GoControlFlowRules;
+
+impl ControlFlowRules for GoControlFlowRules {
+    fn is_function(&self, node: &NodeWithSource) -> bool {
+        is_kind(
+            node,
+            &[
+                "source_file",
+                "function_declaration",
+                "method_declaration",
+                "func_literal",
+            ],
+        )
+    }
+
+    fn function_body<'a>(&self, function: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>> {
+        if function.node.kind() == "source_file" {
+            Some(function.clone())
+        } else {
+            function.child_by_field_name("body")
+        }
+    }
+
+    fn statement<'a>(&self, node: &NodeWithSource<'a>) -> Statement<'a> {
+        go_statement(node).unwrap_or_else(|| Statement::Simple(node.clone()))
+    }
+}
+
+fn go_statement<'a>(node: &NodeWithSource<'a>) -> Option<Statement<'a>> {
+    let field = |name: &str| node.child_by_field_name(name);
+    let statement = match node.node.kind().as_ref() {
+        "source_file" | "block" | "statement_list" => Statement::Block(statements_of(node)),
+        "labeled_statement" => Statement::Block(
+            statements_of(node)
+                .into_iter()
+                .filter(|child| child.node.kind() != "label_name")
+                .collect(),
+        ),
+        "expression_case" | "default_case" | "type_case" | "communication_case" => {
+            Statement::Block(
+                field("communication")
+                    .into_iter()
+                    .chain(node.children_by_field_name("statements"))
+                    .collect(),
+            )
+        }
+        "if_statement" => Statement::If {
+            condition: field("condition")?,
+            consequence: field("consequence")?,
+            alternatives: field("alternative").into_iter().collect(),
+        },
+        "for_statement" => {
+            let iterator = field("iterator");
+            let clause = iterator.as_ref().map(|iterator| iterator.node.kind());
+            let (initializers, header, updates) = match clause.as_deref() {
+                Some("for_clause") => {
+                    let clause = iterator.as_ref()?;
+                    (
+                        clause
+                            .child_by_field_name("initializer")
+                            .into_iter()
+                            .collect(),
+                        clause.child_by_field_name("condition"),
+                        clause.child_by_field_name("update").into_iter().collect(),
+                    )
+                }
+                Some("range_clause") => (
+                    Vec::new(),
+                    iterator.as_ref()?.child_by_field_name("right"),
+                    Vec::new(),
+                ),
+                _ => (Vec::new(), iterator.clone(), Vec::new()),
+            };
+            Statement::Loop(Loop {
+                initializers,
+                infinite: header.is_none(),
+                header,
+                body: field("body")?,
+                updates,
+                otherwise: None,
+                checks_after_body: false,
+            })
+        }
+        "expression_switch_statement" | "type_switch_statement" | "select_statement" => {
+            let cases: Vec<_> = node
+                .named_children()
+                .filter(|child| child.node.kind().ends_with("_case"))
+                .collect();
+            Statement::Switch {
+                subject: field("value"),
+                // A select blocks until one of its cases runs
+                exhaustive: node.node.kind() == "select_statement"
+                    || cases.iter().any(|case| case.node.kind() == "default_case"),
+                cases,
+                falls_through: false,
+            }
+        }
+        // The grammar spells the field of the deferred call `expresion`
+        "defer_statement" => Statement::Defer(field("expresion")?),
+        "return_statement" => Statement::Return(node.clone()),
+        "break_statement" => Statement::Break(node.clone()),
+        "continue_statement" => Statement::Continue(node.clone()),
+        _ => return None,
+    };
+    Some(statement)
 }
 
 impl<'a> MarzanoLanguage<'a> for Go {
@@ -1,5 +1,9 @@
-use crate::language::{fields_for_nodes, Field, MarzanoLanguage, NodeTypes, SortId, TSLanguage};
-use grit_util::Language;
+use crate::{
+    control_flow::{self, statements_of, ControlFlowRules, Loop, Statement},
+    language::{fields_for_nodes, Field, MarzanoLanguage, NodeTypes, SortId, TSLanguage},
+    scope::is_kind,
+};
+use grit_util::{AstNode, ControlFlowGraph, Language};
 use marzano_util::node_with_source::NodeWithSource;
 use std::sync::OnceLock;
 
@@ -69,6 +73,132 @@ impl Language for Java {
             ("class GRIT_CLASS { GRIT_FN(", ") {} }"),
         ]
     }
+
+    fn supports_control_flow(&self) -> bool {
+        true
+    }
+
+    fn control_flow_graph<'a>(
+        &self,
+        node: &NodeWithSource<'a>,
+    ) -> Option<ControlFlowGraph<NodeWithSource<'a>>> {
+        control_flow::control_flow_graph(&JavaControlFlowRules, node)
+    }
+
+    fn is_function_definition(&self, node: &NodeWithSource) -> bool {
+        JavaControlFlowRules.is_function(node)
+    }
+}
+
+/// Control-flow rules for Java. Cases of classic `switch` statements fall
+/// through, while `->` cases do not.
+struct JavaControlFlowRules;
+
+impl ControlFlowRules for JavaControlFlowRules {
+    fn is_function(&self, node: &NodeWithSource) -> bool {
+        is_kind(
+            node,
+            &[
+                "program",
+                "method_declaration",
+                "constructor_declaration",
+                "lambda_expression",
+            ],
+        )
+    }
+
+    fn function_body<'a>(&self, function: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>> {
+        if function.node.kind() == "program" {
+            Some(function.clone())
+        } else {
+            function.child_by_field_name("body")
+        }
+    }
+
+    fn statement<'a>(&self, node: &NodeWithSource<'a>) -> Statement<'a> {
+        java_statement(node).unwrap_or_else(|| Statement::Simple(node.clone()))
+    }
+}
+
+fn java_statement<'a>(node: &NodeWithSource<'a>) -> Option<Statement<'a>> {
+    let field = |name: &str| node.child_by_field_name(name);
+    let statement = match node.node.kind().as_ref() {
+        "program" | "block" | "constructor_body" => Statement::Block(statements_of(node)),
+        "labeled_statement" => Statement::Block(field("statement").into_iter().collect()),
+        "synchronized_statement" | "catch_clause" | "finally_clause" => {
+            Statement::Block(field("body").into_iter().collect())
+        }
+        "switch_block_statement_group" | "switch_rule" => {
+            Statement::Block(node.children_by_field_name("consequence").collect())
+        }
+        "if_statement" => Statement::If {
+            condition: field("condition")?,
+            consequence: field("consequence")?,
+            alternatives: field("alternative").into_iter().collect(),
+        },
+        "for_statement" => {
+            let condition = field("condition");
+            Statement::Loop(Loop {
+                initializers: node.children_by_field_name("init").collect(),
+                infinite: condition.is_none(),
+                header: condition,
+                body: field("body")?,
+                updates: node.children_by_field_name("update").collect(),
+                otherwise: None,
+                checks_after_body: false,
+            })
+        }
+        "enhanced_for_statement" => Statement::Loop(Loop {
+            initializers: Vec::new(),
+            header: field("value"),
+            body: field("body")?,
+            updates: Vec::new(),
+            otherwise: None,
+            infinite: false,
+            checks_after_body: false,
+        }),
+        "while_statement" | "do_statement" => {
+            let condition = field("condition");
+            Statement::Loop(Loop {
+                initializers: Vec::new(),
+                infinite: condition
+                    .as_ref()
+                    .is_some_and(|condition| condition.text().is_ok_and(|text| text == "(true)")),
+                header: condition,
+                body: field("body")?,
+                updates: Vec::new(),
+                otherwise: None,
+                checks_after_body: node.node.kind() == "do_statement",
+            })
+        }
+        "switch_expression" => {
+            let cases: Vec<_> = field("body")?.named_children().collect();
+            Statement::Switch {
+                subject: field("condition"),
+                exhaustive: cases.iter().any(|case| {
+                    case.children_by_field_name("condition")
+                        .any(|label| label.text().is_ok_and(|text| text.trim() == "default"))
+                }),
+                falls_through: cases
+                    .iter()
+                    .any(|case| case.node.kind() == "switch_block_statement_group"),
+                cases,
+            }
+        }
+        "try_statement" | "try_with_resources_statement" => Statement::Try {
+            body: field("body")?,
+            handlers: node.children_by_field_name("catch").collect(),
+            otherwise: None,
+            finalizer: field("finally"),
+        },
+        "return_statement" => Statement::Return(node.clone()),
+        "throw_statement" => Statement::Throw(node.clone()),
+        // `yield` leaves a switch expression, like `break` does
+        "break_statement" | "yield_statement" => Statement::Break(node.clone()),
+        "continue_statement" => Statement::Continue(node.clone()),
+        _ => return None,
+    };
+    Some(statement)
 }
 
 impl<'a> MarzanoLanguage<'a> for Java {
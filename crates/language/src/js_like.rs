@@ -1,9 +1,12 @@
 use crate::{
+    control_flow::{statements_of, ControlFlowRules, Loop, Statement},
+    data_flow::{Assignment, FlowRules},
     language::{
         FieldExpectationCondition, FieldExpectationCondition::Always,
         FieldExpectationCondition::OnlyIf, MarzanoLanguage, MarzanoParser, SortId, TSLanguage,
         Tree,
     },
+    scope::{is_field, is_kind, nearest_scope, ScopeRules},
     vue::get_vue_ranges,
 };
 use grit_util::{AnalysisLogs, AstNode, FileOrigin, Parser, Replacement, SnippetTree};
@@ -214,6 +217,283 @@ pub(crate) fn jslike_check_replacements(
     }
 }
 
+static FUNCTION_SCOPE_NAMES: &[&str] = &[
+    "program",
+    "function",
+    "function_declaration",
+    "generator_function",
+    "generator_function_declaration",
+    "arrow_function",
+    "method_definition",
+];
+
+static BLOCK_SCOPE_NAMES: &[&str] = &[
+    "statement_block",
+    "for_statement",
+    "for_in_statement",
+    "catch_clause",
+    "switch_body",
+];
+
+/// Scope rules for JavaScript and TypeScript: `var` declarations are scoped to
+/// the enclosing function, while `let`, `const`, classes and functions are
+/// scoped to the enclosing block. Type-only declarations are not resolved.
+pub(crate) struct JsLikeScopeRules;
+
+impl ScopeRules for JsLikeScopeRules {
+    fn is_identifier(&self, node: &NodeWithSource) -> bool {
+        match node.node.kind().as_ref() {
+            "identifier"
+            | "shorthand_property_identifier"
+            | "shorthand_property_identifier_pattern" => {
+                // Imported and exported names under an alias are not local bindings
+                !node
+                    .parent()
+                    .is_some_and(|parent| match parent.node.kind().as_ref() {
+                        "import_specifier" => {
+                            is_field(&parent, "name", node)
+                                && parent.child_by_field_name("alias").is_some()
+                        }
+                        "export_specifier" => is_field(&parent, "alias", node),
+                        _ => false,
+                    })
+            }
+            // Class names are type identifiers in TypeScript
+            "type_identifier" => node.parent().is_some_and(|parent| {
+                is_kind(
+                    &parent,
+                    &["class_declaration", "abstract_class_declaration"],
+                ) && is_field(&parent, "name", node)
+            }),
+            _ => false,
+        }
+    }
+
+    fn is_scope(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, FUNCTION_SCOPE_NAMES) || is_kind(node, BLOCK_SCOPE_NAMES)
+    }
+
+    fn declaring_scope<'a>(&self, identifier: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>> {
+        // Climb out of destructuring patterns to the node which declares them
+        let mut child = identifier.clone();
+        let mut parent = child.parent()?;
+        while is_destructured(&parent, &child) {
+            child = parent;
+            parent = child.parent()?;
+        }
+
+        match parent.node.kind().as_ref() {
+            "variable_declarator" if is_field(&parent, "name", &child) => {
+                let declaration = parent.parent()?;
+                if declaration.node.kind() == "variable_declaration" {
+                    nearest_function_scope(&declaration)
+                } else {
+                    nearest_scope(self, &declaration)
+                }
+            }
+            "function"
+            | "function_declaration"
+            | "generator_function"
+            | "generator_function_declaration"
+            | "arrow_function"
+            | "method_definition"
+                if is_field(&parent, "parameters", &child) =>
+            {
+                Some(parent.clone())
+            }
+            "function_declaration"
+            | "generator_function_declaration"
+            | "class_declaration"
+            | "abstract_class_declaration"
+                if is_field(&parent, "name", &child) =>
+            {
+                nearest_scope(self, &parent)
+            }
+            // Function expressions can refer to themselves by name
+            "function" | "generator_function" if is_field(&parent, "name", &child) => {
+                Some(parent.clone())
+            }
+            "catch_clause" if is_field(&parent, "parameter", &child) => Some(parent.clone()),
+            "for_in_statement" if is_field(&parent, "left", &child) => {
+                let kind = parent.child_by_field_name("kind")?;
+                if kind.text().is_ok_and(|kind| kind == "var") {
+                    nearest_function_scope(&parent)
+                } else {
+                    Some(parent.clone())
+                }
+            }
+            "import_specifier" | "import_clause" | "namespace_import" => parent.ancestors().last(),
+            _ => None,
+        }
+    }
+}
+
+impl FlowRules for JsLikeScopeRules {
+    fn is_function(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, FUNCTION_SCOPE_NAMES)
+    }
+
+    fn is_block(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, &["program", "statement_block"])
+    }
+
+    fn is_sequential(&self, node: &NodeWithSource) -> bool {
+        is_kind(
+            node,
+            &[
+                "expression_statement",
+                "lexical_declaration",
+                "variable_declaration",
+                "parenthesized_expression",
+                "sequence_expression",
+            ],
+        )
+    }
+
+    fn assignment<'a>(&self, node: &NodeWithSource<'a>) -> Option<Assignment<'a>> {
+        let (target, value, overwrites) = match node.node.kind().as_ref() {
+            "variable_declarator" => ("name", "value", true),
+            "assignment_expression" | "for_in_statement" => ("left", "right", true),
+            "augmented_assignment_expression" => ("left", "right", false),
+            _ => return None,
+        };
+        Some(Assignment {
+            target: node.child_by_field_name(target)?,
+            value: node.child_by_field_name(value)?,
+            overwrites,
+        })
+    }
+
+    fn is_opaque_target(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, &["member_expression", "subscript_expression"])
+            || node.parent().is_some_and(|parent| {
+                is_kind(
+                    &parent,
+                    &["assignment_pattern", "object_assignment_pattern"],
+                ) && is_field(&parent, "right", node)
+            })
+    }
+}
+
+/// Control-flow rules for JavaScript and TypeScript.
+pub(crate) struct JsLikeControlFlowRules;
+
+impl ControlFlowRules for JsLikeControlFlowRules {
+    fn is_function(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, FUNCTION_SCOPE_NAMES)
+    }
+
+    fn function_body<'a>(&self, function: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>> {
+        if function.node.kind() == "program" {
+            Some(function.clone())
+        } else {
+            function.child_by_field_name("body")
+        }
+    }
+
+    fn statement<'a>(&self, node: &NodeWithSource<'a>) -> Statement<'a> {
+        js_like_statement(node).unwrap_or_else(|| Statement::Simple(node.clone()))
+    }
+}
+
+fn js_like_statement<'a>(node: &NodeWithSource<'a>) -> Option<Statement<'a>> {
+    let field = |name: &str| node.child_by_field_name(name);
+    let is_always_true = |condition: &Option<NodeWithSource>| {
+        condition
+            .as_ref()
+            .is_some_and(|condition| condition.text().is_ok_and(|text| text == "(true)"))
+    };
+    let statement = match node.node.kind().as_ref() {
+        "program" | "statement_block" | "else_clause" => Statement::Block(statements_of(node)),
+        "labeled_statement" | "catch_clause" | "finally_clause" => {
+            Statement::Block(field("body").into_iter().collect())
+        }
+        "switch_case" | "switch_default" => {
+            Statement::Block(node.children_by_field_name("body").collect())
+        }
+        "if_statement" => Statement::If {
+            condition: field("condition")?,
+            consequence: field("consequence")?,
+            alternatives: field("alternative").into_iter().collect(),
+        },
+        "for_statement" => {
+            let condition = field("condition").filter(|c| c.node.kind() != "empty_statement");
+            Statement::Loop(Loop {
+                initializers: field("initializer").into_iter().collect(),
+                infinite: condition.is_none(),
+                header: condition,
+                body: field("body")?,
+                updates: field("increment").into_iter().collect(),
+                otherwise: None,
+                checks_after_body: false,
+            })
+        }
+        "for_in_statement" => Statement::Loop(Loop {
+            initializers: Vec::new(),
+            header: field("right"),
+            body: field("body")?,
+            updates: Vec::new(),
+            otherwise: None,
+            infinite: false,
+            checks_after_body: false,
+        }),
+        "while_statement" | "do_statement" => {
+            let condition = field("condition");
+            Statement::Loop(Loop {
+                initializers: Vec::new(),
+                infinite: is_always_true(&condition),
+                header: condition,
+                body: field("body")?,
+                updates: Vec::new(),
+                otherwise: None,
+                checks_after_body: node.node.kind() == "do_statement",
+            })
+        }
+        "switch_statement" => {
+            let cases: Vec<_> = field("body")?.named_children().collect();
+            Statement::Switch {
+                subject: field("value"),
+                exhaustive: cases
+                    .iter()
+                    .any(|case| case.node.kind() == "switch_default"),
+                cases,
+                falls_through: true,
+            }
+        }
+        "try_statement" => Statement::Try {
+            body: field("body")?,
+            handlers: field("handler").into_iter().collect(),
+            otherwise: None,
+            finalizer: field("finalizer"),
+        },
+        "return_statement" => Statement::Return(node.clone()),
+        "throw_statement" => Statement::Throw(node.clone()),
+        "break_statement" => Statement::Break(node.clone()),
+        "continue_statement" => Statement::Continue(node.clone()),
+        _ => return None,
+    };
+    Some(statement)
+}
+
+/// Whether `child` is a binding target of the destructuring pattern `parent`.
+fn is_destructured(parent: &NodeWithSource, child: &NodeWithSource) -> bool {
+    match parent.node.kind().as_ref() {
+        "object_pattern" | "array_pattern" | "rest_pattern" => true,
+        "pair_pattern" => is_field(parent, "value", child),
+        "assignment_pattern" | "object_assignment_pattern" => is_field(parent, "left", child),
+        "required_parameter" | "optional_parameter" => {
+            is_field(parent, "pattern", child) || is_field(parent, "name", child)
+        }
+        _ => false,
+    }
+}
+
+fn nearest_function_scope<'a>(node: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>> {
+    node.ancestors()
+        .skip(1)
+        .find(|node| is_kind(node, FUNCTION_SCOPE_NAMES))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
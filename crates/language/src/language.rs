@@ -10,7 +10,7 @@ use serde_json::Value;
 use std::{borrow::Cow, cmp::max, collections::HashMap, path::Path};
 pub(crate) use tree_sitter::{Language as TSLanguage, Parser as TSParser, Tree as TSTree};
 
-use crate::sourcemap::EmbeddedSourceMap;
+use crate::{scope::Declarations, sourcemap::EmbeddedSourceMap};
 
 pub type SortId = u16;
 pub type FieldId = u16;
@@ -79,18 +79,25 @@ pub struct Field {
     id: FieldId,
     required: bool,
     multiple: bool,
-    // for now empty, eventually we'll want to capture possible sort types
+    /// The kinds of node the field may hold, with supertypes expanded into
+    /// their concrete subtypes.
     sorts: Vec<SortId>,
 }
 
 impl Field {
-    pub fn new(name: String, id: FieldId, required: bool, multiple: bool) -> Self {
+    pub fn new(
+        name: String,
+        id: FieldId,
+        required: bool,
+        multiple: bool,
+        sorts: Vec<SortId>,
+    ) -> Self {
         Self {
             name,
             id,
             required,
             multiple,
-            sorts: vec![],
+            sorts,
         }
     }
 
@@ -471,6 +478,23 @@ pub trait MarzanoLanguage<'a>: Language<Node<'a> = NodeWithSource<'a>> + NodeTyp
         Ok(None)
     }
 
+    /// Indexes the declarations in the tree of `root`, for
+    /// [`Self::references_in()`] to resolve identifiers against. Returns
+    /// `None` if the language has no scope analysis.
+    fn declarations(&self, _root: &NodeWithSource<'_>) -> Option<Declarations> {
+        None
+    }
+
+    /// Like [`Language::references_of()`], but resolves identifiers against
+    /// the `declarations` of their tree instead of collecting them again.
+    fn references_in<'b>(
+        &self,
+        _declarations: &Declarations,
+        _declaration: &NodeWithSource<'b>,
+    ) -> Vec<NodeWithSource<'b>> {
+        Vec::new()
+    }
+
     fn pad_snippet<'b>(&self, snippet: &'b str, padding: &str) -> Cow<'b, str> {
         if padding.is_empty() {
             return snippet.into();
@@ -620,12 +644,14 @@ fn snippet_nodes_from_index(snippet: &SnippetTree<Tree>) -> Option<NodeWithSourc
     nodes.last().cloned()
 }
 
-// todo
-// also extract multiple and required?
 pub fn fields_for_nodes(language: &TSLanguage, types: &str) -> Vec<Vec<Field>> {
     let mut fields = HashMap::new();
     let node_types: Value = serde_json::from_str(types).unwrap();
     let node_types = node_types.as_array().unwrap();
+    let subtypes: HashMap<&str, &Vec<Value>> = node_types
+        .iter()
+        .filter_map(|node| Some((node["type"].as_str()?, node.get("subtypes")?.as_array()?)))
+        .collect();
     let mut max_kind = 0;
     for node in node_types {
         let node_type = node["type"].as_str().unwrap();
@@ -643,7 +669,13 @@ pub fn fields_for_nodes(language: &TSLanguage, types: &str) -> Vec<Vec<Field>> {
                 let field_id = language.field_id_for_name(field_name).unwrap();
                 let required = value.get("required").unwrap().as_bool().unwrap();
                 let multiple = value.get("multiple").unwrap().as_bool().unwrap();
-                let field = Field::new(field_name.to_owned(), field_id, required, multiple);
+                let mut sorts = vec![];
+                if let Some(types) = value.get("types").and_then(Value::as_array) {
+                    collect_sorts(language, types, &subtypes, &mut sorts);
+                }
+                sorts.sort_unstable();
+                sorts.dedup();
+                let field = Field::new(field_name.to_owned(), field_id, required, multiple, sorts);
                 field_ids.push(field);
             }
         }
@@ -654,11 +686,37 @@ pub fn fields_for_nodes(language: &TSLanguage, types: &str) -> Vec<Vec<Field>> {
         .collect()
 }
 
+/// Resolves the `types` of a field in node-types.json to sort ids.
+///
+/// Supertypes, such as `expression`, have no sort of their own since they never
+/// appear in a tree, so they are replaced by their subtypes.
+fn collect_sorts(
+    language: &TSLanguage,
+    types: &[Value],
+    subtypes: &HashMap<&str, &Vec<Value>>,
+    sorts: &mut Vec<SortId>,
+) {
+    for node_type in types {
+        let Some(name) = node_type["type"].as_str() else {
+            continue;
+        };
+        if let Some(subtypes_of) = subtypes.get(name) {
+            collect_sorts(language, subtypes_of, subtypes, sorts);
+            continue;
+        }
+        let named = node_type["named"].as_bool().unwrap_or_default();
+        let sort = language.id_for_node_kind(name, named);
+        if sort != 0 {
+            sorts.push(sort);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{nodes_from_indices, snippet_nodes_from_index};
     use crate::{
-        language::{MarzanoLanguage, MarzanoParser},
+        language::{MarzanoLanguage, MarzanoParser, NodeTypes},
         tsx::Tsx,
     };
     use grit_util::{Language, Parser};
@@ -675,6 +733,22 @@ mod tests {
         assert!(node.is_some())
     }
 
+    #[test]
+    fn field_sorts_expand_supertypes() {
+        let lang = Tsx::new(None);
+        let ts_lang = lang.get_ts_language();
+        let call = ts_lang.id_for_node_kind("call_expression", true);
+        let function = lang.node_types()[usize::from(call)]
+            .iter()
+            .find(|field| field.name() == "function")
+            .unwrap();
+        // `function` holds an `expression`, which is only a supertype
+        let identifier = ts_lang.id_for_node_kind("identifier", true);
+        let member = ts_lang.id_for_node_kind("member_expression", true);
+        assert!(function.sorts().contains(&identifier));
+        assert!(function.sorts().contains(&member));
+    }
+
     #[test]
     fn snippet_to_nodes() {
         let snippet = "foo('bar')";
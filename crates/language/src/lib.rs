@@ -43,6 +43,74 @@ macro_rules! use_marzano_js_like_delegate {
         fn check_replacements(&self, n: NodeWithSource<'_>, replacements: &mut Vec<Replacement>) {
             jslike_check_replacements(n, replacements)
         }
+
+        fn supports_scope_analysis(&self) -> bool {
+            true
+        }
+
+        fn declaration_of<'a>(
+            &self,
+            identifier: &NodeWithSource<'a>,
+        ) -> Option<NodeWithSource<'a>> {
+            crate::scope::declaration_of(&crate::js_like::JsLikeScopeRules, identifier)
+        }
+
+        fn references_of<'a>(&self, declaration: &NodeWithSource<'a>) -> Vec<NodeWithSource<'a>> {
+            crate::scope::references_in_tree(&crate::js_like::JsLikeScopeRules, declaration)
+        }
+
+        fn supports_data_flow(&self) -> bool {
+            true
+        }
+
+        fn flow_path<'a>(
+            &self,
+            sink: &NodeWithSource<'a>,
+            is_role: &mut dyn FnMut(&NodeWithSource<'a>, grit_util::FlowRole) -> bool,
+        ) -> Option<Vec<NodeWithSource<'a>>> {
+            crate::data_flow::flow_path(&crate::js_like::JsLikeScopeRules, sink, is_role)
+        }
+
+        fn supports_control_flow(&self) -> bool {
+            true
+        }
+
+        fn control_flow_graph<'a>(
+            &self,
+            node: &NodeWithSource<'a>,
+        ) -> Option<grit_util::ControlFlowGraph<NodeWithSource<'a>>> {
+            crate::control_flow::control_flow_graph(&crate::js_like::JsLikeControlFlowRules, node)
+        }
+
+        fn is_function_definition(&self, node: &NodeWithSource) -> bool {
+            crate::control_flow::ControlFlowRules::is_function(
+                &crate::js_like::JsLikeControlFlowRules,
+                node,
+            )
+        }
+    };
+}
+
+macro_rules! use_marzano_js_like_scope_delegate {
+    () => {
+        fn declarations(&self, root: &NodeWithSource<'_>) -> Option<crate::scope::Declarations> {
+            Some(crate::scope::Declarations::collect(
+                &crate::js_like::JsLikeScopeRules,
+                root,
+            ))
+        }
+
+        fn references_in<'b>(
+            &self,
+            declarations: &crate::scope::Declarations,
+            declaration: &NodeWithSource<'b>,
+        ) -> Vec<NodeWithSource<'b>> {
+            crate::scope::references_of(
+                &crate::js_like::JsLikeScopeRules,
+                declarations,
+                declaration,
+            )
+        }
     };
 }
 
@@ -56,8 +124,10 @@ macro_rules! use_marzano_delegate {
     };
 }
 
+mod control_flow;
 pub mod csharp;
 pub mod css;
+mod data_flow;
 pub mod elixir;
 pub mod foreign_language;
 pub mod go;
@@ -80,6 +150,7 @@ pub mod php_only;
 pub mod python;
 pub mod ruby;
 pub mod rust;
+mod scope;
 pub mod solidity;
 pub mod sourcemap;
 pub mod sql;
@@ -89,3 +160,5 @@ pub mod tsx;
 pub mod typescript;
 pub mod vue;
 pub mod yaml;
+
+pub use scope::Declarations;
@@ -1,10 +1,16 @@
 use crate::{
+    control_flow::{self, statements_of, ControlFlowRules, Loop, Statement},
+    data_flow::{self, Assignment, FlowRules},
     language::{fields_for_nodes, Field, MarzanoLanguage, NodeTypes, SortId, TSLanguage, Tree},
     notebooks::MarzanoNotebookParser,
+    scope::{self, is_field, is_kind, nearest_scope, Declarations, ScopeRules},
+};
+use grit_util::{
+    traverse, Ast, AstNode, CodeRange, ControlFlowGraph, FlowRole, Language, Order, Parser,
+    Replacement,
 };
-use grit_util::{Ast, AstNode, CodeRange, Language, Parser, Replacement};
 use marzano_util::node_with_source::NodeWithSource;
-use std::sync::OnceLock;
+use std::{cell::RefCell, collections::HashMap, sync::OnceLock};
 
 static NODE_TYPES_STRING: &str =
     include_str!("../../../resources/node-types/python-node-types.json");
@@ -158,6 +164,352 @@ impl Language for Python {
     fn make_single_line_comment(&self, text: &str) -> String {
         format!("# {}\n", text)
     }
+
+    fn supports_scope_analysis(&self) -> bool {
+        true
+    }
+
+    fn declaration_of<'a>(&self, identifier: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>> {
+        scope::declaration_of(&PythonScopeRules::default(), identifier)
+    }
+
+    fn references_of<'a>(&self, declaration: &NodeWithSource<'a>) -> Vec<NodeWithSource<'a>> {
+        scope::references_in_tree(&PythonScopeRules::default(), declaration)
+    }
+
+    fn supports_data_flow(&self) -> bool {
+        true
+    }
+
+    fn flow_path<'a>(
+        &self,
+        sink: &NodeWithSource<'a>,
+        is_role: &mut dyn FnMut(&NodeWithSource<'a>, FlowRole) -> bool,
+    ) -> Option<Vec<NodeWithSource<'a>>> {
+        data_flow::flow_path(&PythonScopeRules::default(), sink, is_role)
+    }
+
+    fn supports_control_flow(&self) -> bool {
+        true
+    }
+
+    fn control_flow_graph<'a>(
+        &self,
+        node: &NodeWithSource<'a>,
+    ) -> Option<ControlFlowGraph<NodeWithSource<'a>>> {
+        control_flow::control_flow_graph(&PythonControlFlowRules, node)
+    }
+
+    fn is_function_definition(&self, node: &NodeWithSource) -> bool {
+        PythonControlFlowRules.is_function(node)
+    }
+}
+
+static SCOPE_NAMES: &[&str] = &[
+    "module",
+    "function_definition",
+    "lambda",
+    "class_definition",
+    "list_comprehension",
+    "set_comprehension",
+    "dictionary_comprehension",
+    "generator_expression",
+];
+
+/// The scopes which run as functions, shared by the data-flow and control-flow rules.
+static FUNCTION_SCOPE_NAMES: &[&str] = &["module", "function_definition", "lambda"];
+
+/// Scope rules for Python: any binding of a name makes it local to the
+/// enclosing function, unless it is declared `global` or `nonlocal` there.
+/// Names bound in a class body are not visible from its methods.
+#[derive(Default)]
+pub(crate) struct PythonScopeRules {
+    /// The names each function declares `global` or `nonlocal`, with the
+    /// field of the statement which declares them, by function node id.
+    redirects: RefCell<HashMap<usize, HashMap<String, &'static str>>>,
+}
+
+impl ScopeRules for PythonScopeRules {
+    fn is_identifier(&self, node: &NodeWithSource) -> bool {
+        if node.node.kind() != "identifier" {
+            return false;
+        }
+        let Some(parent) = node.parent() else {
+            return true;
+        };
+        match parent.node.kind().as_ref() {
+            "attribute" => !is_field(&parent, "attribute", node),
+            "keyword_argument" => !is_field(&parent, "name", node),
+            // Only the first name of `import a.b` is bound; module paths are not references
+            "dotted_name" => {
+                parent
+                    .parent()
+                    .is_some_and(|import| match import.node.kind().as_ref() {
+                        "import_statement" => parent.named_children().next().as_ref() == Some(node),
+                        "import_from_statement" => is_field(&import, "name", &parent),
+                        "aliased_import" => false,
+                        _ => true,
+                    })
+            }
+            _ => true,
+        }
+    }
+
+    fn is_scope(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, SCOPE_NAMES)
+    }
+
+    fn declaring_scope<'a>(&self, identifier: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>> {
+        let name = identifier.text().ok()?;
+        // Climb out of unpacking patterns to the node which binds them
+        let mut child = identifier.clone();
+        let mut parent = child.parent()?;
+        while is_unpacked(&parent, &child) {
+            child = parent;
+            parent = child.parent()?;
+        }
+
+        match parent.node.kind().as_ref() {
+            "assignment" | "augmented_assignment" | "for_statement"
+                if is_field(&parent, "left", &child) =>
+            {
+                self.binding_scope(&parent, &name)
+            }
+            "named_expression" if is_field(&parent, "name", &child) => {
+                self.binding_scope(&parent, &name)
+            }
+            "as_pattern" | "except_clause" | "aliased_import"
+                if is_field(&parent, "alias", &child) =>
+            {
+                self.binding_scope(&parent, &name)
+            }
+            "import_statement" | "import_from_statement" if is_field(&parent, "name", &child) => {
+                self.binding_scope(&parent, &name)
+            }
+            "function_definition" | "class_definition" if is_field(&parent, "name", &child) => {
+                self.binding_scope(&parent, &name)
+            }
+            "function_definition" if is_field(&parent, "parameters", &child) => {
+                Some(parent.clone())
+            }
+            "lambda_parameters" => parent.parent(),
+            "for_in_clause" if is_field(&parent, "left", &child) => parent.parent(),
+            _ => None,
+        }
+    }
+
+    fn is_visible_from_nested_scopes(&self, scope: &NodeWithSource) -> bool {
+        scope.node.kind() != "class_definition"
+    }
+}
+
+impl FlowRules for PythonScopeRules {
+    fn is_function(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, FUNCTION_SCOPE_NAMES)
+    }
+
+    fn is_block(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, &["module", "block"])
+    }
+
+    fn is_sequential(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, &["expression_statement", "parenthesized_expression"])
+    }
+
+    fn assignment<'a>(&self, node: &NodeWithSource<'a>) -> Option<Assignment<'a>> {
+        let (target, value, overwrites) = match node.node.kind().as_ref() {
+            "assignment" | "for_statement" | "for_in_clause" => (
+                node.child_by_field_name("left"),
+                node.child_by_field_name("right"),
+                true,
+            ),
+            "augmented_assignment" => (
+                node.child_by_field_name("left"),
+                node.child_by_field_name("right"),
+                false,
+            ),
+            "named_expression" => (
+                node.child_by_field_name("name"),
+                node.child_by_field_name("value"),
+                true,
+            ),
+            "as_pattern" => (
+                node.child_by_field_name("alias"),
+                node.named_children().next(),
+                true,
+            ),
+            _ => return None,
+        };
+        Some(Assignment {
+            target: target?,
+            value: value?,
+            overwrites,
+        })
+    }
+
+    fn is_opaque_target(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, &["attribute", "subscript"])
+    }
+}
+
+/// Control-flow rules for Python. Loops and `try` statements may have an
+/// `else` block, and `match` cases never fall through.
+pub(crate) struct PythonControlFlowRules;
+
+impl ControlFlowRules for PythonControlFlowRules {
+    fn is_function(&self, node: &NodeWithSource) -> bool {
+        is_kind(node, FUNCTION_SCOPE_NAMES)
+    }
+
+    fn function_body<'a>(&self, function: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>> {
+        if function.node.kind() == "module" {
+            Some(function.clone())
+        } else {
+            function.child_by_field_name("body")
+        }
+    }
+
+    fn statement<'a>(&self, node: &NodeWithSource<'a>) -> Statement<'a> {
+        python_statement(node).unwrap_or_else(|| Statement::Simple(node.clone()))
+    }
+}
+
+fn python_statement<'a>(node: &NodeWithSource<'a>) -> Option<Statement<'a>> {
+    let field = |name: &str| node.child_by_field_name(name);
+    let children_of_kind = |kinds: &[&str]| -> Vec<NodeWithSource<'a>> {
+        node.named_children()
+            .filter(|child| is_kind(child, kinds))
+            .collect()
+    };
+    let statement = match node.node.kind().as_ref() {
+        "module" | "block" => Statement::Block(statements_of(node)),
+        "else_clause" | "with_statement" => Statement::Block(field("body").into_iter().collect()),
+        "except_clause" | "except_group_clause" | "finally_clause" => {
+            Statement::Block(children_of_kind(&["block"]))
+        }
+        "case_clause" => Statement::Block(field("consequence").into_iter().collect()),
+        "if_statement" => Statement::If {
+            condition: field("condition")?,
+            consequence: field("consequence")?,
+            alternatives: node.children_by_field_name("alternative").collect(),
+        },
+        "elif_clause" => Statement::If {
+            condition: field("condition")?,
+            consequence: field("consequence")?,
+            alternatives: Vec::new(),
+        },
+        "for_statement" | "while_statement" => {
+            let is_while = node.node.kind() == "while_statement";
+            let header = field(if is_while { "condition" } else { "right" });
+            Statement::Loop(Loop {
+                initializers: Vec::new(),
+                infinite: is_while
+                    && header
+                        .as_ref()
+                        .is_some_and(|header| header.text().is_ok_and(|text| text == "True")),
+                header,
+                body: field("body")?,
+                updates: Vec::new(),
+                otherwise: field("alternative"),
+                checks_after_body: false,
+            })
+        }
+        "try_statement" => Statement::Try {
+            body: field("body")?,
+            handlers: children_of_kind(&["except_clause", "except_group_clause"]),
+            otherwise: children_of_kind(&["else_clause"]).pop(),
+            finalizer: children_of_kind(&["finally_clause"]).pop(),
+        },
+        "match_statement" => {
+            let cases: Vec<_> = field("body")?
+                .named_children()
+                .filter(|child| child.node.kind() == "case_clause")
+                .collect();
+            Statement::Switch {
+                subject: field("subject"),
+                exhaustive: cases.iter().any(|case| {
+                    case.child_by_field_name("guard").is_none()
+                        && case
+                            .child_by_field_name("pattern")
+                            .is_some_and(|pattern| pattern.text().is_ok_and(|text| text == "_"))
+                }),
+                cases,
+                falls_through: false,
+            }
+        }
+        "return_statement" => Statement::Return(node.clone()),
+        "raise_statement" => Statement::Throw(node.clone()),
+        "break_statement" => Statement::Break(node.clone()),
+        "continue_statement" => Statement::Continue(node.clone()),
+        _ => return None,
+    };
+    Some(statement)
+}
+
+/// Whether `child` is a binding target of the unpacking pattern `parent`.
+fn is_unpacked(parent: &NodeWithSource, child: &NodeWithSource) -> bool {
+    match parent.node.kind().as_ref() {
+        "pattern_list"
+        | "tuple_pattern"
+        | "list_pattern"
+        | "list_splat_pattern"
+        | "dictionary_splat_pattern"
+        | "as_pattern_target"
+        | "dotted_name" => true,
+        "default_parameter" | "typed_parameter" | "typed_default_parameter" => {
+            is_field(parent, "name", child)
+        }
+        _ => false,
+    }
+}
+
+impl PythonScopeRules {
+    /// Returns the scope a name bound at `node` belongs to, following `global`
+    /// and `nonlocal` statements in the enclosing function.
+    fn binding_scope<'a>(
+        &self,
+        node: &NodeWithSource<'a>,
+        name: &str,
+    ) -> Option<NodeWithSource<'a>> {
+        let scope = nearest_scope(self, node)?;
+        if scope.node.kind() != "function_definition" {
+            return Some(scope);
+        }
+        let redirect = self
+            .redirects
+            .borrow_mut()
+            .entry(scope.node.id())
+            .or_insert_with(|| self.redirects_of(&scope))
+            .get(name)
+            .copied();
+        match redirect {
+            Some("globals") => scope.ancestors().last(),
+            Some(_) => self.binding_scope(&scope, name),
+            None => Some(scope),
+        }
+    }
+
+    /// Collects the names the `global` and `nonlocal` statements of `function`
+    /// itself declare, with the field of the first statement declaring each.
+    fn redirects_of(&self, function: &NodeWithSource) -> HashMap<String, &'static str> {
+        let mut redirects = HashMap::new();
+        for statement in traverse(function.walk(), Order::Pre) {
+            let field = match statement.node.kind().as_ref() {
+                "global_statement" => "globals",
+                "nonlocal_statement" => "non_locals",
+                _ => continue,
+            };
+            if nearest_scope(self, &statement).as_ref() != Some(function) {
+                continue;
+            }
+            for declared in statement.children_by_field_name(field) {
+                if let Ok(name) = declared.text() {
+                    redirects.entry(name.into_owned()).or_insert(field);
+                }
+            }
+        }
+        redirects
+    }
 }
 
 impl<'a> MarzanoLanguage<'a> for Python {
@@ -187,6 +539,18 @@ impl<'a> MarzanoLanguage<'a> for Python {
         let root = snippet.tree.root_node();
         MarzanoLanguage::get_skip_padding_ranges(self, &root)
     }
+
+    fn declarations(&self, root: &NodeWithSource<'_>) -> Option<Declarations> {
+        Some(Declarations::collect(&PythonScopeRules::default(), root))
+    }
+
+    fn references_in<'b>(
+        &self,
+        declarations: &Declarations,
+        declaration: &NodeWithSource<'b>,
+    ) -> Vec<NodeWithSource<'b>> {
+        scope::references_of(&PythonScopeRules::default(), declarations, declaration)
+    }
 }
 
 #[cfg(test)]
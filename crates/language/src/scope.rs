@@ -0,0 +1,245 @@
+//! Lexical scope analysis, used to resolve identifiers to the declarations
+//! they refer to.
+//!
+//! The analysis is driven by a language's [`ScopeRules`], which tell which
+//! nodes are identifiers, which nodes introduce scopes, and in which scope an
+//! identifier in a declaring position declares its binding. An identifier
+//! resolves to the first declaration of its name in the innermost enclosing
+//! scope which declares that name.
+
+use grit_util::{traverse, AstNode, Order};
+use marzano_util::node_with_source::NodeWithSource;
+use std::collections::HashMap;
+
+pub(crate) trait ScopeRules {
+    /// Whether `node` is an identifier which declares or refers to a binding.
+    fn is_identifier(&self, node: &NodeWithSource) -> bool;
+
+    /// Whether `node` introduces a scope.
+    fn is_scope(&self, node: &NodeWithSource) -> bool;
+
+    /// Returns the scope `identifier` declares a binding in, or `None` if the
+    /// identifier is not in a declaring position.
+    fn declaring_scope<'a>(&self, identifier: &NodeWithSource<'a>) -> Option<NodeWithSource<'a>>;
+
+    /// Whether the bindings declared in `scope` are visible from the scopes
+    /// nested inside it, and not only from the scope itself.
+    #[allow(unused_variables)]
+    fn is_visible_from_nested_scopes(&self, scope: &NodeWithSource) -> bool {
+        true
+    }
+}
+
+pub(crate) fn declaration_of<'a>(
+    rules: &impl ScopeRules,
+    identifier: &NodeWithSource<'a>,
+) -> Option<NodeWithSource<'a>> {
+    if !rules.is_identifier(identifier) {
+        return None;
+    }
+    resolve(rules, identifier, |scope, name| {
+        find_declaration(rules, scope, name)
+    })
+}
+
+/// Returns every identifier in the tree of `declarations` which refers to the
+/// binding `declaration` declares.
+pub(crate) fn references_of<'a>(
+    rules: &impl ScopeRules,
+    declarations: &Declarations,
+    declaration: &NodeWithSource<'a>,
+) -> Vec<NodeWithSource<'a>> {
+    let Some(declaration) = declaration_of(rules, declaration) else {
+        return Vec::new();
+    };
+    let (Some(scope), Ok(name)) = (rules.declaring_scope(&declaration), declaration.text()) else {
+        return Vec::new();
+    };
+    traverse(scope.walk(), Order::Pre)
+        .filter(|node| rules.is_identifier(node) && node.text().is_ok_and(|text| text == name))
+        .filter(|node| declarations.resolve(rules, node) == Some(declaration.node.id()))
+        .collect()
+}
+
+/// Returns the references of `declaration`, collecting the declarations of its
+/// tree first.
+pub(crate) fn references_in_tree<'a>(
+    rules: &impl ScopeRules,
+    declaration: &NodeWithSource<'a>,
+) -> Vec<NodeWithSource<'a>> {
+    match declaration.ancestors().last() {
+        Some(root) => references_of(rules, &Declarations::collect(rules, &root), declaration),
+        None => Vec::new(),
+    }
+}
+
+/// Looks `identifier` up in each scope it can see, from the innermost out,
+/// returning the first declaration `lookup` finds.
+fn resolve<'a, T>(
+    rules: &impl ScopeRules,
+    identifier: &NodeWithSource<'a>,
+    mut lookup: impl FnMut(&NodeWithSource<'a>, &str) -> Option<T>,
+) -> Option<T> {
+    let name = identifier.text().ok()?;
+    let start = match rules.declaring_scope(identifier) {
+        Some(scope) => scope,
+        None => nearest_scope(rules, identifier)?,
+    };
+    start
+        .ancestors()
+        .filter(|node| rules.is_scope(node))
+        .enumerate()
+        .filter(|(depth, scope)| *depth == 0 || rules.is_visible_from_nested_scopes(scope))
+        .find_map(|(_, scope)| lookup(&scope, &name))
+}
+
+/// The first declaration of each name in every scope of a tree, collected in
+/// a single pass so identifiers can be resolved without walking their scopes
+/// again.
+///
+/// Scopes and declarations are recorded by node id, so the index only applies
+/// to the tree it was collected from.
+#[derive(Debug)]
+pub struct Declarations {
+    by_scope: HashMap<usize, HashMap<String, usize>>,
+}
+
+impl Declarations {
+    pub(crate) fn collect(rules: &impl ScopeRules, root: &NodeWithSource) -> Self {
+        let mut by_scope: HashMap<usize, HashMap<String, usize>> = HashMap::new();
+        for node in traverse(root.walk(), Order::Pre) {
+            if !rules.is_identifier(&node) {
+                continue;
+            }
+            let (Some(scope), Ok(name)) = (rules.declaring_scope(&node), node.text()) else {
+                continue;
+            };
+            by_scope
+                .entry(scope.node.id())
+                .or_default()
+                .entry(name.into_owned())
+                .or_insert(node.node.id());
+        }
+        Self { by_scope }
+    }
+
+    /// Resolves `identifier` like [`declaration_of`] does, to the id of its
+    /// declaration.
+    fn resolve(&self, rules: &impl ScopeRules, identifier: &NodeWithSource) -> Option<usize> {
+        resolve(rules, identifier, |scope, name| {
+            self.by_scope.get(&scope.node.id())?.get(name).copied()
+        })
+    }
+}
+
+/// Returns the first identifier declaring `name` in `scope` itself, in source
+/// order.
+fn find_declaration<'a>(
+    rules: &impl ScopeRules,
+    scope: &NodeWithSource<'a>,
+    name: &str,
+) -> Option<NodeWithSource<'a>> {
+    traverse(scope.walk(), Order::Pre).find(|node| {
+        rules.is_identifier(node)
+            && node.text().is_ok_and(|text| text == name)
+            && rules
+                .declaring_scope(node)
+                .is_some_and(|declared_in| declared_in == *scope)
+    })
+}
+
+/// Returns the innermost scope strictly enclosing `node`.
+pub(crate) fn nearest_scope<'a>(
+    rules: &impl ScopeRules,
+    node: &NodeWithSource<'a>,
+) -> Option<NodeWithSource<'a>> {
+    node.ancestors().skip(1).find(|node| rules.is_scope(node))
+}
+
+/// Whether `child` is stored in the `field` of `parent`.
+pub(crate) fn is_field(parent: &NodeWithSource, field: &str, child: &NodeWithSource) -> bool {
+    parent
+        .children_by_field_name(field)
+        .any(|field_child| field_child == *child)
+}
+
+pub(crate) fn is_kind(node: &NodeWithSource, kinds: &[&str]) -> bool {
+    kinds.contains(&node.node.kind().as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{javascript::JavaScript, language::MarzanoLanguage, python::Python};
+    use grit_util::{Ast, FileOrigin, Language};
+
+    fn identifiers_named<'a>(root: &NodeWithSource<'a>, name: &str) -> Vec<NodeWithSource<'a>> {
+        traverse(root.walk(), Order::Pre)
+            .filter(|node| node.node.kind() == "identifier" && node.text().unwrap() == name)
+            .collect()
+    }
+
+    #[test]
+    fn resolves_shadowed_javascript_bindings() {
+        let code = r#"const data = load();
+function process(data) {
+  return data.map((item) => item);
+}
+console.log(data);
+"#;
+        let lang = JavaScript::new(None);
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let data = identifiers_named(&root, "data");
+        assert_eq!(data.len(), 4);
+        assert_eq!(lang.declaration_of(&data[2]), Some(data[1].clone()));
+        assert_eq!(lang.declaration_of(&data[3]), Some(data[0].clone()));
+        assert_eq!(
+            lang.references_of(&data[0]),
+            vec![data[0].clone(), data[3].clone()]
+        );
+        assert_eq!(
+            lang.declaration_of(&identifiers_named(&root, "load")[0]),
+            None
+        );
+    }
+
+    #[test]
+    fn resolves_python_globals_and_class_scopes() {
+        let code = r#"count = 0
+
+def increment():
+    global count
+    count += 1
+
+class Counter:
+    count = 10
+
+    def read(self):
+        return count
+"#;
+        let lang = Python::new(None);
+        let tree = lang
+            .get_parser()
+            .parse_file(code, None, &mut vec![].into(), FileOrigin::Fresh)
+            .unwrap();
+        let root = tree.root_node();
+        let count = identifiers_named(&root, "count");
+        assert_eq!(count.len(), 5);
+        assert_eq!(lang.declaration_of(&count[2]), Some(count[0].clone()));
+        assert_eq!(lang.declaration_of(&count[3]), Some(count[3].clone()));
+        assert_eq!(lang.declaration_of(&count[4]), Some(count[0].clone()));
+        assert_eq!(
+            lang.references_of(&count[0]),
+            vec![
+                count[0].clone(),
+                count[1].clone(),
+                count[2].clone(),
+                count[4].clone()
+            ]
+        );
+    }
+}
@@ -20,6 +20,7 @@ use crate::{
     python::Python,
     ruby::Ruby,
     rust::Rust,
+    scope::Declarations,
     solidity::Solidity,
     sql::Sql,
     toml::Toml,
@@ -650,6 +651,61 @@ macro_rules! generate_target_language {
                     $(Self::$language(lang) => Language::make_single_line_comment(lang, text)),+
                 }
             }
+
+            fn supports_scope_analysis(&self) -> bool {
+                match self {
+                    $(Self::$language(lang) => Language::supports_scope_analysis(lang)),+
+                }
+            }
+
+            fn declaration_of<'a>(&self, identifier: &Self::Node<'a>) -> Option<Self::Node<'a>> {
+                match self {
+                    $(Self::$language(lang) => Language::declaration_of(lang, identifier)),+
+                }
+            }
+
+            fn references_of<'a>(&self, declaration: &Self::Node<'a>) -> Vec<Self::Node<'a>> {
+                match self {
+                    $(Self::$language(lang) => Language::references_of(lang, declaration)),+
+                }
+            }
+
+            fn supports_data_flow(&self) -> bool {
+                match self {
+                    $(Self::$language(lang) => Language::supports_data_flow(lang)),+
+                }
+            }
+
+            fn flow_path<'a>(
+                &self,
+                sink: &Self::Node<'a>,
+                is_role: &mut dyn FnMut(&Self::Node<'a>, grit_util::FlowRole) -> bool,
+            ) -> Option<Vec<Self::Node<'a>>> {
+                match self {
+                    $(Self::$language(lang) => Language::flow_path(lang, sink, is_role)),+
+                }
+            }
+
+            fn supports_control_flow(&self) -> bool {
+                match self {
+                    $(Self::$language(lang) => Language::supports_control_flow(lang)),+
+                }
+            }
+
+            fn control_flow_graph<'a>(
+                &self,
+                node: &Self::Node<'a>,
+            ) -> Option<grit_util::ControlFlowGraph<Self::Node<'a>>> {
+                match self {
+                    $(Self::$language(lang) => Language::control_flow_graph(lang, node)),+
+                }
+            }
+
+            fn is_function_definition(&self, node: &Self::Node<'_>) -> bool {
+                match self {
+                    $(Self::$language(lang) => Language::is_function_definition(lang, node)),+
+                }
+            }
         }
 
         impl NodeTypes for TargetLanguage {
@@ -712,6 +768,22 @@ macro_rules! generate_target_language {
                     $(Self::$language(lang) => MarzanoLanguage::get_equivalence_class(lang, sort, text)),+
                 }
             }
+
+            fn declarations(&self, root: &NodeWithSource<'_>) -> Option<Declarations> {
+                match self {
+                    $(Self::$language(lang) => MarzanoLanguage::declarations(lang, root)),+
+                }
+            }
+
+            fn references_in<'b>(
+                &self,
+                declarations: &Declarations,
+                declaration: &NodeWithSource<'b>,
+            ) -> Vec<NodeWithSource<'b>> {
+                match self {
+                    $(Self::$language(lang) => MarzanoLanguage::references_in(lang, declarations, declaration)),+
+                }
+            }
         }
 
         // when built to wasm the language must be initialized with a parser at least once
@@ -133,6 +133,8 @@ impl Language for Tsx {
 }
 
 impl<'a> MarzanoLanguage<'a> for Tsx {
+    use_marzano_js_like_scope_delegate!();
+
     fn get_ts_language(&self) -> &TSLanguage {
         self.language
     }
@@ -129,6 +129,8 @@ impl Language for TypeScript {
 }
 
 impl<'a> MarzanoLanguage<'a> for TypeScript {
+    use_marzano_js_like_scope_delegate!();
+
     fn get_ts_language(&self) -> &TSLanguage {
         self.language
     }
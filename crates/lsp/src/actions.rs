@@ -1,9 +1,10 @@
 use anyhow::{anyhow, Result};
 use marzano_core::{
-    api::{make_suppress_comment, MatchResult},
+    api::{make_suppress_comment, FixSafety, MatchResult},
     fs::extract_ranges,
 };
 use marzano_language::target_language::{PatternLanguage, TargetLanguage};
+use marzano_util::runtime::ExecutionContext;
 use tower_lsp::lsp_types::{
     self, CodeAction, CodeActionKind, CodeActionOrCommand, Command, DocumentChanges, OneOf,
     OptionalVersionedTextDocumentIdentifier, Range, TextDocumentEdit, TextDocumentItem, TextEdit,
@@ -21,11 +22,13 @@ pub fn get_code_actions(
     document: TextDocumentItem,
     check_info: CheckInfo,
     lsp_range: Range,
+    context: &ExecutionContext,
     #[cfg(feature = "caching")] cache: &Option<Arc<RwLock<Cache>>>,
 ) -> Result<Vec<CodeActionOrCommand>> {
     let pattern_results = check_file(
         &document,
         &check_info,
+        context,
         #[cfg(feature = "caching")]
         cache,
     )?;
@@ -43,7 +46,12 @@ pub fn get_code_actions(
                 for range in intersecting_ranges {
                     let local_name = &pattern.name();
                     if matches!(result, MatchResult::Rewrite(_)) {
-                        let title = format!("Apply {}", local_name);
+                        let safety = pattern.fix_safety();
+                        let title = if safety == FixSafety::Safe {
+                            format!("Apply {}", local_name)
+                        } else {
+                            format!("Apply {} ({})", local_name, safety)
+                        };
                         let body = &pattern.body;
                         let language = &pattern.language;
                         let command = Command::new(
@@ -60,10 +68,38 @@ pub fn get_code_actions(
                             title,
                             kind: Some(CodeActionKind::QUICKFIX),
                             command: Some(command),
+                            // Only safe fixes may be applied automatically, such as on save
+                            is_preferred: Some(safety == FixSafety::Safe),
                             ..Default::default()
                         };
                         code_actions.push(CodeActionOrCommand::CodeAction(apply_action));
                     }
+                    if matches!(result, MatchResult::Match(_)) {
+                        for fix in pattern.alternative_fixes() {
+                            let title = fix.label.clone();
+                            let language = &pattern.language;
+                            let command = Command::new(
+                                title.clone(),
+                                LspCommand::ApplyResult.to_string(),
+                                Some(vec![
+                                    serde_json::Value::String(document.uri.to_string()),
+                                    serde_json::Value::String(fix.body.to_string()),
+                                    serde_json::Value::String(language.to_string()),
+                                    serde_json::to_value(range).unwrap(),
+                                ]),
+                            );
+                            let fix_action = CodeAction {
+                                title,
+                                kind: Some(CodeActionKind::QUICKFIX),
+                                command: Some(command),
+                                is_preferred: Some(
+                                    fix.preferred && pattern.fix_safety() == FixSafety::Safe,
+                                ),
+                                ..Default::default()
+                            };
+                            code_actions.push(CodeActionOrCommand::CodeAction(fix_action));
+                        }
+                    }
                     let suppress_action = make_suppress_action(&document, &range, local_name)?;
                     code_actions.push(CodeActionOrCommand::CodeAction(suppress_action));
                 }
@@ -12,11 +12,17 @@ use tower_lsp::{
 };
 
 use crate::{
+    executor::IntenseExecutor,
     patterns::get_grit_files_from_uri,
     util::{get_ai_built_in_functions_for_feature, rewrite_as_edit, uri_to_file_path},
 };
 
-pub async fn apply_named_pattern(document: &TextDocumentItem, pattern_name: &str, client: &Client) {
+pub async fn apply_named_pattern(
+    document: &TextDocumentItem,
+    pattern_name: &str,
+    client: &Client,
+    executor: &IntenseExecutor,
+) {
     let grit_files: PatternsDirectory = get_grit_files_from_uri(document.uri.as_ref(), true).await;
     let presumptive_grit_file = grit_files.get(format!("{}.grit", pattern_name).as_str());
     let lang = match presumptive_grit_file {
@@ -31,6 +37,7 @@ pub async fn apply_named_pattern(document: &TextDocumentItem, pattern_name: &str
         grit_files,
         lang,
         client,
+        executor,
         Some(pattern_name.to_string()),
         None,
     )
@@ -43,6 +50,7 @@ pub async fn apply_pattern_body(
     grit_files: PatternsDirectory,
     lang: PatternLanguage,
     client: &Client,
+    executor: &IntenseExecutor,
     name: Option<String>,
     range: Option<Range>,
 ) {
@@ -54,7 +62,6 @@ pub async fn apply_pattern_body(
         }
     };
 
-    let context = ExecutionContext::default();
     let pattern_libs = match grit_files.get_language_directory_or_default(Some(lang)) {
         Ok(lib) => lib,
         Err(e) => {
@@ -84,11 +91,21 @@ pub async fn apply_pattern_body(
         }
     };
 
-    let file_content = &document.text;
-    let execution_result = problem.execute_file(
-        &RichFile::new(file_path_string, file_content.to_owned()),
-        &context,
-    );
+    let file = RichFile::new(file_path_string, document.text.to_owned());
+    // Dropping the task, as happens when the request is cancelled, stops the execution
+    let execution_result = match executor
+        .spawn_cancellable(move |cancellation| {
+            let context = ExecutionContext::default().with_cancellation(cancellation);
+            problem.execute_file(&file, &context)
+        })
+        .await
+    {
+        Ok(results) => results,
+        Err(e) => {
+            client.show_message(MessageType::ERROR, e.to_string()).await;
+            return;
+        }
+    };
     let mut text_edits = HashMap::new();
     for r in execution_result {
         if let MatchResult::Rewrite(rewrite) = r {
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use grit_util::error::GritPatternError;
 use std::collections::{BTreeMap, HashMap};
 
 #[cfg(feature = "caching")]
@@ -60,9 +61,9 @@ pub async fn get_check_info(document: &TextDocumentItem) -> Result<Option<CheckI
 pub fn check_file(
     document: &TextDocumentItem,
     check_info: &CheckInfo,
+    context: &ExecutionContext,
     #[cfg(feature = "caching")] cache: &Option<Arc<RwLock<Cache>>>,
 ) -> Result<Vec<(ResolvedGritDefinition, Vec<MatchResult>)>> {
-    let context = ExecutionContext::default();
     let file_path = uri_to_file_path(document.uri.as_ref())?;
     let file_content = &document.text;
     #[cfg(feature = "caching")]
@@ -108,8 +109,12 @@ pub fn check_file(
                 file_path.to_string_lossy().to_string(),
                 file_content.to_owned(),
             ),
-            &context,
+            context,
         );
+        // Results for only some of the patterns would be misleading
+        if context.is_cancelled() {
+            return Err(GritPatternError::Cancelled.into());
+        }
         #[cfg(feature = "caching")]
         if let Some(cache) = &cache {
             if execution_result.is_empty() {
@@ -128,6 +133,7 @@ pub async fn fix_file(document: &TextDocumentItem, client: &Client) -> Result<bo
         Some(info) => check_file(
             document,
             &info,
+            &ExecutionContext::default(),
             #[cfg(feature = "caching")]
             &None,
         )?
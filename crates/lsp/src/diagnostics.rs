@@ -5,9 +5,11 @@ use std::sync::{Arc, RwLock};
 use anyhow::Result;
 #[cfg(feature = "caching")]
 use grit_cache::cache::Cache;
-use marzano_core::{api::EnforcementLevel, fs::extract_ranges};
+use marzano_core::{api::EnforcementLevel, fs::extract_ranges, lint::lint_pattern};
 
-use marzano_gritmodule::fetcher::ModuleRepo;
+use marzano_gritmodule::{fetcher::ModuleRepo, patterns_directory::PatternsDirectory};
+use marzano_language::target_language::{PatternLanguage, TargetLanguage};
+use marzano_util::runtime::ExecutionContext;
 use tower_lsp::lsp_types::{CodeDescription, Diagnostic, DiagnosticSeverity, TextDocumentItem};
 
 use crate::{
@@ -20,11 +22,13 @@ pub fn get_diagnostics(
     check_info: CheckInfo,
     local_repo: &ModuleRepo,
     local_path: &Path,
+    context: &ExecutionContext,
     #[cfg(feature = "caching")] cache: &Option<Arc<RwLock<Cache>>>,
 ) -> Result<Vec<Diagnostic>> {
     let pattern_results = check_file(
         &document,
         &check_info,
+        context,
         #[cfg(feature = "caching")]
         cache,
     )?;
@@ -43,8 +47,8 @@ pub fn get_diagnostics(
                     range: convert_grit_range_to_lsp_range(&range),
                     severity,
                     message: pattern
-                        .description()
-                        .map(|v| v.to_owned())
+                        .message(&result, &range)
+                        .or_else(|| pattern.description().map(|v| v.to_owned()))
                         .unwrap_or(format!("Matches pattern {}", pattern.local_name)),
                     source: Some("grit".into()),
                     code: Some(tower_lsp::lsp_types::NumberOrString::String(
@@ -62,3 +66,39 @@ pub fn get_diagnostics(
 
     Ok(diagnostics)
 }
+
+/// Lints a GritQL document for mistakes which compile, but are unlikely to be intended.
+pub fn get_lint_diagnostics(
+    document: TextDocumentItem,
+    grit_files: PatternsDirectory,
+) -> Result<Vec<Diagnostic>> {
+    let lang = PatternLanguage::get_language(&document.text);
+    let Ok(target_lang) = TargetLanguage::try_from(lang.unwrap_or_default()) else {
+        return Ok(vec![]);
+    };
+    let pattern_libs = grit_files.get_language_directory_or_default(lang)?;
+    // The document is often mid-edit, so compilation errors are left to the editor
+    let Ok(logs) = lint_pattern(&document.text, &pattern_libs, target_lang, None) else {
+        return Ok(vec![]);
+    };
+    let diagnostics = logs
+        .logs()
+        .into_iter()
+        .filter_map(|log| {
+            let range = log.range?;
+            let message = log
+                .message
+                .strip_prefix("Warning: ")
+                .unwrap_or(&log.message)
+                .to_string();
+            Some(Diagnostic {
+                range: convert_grit_range_to_lsp_range(&range),
+                severity: Some(DiagnosticSeverity::WARNING),
+                message,
+                source: Some("grit".into()),
+                ..Default::default()
+            })
+        })
+        .collect();
+    Ok(diagnostics)
+}
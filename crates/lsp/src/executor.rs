@@ -11,7 +11,14 @@
 ///
 /// The main purpose of this wrapper is to aggregate all of the CPU intensive work into one place.
 /// This makes it easier to add a dedicated thread pool in the future.
-use tokio::task::JoinHandle;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use grit_util::CancellationToken;
+use tokio::task::{JoinError, JoinHandle};
 
 #[derive(Debug)]
 pub struct IntenseExecutor {}
@@ -30,4 +37,53 @@ impl IntenseExecutor {
     {
         tokio::task::spawn_blocking(f)
     }
+
+    /// Spawn a new task which is handed a cancellation token.
+    ///
+    /// The token is cancelled when the returned task is dropped before it completes,
+    /// which is what happens when tower-lsp receives a `$/cancelRequest` for the request
+    /// awaiting it.
+    pub fn spawn_cancellable<F, R>(&self, f: F) -> CancellableTask<R>
+    where
+        F: FnOnce(CancellationToken) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let cancellation = CancellationToken::new();
+        let task_cancellation = cancellation.clone();
+        CancellableTask {
+            handle: self.spawn(move || f(task_cancellation)),
+            guard: CancelOnDrop(cancellation),
+        }
+    }
+}
+
+/// Cancels a token when dropped.
+#[derive(Debug)]
+pub struct CancelOnDrop(pub CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+#[derive(Debug)]
+pub struct CancellableTask<R> {
+    handle: JoinHandle<R>,
+    guard: CancelOnDrop,
+}
+
+impl<R> CancellableTask<R> {
+    /// The token handed to the task, which can also be used to cancel it explicitly.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.guard.0
+    }
+}
+
+impl<R> Future for CancellableTask<R> {
+    type Output = Result<R, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.handle).poll(cx)
+    }
 }
@@ -15,13 +15,16 @@ use crate::{
 pub async fn search_query(
     documents: Vec<TextDocumentItem>,
     query: String,
+    context: ExecutionContext,
 ) -> (Vec<Error>, HashMap<String, Vec<MatchResult>>) {
-    let context = ExecutionContext::default();
     let mut results = HashMap::new();
     let mut errors = Vec::new();
 
     // iterate through the document map
     for document in documents.iter() {
+        if context.is_cancelled() {
+            break;
+        }
         let file_path = match uri_to_file_path(document.uri.as_ref()) {
             Ok(path) => path,
             Err(e) => {
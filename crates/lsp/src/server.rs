@@ -1,8 +1,13 @@
 use anyhow::bail;
 use anyhow::Context;
 
+use dashmap::DashMap;
+use grit_util::error::GritPatternError;
+use grit_util::CancellationToken;
+
 use marzano_gritmodule::parser::extract_relative_path;
 use marzano_language::target_language::PatternLanguage;
+use marzano_util::runtime::ExecutionContext;
 use serde_json::Value;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
@@ -20,9 +25,9 @@ use crate::commands::{
     ShowPatternSelectorParams,
 };
 use crate::definition::get_identifier;
-use crate::diagnostics::get_diagnostics;
+use crate::diagnostics::{get_diagnostics, get_lint_diagnostics};
 use crate::documents::run_doc_manager;
-use crate::executor::IntenseExecutor;
+use crate::executor::{CancelOnDrop, IntenseExecutor};
 use crate::language::language_id_to_pattern_language;
 use crate::manager::GritServerManager;
 use crate::patterns::{get_grit_files_from_uri, resolve_from_uri};
@@ -45,6 +50,8 @@ struct GritServer {
     client: Client,
     manager: GritServerManager,
     executor: IntenseExecutor,
+    /// Cancels the diagnostics still being computed for a document once it changes again
+    pending_checks: DashMap<String, CancellationToken>,
     #[cfg(feature = "project_diagnostics")]
     watched_files: DashSet<String>,
 }
@@ -85,6 +92,7 @@ impl GritServer {
             grit_files,
             language,
             &self.client,
+            &self.executor,
             None,
             Some(range),
         )
@@ -94,9 +102,13 @@ impl GritServer {
 
     async fn compute_search(&self, query: String) -> anyhow::Result<()> {
         let documents = self.manager.must_get_documents(&self.client).await?;
+        // The search runs as part of this request, so it is cancelled if the request is
+        let cancellation = CancellationToken::new();
+        let _cancel_on_drop = CancelOnDrop(cancellation.clone());
+        let context = ExecutionContext::default().with_cancellation(cancellation);
         let (errors, results) = self
             .executor
-            .spawn(move || search_query(documents, query))
+            .spawn(move || search_query(documents, query, context))
             .await?
             .await;
         for error in errors {
@@ -142,7 +154,10 @@ impl GritServer {
 
         let actions = self
             .executor
-            .spawn(move || get_code_actions(document, check_info, params.range))
+            .spawn_cancellable(move |cancellation| {
+                let context = ExecutionContext::default().with_cancellation(cancellation);
+                get_code_actions(document, check_info, params.range, &context)
+            })
             .await??;
 
         Ok(Some(actions))
@@ -158,6 +173,18 @@ impl GritServer {
                 .await;
             return Ok(());
         }
+        if params.language_id == "grit" {
+            let grit_files = get_grit_files_from_uri(params.uri.as_ref(), false).await;
+            let document = params.clone();
+            let diagnostics = self
+                .executor
+                .spawn(move || get_lint_diagnostics(document, grit_files))
+                .await??;
+            self.client
+                .publish_diagnostics(params.uri.to_owned(), diagnostics, None)
+                .await;
+            return Ok(());
+        }
         let check_info = match get_check_info(params).await? {
             Some(info) => info,
             None => return Ok(()),
@@ -185,19 +212,32 @@ impl GritServer {
             return Ok(());
         };
 
-        let diagnostics = self
-            .executor
-            .spawn(move || {
-                get_diagnostics(
-                    doc_clone,
-                    check_clone,
-                    &our_repo,
-                    &our_path,
-                    #[cfg(feature = "caching")]
-                    &None,
-                )
-            })
-            .await??;
+        let task = self.executor.spawn_cancellable(move |cancellation| {
+            let context = ExecutionContext::default().with_cancellation(cancellation);
+            get_diagnostics(
+                doc_clone,
+                check_clone,
+                &our_repo,
+                &our_path,
+                &context,
+                #[cfg(feature = "caching")]
+                &None,
+            )
+        });
+        if let Some(superseded) = self
+            .pending_checks
+            .insert(params.uri.to_string(), task.cancellation().clone())
+        {
+            superseded.cancel();
+        }
+        let diagnostics = match task.await? {
+            Ok(diagnostics) => diagnostics,
+            // A newer change is being checked instead
+            Err(e) if matches!(e.downcast_ref(), Some(GritPatternError::Cancelled)) => {
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
 
         self.client
             .log_message(
@@ -496,6 +536,9 @@ impl LanguageServer for GritServer {
     #[instrument(skip(self, params), fields(path = params.text_document.uri.to_string()))]
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         let uri_string = params.text_document.uri.to_string();
+        if let Some((_, pending)) = self.pending_checks.remove(&uri_string) {
+            pending.cancel();
+        }
         let _ = self.manager.drop_document(uri_string).await;
     }
 
@@ -565,7 +608,7 @@ impl LanguageServer for GritServer {
                         }
                     };
 
-                    apply_named_pattern(&document, &pattern, &self.client).await;
+                    apply_named_pattern(&document, &pattern, &self.client, &self.executor).await;
                 }
                 LspCommand::ApplyPattern => {
                     let (Some(body), Some(uri)) = (args.pop(), args.pop()) else {
@@ -588,6 +631,7 @@ impl LanguageServer for GritServer {
                         grit_files,
                         lang,
                         &self.client,
+                        &self.executor,
                         None,
                         None,
                     )
@@ -677,6 +721,7 @@ pub async fn start_language_server() -> anyhow::Result<()> {
         client,
         manager: GritServerManager::new(docs),
         executor: IntenseExecutor::new(),
+        pending_checks: DashMap::new(),
         #[cfg(feature = "project_diagnostics")]
         watched_files: DashSet::new(),
     });
@@ -709,6 +754,7 @@ mod test {
             client,
             manager: GritServerManager::new(docs),
             executor: IntenseExecutor::new(),
+            pending_checks: DashMap::new(),
             #[cfg(feature = "project_diagnostics")]
             watched_files: DashSet::new(),
         });
@@ -174,25 +174,37 @@ pub async fn maybe_test_pattern(
 
     // Report and test each sample
     for (index, sample) in samples.iter().enumerate() {
-        let (offset_position, offset_bytes) = match sample.input_range {
-            Some(range) => (range.start, range.start_byte),
-            None => (Position::first(), 0),
+        // Multi-file samples have a separate code block for each input file
+        let sample_offset = |path: &str| {
+            let range = sample
+                .input_files
+                .iter()
+                .find(|f| f.path == path)
+                .and_then(|f| f.range)
+                .or(sample.input_range);
+            match range {
+                Some(range) => (range.start, range.start_byte),
+                None => (Position::first(), 0),
+            }
         };
         let runtime = ExecutionContext::default();
         let outcome = test_pattern_sample(&compiled, sample, runtime);
         for mut result in outcome.matches {
             match result {
                 MatchResult::Match(ref mut m) => {
+                    let (offset_position, offset_bytes) = sample_offset(&m.source_file);
                     m.ranges.iter_mut().for_each(|r| {
                         r.add(offset_position, offset_bytes);
                     });
                 }
                 MatchResult::Rewrite(ref mut m) => {
+                    let (offset_position, offset_bytes) = sample_offset(&m.original.source_file);
                     m.original.ranges.iter_mut().for_each(|r| {
                         r.add(offset_position, offset_bytes);
                     });
                 }
                 MatchResult::RemoveFile(ref mut m) => {
+                    let (offset_position, offset_bytes) = sample_offset(&m.original.source_file);
                     m.original.ranges.iter_mut().for_each(|r| {
                         r.add(offset_position, offset_bytes);
                     });
@@ -8,10 +8,15 @@ use std::{
 use clap::ValueEnum;
 use colored::Colorize;
 use dialoguer::Input;
+use grit_util::ByteRange;
 use indicatif::ProgressBar;
 use log::info;
 use marzano_core::{
-    api::{derive_log_level, is_match, AnalysisLog, AnalysisLogLevel, MatchResult},
+    api::{
+        derive_log_level, is_match, make_suppress_comment, AnalysisLog, AnalysisLogLevel, FixEdit,
+        MatchResult, Rewrite,
+    },
+    fixes::apply_fix_edits,
     fs::apply_rewrite,
 };
 use marzano_language::target_language::TargetLanguage;
@@ -25,6 +30,8 @@ pub struct ApplyDetails {
     pub matched: i32,
     pub rewritten: i32,
     pub named_pattern: Option<String>,
+    /// The label of the alternative fix to apply to every match which offers it
+    pub fix_label: Option<String>,
 }
 
 pub trait Messager: Send + Sync {
@@ -54,6 +61,11 @@ pub trait Messager: Send + Sync {
         }
     }
 
+    /// Formats the code affected by a result, once it has been applied
+    fn format_rewrite(&mut self, result: MatchResult) -> anyhow::Result<()> {
+        format_result(result)
+    }
+
     /// This is the main entrypoint for handling a group of results
     /// In interactive mode, it is responsible for asking the user what to do with each result
     ///
@@ -147,7 +159,22 @@ pub trait Messager: Send + Sync {
 
             self.emit(&r)?;
 
-            if !dry_run {
+            if !dry_run && (*interactive || details.fix_label.is_some()) {
+                if let MatchResult::Match(m) = &r {
+                    if !m.fixes.is_empty() {
+                        if !self.apply_alternative_fixes(
+                            &r,
+                            details,
+                            should_format,
+                            interactive,
+                            pg,
+                            language,
+                        )? {
+                            return Ok(false);
+                        }
+                        continue;
+                    }
+                }
                 if is_match(&r) {
                     let file_name = r
                         .file_name()
@@ -233,13 +260,125 @@ pub trait Messager: Send + Sync {
                 }
                 self.apply_rewrite(&r)?;
                 if should_format {
-                    format_result(r)?;
+                    self.format_rewrite(r)?;
                 }
             }
         }
         Ok(true)
     }
 
+    /// Applies the alternative fixes chosen for the ranges of a match, either with `--fix-label`
+    /// or interactively. Ranges without a chosen fix are left as they are.
+    ///
+    /// Returns true if the process should continue, false if it should stop
+    fn apply_alternative_fixes(
+        &mut self,
+        result: &MatchResult,
+        details: &mut ApplyDetails,
+        should_format: bool,
+        interactive: &mut bool,
+        pg: Option<&ProgressBar>,
+        language: &TargetLanguage,
+    ) -> anyhow::Result<bool> {
+        let MatchResult::Match(m) = result else {
+            return Ok(true);
+        };
+        let content = m
+            .content
+            .as_deref()
+            .ok_or_else(|| anyhow::Error::msg("File content is missing"))?;
+        let mut edits: Vec<FixEdit> = Vec::new();
+        let mut should_continue = true;
+        for range in &m.ranges {
+            let fixes: Vec<_> = m.fixes.iter().filter(|fix| &fix.range == range).collect();
+            if fixes.is_empty() {
+                continue;
+            }
+            let chosen = if let Some(label) = &details.fix_label {
+                fixes.into_iter().find(|fix| &fix.label == label)
+            } else if *interactive {
+                let location = format!(
+                    "{}:{}:{}",
+                    m.source_file, range.start.line, range.start.column
+                );
+                if let Some(pg) = pg {
+                    pg.set_prefix("Found fixes in")
+                } else {
+                    info!("{}", format!("Found fixes in {location}").dimmed().bold());
+                }
+                for (i, fix) in fixes.iter().enumerate() {
+                    let preferred = if fix.preferred { " (preferred)" } else { "" };
+                    info!("  {}) {}{}", i + 1, fix.label, preferred.dimmed());
+                }
+                let actions = format!("[1-{},(n)o,(s)uppress,(q)uit]", fixes.len());
+                let actions_bold = actions.bold().blue();
+                let selection = Input::<String>::new()
+                    .with_prompt(format!("Apply a fix {actions_bold}"))
+                    .validate_with(|input: &String| -> Result<(), String> {
+                        let input = input.trim().to_lowercase();
+                        let is_fix = input
+                            .parse::<usize>()
+                            .is_ok_and(|i| (1..=fixes.len()).contains(&i));
+                        if is_fix || ["n", "s", "q"].contains(&input.as_str()) {
+                            Ok(())
+                        } else {
+                            Err(format!("Not a valid choice in {actions:}"))
+                        }
+                    })
+                    .interact_text()?;
+                if let Some(pg) = pg {
+                    pg.set_prefix("Analyzing")
+                }
+                match selection.trim().to_lowercase().as_str() {
+                    "n" => {
+                        self.track_reject(result)?;
+                        None
+                    }
+                    "s" => {
+                        self.track_supress(result)?;
+                        let start = range.start_byte as usize;
+                        edits.push(FixEdit {
+                            range: ByteRange::new(start, start),
+                            replacement: make_suppress_comment(
+                                details.named_pattern.as_deref(),
+                                language,
+                            ),
+                        });
+                        None
+                    }
+                    "q" => {
+                        self.track_reject(result)?;
+                        *interactive = false;
+                        should_continue = false;
+                        break;
+                    }
+                    choice => choice
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| i.checked_sub(1))
+                        .and_then(|i| fixes.get(i).copied()),
+                }
+            } else {
+                None
+            };
+            if let Some(fix) = chosen {
+                self.track_accept(result)?;
+                edits.extend(fix.edits.iter().cloned());
+            }
+        }
+        // Fixes chosen before quitting are still applied
+        if !edits.is_empty() {
+            details.rewritten += 1;
+            let fixed = apply_fix_edits(content, &edits)?;
+            let rewrite = MatchResult::Rewrite(Rewrite::for_file(&m.source_file, content, &fixed));
+            self.apply_rewrite(&rewrite)?;
+            if should_format {
+                self.format_rewrite(rewrite)?;
+            }
+        }
+        Ok(should_continue)
+    }
+
     // Write a message to the output
     fn raw_emit(&mut self, message: &MatchResult) -> anyhow::Result<()>;
 
@@ -3,6 +3,7 @@ pub mod format;
 mod logs;
 pub mod output_mode;
 pub mod testing;
+pub mod transaction;
 pub mod workflows;
 
 pub use logs::LogMessage;
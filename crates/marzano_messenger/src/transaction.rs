@@ -0,0 +1,117 @@
+use anyhow::Result;
+use marzano_core::{
+    api::{AnalysisLog, MatchResult},
+    transaction::Transaction,
+};
+use std::path::Path;
+
+use crate::{
+    emit::{Messager, VisibilityLevels},
+    format::format_result,
+    workflows::PackagedWorkflowOutcome,
+    SimpleLogMessage,
+};
+
+/// Wraps a messenger so the results it applies are staged in a [`Transaction`]
+/// instead of being written to disk right away.
+///
+/// Formatting is deferred until the transaction is committed, since it works
+/// on the files on disk.
+pub struct TransactionalMessenger<M> {
+    inner: M,
+    transaction: Transaction,
+    to_format: Vec<MatchResult>,
+}
+
+impl<M: Messager> TransactionalMessenger<M> {
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            transaction: Transaction::new(),
+            to_format: Vec::new(),
+        }
+    }
+
+    /// Writes the staged changes to disk, recording a journal in `journal_dir`
+    /// so they can be undone, and formats them.
+    ///
+    /// Returns the wrapped messenger, along with the number of changed files.
+    pub fn commit(mut self, journal_dir: &Path) -> (M, Result<usize>) {
+        let committed = self.transaction.commit(journal_dir);
+        if committed.is_ok() {
+            for result in self.to_format {
+                if let Err(e) = format_result(result) {
+                    let log = AnalysisLog::floating_error(format!("Failed to format file: {}", e));
+                    let _ = self.inner.emit(&MatchResult::AnalysisLog(log));
+                }
+            }
+        }
+        (self.inner, committed)
+    }
+
+    /// Drops the staged changes, leaving the files on disk untouched.
+    pub fn discard(self) -> M {
+        self.inner
+    }
+}
+
+impl<M: Messager> Messager for TransactionalMessenger<M> {
+    fn get_min_level(&self) -> VisibilityLevels {
+        self.inner.get_min_level()
+    }
+
+    fn apply_rewrite(&mut self, result: &MatchResult) -> Result<()> {
+        if let Err(e) = self.transaction.stage(result) {
+            let err_string = format!("Failed to apply rewrite: {}", e);
+            let err_log = if let Some(file_name) = result.file_name() {
+                AnalysisLog::new_error(err_string, file_name)
+            } else {
+                AnalysisLog::floating_error(err_string)
+            };
+            self.emit(&MatchResult::AnalysisLog(err_log))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn format_rewrite(&mut self, result: MatchResult) -> Result<()> {
+        self.to_format.push(result);
+        Ok(())
+    }
+
+    fn raw_emit(&mut self, message: &MatchResult) -> Result<()> {
+        self.inner.raw_emit(message)
+    }
+
+    fn emit_log(&mut self, log: &SimpleLogMessage) -> Result<()> {
+        self.inner.emit_log(log)
+    }
+
+    fn emit_estimate(&mut self, count: usize) -> Result<()> {
+        self.inner.emit_estimate(count)
+    }
+
+    fn start_workflow(&mut self) -> Result<()> {
+        self.inner.start_workflow()
+    }
+
+    async fn finish_workflow(&mut self, outcome: &PackagedWorkflowOutcome) -> Result<()> {
+        self.inner.finish_workflow(outcome).await
+    }
+
+    fn get_workflow_status(&mut self) -> Result<Option<&PackagedWorkflowOutcome>> {
+        self.inner.get_workflow_status()
+    }
+
+    fn track_accept(&mut self, accepted: &MatchResult) -> Result<()> {
+        self.inner.track_accept(accepted)
+    }
+
+    fn track_reject(&mut self, rejected: &MatchResult) -> Result<()> {
+        self.inner.track_reject(rejected)
+    }
+
+    fn track_supress(&mut self, supressed: &MatchResult) -> Result<()> {
+        self.inner.track_supress(supressed)
+    }
+}
@@ -0,0 +1,134 @@
+mod results;
+
+use marzano_core::{
+    pattern_compiler::{src_to_problem_libs, CompilationResult},
+    problem::Problem,
+};
+use marzano_language::target_language::{PatternLanguage, TargetLanguage};
+use marzano_util::{
+    rich_path::{RichFile, RichPath},
+    runtime::ExecutionContext,
+};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*,
+};
+use results::{
+    results_to_py, AnalysisLog, CreateFile, Match, Range, RemoveFile, Rewrite, Variable,
+};
+use std::{collections::BTreeMap, path::PathBuf};
+
+create_exception!(
+    gritql_native,
+    GritError,
+    PyException,
+    "Raised when a pattern cannot be compiled or its results cannot be read."
+);
+
+pub(crate) fn to_py_err(e: anyhow::Error) -> PyErr {
+    match e.downcast::<grit_util::AnalysisLog>() {
+        Ok(log) => GritError::new_err(log.message),
+        Err(e) => GritError::new_err(format!("{:#}", e)),
+    }
+}
+
+/// A compiled pattern, which can be run any number of times without being
+/// compiled again.
+#[pyclass(frozen, module = "gritql_native")]
+struct Pattern {
+    problem: Problem,
+    warnings: Vec<AnalysisLog>,
+}
+
+#[pymethods]
+impl Pattern {
+    /// Warnings raised while compiling the pattern.
+    #[getter]
+    fn warnings(&self) -> Vec<AnalysisLog> {
+        self.warnings.clone()
+    }
+
+    /// Runs the pattern on in-memory files, given as `(path, content)` pairs.
+    ///
+    /// Nothing is written to disk, rewrites are only returned.
+    fn execute_files(
+        &self,
+        py: Python<'_>,
+        files: Vec<(String, String)>,
+    ) -> PyResult<Vec<PyObject>> {
+        let files = files
+            .into_iter()
+            .map(|(path, content)| RichFile::new(path, content))
+            .collect();
+        let results = py.allow_threads(|| {
+            self.problem
+                .execute_files(files, &ExecutionContext::default())
+        });
+        results_to_py(py, results)
+    }
+
+    /// Runs the pattern on the files at `paths`.
+    ///
+    /// Nothing is written to disk, rewrites are only returned.
+    fn execute_paths(&self, py: Python<'_>, paths: Vec<PathBuf>) -> PyResult<Vec<PyObject>> {
+        let paths: Vec<RichPath> = paths
+            .into_iter()
+            .map(|path| RichPath::new(path, None))
+            .collect();
+        let (results, _) = py.allow_threads(|| {
+            self.problem
+                .execute_paths(paths.iter().collect(), &ExecutionContext::default())
+        });
+        results_to_py(py, results)
+    }
+}
+
+/// Compiles a GritQL pattern.
+///
+/// `language` is the name used in GritQL `language` declarations, and only
+/// applies if the pattern does not declare its own. `libs` maps file names to
+/// the source of the pattern libraries the pattern may call.
+#[pyfunction]
+#[pyo3(signature = (pattern, language = None, libs = None))]
+fn compile(
+    py: Python<'_>,
+    pattern: String,
+    language: Option<&str>,
+    libs: Option<BTreeMap<String, String>>,
+) -> PyResult<Pattern> {
+    let language = match language {
+        Some(name) => PatternLanguage::from_string(name, None)
+            .ok_or_else(|| PyValueError::new_err(format!("Unsupported language: {}", name)))?,
+        None => PatternLanguage::default(),
+    };
+    let language = TargetLanguage::try_from(language).map_err(to_py_err)?;
+    let libs = libs.unwrap_or_default();
+
+    let CompilationResult {
+        compilation_warnings,
+        problem,
+    } = py
+        .allow_threads(|| src_to_problem_libs(pattern, &libs, language, None, None, None, None))
+        .map_err(to_py_err)?;
+    let warnings = compilation_warnings
+        .iter()
+        .map(|warning| AnalysisLog::from(marzano_core::api::AnalysisLog::from(warning.clone())))
+        .collect();
+    Ok(Pattern { problem, warnings })
+}
+
+#[pymodule]
+fn gritql_native(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(compile, m)?)?;
+    m.add_class::<Pattern>()?;
+    m.add_class::<Match>()?;
+    m.add_class::<Rewrite>()?;
+    m.add_class::<CreateFile>()?;
+    m.add_class::<RemoveFile>()?;
+    m.add_class::<AnalysisLog>()?;
+    m.add_class::<Range>()?;
+    m.add_class::<Variable>()?;
+    m.add("GritError", m.py().get_type::<GritError>())?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use crate::to_py_err;
+use marzano_core::api::{self, FileMatchResult, MatchResult};
+use pyo3::prelude::*;
+
+/// A range in a file, with 1-based lines and columns.
+#[pyclass(frozen, get_all, module = "gritql_native")]
+#[derive(Clone, Debug)]
+pub struct Range {
+    start_line: u32,
+    start_column: u32,
+    end_line: u32,
+    end_column: u32,
+    start_byte: u32,
+    end_byte: u32,
+}
+
+impl From<&grit_util::Range> for Range {
+    fn from(range: &grit_util::Range) -> Self {
+        Self {
+            start_line: range.start.line,
+            start_column: range.start.column,
+            end_line: range.end.line,
+            end_column: range.end.column,
+            start_byte: range.start_byte,
+            end_byte: range.end_byte,
+        }
+    }
+}
+
+fn to_ranges(ranges: &[grit_util::Range]) -> Vec<Range> {
+    ranges.iter().map(Range::from).collect()
+}
+
+/// A variable bound by a match, with the ranges it was bound to.
+#[pyclass(frozen, get_all, module = "gritql_native")]
+#[derive(Clone, Debug)]
+pub struct Variable {
+    name: String,
+    ranges: Vec<Range>,
+}
+
+/// A match which does not change the file.
+#[pyclass(frozen, get_all, module = "gritql_native")]
+#[derive(Clone, Debug)]
+pub struct Match {
+    source_file: String,
+    ranges: Vec<Range>,
+    variables: Vec<Variable>,
+}
+
+/// A file rewritten by the pattern. `rewritten_file` differs from
+/// `source_file` if the file was also moved.
+#[pyclass(frozen, get_all, module = "gritql_native")]
+#[derive(Clone, Debug)]
+pub struct Rewrite {
+    source_file: String,
+    rewritten_file: String,
+    original_content: Option<String>,
+    content: String,
+    ranges: Vec<Range>,
+}
+
+/// A file created by the pattern.
+#[pyclass(frozen, get_all, module = "gritql_native")]
+#[derive(Clone, Debug)]
+pub struct CreateFile {
+    source_file: String,
+    content: String,
+}
+
+/// A file removed by the pattern.
+#[pyclass(frozen, get_all, module = "gritql_native")]
+#[derive(Clone, Debug)]
+pub struct RemoveFile {
+    source_file: String,
+}
+
+/// A warning or error raised while compiling or running a pattern.
+#[pyclass(frozen, get_all, module = "gritql_native")]
+#[derive(Clone, Debug)]
+pub struct AnalysisLog {
+    level: u16,
+    message: String,
+    file: String,
+    line: u32,
+    column: u32,
+}
+
+impl From<api::AnalysisLog> for AnalysisLog {
+    fn from(log: api::AnalysisLog) -> Self {
+        Self {
+            level: log.level,
+            message: log.message,
+            file: log.file,
+            line: log.position.line,
+            column: log.position.column,
+        }
+    }
+}
+
+/// Converts the results of an execution into their Python classes, leaving
+/// out the bookkeeping results which are only meaningful to the CLI.
+pub(crate) fn results_to_py(py: Python<'_>, results: Vec<MatchResult>) -> PyResult<Vec<PyObject>> {
+    let mut converted = Vec::with_capacity(results.len());
+    for result in results {
+        let object = match result {
+            MatchResult::Match(m) => Match {
+                ranges: to_ranges(&m.ranges),
+                variables: m
+                    .variables
+                    .iter()
+                    .map(|variable| Variable {
+                        name: variable.name.clone(),
+                        ranges: to_ranges(&variable.ranges),
+                    })
+                    .collect(),
+                source_file: m.source_file,
+            }
+            .into_pyobject(py)?
+            .into_any()
+            .unbind(),
+            MatchResult::Rewrite(r) => Rewrite {
+                content: r.content().map_err(to_py_err)?.to_owned(),
+                ranges: to_ranges(&r.original.ranges),
+                source_file: r.original.source_file,
+                rewritten_file: r.rewritten.source_file,
+                original_content: r.original.content,
+            }
+            .into_pyobject(py)?
+            .into_any()
+            .unbind(),
+            MatchResult::CreateFile(f) => CreateFile {
+                content: f.content().map_err(to_py_err)?.to_owned(),
+                source_file: f.rewritten.source_file,
+            }
+            .into_pyobject(py)?
+            .into_any()
+            .unbind(),
+            MatchResult::RemoveFile(f) => RemoveFile {
+                source_file: f.original.source_file,
+            }
+            .into_pyobject(py)?
+            .into_any()
+            .unbind(),
+            MatchResult::AnalysisLog(log) => AnalysisLog::from(log)
+                .into_pyobject(py)?
+                .into_any()
+                .unbind(),
+            MatchResult::PatternInfo(_)
+            | MatchResult::AllDone(_)
+            | MatchResult::InputFile(_)
+            | MatchResult::DoneFile(_) => continue,
+        };
+        converted.push(object);
+    }
+    Ok(converted)
+}
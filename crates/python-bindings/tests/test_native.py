@@ -0,0 +1,54 @@
+import pytest
+
+import gritql_native as grit
+
+
+def test_match_in_memory_files():
+    pattern = grit.compile("`console.log($message)`", language="js")
+    results = pattern.execute_files([("a.js", "console.log('hello');"), ("b.js", "alert(1);")])
+
+    assert len(results) == 1
+    match = results[0]
+    assert isinstance(match, grit.Match)
+    assert match.source_file == "a.js"
+    assert match.ranges[0].start_line == 1
+    assert "$message" in [variable.name for variable in match.variables]
+
+
+def test_rewrite_in_memory_files():
+    pattern = grit.compile("`console.log($message)` => `console.warn($message)`", language="js")
+    results = pattern.execute_files([("a.js", "console.log('hello');")])
+
+    assert len(results) == 1
+    rewrite = results[0]
+    assert isinstance(rewrite, grit.Rewrite)
+    assert rewrite.source_file == "a.js"
+    assert rewrite.content == "console.warn('hello');"
+
+
+def test_pattern_declares_its_language():
+    pattern = grit.compile("language python\n`print($x)`")
+    results = pattern.execute_files([("a.py", "print(1)")])
+
+    assert [type(r) for r in results] == [grit.Match]
+
+
+def test_execute_paths(tmp_path):
+    target = tmp_path / "a.js"
+    target.write_text("console.log('hello');")
+    pattern = grit.compile("`console.log($message)` => .", language="js")
+    results = pattern.execute_paths([str(target)])
+
+    assert [type(r) for r in results] == [grit.Rewrite]
+    # Rewrites are returned, not applied
+    assert target.read_text() == "console.log('hello');"
+
+
+def test_invalid_pattern():
+    with pytest.raises(grit.GritError):
+        grit.compile("`console.log(` => ", language="js")
+
+
+def test_unsupported_language():
+    with pytest.raises(ValueError):
+        grit.compile("`x`", language="cobol")
@@ -1,6 +1,10 @@
 use anyhow::Result;
+use grit_util::{CancellationToken, ClauseCoverage, ExecutionLimits, MatchTrace, Range};
 use http::HeaderMap;
 use std::env;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
 #[cfg(feature = "network_requests")]
 use tokio::runtime::Handle;
 
@@ -21,6 +25,11 @@ pub struct ExecutionContext {
     reqwest: reqwest::Client,
     /// Ignore limit patterns - this is important for scans
     pub ignore_limit_pattern: bool,
+    clause_coverage: Option<Arc<ClauseCoverage>>,
+    match_trace: Option<Arc<MatchTrace>>,
+    type_provider: Option<Arc<dyn TypeProvider>>,
+    limits: ExecutionLimits,
+    cancellation: Option<CancellationToken>,
 }
 
 #[cfg(all(
@@ -54,6 +63,11 @@ pub struct ExecutionContext {
     fetch: FetchFn,
     pub exec_external: ExecExternalFn,
     pub ignore_limit_pattern: bool,
+    clause_coverage: Option<Arc<ClauseCoverage>>,
+    match_trace: Option<Arc<MatchTrace>>,
+    type_provider: Option<Arc<dyn TypeProvider>>,
+    limits: ExecutionLimits,
+    cancellation: Option<CancellationToken>,
 }
 
 #[cfg(not(any(test, feature = "network_requests_common")))]
@@ -61,6 +75,11 @@ pub struct ExecutionContext {
 pub struct ExecutionContext {
     llm_api: Option<LanguageModelAPI>,
     pub ignore_limit_pattern: bool,
+    clause_coverage: Option<Arc<ClauseCoverage>>,
+    match_trace: Option<Arc<MatchTrace>>,
+    type_provider: Option<Arc<dyn TypeProvider>>,
+    limits: ExecutionLimits,
+    cancellation: Option<CancellationToken>,
 }
 
 impl ExecutionContext {
@@ -107,6 +126,11 @@ impl ExecutionContext {
             fetch,
             exec_external,
             ignore_limit_pattern: false,
+            clause_coverage: None,
+            match_trace: None,
+            type_provider: None,
+            limits: ExecutionLimits::default(),
+            cancellation: None,
         }
     }
 
@@ -115,6 +139,61 @@ impl ExecutionContext {
         self
     }
 
+    /// Record the pattern clauses reached during execution into `coverage`
+    pub fn with_clause_coverage(mut self, coverage: Arc<ClauseCoverage>) -> Self {
+        self.clause_coverage = Some(coverage);
+        self
+    }
+
+    pub fn clause_coverage(&self) -> Option<&ClauseCoverage> {
+        self.clause_coverage.as_deref()
+    }
+
+    /// Record the evaluation of the pattern against nodes on a line into `trace`
+    pub fn with_match_trace(mut self, trace: Arc<MatchTrace>) -> Self {
+        self.match_trace = Some(trace);
+        self
+    }
+
+    pub fn match_trace(&self) -> Option<&MatchTrace> {
+        self.match_trace.as_deref()
+    }
+
+    /// Look up static types of matched nodes through `provider`
+    pub fn with_type_provider(mut self, provider: Arc<dyn TypeProvider>) -> Self {
+        self.type_provider = Some(provider);
+        self
+    }
+
+    pub fn type_provider(&self) -> Option<&dyn TypeProvider> {
+        self.type_provider.as_deref()
+    }
+
+    /// Limit the time and steps each pattern may spend on each file
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> ExecutionLimits {
+        self.limits
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
     #[cfg(feature = "network_requests")]
     pub fn send_request(
         &self,
@@ -191,6 +270,11 @@ impl Default for ExecutionContext {
             handle: Handle::try_current().ok(),
             reqwest: reqwest::Client::new(),
             ignore_limit_pattern: false,
+            clause_coverage: None,
+            match_trace: None,
+            type_provider: None,
+            limits: ExecutionLimits::default(),
+            cancellation: None,
         }
     }
 
@@ -208,6 +292,11 @@ impl Default for ExecutionContext {
                 Err(anyhow::anyhow!("External functions are disabled"))
             },
             ignore_limit_pattern: false,
+            clause_coverage: None,
+            match_trace: None,
+            type_provider: None,
+            limits: ExecutionLimits::default(),
+            cancellation: None,
         }
     }
 
@@ -216,10 +305,25 @@ impl Default for ExecutionContext {
         Self {
             llm_api: None,
             ignore_limit_pattern: false,
+            clause_coverage: None,
+            match_trace: None,
+            type_provider: None,
+            limits: ExecutionLimits::default(),
+            cancellation: None,
         }
     }
 }
 
+/// Supplies static type information for source files, typically by asking an
+/// external type checker.
+pub trait TypeProvider: Debug + Send + Sync {
+    /// Returns the type of the expression at `range` in the file at `path`,
+    /// whose current contents are `source`.
+    ///
+    /// Returns `None` if the type checker has no type for the expression.
+    fn type_of(&self, path: &Path, source: &str, range: &Range) -> Result<Option<String>>;
+}
+
 #[derive(Clone, Debug)]
 pub struct LanguageModelAPI {
     pub base_endpoint: String,
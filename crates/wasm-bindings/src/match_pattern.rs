@@ -6,6 +6,8 @@ use marzano_core::{
     api::{AnalysisLog, InputFile, MatchResult, PatternInfo},
     built_in_functions::BuiltIns,
     pattern_compiler::CompilationResult,
+    problem::Problem,
+    sdk::LanguageSdk,
     tree_sitter_serde::tree_sitter_node_to_json,
 };
 use marzano_language::grit_ts_node::{grit_node_types, GritNodeTypes, NODE_TYPES_STRING};
@@ -19,7 +21,7 @@ use marzano_util::rich_path::RichFile;
 use marzano_util::runtime::{ExecutionContext, LanguageModelAPI};
 use std::{
     collections::{BTreeMap, HashMap},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::OnceLock,
 };
 use tree_sitter::{Language as TSLanguage, Parser as TSParser};
@@ -174,6 +176,10 @@ async fn run_pattern_builder_internal(
     pattern: UncompiledPatternBuilder,
     paths: Vec<String>,
     contents: Vec<String>,
+    lib_paths: Vec<String>,
+    lib_contents: Vec<String>,
+    llm_api_base: String,
+    llm_api_bearer_token: String,
 ) -> anyhow::Result<Vec<MatchResult>> {
     console_error_panic_hook::set_once();
 
@@ -181,16 +187,11 @@ async fn run_pattern_builder_internal(
     let _ = web_tree_sitter_sg::TreeSitter::init().await;
 
     // We *must* initialize the language first
-    let our_lang = PatternLanguage::Tsx;
-    let lang = get_language(our_lang).await?;
-
-    let context = ExecutionContext::default();
+    let mut parser = setup_grit_parser().await?;
+    let lang = get_builder_language(lib_paths, lib_contents, &mut parser).await?;
 
-    #[cfg(not(feature = "ai_builtins"))]
-    let injected_builtins: Option<BuiltIns> = None;
-    #[cfg(feature = "ai_builtins")]
-    let injected_builtins = Some(ai_builtins::ai_builtins::get_ai_built_in_functions());
-    let pattern = pattern.build()?;
+    let context = wasm_execution_context(llm_api_base, llm_api_bearer_token);
+    let pattern = pattern.build_with(&mut LanguageSdk::from_language(lang))?;
 
     let files: Vec<RichFile> = paths
         .into_iter()
@@ -209,7 +210,7 @@ pub async fn run_pattern(
     paths: Vec<String>,
     // The contents of the files to match against, in the same order as `paths`.
     contents: Vec<String>,
-    // Library file names, for the language of the pattern.
+    // Library file names, whose language declaration is used for the pattern.
     lib_paths: Vec<String>,
     // Library file contents, in the same order as `lib_paths`.
     lib_contents: Vec<String>,
@@ -219,12 +220,13 @@ pub async fn run_pattern(
     llm_api_bearer_token: String,
 ) -> Result<JsValue, JsError> {
     let result = match run_pattern_builder_internal(
-        pattern, paths,
+        pattern,
+        paths,
         contents,
-        // lib_paths,
-        // lib_contents,
-        // llm_api_base,
-        // llm_api_bearer_token,
+        lib_paths,
+        lib_contents,
+        llm_api_base,
+        llm_api_bearer_token,
     )
     .await
     {
@@ -234,6 +236,28 @@ pub async fn run_pattern(
     Ok(serde_wasm_bindgen::to_value(&result)?)
 }
 
+#[cfg(target_arch = "wasm32")]
+async fn compile_pattern_internal(
+    pattern: String,
+    lib_paths: Vec<String>,
+    lib_contents: Vec<String>,
+) -> anyhow::Result<CompilationResult> {
+    // TODO remove this line once initialize_tree_sitter function works
+    let _ = web_tree_sitter_sg::TreeSitter::init().await;
+    let mut pure_parser = setup_grit_parser().await?;
+    let parser = &mut pure_parser;
+    let ParsedPattern { libs, lang, .. } =
+        get_parsed_pattern(&pattern, lib_paths, lib_contents, parser).await?;
+
+    #[cfg(not(feature = "ai_builtins"))]
+    let injected_builtins: Option<BuiltIns> = None;
+    #[cfg(feature = "ai_builtins")]
+    let injected_builtins = Some(ai_builtins::ai_builtins::get_ai_built_in_functions());
+    let builder =
+        CompiledPatternBuilder::start(pattern, &libs, lang, None, parser, injected_builtins)?;
+    builder.compile(None, None, true)
+}
+
 #[cfg(target_arch = "wasm32")]
 async fn match_pattern_internal(
     pattern: String,
@@ -245,13 +269,22 @@ async fn match_pattern_internal(
     // LLM API bearer token
     llm_api_bearer_token: String,
 ) -> anyhow::Result<Vec<MatchResult>> {
-    // TODO remove this line once initialize_tree_sitter function works
-    let _ = web_tree_sitter_sg::TreeSitter::init().await;
-    let mut pure_parser = setup_grit_parser().await?;
-    let parser = &mut pure_parser;
-    let ParsedPattern { libs, lang, .. } =
-        get_parsed_pattern(&pattern, lib_paths, lib_contents, parser).await?;
+    let CompilationResult {
+        problem: pattern, ..
+    } = compile_pattern_internal(pattern, lib_paths, lib_contents).await?;
+    let context = wasm_execution_context(llm_api_base, llm_api_bearer_token);
+    let files: Vec<RichFile> = paths
+        .into_iter()
+        .zip(contents)
+        .map(|(p, c)| RichFile::new(p, c))
+        .collect();
+    let results = pattern.execute_files(files, &context);
+    Ok(results)
+}
 
+/// Builds an execution context which routes API requests and foreign function calls through JS.
+#[cfg(target_arch = "wasm32")]
+fn wasm_execution_context(llm_api_base: String, llm_api_bearer_token: String) -> ExecutionContext {
     let context = ExecutionContext::new(
         |url, headers, json| {
             let body = serde_json::to_string(json)?;
@@ -288,7 +321,7 @@ async fn match_pattern_internal(
         },
     );
 
-    let context = if !llm_api_base.is_empty() {
+    if !llm_api_base.is_empty() {
         let llm_api = LanguageModelAPI {
             base_endpoint: llm_api_base,
             bearer_token: llm_api_bearer_token,
@@ -297,23 +330,7 @@ async fn match_pattern_internal(
         context.with_llm_api(llm_api)
     } else {
         context
-    };
-    #[cfg(not(feature = "ai_builtins"))]
-    let injected_builtins: Option<BuiltIns> = None;
-    #[cfg(feature = "ai_builtins")]
-    let injected_builtins = Some(ai_builtins::ai_builtins::get_ai_built_in_functions());
-    let builder =
-        CompiledPatternBuilder::start(pattern, &libs, lang, None, parser, injected_builtins)?;
-    let CompilationResult {
-        problem: pattern, ..
-    } = builder.compile(None, None, true)?;
-    let files: Vec<RichFile> = paths
-        .into_iter()
-        .zip(contents)
-        .map(|(p, c)| RichFile::new(p, c))
-        .collect();
-    let results = pattern.execute_files(files, &context);
-    Ok(results)
+    }
 }
 
 fn error_to_log(e: anyhow::Error) -> MatchResult {
@@ -366,6 +383,101 @@ pub async fn match_pattern(
     Ok(serde_wasm_bindgen::to_value(&result)?)
 }
 
+/// A pattern compiled once, along with its libraries, which can then be run
+/// against any number of files without being compiled again.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct CompiledPattern {
+    problem: Problem,
+    context: ExecutionContext,
+    warnings: Vec<MatchResult>,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl CompiledPattern {
+    /// Compiles a pattern, throwing the analysis log if it is invalid.
+    pub async fn compile(
+        pattern: String,
+        // Library file names, for the language of the pattern.
+        lib_paths: Vec<String>,
+        // Library file contents, in the same order as `lib_paths`.
+        lib_contents: Vec<String>,
+        // LLM API base
+        llm_api_base: String,
+        // LLM API bearer token
+        llm_api_bearer_token: String,
+    ) -> Result<CompiledPattern, JsValue> {
+        console_error_panic_hook::set_once();
+        let CompilationResult {
+            problem,
+            compilation_warnings,
+        } = match compile_pattern_internal(pattern, lib_paths, lib_contents).await {
+            Ok(compiled) => compiled,
+            Err(e) => return Err(serde_wasm_bindgen::to_value(&error_to_log(e))?),
+        };
+        Ok(CompiledPattern {
+            problem,
+            context: wasm_execution_context(llm_api_base, llm_api_bearer_token),
+            warnings: compilation_warnings
+                .iter()
+                .map(|w| MatchResult::AnalysisLog(w.clone().into()))
+                .collect(),
+        })
+    }
+
+    /// The warnings raised while compiling the pattern.
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> Result<JsValue, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.warnings)?)
+    }
+
+    /// Runs the pattern against a single file, passing each result to `on_result`
+    /// as soon as it is available.
+    #[wasm_bindgen(js_name = runOnFile)]
+    pub fn run_on_file(
+        &self,
+        path: String,
+        content: String,
+        on_result: &js_sys::Function,
+    ) -> Result<(), JsValue> {
+        let file = RichFile::new(path, content);
+        for result in self.problem.execute_file(&file, &self.context) {
+            on_result.call1(&JsValue::NULL, &serde_wasm_bindgen::to_value(&result)?)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the pattern against each file in turn, streaming the results of
+    /// every file to `on_result` before moving on to the next one.
+    #[wasm_bindgen(js_name = runOnFiles)]
+    pub fn run_on_files(
+        &self,
+        // The paths of the files to match against.
+        paths: Vec<String>,
+        // The contents of the files to match against, in the same order as `paths`.
+        contents: Vec<String>,
+        on_result: &js_sys::Function,
+    ) -> Result<(), JsValue> {
+        if self.problem.is_multifile {
+            // Multifile patterns need to see every file before producing results
+            let files = paths
+                .into_iter()
+                .zip(contents)
+                .map(|(p, c)| RichFile::new(p, c))
+                .collect();
+            for result in self.problem.execute_files(files, &self.context) {
+                on_result.call1(&JsValue::NULL, &serde_wasm_bindgen::to_value(&result)?)?;
+            }
+            return Ok(());
+        }
+        for (path, content) in paths.into_iter().zip(contents) {
+            self.run_on_file(path, content, on_result)?;
+        }
+        Ok(())
+    }
+}
+
 struct ParsedPattern {
     libs: BTreeMap<String, String>,
     tree: Tree,
@@ -384,6 +496,22 @@ async fn get_parsed_pattern(
     Ok(ParsedPattern { libs, tree, lang })
 }
 
+/// Builder patterns have no language declaration of their own, so they are
+/// built for the language of the first library file which declares one, or TSX.
+async fn get_builder_language(
+    lib_paths: Vec<String>,
+    lib_contents: Vec<String>,
+    parser: &mut MarzanoGritParser,
+) -> anyhow::Result<TargetLanguage> {
+    for (path, content) in lib_paths.iter().zip(&lib_contents) {
+        let tree = parser.parse_file(content, Some(Path::new(path)))?;
+        if let Some(lang) = PatternLanguage::from_tree(&tree) {
+            return get_language(lang).await;
+        }
+    }
+    get_language(PatternLanguage::Tsx).await
+}
+
 #[cfg(test)]
 fn get_parser_path() -> String {
     format!("{}{}", env!("CARGO_MANIFEST_DIR"), "/wasm_parsers")
@@ -435,22 +563,44 @@ async fn setup_grit_parser() -> anyhow::Result<MarzanoGritParser> {
 
 async fn get_language(lang: PatternLanguage) -> anyhow::Result<TargetLanguage> {
     if lang.is_initialized() {
-        Ok(TargetLanguage::try_from(lang)?)
-    } else {
-        if matches!(
-            lang,
-            PatternLanguage::JavaScript
-                | PatternLanguage::TypeScript
-                | PatternLanguage::Tsx
-                | PatternLanguage::Css
-        ) {
-            // javascript also parses vue files to look for javascript so
-            // we need to initialize the Vue struct with a wasm parser
-            let vue_lang = get_cached_lang(&PatternLanguage::Vue).await?;
-            let _ = PatternLanguage::Vue.to_target_with_ts_lang(vue_lang.clone());
+        return Ok(TargetLanguage::try_from(lang)?);
+    }
+    let required = required_languages(lang);
+    let mut missing = vec![];
+    for required_lang in &required {
+        if get_cached_lang(required_lang).await.is_err() {
+            missing.push(grammar_name(required_lang)?);
         }
-        let ts_lang = get_cached_lang(&lang).await?;
-        Ok(lang.to_target_with_ts_lang(ts_lang.clone())?)
+    }
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Missing grammars for {}: {}. Register them with registerGrammar before using this language.",
+            lang,
+            missing.join(", ")
+        );
+    }
+    let mut target = None;
+    for required_lang in required {
+        let ts_lang = get_cached_lang(&required_lang).await?;
+        target = Some(required_lang.to_target_with_ts_lang(ts_lang.clone())?);
+    }
+    target.ok_or_else(|| anyhow::anyhow!("No grammar is required for {}", lang))
+}
+
+/// The languages whose grammars must be loaded before `lang` can be used, ending with `lang` itself.
+fn required_languages(lang: PatternLanguage) -> Vec<PatternLanguage> {
+    if matches!(
+        lang,
+        PatternLanguage::JavaScript
+            | PatternLanguage::TypeScript
+            | PatternLanguage::Tsx
+            | PatternLanguage::Css
+    ) {
+        // javascript also parses vue files to look for javascript so
+        // we need to initialize the Vue struct with a wasm parser
+        vec![PatternLanguage::Vue, lang]
+    } else {
+        vec![lang]
     }
 }
 
@@ -460,38 +610,125 @@ async fn get_language_for_tree(tree: &Tree) -> anyhow::Result<TargetLanguage> {
     Ok(lang)
 }
 
-fn pattern_language_to_path(lang: &PatternLanguage) -> anyhow::Result<String> {
-    let wasm_file = match lang {
-        PatternLanguage::JavaScript => Ok("/tree-sitter-javascript.wasm"),
-        PatternLanguage::TypeScript => Ok("/tree-sitter-typescript.wasm"),
-        PatternLanguage::Tsx => Ok("/tree-sitter-tsx.wasm"),
-        PatternLanguage::Html => Ok("/tree-sitter-html.wasm"),
-        PatternLanguage::Css => Ok("/tree-sitter-css.wasm"),
-        PatternLanguage::Json => Ok("/tree-sitter-json.wasm"),
-        PatternLanguage::Java => Ok("/tree-sitter-java.wasm"),
-        PatternLanguage::Kotlin => Ok("/tree-sitter-kotlin.wasm"),
-        PatternLanguage::CSharp => Err(anyhow::anyhow!("CSharp wasm is not currently supported")),
-        PatternLanguage::Python => Ok("/tree-sitter-python.wasm"),
-        PatternLanguage::MarkdownBlock => Ok("/tree-sitter-markdown-block.wasm"), // def wrong
-        PatternLanguage::MarkdownInline => Ok("/tree-sitter-markdown_inline.wasm"), // def wrong
-        PatternLanguage::Go => Ok("/tree-sitter-go.wasm"),
-        PatternLanguage::Rust => Ok("/tree-sitter-rust.wasm"),
-        PatternLanguage::Ruby => Ok("/tree-sitter-ruby.wasm"),
-        PatternLanguage::Elixir => Ok("/tree-sitter-elixir.wasm"),
-        PatternLanguage::Solidity => Ok("/tree-sitter-solidity.wasm"),
-        PatternLanguage::Hcl => Ok("/tree-sitter-hcl.wasm"),
-        PatternLanguage::Yaml => Ok("/tree-sitter-yaml.wasm"),
-        PatternLanguage::Sql => Ok("/tree-sitter-sql.wasm"),
-        PatternLanguage::Vue => Ok("/tree-sitter-vue.wasm"),
-        PatternLanguage::Toml => Ok("/tree-sitter-toml.wasm"),
-        PatternLanguage::Php => Ok("/tree-sitter-php.wasm"),
-        PatternLanguage::PhpOnly => Ok("/tree-sitter-php_only.wasm"),
+/// The name hosts register the grammar of a language under, which is also the
+/// stem of the `tree-sitter-<name>.wasm` file it is loaded from by default.
+fn grammar_name(lang: &PatternLanguage) -> anyhow::Result<&'static str> {
+    match lang {
+        PatternLanguage::JavaScript => Ok("javascript"),
+        PatternLanguage::TypeScript => Ok("typescript"),
+        PatternLanguage::Tsx => Ok("tsx"),
+        PatternLanguage::Html => Ok("html"),
+        PatternLanguage::Css => Ok("css"),
+        PatternLanguage::Json => Ok("json"),
+        PatternLanguage::Java => Ok("java"),
+        PatternLanguage::Kotlin => Ok("kotlin"),
+        PatternLanguage::CSharp => Ok("c-sharp"),
+        PatternLanguage::Python => Ok("python"),
+        PatternLanguage::MarkdownBlock => Ok("markdown-block"), // def wrong
+        PatternLanguage::MarkdownInline => Ok("markdown_inline"), // def wrong
+        PatternLanguage::Go => Ok("go"),
+        PatternLanguage::Rust => Ok("rust"),
+        PatternLanguage::Ruby => Ok("ruby"),
+        PatternLanguage::Elixir => Ok("elixir"),
+        PatternLanguage::Solidity => Ok("solidity"),
+        PatternLanguage::Hcl => Ok("hcl"),
+        PatternLanguage::Yaml => Ok("yaml"),
+        PatternLanguage::Sql => Ok("sql"),
+        PatternLanguage::Vue => Ok("vue"),
+        PatternLanguage::Toml => Ok("toml"),
+        PatternLanguage::Php => Ok("php"),
+        PatternLanguage::PhpOnly => Ok("php_only"),
         PatternLanguage::Universal => Err(anyhow::anyhow!("Universal does not have a parser")),
-    }?;
-    let final_file = format!("{}{}", get_parser_path(), wasm_file);
+    }
+}
+
+fn language_for_grammar(name: &str) -> Option<PatternLanguage> {
+    PatternLanguage::enumerate()
+        .into_iter()
+        .find(|lang| grammar_name(lang).is_ok_and(|grammar| grammar == name))
+}
+
+fn pattern_language_to_path(lang: &PatternLanguage) -> anyhow::Result<String> {
+    let final_file = format!(
+        "{}/tree-sitter-{}.wasm",
+        get_parser_path(),
+        grammar_name(lang)?
+    );
     Ok(final_file)
 }
 
+/// Lists the names grammars can be registered under.
+#[wasm_bindgen(js_name = grammarNames)]
+pub fn grammar_names() -> Vec<String> {
+    PatternLanguage::enumerate()
+        .iter()
+        .filter_map(|lang| grammar_name(lang).ok())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Registers the grammar for a language from the bytes of its `.wasm` file, so
+/// it does not need to be fetched from the default parser path.
+/// Registering a grammar which is already loaded has no effect.
+#[wasm_bindgen(js_name = registerGrammar)]
+#[cfg(target_arch = "wasm32")]
+pub async fn register_grammar(
+    // The grammar name, as listed by `grammarNames`.
+    name: String,
+    // The contents of the grammar's `.wasm` file.
+    grammar: Vec<u8>,
+) -> Result<(), JsError> {
+    register_grammar_internal(&name, &grammar)
+        .await
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn register_grammar_internal(name: &str, grammar: &[u8]) -> anyhow::Result<()> {
+    let Some(lang) = language_for_grammar(name) else {
+        anyhow::bail!(
+            "Unknown grammar {}, expected one of: {}",
+            name,
+            grammar_names().join(", ")
+        );
+    };
+    let lang_store = get_lang_store(&lang)?;
+    if lang_store.get().is_some() {
+        return Ok(());
+    }
+    let bytes = js_sys::Uint8Array::from(grammar);
+    let ts_lang = web_tree_sitter_sg::Language::load_bytes(&bytes)
+        .await
+        .map_err(tree_sitter::LanguageError::from)?;
+    let _language_already_set = lang_store.set(TSLanguage::from(ts_lang));
+    Ok(())
+}
+
+/// Lists the grammars which have not been loaded yet, but are needed to run the pattern.
+/// Hosts can fetch and register them before running the pattern.
+#[wasm_bindgen(js_name = missingGrammars)]
+#[cfg(target_arch = "wasm32")]
+pub async fn missing_grammars(pattern: String) -> Result<Vec<String>, JsError> {
+    // TODO remove this line once initialize_tree_sitter function works
+    let _ = web_tree_sitter_sg::TreeSitter::init().await;
+    let mut parser = setup_grit_parser()
+        .await
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let tree = parser
+        .parse_file(&pattern, None)
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let lang = PatternLanguage::from_tree(&tree).unwrap_or_default();
+    let mut missing = vec![];
+    for required_lang in required_languages(lang) {
+        let loaded = get_lang_store(&required_lang).is_ok_and(|store| store.get().is_some());
+        if !loaded {
+            let name = grammar_name(&required_lang).map_err(|e| JsError::new(&e.to_string()))?;
+            missing.push(name.to_string());
+        }
+    }
+    Ok(missing)
+}
+
 #[cfg(target_arch = "wasm32")]
 async fn get_lang(parser_path: &str) -> anyhow::Result<TSLanguage> {
     let lang = web_tree_sitter_sg::Language::load_path(parser_path)
@@ -545,8 +782,13 @@ fn get_lang_store(_language: &PatternLanguage) -> anyhow::Result<&'static OnceLo
 mod tests {
 
     use super::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen::JsCast;
     use wasm_bindgen_test::*;
 
+    /// Grammars which are not shipped in `wasm_parsers`, and must be registered by the host.
+    const UNSHIPPED_GRAMMARS: &[&str] = &["c-sharp", "elixir", "kotlin"];
+
     #[wasm_bindgen_test]
     async fn parse_grit() {
         let _ = web_tree_sitter_sg::TreeSitter::init().await;
@@ -554,4 +796,227 @@ mod tests {
         let parser = setup_grit_parser().await;
         assert!(parser.is_ok());
     }
+
+    #[wasm_bindgen_test]
+    async fn initializes_every_language() {
+        let _ = web_tree_sitter_sg::TreeSitter::init().await;
+
+        for lang in PatternLanguage::enumerate() {
+            let name = grammar_name(&lang).unwrap();
+            let result = get_language(lang).await;
+            if UNSHIPPED_GRAMMARS.contains(&name) {
+                let error = result.unwrap_err().to_string();
+                assert!(error.contains(name), "{}", error);
+            } else {
+                assert!(
+                    result.is_ok(),
+                    "failed to initialize {}: {:?}",
+                    name,
+                    result.err()
+                );
+            }
+        }
+    }
+
+    /// Compares rewritten files the same way the native tests do, ignoring
+    /// trailing whitespace.
+    #[cfg(target_arch = "wasm32")]
+    fn normalize(content: &str) -> String {
+        content
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    }
+
+    /// Runs the cases the native tests run for every language, which must
+    /// rewrite the same way in WASM.
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    async fn rewrites_like_native() {
+        let _ = web_tree_sitter_sg::TreeSitter::init().await;
+
+        let cases: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("../../core/fixtures/language_parity.json")).unwrap();
+        for case in cases {
+            let field = |name: &str| case[name].as_str().unwrap().to_string();
+            let lang = PatternLanguage::get_language(&field("pattern")).unwrap();
+            if UNSHIPPED_GRAMMARS.contains(&grammar_name(&lang).unwrap()) {
+                continue;
+            }
+            let results = match_pattern_internal(
+                field("pattern"),
+                vec![field("file")],
+                vec![field("source")],
+                vec![],
+                vec![],
+                String::new(),
+                String::new(),
+            )
+            .await
+            .unwrap();
+            let rewritten = results.iter().find_map(|result| match result {
+                MatchResult::Rewrite(rewrite) => rewrite.rewritten.content.clone(),
+                _ => None,
+            });
+            assert_eq!(
+                rewritten.as_deref().map(normalize),
+                Some(normalize(&field("expected"))),
+                "{} was not rewritten like native, got {:?}",
+                field("file"),
+                results
+            );
+        }
+    }
+
+    /// A library file defining a named pattern, for the patterns under test to call.
+    #[cfg(target_arch = "wasm32")]
+    const RENAME_LIB: &str = "language js\n\npattern rename_foo() { `foo($x)` => `bar($x)` }";
+
+    /// Collects every result streamed to the callback it is passed as.
+    #[cfg(target_arch = "wasm32")]
+    fn collect_results(run: impl FnOnce(&js_sys::Function)) -> Vec<MatchResult> {
+        let results = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let streamed = results.clone();
+        let on_result = Closure::<dyn FnMut(JsValue)>::new(move |result: JsValue| {
+            streamed
+                .borrow_mut()
+                .push(serde_wasm_bindgen::from_value::<MatchResult>(result).unwrap());
+        });
+        run(on_result.as_ref().unchecked_ref());
+        results.take()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn rewritten_files(results: &[MatchResult]) -> Vec<(String, String)> {
+        results
+            .iter()
+            .filter_map(|result| match result {
+                MatchResult::Rewrite(rewrite) => Some((
+                    rewrite.rewritten.source_file.clone(),
+                    normalize(rewrite.rewritten.content.as_deref().unwrap_or_default()),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn compile_rename() -> CompiledPattern {
+        CompiledPattern::compile(
+            "language js\n\nrename_foo()".to_string(),
+            vec!["rename.grit".to_string()],
+            vec![RENAME_LIB.to_string()],
+            String::new(),
+            String::new(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    async fn compiles_patterns_with_libraries() {
+        let _ = web_tree_sitter_sg::TreeSitter::init().await;
+
+        let compiled = compile_rename().await;
+        let warnings: Vec<MatchResult> =
+            serde_wasm_bindgen::from_value(compiled.warnings().unwrap()).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let missing_library = CompiledPattern::compile(
+            "language js\n\nrename_foo()".to_string(),
+            vec![],
+            vec![],
+            String::new(),
+            String::new(),
+        )
+        .await;
+        assert!(missing_library.is_err());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    async fn runs_compiled_pattern_on_file() {
+        let _ = web_tree_sitter_sg::TreeSitter::init().await;
+
+        let compiled = compile_rename().await;
+        let results = collect_results(|on_result| {
+            compiled
+                .run_on_file("a.js".to_string(), "foo(1);".to_string(), on_result)
+                .unwrap()
+        });
+        assert_eq!(
+            rewritten_files(&results),
+            vec![("a.js".to_string(), "bar(1);".to_string())]
+        );
+
+        // The compiled pattern can be run again, on another file
+        let results = collect_results(|on_result| {
+            compiled
+                .run_on_file("b.js".to_string(), "baz(2);".to_string(), on_result)
+                .unwrap()
+        });
+        assert!(rewritten_files(&results).is_empty(), "{:?}", results);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    async fn runs_compiled_pattern_on_files() {
+        let _ = web_tree_sitter_sg::TreeSitter::init().await;
+
+        let compiled = compile_rename().await;
+        let results = collect_results(|on_result| {
+            compiled
+                .run_on_files(
+                    vec!["a.js".to_string(), "b.js".to_string(), "c.js".to_string()],
+                    vec![
+                        "foo(1);".to_string(),
+                        "baz(2);".to_string(),
+                        "foo(3);".to_string(),
+                    ],
+                    on_result,
+                )
+                .unwrap()
+        });
+        assert_eq!(
+            rewritten_files(&results),
+            vec![
+                ("a.js".to_string(), "bar(1);".to_string()),
+                ("c.js".to_string(), "bar(3);".to_string()),
+            ]
+        );
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    async fn builder_patterns_use_library_language() {
+        let _ = web_tree_sitter_sg::TreeSitter::init().await;
+
+        let mut parser = setup_grit_parser().await.unwrap();
+        let lang = get_builder_language(
+            vec!["helpers.grit".to_string()],
+            vec!["language python\n\npattern helper() { `print($x)` }".to_string()],
+            &mut parser,
+        )
+        .await
+        .unwrap();
+        assert_eq!(lang.language_name(), "Python");
+
+        let lang = get_builder_language(vec![], vec![], &mut parser)
+            .await
+            .unwrap();
+        assert_eq!(lang.language_name(), "TSX");
+    }
+
+    #[wasm_bindgen_test]
+    fn every_language_has_a_grammar() {
+        for lang in PatternLanguage::enumerate() {
+            let name = grammar_name(&lang).unwrap();
+            assert_eq!(language_for_grammar(name), Some(lang));
+        }
+        assert!(grammar_name(&PatternLanguage::Universal).is_err());
+    }
 }
@@ -0,0 +1,14 @@
+#!/bin/bash
+# Builds the WASM parsers shipped with the WASM bindings from the generated
+# grammars in language-metavariables, without regenerating them:
+#   ./resources/build_wasm_parsers.sh c-sharp elixir kotlin
+# Requires the tree-sitter CLI, and either emscripten or docker.
+set -euo pipefail
+
+cd "$(dirname "$0")"
+out="$(pwd)/../crates/wasm-bindings/wasm_parsers"
+for lang in "$@"; do
+  tree-sitter build --wasm \
+    --output "$out/tree-sitter-$lang.wasm" \
+    "language-metavariables/tree-sitter-$lang"
+done
@@ -247,7 +247,7 @@ async function buildLanguage(language) {
     log(`Copying C# output node types`);
     await copyNodeTypes(language);
     log(`Copying C# wasm parser`);
-    fs.copyFile(
+    await fs.copyFile(
       path.join(
         LANGUAGE_METAVARIABLES_DIR,
         "tree-sitter-c-sharp/tree-sitter-c_sharp.wasm",